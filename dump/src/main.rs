@@ -1,12 +1,16 @@
 use anyhow::Result as AnyResult;
 use clap::{Command, arg, value_parser};
-use image::ImageFormat;
+use image::{
+    ImageFormat,
+    imageops::{FilterType, resize},
+};
 use mimalloc::MiMalloc;
 use nipdf::{
-    file::File,
-    object::{Object, RuntimeObjectId},
+    file::{File, ObjectResolver, ResolvedFile},
+    object::{Dictionary, Object, PdfObject, RuntimeObjectId, Stream, XRefEntry},
 };
-use nipdf_render::{RenderOptionBuilder, render_steps};
+use nipdf_render::{ForceFont, RenderOptionBuilder, render_steps_pixmap};
+use prescript::{name, sname};
 use std::{
     collections::HashSet,
     io::{BufWriter, Cursor, copy, stdout},
@@ -35,7 +39,7 @@ fn cli() -> Command {
                         .required(true),
                 )
                 .arg(arg!(--raw "Skip decoding stream content"))
-                .arg(arg!(--png "Assume stream is image, decode and convert to PNG")),
+                .arg(arg!(--image "Assume stream is image, output JPEG as is if DCTDecode, otherwise decode and convert to PNG")),
         )
         .subcommand(
             Command::new("page")
@@ -48,9 +52,19 @@ fn cli() -> Command {
                 .arg(arg!(-p --password <password> "Password for encrypted PDF file"))
                 .arg(arg!(--pages "display total page numbers"))
                 .arg(arg!(--id "display page object ID"))
+                .arg(arg!(--resources "display resolved page resources"))
                 .arg(arg!(--png "Render page to PNG"))
                 .arg(arg!(--zoom [zoom] "Zoom factor for PNG rendering, default: 1.75"))
                 .arg(arg!(--"no-crop" "Do not apply CropBox"))
+                .arg(
+                    arg!(--"force-font" <font_file> "Render all text using the font file")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"force-font-name" <font_name> "Render all text using the system font")
+                        .conflicts_with("force-font"),
+                )
+                .arg(arg!(--"debug-clip" "Stroke outline of clip paths in red"))
                 .arg(arg!(--steps <steps> "Stop render after <steps> graphic steps"))
                 .arg(arg!([page_no] "page number (start from zero) to dump")),
         )
@@ -69,31 +83,64 @@ fn cli() -> Command {
                         .required(true),
                 ),
         )
+        .subcommand(
+            Command::new("xref")
+                .about("dump cross reference entries")
+                .arg(
+                    arg!(-f <filename> "PDF file to dump")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(true),
+                )
+                .arg(arg!(-p --password <password> "Password for encrypted PDF file")),
+        )
 }
 
-fn open(path: impl AsRef<Path>, password: &str) -> AnyResult<File> {
+fn open(path: impl AsRef<Path>, password: &str) -> AnyResult<ResolvedFile> {
     let buf = std::fs::read(path)?;
-    File::parse(buf, password).map_err(|e| e.into())
+    File::open_resolver(buf, password).map_err(|e| e.into())
 }
 
-fn dump_stream(path: &PathBuf, password: &str, id: u32, raw: bool, as_png: bool) -> AnyResult<()> {
+/// Decode image stream to PNG, `/SMask` composited as alpha channel.
+fn image_to_png(resolver: &ObjectResolver, s: &Stream) -> AnyResult<Vec<u8>> {
+    let mut img = s.decode_image(resolver, None)?.into_rgba8();
+    if let Some(s_mask) = resolver.opt_resolve_container_value(s.as_dict(), &sname("SMask"))? {
+        let mut mask = s_mask.stream()?.decode_image(resolver, None)?.into_luma8();
+        if mask.dimensions() != img.dimensions() {
+            mask = resize(&mask, img.width(), img.height(), FilterType::Triangle);
+        }
+        for (p, m) in img.pixels_mut().zip(mask.pixels()) {
+            p[3] = m[0];
+        }
+    }
+    let mut buf = Cursor::new(Vec::new());
+    img.write_to(&mut buf, ImageFormat::Png)?;
+    Ok(buf.into_inner())
+}
+
+fn dump_stream(
+    path: &PathBuf,
+    password: &str,
+    id: u32,
+    raw: bool,
+    as_image: bool,
+) -> AnyResult<()> {
     let f = open(path, password)?;
-    let resolver = f.resolver()?;
+    let resolver = f.resolver();
     let obj = resolver.resolve(id)?;
     match obj {
         Object::Stream(s) => {
             let decoded;
-            let png_buffer;
+            let image_buffer;
             let mut buf = if raw {
-                s.raw(&resolver)?
-            } else if as_png {
-                let img = s.decode_image(&resolver, None)?;
-                let mut buf = Cursor::new(Vec::new());
-                img.write_to(&mut buf, ImageFormat::Png)?;
-                png_buffer = buf.into_inner();
-                &png_buffer
+                s.raw(resolver)?
+            } else if as_image {
+                image_buffer = match s.decode_jpeg(resolver)? {
+                    Some(jpeg) => jpeg.into_owned(),
+                    None => image_to_png(resolver, s)?,
+                };
+                &image_buffer
             } else {
-                decoded = s.decode(&resolver)?;
+                decoded = s.decode(resolver)?;
                 decoded.as_ref()
             };
             copy(&mut buf, &mut BufWriter::new(&mut stdout()))?;
@@ -109,10 +156,13 @@ struct DumpPageArgs<'a> {
     page_no: Option<u32>,
     show_total_pages: bool,
     show_page_id: bool,
+    show_resources: bool,
     to_png: bool,
     steps: Option<usize>,
     zoom: Option<f32>,
     no_crop: bool,
+    force_font: Option<ForceFont>,
+    debug_clip: bool,
 }
 
 fn dump_page(args: DumpPageArgs<'_>) -> AnyResult<()> {
@@ -122,15 +172,18 @@ fn dump_page(args: DumpPageArgs<'_>) -> AnyResult<()> {
         page_no,
         show_total_pages,
         show_page_id,
+        show_resources,
         to_png,
         steps,
         zoom,
         no_crop,
+        force_font,
+        debug_clip,
     } = args;
 
     let f = open(path, password)?;
-    let resolver = f.resolver()?;
-    let catalog = f.catalog(&resolver)?;
+    let resolver = f.resolver();
+    let catalog = f.catalog()?;
 
     if show_total_pages {
         println!("{}", catalog.pages()?.len());
@@ -138,18 +191,24 @@ fn dump_page(args: DumpPageArgs<'_>) -> AnyResult<()> {
         let page_no = page_no.expect("page number is required");
         let page = &catalog.pages()?[page_no as usize];
         println!("{}", page.id());
+    } else if show_resources {
+        let page_no = page_no.expect("page number is required");
+        let page = &catalog.pages()?[page_no as usize];
+        dump_resources(page.resources().dict(), resolver)?;
     } else if to_png {
         let page_no = page_no.expect("page number is required");
         let page = &catalog.pages()?[page_no as usize];
-        let image = render_steps(
+        let canvas = render_steps_pixmap(
             page,
-            RenderOptionBuilder::new().zoom(zoom.unwrap_or(1.75)),
+            RenderOptionBuilder::new()
+                .zoom(zoom.unwrap_or(1.75))
+                .force_font(force_font)
+                .debug_clip(debug_clip),
             steps,
             no_crop,
+            None,
         )?;
-        let mut buf = vec![];
-        let mut cursor = Cursor::new(&mut buf);
-        image.write_to(&mut cursor, ImageFormat::Png)?;
+        let buf = canvas.encode_png()?;
         copy(&mut &buf[..], &mut BufWriter::new(&mut stdout()))?;
     } else if let Some(page_no) = page_no {
         let page = &catalog.pages()?[page_no as usize];
@@ -162,9 +221,60 @@ fn dump_page(args: DumpPageArgs<'_>) -> AnyResult<()> {
     Ok(())
 }
 
+/// Resource dictionary entry keys to dump, in display order.
+const RESOURCE_CATEGORIES: [&str; 5] = ["Font", "XObject", "ColorSpace", "Pattern", "ExtGState"];
+
+/// Type of resource entry, `/Subtype` or `/Type` of dictionary or stream,
+/// family name of color space array.
+fn resource_type(obj: &Object) -> Option<String> {
+    let name = match obj {
+        Object::Name(n) => Some(n.clone()),
+        Object::Array(arr) => arr.first().and_then(|o| o.opt_name()),
+        Object::Dictionary(_) | Object::Stream(_) => {
+            let d = obj.as_dict().ok()?;
+            d.get(&sname("Subtype"))
+                .or_else(|| d.get(&sname("Type")))
+                .and_then(|o| o.opt_name())
+        }
+        _ => None,
+    };
+    name.map(|n| format!("/{}", n))
+}
+
+fn dump_resources(resources: &Dictionary, resolver: &ObjectResolver) -> AnyResult<()> {
+    for category in RESOURCE_CATEGORIES {
+        let Some(entries) = resolver.opt_resolve_container_value(resources, &sname(category))?
+        else {
+            continue;
+        };
+
+        println!("{}:", category);
+        let entries = entries.as_dict()?;
+        let mut names = entries.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let v = entries.get(name).unwrap();
+            let id = v.opt_reference().map(|r| r.id().id());
+            let obj = match id {
+                Some(id) => resolver.resolve(id)?,
+                None => v,
+            };
+            println!(
+                "  /{} {} {}",
+                name,
+                id.map_or_else(|| "inline".to_owned(), |id| format!("OBJ {}", id)),
+                resource_type(obj).as_deref().unwrap_or("-"),
+            );
+            obj.to_doc().render(80, &mut stdout())?;
+            print!("\n\n");
+        }
+    }
+    Ok(())
+}
+
 fn dump_object(path: &PathBuf, password: &str, id: u32) -> AnyResult<()> {
     let f = open(path, password)?;
-    let resolver = f.resolver()?;
+    let resolver = f.resolver();
 
     let id = RuntimeObjectId(id);
     let mut id_wait_scanned = vec![id];
@@ -189,6 +299,25 @@ fn dump_object(path: &PathBuf, password: &str, id: u32) -> AnyResult<()> {
     Ok(())
 }
 
+/// One line per entry: `<id> <offset> <generation> n|f` for objects in
+/// file, `<id> in <stream id> <index>` for objects in object stream.
+fn dump_xref(path: &PathBuf, password: &str) -> AnyResult<()> {
+    let f = open(path, password)?;
+    for (id, entry) in f.file().xref().entries() {
+        match entry {
+            XRefEntry::InFile(pos) => println!(
+                "{} {:010} {:05} {}",
+                id,
+                pos.offset(),
+                pos.generation(),
+                if pos.is_used() { 'n' } else { 'f' }
+            ),
+            XRefEntry::InStream(stream_id, idx) => println!("{} in {} {}", id, stream_id, idx),
+        }
+    }
+    Ok(())
+}
+
 fn main() {
     env_logger::init();
 
@@ -200,7 +329,7 @@ fn main() {
                 .map_or_else(|| "", |p| p.as_str()),
             *sub_m.get_one::<u32>("object_id").unwrap(),
             sub_m.get_one::<bool>("raw").copied().unwrap_or_default(),
-            sub_m.get_one::<bool>("png").copied().unwrap_or_default(),
+            sub_m.get_one::<bool>("image").copied().unwrap_or_default(),
         ),
         Some(("page", sub_m)) => dump_page(DumpPageArgs {
             path: sub_m.get_one::<PathBuf>("filename").unwrap(),
@@ -212,6 +341,10 @@ fn main() {
                 .and_then(|s| s.parse().ok()),
             show_total_pages: sub_m.get_one::<bool>("pages").copied().unwrap_or_default(),
             show_page_id: sub_m.get_one::<bool>("id").copied().unwrap_or_default(),
+            show_resources: sub_m
+                .get_one::<bool>("resources")
+                .copied()
+                .unwrap_or_default(),
             to_png: sub_m.get_one::<bool>("png").copied().unwrap_or_default(),
             steps: sub_m
                 .get_one::<String>("steps")
@@ -221,6 +354,19 @@ fn main() {
                 .get_one::<bool>("no-crop")
                 .copied()
                 .unwrap_or_default(),
+            force_font: sub_m
+                .get_one::<PathBuf>("force-font")
+                .cloned()
+                .map(ForceFont::Path)
+                .or_else(|| {
+                    sub_m
+                        .get_one::<String>("force-font-name")
+                        .map(|s| ForceFont::Name(name(s)))
+                }),
+            debug_clip: sub_m
+                .get_one::<bool>("debug-clip")
+                .copied()
+                .unwrap_or_default(),
         }),
         Some(("object", sub_m)) => dump_object(
             sub_m.get_one("filename").unwrap(),
@@ -229,6 +375,12 @@ fn main() {
                 .map_or_else(|| "", |p| p.as_str()),
             *sub_m.get_one::<u32>("object_id").unwrap(),
         ),
+        Some(("xref", sub_m)) => dump_xref(
+            sub_m.get_one("filename").unwrap(),
+            sub_m
+                .get_one::<String>("password")
+                .map_or_else(|| "", |p| p.as_str()),
+        ),
         _ => todo!(),
     }
     .unwrap();
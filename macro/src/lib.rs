@@ -33,7 +33,7 @@ pub fn try_from_name_object(input: TokenStream) -> TokenStream {
 
         impl<'b> crate::graphics::ConvertFromObject<'b> for #t {
             fn convert_from_object(objects: &'b mut Vec<crate::object::Object>) -> Result<Self, crate::object::ObjectValueError> {
-                let o = objects.pop().unwrap();
+                let o = objects.pop().ok_or(crate::object::ObjectValueError::GraphicsOperationSchemaError)?;
                 #t::try_from(&o).map_err(|_| crate::object::ObjectValueError::GraphicsOperationSchemaError)
             }
         }
@@ -79,7 +79,7 @@ pub fn try_from_int_object(input: TokenStream) -> TokenStream {
 
         impl<'b> crate::graphics::ConvertFromObject<'b> for #t {
             fn convert_from_object(objects: &'b mut Vec<crate::object::Object>) -> Result<Self, crate::object::ObjectValueError> {
-                let o = objects.pop().unwrap();
+                let o = objects.pop().ok_or(crate::object::ObjectValueError::GraphicsOperationSchemaError)?;
                 #t::try_from(&o).map_err(|_| crate::object::ObjectValueError::GraphicsOperationSchemaError)
             }
         }
@@ -8,7 +8,7 @@ use educe::Educe;
 use either::Either;
 use log::error;
 use std::{
-    io::{Cursor, SeekFrom},
+    io::{Cursor, ErrorKind, SeekFrom},
     iter::repeat,
 };
 
@@ -78,6 +78,8 @@ pub enum DecodeError {
     // HorizontalRunColorMismatch,
     #[error("Unknown code")]
     InvalidCode,
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
 }
 
 type Result<T> = std::result::Result<T, DecodeError>;
@@ -104,6 +106,8 @@ enum Group4Code {
     Vertical(i8),
     Extension,
     EndOfBlock,
+    /// Twelve zero bits, fill bits before EOL of Group 3 2D line
+    Fill,
 
     NotDef,
 }
@@ -129,7 +133,7 @@ fn build_group4_huffman_tree() -> Box<[ReadHuffmanTree<BigEndian, Group4Code>]>
         (Group4Code::NotDef,       vec![0, 0, 0, 0, 0, 0, 0, 0, 1]),
         (Group4Code::NotDef,       vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
         (Group4Code::NotDef,       vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]),
-        (Group4Code::NotDef,       vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        (Group4Code::Fill,         vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
     ])
     .unwrap()
 }
@@ -506,6 +510,8 @@ struct Group4LineDecoder {
     huffman: RunHuffmanTree,
     group4_huffman: Box<[ReadHuffmanTree<BigEndian, Group4Code>]>,
     color: Color,
+    /// Decode 2D line of Group 3, EOL ends the line
+    group3: bool,
 }
 
 impl Group4LineDecoder {
@@ -514,6 +520,14 @@ impl Group4LineDecoder {
             huffman: build_run_huffman(Algorithm::Group4),
             group4_huffman: build_group4_huffman_tree(),
             color: Color::default(),
+            group3: false,
+        }
+    }
+
+    fn group3() -> Self {
+        Self {
+            group3: true,
+            ..Self::new()
         }
     }
 }
@@ -530,7 +544,12 @@ impl LineDecoder for Group4LineDecoder {
     ) -> Result<ProcessPEResult> {
         use ProcessPEResult::*;
 
+        let start = reader.position_in_bits()?;
         match reader.read_huffman(&self.group4_huffman)? {
+            Group4Code::EndOfBlock | Group4Code::Fill if self.group3 => {
+                reader.seek_bits(SeekFrom::Start(start))?;
+                Ok(Pixels1(line.blank_rest()))
+            }
             Group4Code::Pass => {
                 let b1 = line.last.b1(line.pos, self.color.is_white());
                 let b2 = line.last.next_flip(Some(b1));
@@ -560,9 +579,9 @@ impl LineDecoder for Group4LineDecoder {
             }
             Group4Code::Extension => {
                 let ext_bits: u8 = reader.read(3)?;
-                todo!("Extension ({ext_bits})");
+                Err(DecodeError::Unsupported(format!("extension ({ext_bits})")))
             }
-            Group4Code::NotDef => Err(DecodeError::InvalidCode),
+            Group4Code::Fill | Group4Code::NotDef => Err(DecodeError::InvalidCode),
         }
     }
 }
@@ -581,33 +600,52 @@ impl Group3_1DLineDecoder {
     }
 }
 
-fn read_eol_with_fill_padding(
-    mut zeros_hit: u16,
-    reader: &mut BitReader<Cursor<&[u8]>, BigEndian>,
-) -> Result<()> {
-    while !reader.read_bit()? {
-        zeros_hit += 1;
-    }
-    if zeros_hit < 11 {
-        return Err(DecodeError::InvalidCode);
-    }
-    Ok(())
+/// Peek next `n` bits, None if not enough bits left.
+fn peek_bits(reader: &mut BitReader<Cursor<&[u8]>, BigEndian>, n: u32) -> Result<Option<u16>> {
+    let pos = reader.position_in_bits()?;
+    let r = match reader.read::<u16>(n) {
+        Ok(v) => Some(v),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+        Err(e) => return Err(e.into()),
+    };
+    reader.seek_bits(SeekFrom::Start(pos))?;
+    Ok(r)
 }
 
-/// return true if eol, false if eob
-fn read_eol_or_eob(n_eols: u8, reader: &mut BitReader<Cursor<&[u8]>, BigEndian>) -> Result<bool> {
-    let pos = reader.position_in_bits()?;
-    for _ in 0..n_eols {
-        match reader.read::<u16>(12) {
-            Ok(1) => continue,
-            _ => {
+/// Skip fill bits and EOLs before a Group 3 line, EOLs are optional whether
+/// `/EndOfLine` set or not. For 2D, `two_d` is true, each EOL followed by a
+/// tag bit, the tag bit of the line is not consumed. Returns false if no line
+/// follows: end of data or RTC, several EOLs in a row.
+fn start_group3_line(
+    reader: &mut BitReader<Cursor<&[u8]>, BigEndian>,
+    two_d: bool,
+) -> Result<bool> {
+    let mut eols = 0;
+    loop {
+        match peek_bits(reader, 12)? {
+            Some(0) => reader.skip(1)?,
+            Some(1) => {
+                reader.skip(12)?;
+                eols += 1;
+                if two_d && peek_bits(reader, 13)?.is_some_and(|v| v & 0xfff == 1) {
+                    reader.skip(1)?;
+                }
+            }
+            Some(_) => break,
+            None => {
+                // last line may be shorter than 12 bits, data left if any
+                // bit set
+                let pos = reader.position_in_bits()?;
+                let mut data_left = false;
+                while let Ok(bit) = reader.read_bit() {
+                    data_left |= bit;
+                }
                 reader.seek_bits(SeekFrom::Start(pos))?;
-                return Ok(true);
+                return Ok(data_left && eols < 2);
             }
         }
     }
-    // six continuous EOLs is end of block
-    Ok(false)
+    Ok(eols < 2)
 }
 
 struct Group3_2DLineDecoder {
@@ -632,11 +670,10 @@ impl LineDecoder for Group3_2DLineDecoder {
         line: &LineBuffer<'_>,
     ) -> Result<ProcessPEResult> {
         if !self.use_inner {
-            read_eol_with_fill_padding(0, reader)?;
             self.use_inner = true;
             self.inner = match reader.read_bit()? {
                 true => Either::Left(Group3_1DLineDecoder::new()),
-                false => Either::Right(Group4LineDecoder::new()),
+                false => Either::Right(Group4LineDecoder::group3()),
             };
         }
 
@@ -663,6 +700,7 @@ impl LineDecoder for Group3_1DLineDecoder {
     ) -> Result<ProcessPEResult> {
         use ProcessPEResult::*;
 
+        let start = reader.position_in_bits()?;
         match next_run(reader, &self.huffman, self.color)? {
             PictualElement::Black(n) => {
                 self.color = Color::White;
@@ -672,31 +710,13 @@ impl LineDecoder for Group3_1DLineDecoder {
                 self.color = Color::Black;
                 Ok(Pixels1((Color::White, n)))
             }
-            PictualElement::Eol => {
-                let px = (
-                    Color::White,
-                    (line.last.0.len() - line.pos()).try_into().unwrap(),
-                );
-                if read_eol_or_eob(5, reader)? {
-                    Ok(Pixels1(px))
-                } else {
-                    Ok(EndOfBlock)
-                }
-            }
-            PictualElement::TwelveZeros => {
-                let px = (
-                    Color::White,
-                    (line.last.0.len() - line.pos()).try_into().unwrap(),
-                );
-                read_eol_with_fill_padding(12, reader)?;
-                if read_eol_or_eob(5, reader)? {
-                    Ok(Pixels1(px))
-                } else {
-                    Ok(EndOfBlock)
-                }
+            // EOL ends the line early, left to start of next line
+            PictualElement::Eol | PictualElement::TwelveZeros => {
+                reader.seek_bits(SeekFrom::Start(start))?;
+                Ok(Pixels1(line.blank_rest()))
             }
             PictualElement::NotDef(n) => unreachable!("NotDef({n})"),
-            c => todo!("{:?}", c),
+            c => Err(DecodeError::Unsupported(format!("{:?}", c))),
         }
     }
 }
@@ -726,6 +746,15 @@ impl<'a> LineBuffer<'a> {
         self.pos.unwrap_or_default() as usize
     }
 
+    /// White pixels to fill rest of the line, such as line ended by EOL
+    /// before all columns decoded.
+    fn blank_rest(&self) -> Pixels {
+        (
+            Color::White,
+            (self.last.0.len() - self.pos()).try_into().unwrap(),
+        )
+    }
+
     pub fn push_pixels(&mut self, color: Color, counts: u16) {
         let pos = self.pos();
         for i in pos..(pos + counts as usize) {
@@ -767,10 +796,15 @@ impl Decoder {
             repeat(true).take(self.width as usize).collect(),
         );
         let mut reader = BitReader::endian(Cursor::new(buf), BigEndian);
+        let group3 = self.algorithm != Algorithm::Group4;
+        let two_d = matches!(self.algorithm, Algorithm::Group3_2D(_));
         loop {
             if self.flags.encoded_byte_align {
                 reader.byte_align();
             }
+            if group3 && !start_group3_line(&mut reader, two_d)? {
+                break;
+            }
 
             let finished = match ld.decode_line(&mut reader, &mut line_buffer)? {
                 DecodeLineResult::LineFullfilled => false,
@@ -790,6 +824,13 @@ impl Decoder {
             line_buffer = LineBuffer::new(&r[r.len() - self.width as usize..], line);
             ld.reset();
         }
+        // missing rows are blank
+        if let Some(rows) = self.rows {
+            let len = rows as usize * self.width as usize;
+            if r.len() < len {
+                r.resize(len, true);
+            }
+        }
         Ok(r)
     }
 
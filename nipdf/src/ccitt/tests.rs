@@ -126,6 +126,58 @@ fn group3_2d() {
     insta::assert_debug_snapshot!(decoder.decode(include_bytes!("group3-2d")).unwrap());
 }
 
+#[test]
+fn group3_1d_eol_byte_align() {
+    let flags = Flags {
+        encoded_byte_align: true,
+        ..Default::default()
+    };
+    let decoder = Decoder {
+        algorithm: Algorithm::Group3_1D,
+        flags,
+        width: 16,
+        rows: Some(4),
+    };
+    // each line begins with EOL, second line ended by EOL after 4 black
+    // pixels, ends with RTC
+    let data = decoder.decode(include_bytes!("group3-1d-eol")).unwrap();
+    assert_eq!(vec![0xf0, 0x0f, 0x0f, 0xff, 0xcf, 0xff, 0x00, 0x00], data);
+}
+
+#[test]
+fn group3_2d_without_eol() {
+    let flags = Flags {
+        end_of_block: false,
+        ..Default::default()
+    };
+    let decoder = Decoder {
+        algorithm: Algorithm::Group3_2D(2),
+        flags,
+        width: 16,
+        rows: Some(4),
+    };
+    // 1D lines followed by 2D lines same as the 1D ones, tag bits only
+    let data = decoder
+        .decode(include_bytes!("group3-mixed-no-eol"))
+        .unwrap();
+    assert_eq!(vec![0xf0, 0x0f, 0xf0, 0x0f, 0xcf, 0xff, 0xcf, 0xff], data);
+}
+
+#[test]
+fn missing_rows_blank() {
+    let decoder = Decoder {
+        algorithm: Algorithm::Group3_1D,
+        flags: Flags {
+            encoded_byte_align: true,
+            ..Default::default()
+        },
+        width: 16,
+        rows: Some(6),
+    };
+    let data = decoder.decode(include_bytes!("group3-1d-eol")).unwrap();
+    assert_eq!(&[0xff; 4], &data[8..]);
+}
+
 #[test_case(Color::White, 0, &[0b0011_0101] ; "white 0")]
 #[test_case(Color::White, 1, &[0b0001_1100] ; "white 1")]
 #[test_case(Color::White, 64, &[0b1101_1001, 0b1010_1000]; "white 64")]
@@ -1,20 +1,21 @@
 //! Contains types of PDF file structures.
 
 use crate::{
-    file::encrypt::Authorizer,
+    file::encrypt::{Aes256Authorizer, Authorizer, StandardHandlerRevision},
     object::{
-        Array, Dictionary, Entry, FrameSet, HexString, LiteralString, Object, ObjectId,
-        ObjectValueError, PdfObject, Resolver, RuntimeObjectId, Stream, TrailerDict,
+        Array, Dictionary, Entry, FilePos, FilterRegistry, FrameSet, HexString, LiteralString,
+        Object, ObjectId, ObjectValueError, PdfObject, Resolver, RuntimeObjectId, Stream,
+        TrailerDict,
     },
     parser::{
         ParseResult, parse_frame_set, parse_header, parse_indirect_object, parse_indirect_stream,
         parse_object, ws_terminated,
     },
 };
-use ahash::{HashMap, HashMapExt};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use anyhow::Result as AnyResult;
 use either::Either;
-use log::error;
+use log::{error, warn};
 use nipdf_macro::pdf_object;
 use nom::Finish;
 use once_cell::unsync::OnceCell;
@@ -23,6 +24,15 @@ use std::{iter::repeat_with, rc::Rc};
 
 pub mod page;
 pub use page::*;
+mod resolved;
+pub use resolved::ResolvedFile;
+mod optional_content;
+pub use optional_content::OptionalContent;
+mod outline;
+pub use outline::{OutlineFlags, OutlineItem};
+mod acro_form;
+pub use acro_form::{AcroFormDict, FieldFlags, FieldType, FieldValue, FormField};
+mod save;
 
 pub(crate) mod encrypt;
 
@@ -31,14 +41,15 @@ pub use encrypt::EncryptDict;
 
 #[derive(Debug, Copy, Clone)]
 pub enum ObjectPos {
-    Offset(u32),
+    /// (offset, generation)
+    Offset(u32, u16),
     InStream(RuntimeObjectId, u16),
 }
 
 impl<'a> From<&'a Entry> for ObjectPos {
     fn from(e: &'a Entry) -> Self {
         match e {
-            Entry::InFile(pos) => ObjectPos::Offset(pos.offset()),
+            Entry::InFile(pos) => ObjectPos::Offset(pos.offset(), pos.generation()),
             Entry::InStream(id, idx) => ObjectPos::InStream(*id, *idx),
         }
     }
@@ -99,6 +110,8 @@ impl ObjectStream {
 #[derive(Debug)]
 pub struct XRefTable {
     id_offset: IDOffsetMap,
+    /// Free entries, only for diagnostics
+    free: HashMap<RuntimeObjectId, FilePos>,
     // object id -> offset
     object_streams: HashMap<RuntimeObjectId, OnceCell<ObjectStream>>, // stream id -> ObjectStream
 }
@@ -119,6 +132,7 @@ impl XRefTable {
 
         Self {
             id_offset,
+            free: HashMap::new(),
             object_streams: object_stream,
         }
     }
@@ -127,6 +141,7 @@ impl XRefTable {
     pub fn empty() -> Self {
         Self {
             id_offset: IDOffsetMap::default(),
+            free: HashMap::new(),
             object_streams: HashMap::new(),
         }
     }
@@ -148,26 +163,38 @@ impl XRefTable {
                 .unwrap()
                 .try_into()
                 .unwrap();
-            id_offset.insert(o.id().id(), ObjectPos::Offset(pos));
+            id_offset.insert(o.id().id(), ObjectPos::Offset(pos, o.id().generation()));
         }
 
         Self::new(id_offset)
     }
 
-    fn scan(frame_set: &FrameSet) -> IDOffsetMap {
+    /// Returns used entries and free entries, newer frame overrides older.
+    fn scan(frame_set: &FrameSet) -> (IDOffsetMap, HashMap<RuntimeObjectId, FilePos>) {
         let mut r = IDOffsetMap::with_capacity(5000);
+        let mut free = HashMap::new();
         for (id, entry) in frame_set.iter().rev().flat_map(|f| f.xref_section.iter()) {
-            if entry.is_used() {
-                r.insert(RuntimeObjectId(*id), entry.into());
-            } else if *id != 0 {
-                r.remove(id);
+            let id = RuntimeObjectId(*id);
+            match entry {
+                Entry::InFile(pos) if !pos.is_used() => {
+                    r.remove(&id);
+                    free.insert(id, *pos);
+                }
+                _ => {
+                    r.insert(id, entry.into());
+                    free.remove(&id);
+                }
             }
         }
-        r
+        (r, free)
     }
 
     pub fn from_frame_set(frame_set: &FrameSet) -> Self {
-        Self::new(Self::scan(frame_set))
+        let (id_offset, free) = Self::scan(frame_set);
+        Self {
+            free,
+            ..Self::new(id_offset)
+        }
     }
 
     /// Return `buf` start from where `id` is
@@ -178,7 +205,7 @@ impl XRefTable {
         encrypt_info: Option<&EncryptInfo>,
     ) -> Option<Either<&'a [u8], &'b [u8]>> {
         self.id_offset.get(&id.into()).map(|entry| match entry {
-            ObjectPos::Offset(offset) => Either::Left(&buf[*offset as usize..]),
+            ObjectPos::Offset(offset, _) => Either::Left(&buf[*offset as usize..]),
             ObjectPos::InStream(id, idx) => {
                 let object_stream = self.object_streams[id]
                     .get_or_try_init(|| {
@@ -218,16 +245,16 @@ impl XRefTable {
                     |buf| {
                         parse_indirect_object(buf)
                             .finish()
-                            .map(|(_, o)| {
+                            .map_err(ObjectValueError::from)
+                            .and_then(|(_, o)| {
                                 let id = o.id();
                                 let o = o.take();
                                 if let Some(encrypt_info) = encrypt_info {
                                     decrypt_string(encrypt_info, id, o)
                                 } else {
-                                    o
+                                    Ok(o)
                                 }
                             })
-                            .map_err(ObjectValueError::from)
                     },
                     |buf| {
                         parse_object(buf)
@@ -239,6 +266,14 @@ impl XRefTable {
             })
     }
 
+    /// Generation number of object `id`, objects in object stream are always 0.
+    pub fn generation(&self, id: impl Into<RuntimeObjectId>) -> Option<u16> {
+        self.id_offset.get(&id.into()).map(|pos| match pos {
+            ObjectPos::Offset(_, generation) => *generation,
+            ObjectPos::InStream(..) => 0,
+        })
+    }
+
     pub fn iter_ids(&self) -> impl Iterator<Item = RuntimeObjectId> + '_ {
         self.id_offset.keys().copied()
     }
@@ -246,52 +281,82 @@ impl XRefTable {
     pub fn count(&self) -> usize {
         self.id_offset.len()
     }
+
+    /// All entries sorted by object id, free entries included, for
+    /// diagnostics. Free entries of xref stream have offset 0.
+    pub fn entries(&self) -> Vec<(RuntimeObjectId, Entry)> {
+        let mut r: Vec<_> = self
+            .id_offset
+            .iter()
+            .map(|(id, pos)| {
+                (*id, match pos {
+                    ObjectPos::Offset(offset, generation) => {
+                        Entry::in_file(*offset, *generation, true)
+                    }
+                    ObjectPos::InStream(stream_id, idx) => Entry::in_stream(*stream_id, *idx),
+                })
+            })
+            .chain(self.free.iter().map(|(id, pos)| (*id, Entry::InFile(*pos))))
+            .collect();
+        r.sort_by_key(|(id, _)| id.0);
+        r
+    }
 }
 
 /// Decrypt HexString/LiteralString nested in object.
-fn decrypt_string(encrypt_info: &EncryptInfo, id: ObjectId, mut o: Object) -> Object {
+fn decrypt_string(
+    encrypt_info: &EncryptInfo,
+    id: ObjectId,
+    mut o: Object,
+) -> Result<Object, ObjectValueError> {
     struct Decryptor<'a>(&'a EncryptInfo, ObjectId);
 
     impl<'a> Decryptor<'a> {
-        fn hex_string(&self, s: &mut HexString) {
-            self.0.string_decrypt(self.1, &mut s.0);
+        fn hex_string(&self, s: &mut HexString) -> Result<(), ObjectValueError> {
+            self.0.string_decrypt(self.1, &mut s.0)
         }
 
-        fn literal_string(&self, s: &mut LiteralString) {
-            self.0.string_decrypt(self.1, &mut s.0);
+        fn literal_string(&self, s: &mut LiteralString) -> Result<(), ObjectValueError> {
+            self.0.string_decrypt(self.1, &mut s.0)
         }
 
-        fn dict(&self, dict: &mut Dictionary) {
+        fn dict(&self, dict: &mut Dictionary) -> Result<(), ObjectValueError> {
+            let mut r = Ok(());
             dict.update(|d| {
-                for (_, v) in d.iter_mut() {
-                    self.decrypt(v);
-                }
-            })
+                r = d.values_mut().try_for_each(|v| self.decrypt(v));
+            });
+            r
         }
 
-        fn arr(&self, arr: &mut Array) {
-            Object::update_array_items(arr, |o| self.decrypt(o));
+        fn arr(&self, arr: &mut Array) -> Result<(), ObjectValueError> {
+            let mut r = Ok(());
+            Object::update_array_items(arr, |o| {
+                if r.is_ok() {
+                    r = self.decrypt(o);
+                }
+            });
+            r
         }
 
-        fn stream(&self, stream: &mut Rc<Stream>) {
+        fn stream(&self, stream: &mut Rc<Stream>) -> Result<(), ObjectValueError> {
             let stream = Rc::make_mut(stream);
-            self.dict(&mut stream.0);
+            self.dict(&mut stream.0)
         }
 
-        fn decrypt(&self, o: &mut Object) {
+        fn decrypt(&self, o: &mut Object) -> Result<(), ObjectValueError> {
             match o {
                 Object::HexString(s) => self.hex_string(s),
                 Object::LiteralString(s) => self.literal_string(s),
                 Object::Dictionary(d) => self.dict(d),
                 Object::Array(arr) => self.arr(arr),
                 Object::Stream(s) => self.stream(s),
-                _ => {}
+                _ => Ok(()),
             }
         }
     }
 
-    Decryptor(encrypt_info, id).decrypt(&mut o);
-    o
+    Decryptor(encrypt_info, id).decrypt(&mut o)?;
+    Ok(o)
 }
 
 pub trait DataContainer {
@@ -325,13 +390,22 @@ impl EncryptInfo {
         }
     }
 
-    pub fn stream_decrypt(&self, filter: Option<Name>, id: ObjectId, data: &mut Vec<u8>) {
+    pub fn stream_decrypt(
+        &self,
+        filter: Option<Name>,
+        id: ObjectId,
+        data: &mut Vec<u8>,
+    ) -> Result<(), ObjectValueError> {
         self.filters
             .stream_filter(filter)
             .decrypt(&self.encript_key, id, data)
     }
 
-    pub fn string_decrypt(&self, id: ObjectId, data: &mut impl VecLike) {
+    pub fn string_decrypt(
+        &self,
+        id: ObjectId,
+        data: &mut impl VecLike,
+    ) -> Result<(), ObjectValueError> {
         self.filters
             .string_filter()
             .decrypt(&self.encript_key, id, data)
@@ -343,6 +417,7 @@ pub struct ObjectResolver<'a> {
     xref_table: &'a XRefTable,
     objects: HashMap<RuntimeObjectId, OnceCell<Object>>,
     encript_info: Option<EncryptInfo>,
+    filter_registry: Option<Rc<FilterRegistry>>,
 }
 
 impl<'a> ObjectResolver<'a> {
@@ -361,6 +436,7 @@ impl<'a> ObjectResolver<'a> {
             xref_table,
             objects,
             encript_info,
+            filter_registry: None,
         }
     }
 
@@ -368,6 +444,15 @@ impl<'a> ObjectResolver<'a> {
         self.encript_info.as_ref()
     }
 
+    /// Decode streams with custom filters in `registry` before built-in ones.
+    pub fn set_filter_registry(&mut self, registry: Rc<FilterRegistry>) {
+        self.filter_registry = Some(registry);
+    }
+
+    pub(crate) fn filter_registry(&self) -> Option<&FilterRegistry> {
+        self.filter_registry.as_deref()
+    }
+
     /// Return total objects count.
     #[allow(dead_code)]
     pub fn n(&self) -> usize {
@@ -381,6 +466,7 @@ impl<'a> ObjectResolver<'a> {
             xref_table,
             objects: HashMap::default(),
             encript_info: None,
+            filter_registry: None,
         }
     }
 
@@ -389,6 +475,56 @@ impl<'a> ObjectResolver<'a> {
         self.objects.insert(id.into(), OnceCell::with_value(v));
     }
 
+    /// Ids of indirect objects referenced from `roots`, collected transitively.
+    /// Dictionary entries are followed only if `follow_key` returns true,
+    /// missing objects are ignored.
+    pub fn referenced_object_ids<'c>(
+        &'c self,
+        roots: impl IntoIterator<Item = &'c Object>,
+        follow_key: impl Fn(&Name) -> bool,
+    ) -> Result<HashSet<RuntimeObjectId>, ObjectValueError> {
+        let mut r = HashSet::new();
+        let mut pending: Vec<&Object> = roots.into_iter().collect();
+        while let Some(o) = pending.pop() {
+            let d = match o {
+                Object::Reference(reference) => {
+                    let id = reference.id().id();
+                    if r.insert(id) {
+                        match self.resolve(id) {
+                            Ok(o) => pending.push(o),
+                            Err(ObjectValueError::ObjectIDNotFound(_)) => {}
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    continue;
+                }
+                Object::Array(arr) => {
+                    pending.extend(arr.iter());
+                    continue;
+                }
+                Object::Dictionary(d) => d,
+                Object::Stream(s) => s.as_dict(),
+                _ => continue,
+            };
+            pending.extend(d.iter().filter(|(k, _)| follow_key(k)).map(|(_, v)| v));
+        }
+        Ok(r)
+    }
+
+    /// Iterate all indirect objects in id order, yields `(id, generation, object)`.
+    /// Objects stored in object streams are included, object streams are parsed
+    /// on demand.
+    pub fn iter_objects(
+        &self,
+    ) -> impl Iterator<Item = Result<(RuntimeObjectId, u16, &Object), ObjectValueError>> + '_ {
+        let mut ids = self.objects.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable_by_key(|id| id.0);
+        ids.into_iter().map(|id| {
+            let generation = self.xref_table.generation(id).unwrap_or_default();
+            self.resolve(id).map(|o| (id, generation, o))
+        })
+    }
+
     /// Resolve pdf object from object, if object is dict, use it as pdf object,
     /// if object is reference, resolve it
     pub fn resolve_pdf_object2<'b, T: PdfObject<'b, Self>>(
@@ -471,12 +607,37 @@ impl<'a> ObjectResolver<'a> {
         let obj = c.get_value(id).ok_or(ObjectValueError::DictKeyNotFound)?;
 
         if let Object::Reference(id) = obj {
-            self.resolve(id.id().id()).map(|o| (Some(id.id().id()), o))
+            self.resolve_chain(id.id().id())
+                .map(|(id, o)| (Some(id), o))
         } else {
             Ok((None, obj))
         }
     }
 
+    /// Resolve object `id`, if it is a reference, follow the references until
+    /// a non-reference object, returns it with its id.
+    /// Returns `CyclicReference` error if references loop back.
+    fn resolve_chain(
+        &self,
+        id: RuntimeObjectId,
+    ) -> Result<(RuntimeObjectId, &Object), ObjectValueError> {
+        // reference chain is normally one or two hops, Vec is faster than HashSet
+        let mut visited = vec![id];
+        let mut id = id;
+        loop {
+            match self.resolve(id)? {
+                Object::Reference(r) => {
+                    id = r.id().id();
+                    if visited.contains(&id) {
+                        return Err(ObjectValueError::CyclicReference(id));
+                    }
+                    visited.push(id);
+                }
+                o => return Ok((id, o)),
+            }
+        }
+    }
+
     /// Resolve pdf_object by id, if its end value is dictionary, return with one element vec.
     /// If its end value is array, return all elements in array.
     pub fn resolve_one_or_more_pdf_object<'b, T: PdfObject<'b, Self>>(
@@ -525,7 +686,7 @@ impl<'a> Resolver for ObjectResolver<'a> {
 
     fn resolve_reference<'b>(&'b self, v: &'b Object) -> Result<&'b Object, ObjectValueError> {
         if let Object::Reference(id) = v {
-            self.resolve(id.id().id())
+            self.resolve_chain(id.id().id()).map(|(_, o)| o)
         } else {
             Ok(v)
         }
@@ -537,6 +698,270 @@ trait CatalogDictTrait {
     fn version(&self) -> Option<Name>;
     #[nested]
     fn pages(&self) -> PageDict<'a, 'b>;
+    fn struct_tree_root(&self) -> Option<&'b Dictionary>;
+    fn dests(&self) -> Option<&'b Dictionary>;
+    fn names(&self) -> Option<&'b Dictionary>;
+    #[key("OCProperties")]
+    fn oc_properties(&self) -> Option<&'b Dictionary>;
+    fn collection(&self) -> Option<&'b Dictionary>;
+    #[key("AcroForm")]
+    fn acro_form(&self) -> Option<&'b Dictionary>;
+    fn outlines(&self) -> Option<&'b Dictionary>;
+}
+
+/// Optional content group (layer), see PDF 32000-1:2008 8.11.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pub id: RuntimeObjectId,
+    pub name: String,
+    /// Visibility in default configuration `/D` of `/OCProperties`.
+    pub visible: bool,
+}
+
+/// Walk name tree (`entries` is `Names`) or number tree (`Nums`) `node` in
+/// key order, see PDF 32000-1:2008 7.9.6 and 7.9.7. Kids whose `/Limits`
+/// rejected by `in_range` are skipped, `f` called with each key and value,
+/// walking stops if it returns true. Nodes visited twice are skipped to break
+/// reference cycles. Returns true if stopped by `f`.
+fn walk_tree<'b>(
+    resolver: &'b ObjectResolver,
+    node: &'b Dictionary,
+    entries: &'static str,
+    in_range: &impl Fn(&'b Object, &'b Object) -> Result<bool, ObjectValueError>,
+    f: &mut impl FnMut(&'b Object, &'b Object) -> Result<bool, ObjectValueError>,
+    visited: &mut HashSet<RuntimeObjectId>,
+) -> Result<bool, ObjectValueError> {
+    if let Some(pairs) = resolver.opt_resolve_container_value(node, &sname(entries))? {
+        for pair in pairs.arr()?.chunks_exact(2) {
+            if f(&pair[0], &pair[1])? {
+                return Ok(true);
+            }
+        }
+    }
+
+    let Some(kids) = resolver.opt_resolve_container_value(node, &sname("Kids"))? else {
+        return Ok(false);
+    };
+    for kid in kids.arr()?.iter() {
+        if !visit_once(kid, visited) {
+            warn!("tree node {:?} visited twice", kid);
+            continue;
+        }
+        let kid = resolver.resolve_reference(kid)?.as_dict()?;
+        // `/Limits` of less than two keys ignored
+        if let Some(limits) = resolver.opt_resolve_container_value(kid, &sname("Limits"))? {
+            if let [lo, hi, ..] = &limits.arr()?[..] {
+                if !in_range(lo, hi)? {
+                    continue;
+                }
+            }
+        }
+        if walk_tree(resolver, kid, entries, in_range, f, visited)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Lookup value of `key` in name tree `node`, see PDF 32000-1:2008 7.9.6
+fn lookup_name_tree<'b>(
+    resolver: &'b ObjectResolver,
+    node: &'b Dictionary,
+    key: &[u8],
+) -> Result<Option<&'b Object>, ObjectValueError> {
+    let mut r = None;
+    walk_tree(
+        resolver,
+        node,
+        "Names",
+        &|lo, hi| Ok(lo.as_byte_string()? <= key && key <= hi.as_byte_string()?),
+        &mut |k, v| {
+            let found = k.as_byte_string()? == key;
+            r = found.then_some(v);
+            Ok(found)
+        },
+        &mut HashSet::new(),
+    )?;
+    r.map(|v| resolver.resolve_reference(v)).transpose()
+}
+
+/// Collect all key value pairs of name tree `node` in key order.
+fn collect_name_tree<'b>(
+    resolver: &'b ObjectResolver,
+    node: &'b Dictionary,
+    r: &mut Vec<(&'b [u8], &'b Object)>,
+) -> Result<(), ObjectValueError> {
+    walk_tree(
+        resolver,
+        node,
+        "Names",
+        &|_, _| Ok(true),
+        &mut |k, v| {
+            r.push((k.as_byte_string()?, resolver.resolve_reference(v)?));
+            Ok(false)
+        },
+        &mut HashSet::new(),
+    )?;
+    Ok(())
+}
+
+/// Decode text string, UTF-16BE if starts with BOM, otherwise treated as
+/// UTF-8 which is compatible with PDFDocEncoding for ASCII.
+pub(crate) fn decode_text_string(s: &[u8]) -> String {
+    match s.strip_prefix(b"\xFE\xFF") {
+        Some(s) => String::from_utf16_lossy(
+            &s.chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        None => String::from_utf8_lossy(s).into_owned(),
+    }
+}
+
+/// Lookup value of `key` in number tree `node`, see PDF 32000-1:2008 7.9.7
+fn lookup_number_tree<'b>(
+    resolver: &'b ObjectResolver,
+    node: &'b Dictionary,
+    key: i32,
+) -> Result<Option<&'b Object>, ObjectValueError> {
+    let mut r = None;
+    walk_tree(
+        resolver,
+        node,
+        "Nums",
+        &|lo, hi| Ok(lo.int()? <= key && key <= hi.int()?),
+        &mut |k, v| {
+            let found = k.int()? == key;
+            r = found.then_some(v);
+            Ok(found)
+        },
+        &mut HashSet::new(),
+    )?;
+    r.map(|v| resolver.resolve_reference(v)).transpose()
+}
+
+/// JavaScript source of an action, see [`Catalog::document_javascript()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaScript {
+    /// Name tree key of document level script, or trigger key of additional
+    /// actions `/AA`, such as `K` for keystroke, `PO` for page open.
+    pub name: String,
+    pub source: String,
+}
+
+/// True if `o` is not a reference, or referenced object not in `visited`,
+/// the object id added to `visited`.
+fn visit_once(o: &Object, visited: &mut HashSet<RuntimeObjectId>) -> bool {
+    match o {
+        Object::Reference(r) => visited.insert(r.id().id()),
+        _ => true,
+    }
+}
+
+/// Collect source of JavaScript `action` and actions chained by its `/Next`,
+/// see PDF 32000-1:2008 12.6.4.16. Script is a text string or stream.
+/// Actions visited twice are skipped to break reference cycles.
+fn collect_javascript(
+    resolver: &ObjectResolver,
+    action: &Object,
+    visited: &mut HashSet<RuntimeObjectId>,
+    r: &mut Vec<String>,
+) -> Result<(), ObjectValueError> {
+    if !visit_once(action, visited) {
+        warn!("action {:?} visited twice", action);
+        return Ok(());
+    }
+    let action = resolver.resolve_reference(action)?.as_dict()?;
+    let is_js = resolver
+        .opt_resolve_container_value(action, &sname("S"))?
+        .is_some_and(|s| s.name().is_ok_and(|s| s == sname("JavaScript")));
+    if is_js {
+        if let Some(js) = resolver.opt_resolve_container_value(action, &sname("JS"))? {
+            r.push(match js {
+                Object::Stream(s) => decode_text_string(&s.decode(resolver)?),
+                js => decode_text_string(js.as_byte_string()?),
+            });
+        }
+    }
+
+    match resolver.opt_resolve_container_value(action, &sname("Next"))? {
+        Some(Object::Array(next)) => {
+            for a in next.iter() {
+                collect_javascript(resolver, a, visited, r)?;
+            }
+        }
+        Some(next) => collect_javascript(resolver, next, visited, r)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// JavaScript of additional actions `/AA` of annotation, form field or page,
+/// in trigger key order.
+pub(crate) fn additional_actions_javascript(
+    resolver: &ObjectResolver,
+    d: &Dictionary,
+) -> Result<Vec<JavaScript>, ObjectValueError> {
+    let Some(aa) = resolver.opt_resolve_container_value(d, &sname("AA"))? else {
+        return Ok(vec![]);
+    };
+    let mut triggers: Vec<_> = aa.as_dict()?.iter().collect();
+    triggers.sort_unstable_by_key(|(k, _)| k.as_str());
+    let mut r = vec![];
+    for (trigger, action) in triggers {
+        let mut scripts = vec![];
+        collect_javascript(resolver, action, &mut HashSet::new(), &mut scripts)?;
+        r.extend(scripts.into_iter().map(|source| JavaScript {
+            name: trigger.as_str().to_owned(),
+            source,
+        }));
+    }
+    Ok(r)
+}
+
+/// Collect JavaScript of `/AA` of form field `field` and its kids, with
+/// fully qualified field name, see PDF 32000-1:2008 12.7.3.2. Fields
+/// visited twice are skipped to break reference cycles.
+fn collect_field_javascript(
+    resolver: &ObjectResolver,
+    field: &Object,
+    parent_name: &str,
+    visited: &mut HashSet<RuntimeObjectId>,
+    r: &mut Vec<(String, JavaScript)>,
+) -> Result<(), ObjectValueError> {
+    if !visit_once(field, visited) {
+        warn!("form field {:?} visited twice", field);
+        return Ok(());
+    }
+    let field = resolver.resolve_reference(field)?.as_dict()?;
+    // widget annotation kids have no `/T`, and share name of their field
+    let name = match resolver.opt_resolve_container_value(field, &sname("T"))? {
+        Some(t) if parent_name.is_empty() => decode_text_string(t.as_byte_string()?),
+        Some(t) => format!(
+            "{}.{}",
+            parent_name,
+            decode_text_string(t.as_byte_string()?)
+        ),
+        None => parent_name.to_owned(),
+    };
+    r.extend(
+        additional_actions_javascript(resolver, field)?
+            .into_iter()
+            .map(|js| (name.clone(), js)),
+    );
+    if let Some(kids) = resolver.opt_resolve_container_value(field, &sname("Kids"))? {
+        for kid in kids.arr()?.iter() {
+            collect_field_javascript(resolver, kid, &name, visited, r)?;
+        }
+    }
+    Ok(())
+}
+
+/// Embedded document of PDF Portfolio, see [`Catalog::collection_documents()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedFile {
+    pub name: String,
+    pub data: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -558,13 +983,268 @@ impl<'a, 'b: 'a> Catalog<'a, 'b> {
         Page::parse(self.d.pages().unwrap())
     }
 
+    /// Count of pages by `/Count` of page tree root, without walking the
+    /// tree.
+    pub fn page_count(&self) -> AnyResult<usize> {
+        self.d.pages()?.page_count()
+    }
+
+    /// Page at `index`, without loading other pages, see `page_count()`.
+    pub fn page_at(&self, index: usize) -> AnyResult<Page<'a, 'b>> {
+        Page::parse_at(self.d.pages()?, index)
+    }
+
     pub fn ver(&self) -> Option<Name> {
         self.d.version().unwrap()
     }
+
+    /// Map MCIDs of marked-content sequences in `page` to the structure
+    /// elements they belong to, resolved by `/StructParents` of the page in
+    /// `/ParentTree` of the structure tree root.
+    ///
+    /// Returns empty map if the document is not tagged, or page has no
+    /// `/StructParents`.
+    pub fn struct_elements(&self, page: &Page) -> AnyResult<HashMap<i32, RuntimeObjectId>> {
+        let mut r = HashMap::new();
+        let (Some(root), Some(key)) = (self.d.struct_tree_root()?, page.struct_parents()) else {
+            return Ok(r);
+        };
+        let resolver = self.d.resolver();
+        let Some(parent_tree) = resolver.opt_resolve_container_value(root, &sname("ParentTree"))?
+        else {
+            return Ok(r);
+        };
+        let Some(elements) = lookup_number_tree(resolver, parent_tree.as_dict()?, key)? else {
+            return Ok(r);
+        };
+
+        // value of page in parent tree is an array of structure elements,
+        // indexed by MCID
+        let elements = elements.arr()?;
+        for mcid in page.marked_content_ids()? {
+            let element = usize::try_from(mcid).ok().and_then(|i| elements.get(i));
+            if let Some(Object::Reference(id)) = element {
+                r.insert(mcid, id.id().id());
+            }
+        }
+        Ok(r)
+    }
+
+    /// Resolve destination to target page id and view, `dest` can be an
+    /// explicit destination array, a name in catalog `/Dests`, or a string in
+    /// `/Dests` name tree of catalog `/Names`, see PDF 32000-1:2008 12.3.2.
+    ///
+    /// Returns None if named destination not found, or destination refers to
+    /// page by number (only valid for remote go-to actions).
+    pub fn resolve_dest(&self, dest: &Object) -> AnyResult<Option<(RuntimeObjectId, DestView)>> {
+        let resolver = self.d.resolver();
+        let dest = resolver.resolve_reference(dest)?;
+        let dest = match dest {
+            Object::Array(_) => Some(dest),
+            Object::Name(name) => match self.d.dests()? {
+                Some(dests) => resolver.opt_resolve_container_value(dests, name)?,
+                None => None,
+            },
+            Object::LiteralString(_) | Object::HexString(_) => {
+                let tree = match self.d.names()? {
+                    Some(names) => resolver.opt_resolve_container_value(names, &sname("Dests"))?,
+                    None => None,
+                };
+                match tree {
+                    Some(tree) => {
+                        lookup_name_tree(resolver, tree.as_dict()?, dest.as_byte_string()?)?
+                    }
+                    None => None,
+                }
+            }
+            _ => return Err(ObjectValueError::UnexpectedType.into()),
+        };
+        let Some(dest) = dest else {
+            return Ok(None);
+        };
+
+        // value of named destination can be a dictionary, destination in `/D`
+        let dest = match dest {
+            Object::Dictionary(d) => resolver.resolve_container_value(d, &sname("D"))?,
+            _ => dest,
+        };
+        let dest = dest.arr()?;
+        let Some(Object::Reference(page)) = dest.first() else {
+            return Ok(None);
+        };
+        Ok(Some((page.id().id(), DestView::parse(&dest[1..])?)))
+    }
+
+    /// Embedded PDF documents of PDF Portfolio, whose catalog has
+    /// `/Collection`. Documents are file specifications in `/EmbeddedFiles`
+    /// name tree of catalog `/Names`, see PDF 32000-1:2008 12.3.5 and 7.11.4.
+    ///
+    /// Returns empty vec if the document is not a portfolio, embedded files
+    /// not PDF are skipped.
+    pub fn collection_documents(&self) -> AnyResult<Vec<EmbeddedFile>> {
+        if self.d.collection()?.is_none() {
+            return Ok(vec![]);
+        }
+        let Some(names) = self.d.names()? else {
+            return Ok(vec![]);
+        };
+        let resolver = self.d.resolver();
+        let Some(tree) = resolver.opt_resolve_container_value(names, &sname("EmbeddedFiles"))?
+        else {
+            return Ok(vec![]);
+        };
+        let mut files = vec![];
+        collect_name_tree(resolver, tree.as_dict()?, &mut files)?;
+
+        let mut r = vec![];
+        for (key, spec) in files {
+            let spec = spec.as_dict()?;
+            let Some(ef) = resolver.opt_resolve_container_value(spec, &sname("EF"))? else {
+                continue;
+            };
+            let ef = ef.as_dict()?;
+            let stream = match resolver.opt_resolve_container_value(ef, &sname("UF"))? {
+                Some(s) => s,
+                None => resolver.resolve_container_value(ef, &sname("F"))?,
+            };
+            let data = stream.stream()?.decode(resolver)?;
+            // tolerate garbage before header
+            if memchr::memmem::find(&data[..data.len().min(1024)], b"%PDF-").is_none() {
+                continue;
+            }
+
+            // prefer unicode file name, fallback to name tree key
+            let mut name = key;
+            for k in ["UF", "F"] {
+                if let Some(v) = resolver.opt_resolve_container_value(spec, &sname(k))? {
+                    name = v.as_byte_string()?;
+                    break;
+                }
+            }
+            r.push(EmbeddedFile {
+                name: decode_text_string(name),
+                data: data.into_owned(),
+            });
+        }
+        Ok(r)
+    }
+
+    /// Document level JavaScript in `/JavaScript` name tree of catalog
+    /// `/Names`, executed when the document opened, see PDF 32000-1:2008
+    /// 12.6.4.16. Scripts are extracted only, not executed.
+    pub fn document_javascript(&self) -> AnyResult<Vec<JavaScript>> {
+        let Some(names) = self.d.names()? else {
+            return Ok(vec![]);
+        };
+        let resolver = self.d.resolver();
+        let Some(tree) = resolver.opt_resolve_container_value(names, &sname("JavaScript"))? else {
+            return Ok(vec![]);
+        };
+        let mut actions = vec![];
+        collect_name_tree(resolver, tree.as_dict()?, &mut actions)?;
+
+        let mut r = vec![];
+        for (key, action) in actions {
+            let mut scripts = vec![];
+            collect_javascript(resolver, action, &mut HashSet::new(), &mut scripts)?;
+            r.extend(scripts.into_iter().map(|source| JavaScript {
+                name: decode_text_string(key),
+                source,
+            }));
+        }
+        Ok(r)
+    }
+
+    /// JavaScript of additional actions `/AA` of interactive form fields in
+    /// `/AcroForm`, paired with fully qualified field name. Widget
+    /// annotations not reachable from `/Fields` are not included, see
+    /// [`AnnotationDict::additional_javascript()`].
+    ///
+    /// [`AnnotationDict::additional_javascript()`]: crate::file::page::AnnotationDict::additional_javascript
+    pub fn field_javascript(&self) -> AnyResult<Vec<(String, JavaScript)>> {
+        let Some(acro_form) = self.d.acro_form()? else {
+            return Ok(vec![]);
+        };
+        let resolver = self.d.resolver();
+        let Some(fields) = resolver.opt_resolve_container_value(acro_form, &sname("Fields"))?
+        else {
+            return Ok(vec![]);
+        };
+        let mut r = vec![];
+        let mut visited = HashSet::new();
+        for field in fields.arr()?.iter() {
+            collect_field_javascript(resolver, field, "", &mut visited, &mut r)?;
+        }
+        Ok(r)
+    }
+
+    /// Optional content groups listed in `/OCGs` of catalog `/OCProperties`,
+    /// visibility resolved by `/BaseState`, `/ON` and `/OFF` of default
+    /// configuration `/D`, see PDF 32000-1:2008 8.11.4.3.
+    ///
+    /// Returns empty vec if the document has no optional content.
+    pub fn layers(&self) -> AnyResult<Vec<Layer>> {
+        let Some(props) = self.d.oc_properties()? else {
+            return Ok(vec![]);
+        };
+        let resolver = self.d.resolver();
+        let ref_ids = |d: &Dictionary, key: &'static str| -> AnyResult<HashSet<RuntimeObjectId>> {
+            let mut r = HashSet::new();
+            if let Some(arr) = resolver.opt_resolve_container_value(d, &sname(key))? {
+                for o in arr.arr()?.iter() {
+                    if let Object::Reference(id) = o {
+                        r.insert(id.id().id());
+                    }
+                }
+            }
+            Ok(r)
+        };
+
+        let (base_on, on, off) = match resolver.opt_resolve_container_value(props, &sname("D"))? {
+            Some(d) => {
+                let d = d.as_dict()?;
+                // `/Unchanged` is not meaningful for default configuration,
+                // treat it as `/ON`
+                let base_on = match resolver.opt_resolve_container_value(d, &sname("BaseState"))? {
+                    Some(state) => state.name()? != sname("OFF"),
+                    None => true,
+                };
+                (base_on, ref_ids(d, "ON")?, ref_ids(d, "OFF")?)
+            }
+            None => (true, HashSet::new(), HashSet::new()),
+        };
+
+        let ocgs = resolver.resolve_container_value(props, &sname("OCGs"))?;
+        let mut r = vec![];
+        for o in ocgs.arr()?.iter() {
+            let Object::Reference(id) = o else {
+                continue;
+            };
+            let id = id.id().id();
+            let ocg = resolver.resolve(id)?.as_dict()?;
+            let name = match resolver.opt_resolve_container_value(ocg, &sname("Name"))? {
+                Some(name) => decode_text_string(name.as_byte_string()?),
+                None => String::new(),
+            };
+            let visible = if base_on {
+                !off.contains(&id)
+            } else {
+                on.contains(&id)
+            };
+            r.push(Layer { id, name, visible });
+        }
+        Ok(r)
+    }
+
+    /// Visibility state of default configuration, see [`Catalog::layers()`].
+    pub fn optional_content(&self) -> AnyResult<OptionalContent> {
+        Ok(OptionalContent::new(&self.layers()?))
+    }
 }
 
 pub struct File {
     root_id: RuntimeObjectId,
+    info_id: Option<RuntimeObjectId>,
     head_ver: Option<String>,
     data: Vec<u8>,
     xref: XRefTable,
@@ -625,11 +1305,14 @@ fn open_encrypt(
         "unsupported security handler (SubFilter)"
     );
 
-    let authorizer = Authorizer::new(&encrypt, &trailer)?;
-
-    authorizer.authorize(password.as_bytes()).map_or_else(
+    let key = if encrypt.revison()? == StandardHandlerRevision::V6 {
+        Aes256Authorizer::new(&encrypt)?.authorize(password.as_bytes())
+    } else {
+        Authorizer::new(&encrypt, &trailer)?.authorize(password.as_bytes())
+    };
+    key.map_or_else(
         || Err(FileError::InvalidPassword),
-        |k| Ok(Some(EncryptInfo::new(k, encrypt.crypt_filters()))),
+        |k| Ok(Some(EncryptInfo::new(k, encrypt.crypt_filters()?))),
     )
 }
 
@@ -649,10 +1332,16 @@ impl File {
 
         let root_id = trailers.iter().find_map(|t| t.get(&sname("Root"))).unwrap();
         let root_id = root_id.reference().unwrap().id().id();
+        let info_id = trailers
+            .iter()
+            .find_map(|t| t.get(&sname("Info")))
+            .and_then(|o| o.reference().ok())
+            .map(|r| r.id().id());
 
         Ok(Self {
             head_ver: head_ver.map(|s| s.to_owned()),
             root_id,
+            info_id,
             data: buf,
             xref,
             encrypt_info: encrypt_key,
@@ -667,6 +1356,11 @@ impl File {
         ))
     }
 
+    /// Cross reference table merged from all frames.
+    pub fn xref(&self) -> &XRefTable {
+        &self.xref
+    }
+
     pub fn version<'a>(
         &'a self,
         resolver: &'a ObjectResolver<'a>,
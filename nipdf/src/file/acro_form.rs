@@ -0,0 +1,242 @@
+//! Interactive form (AcroForm) fields, see PDF 32000-1:2008 12.7.
+use super::{Catalog, ObjectResolver, Rectangle, decode_text_string, visit_once};
+use crate::object::{Dictionary, Object, ObjectValueError, PdfObject, Resolver, RuntimeObjectId};
+use ahash::{HashSet, HashSetExt};
+use anyhow::Result as AnyResult;
+use bitflags::bitflags;
+use log::warn;
+use nipdf_macro::{TryFromIntObjectForBitflags, pdf_object};
+use prescript::{Name, sname};
+
+bitflags! {
+    /// Field flags `/Ff`, see PDF 32000-1:2008 Table 221, 226, 228 and 230
+    #[derive(TryFromIntObjectForBitflags, Debug, PartialEq, Eq, Copy, Clone, Default)]
+    pub struct FieldFlags: u32 {
+        const READ_ONLY = 1;
+        const REQUIRED = 1 << 1;
+        const NO_EXPORT = 1 << 2;
+        const MULTILINE = 1 << 12;
+        const PASSWORD = 1 << 13;
+        const NO_TOGGLE_TO_OFF = 1 << 14;
+        const RADIO = 1 << 15;
+        const PUSHBUTTON = 1 << 16;
+        const COMBO = 1 << 17;
+        const EDIT = 1 << 18;
+        const MULTI_SELECT = 1 << 21;
+    }
+}
+
+#[pdf_object(())]
+pub trait AcroFormDictTrait {
+    #[or_default]
+    fn need_appearances(&self) -> bool;
+    /// Default appearance of variable text fields.
+    #[key("DA")]
+    fn default_appearance(&self) -> Option<&str>;
+}
+
+/// Type of form field `/FT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// `Btn`, push button, check box or radio button, see [`FieldFlags`].
+    Button,
+    /// `Tx`
+    Text,
+    /// `Ch`, list box or combo box.
+    Choice,
+    /// `Sig`
+    Signature,
+}
+
+impl TryFrom<&Name> for FieldType {
+    type Error = ObjectValueError;
+
+    fn try_from(name: &Name) -> Result<Self, Self::Error> {
+        Ok(match name.as_str() {
+            "Btn" => Self::Button,
+            "Tx" => Self::Text,
+            "Ch" => Self::Choice,
+            "Sig" => Self::Signature,
+            _ => return Err(ObjectValueError::UnexpectedType),
+        })
+    }
+}
+
+/// Value `/V` of form field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    /// Text string or stream of text field, option of choice field.
+    Text(String),
+    /// Options of multiple selection choice field.
+    Texts(Vec<String>),
+    /// State of check box or radio button, such as `Off`, or name of
+    /// the selected appearance state.
+    Name(Name),
+    /// Signature dictionary of signed signature field.
+    Signature(Option<RuntimeObjectId>),
+}
+
+/// Terminal field of interactive form, see [`AcroFormDict::fields()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    /// Fully qualified name, `/T` of the field and its ancestors joined by
+    /// `.`, ancestors without `/T` are skipped.
+    pub name: String,
+    /// None if not defined by the field or its ancestors.
+    pub field_type: Option<FieldType>,
+    pub flags: FieldFlags,
+    /// Value inherited from ancestors if the field has no `/V`.
+    pub value: Option<FieldValue>,
+    /// `/Rect` of widget annotations of the field, one for each check box
+    /// or radio button of a group, empty if the field has no widget.
+    pub rects: Vec<Rectangle>,
+}
+
+/// Inheritable field attributes, see PDF 32000-1:2008 Table 220.
+#[derive(Clone, Default)]
+struct Inherited<'b> {
+    name: String,
+    field_type: Option<Name>,
+    flags: Option<&'b Object>,
+    value: Option<&'b Object>,
+}
+
+impl<'a, 'b: 'a> AcroFormDict<'a, 'b> {
+    /// Terminal fields in `/Fields` tree in depth-first order, fields
+    /// having child fields are not returned, their attributes inherited by
+    /// the children. Fields visited twice are skipped to break reference
+    /// cycles, fields of unknown `/FT` skipped.
+    pub fn fields(&self) -> AnyResult<Vec<FormField>> {
+        let resolver = self.resolver();
+        let Some(fields) = resolver.opt_resolve_container_value(self.dict(), &sname("Fields"))?
+        else {
+            return Ok(vec![]);
+        };
+        let mut r = vec![];
+        let mut visited = HashSet::new();
+        for field in fields.arr()?.iter() {
+            collect_fields(resolver, field, &Inherited::default(), &mut visited, &mut r)?;
+        }
+        Ok(r)
+    }
+}
+
+/// Widget annotation kids have no `/T`, other kids are child fields.
+fn is_widget(d: &Dictionary) -> bool {
+    !d.contains_key(&sname("T"))
+        && d.get(&sname("Subtype"))
+            .is_some_and(|s| s.name().is_ok_and(|s| s == sname("Widget")))
+}
+
+fn collect_fields<'b>(
+    resolver: &'b ObjectResolver,
+    field: &'b Object,
+    parent: &Inherited<'b>,
+    visited: &mut HashSet<RuntimeObjectId>,
+    r: &mut Vec<FormField>,
+) -> AnyResult<()> {
+    if !visit_once(field, visited) {
+        warn!("form field {:?} visited twice", field);
+        return Ok(());
+    }
+    let field = resolver.resolve_reference(field)?.as_dict()?;
+    let get = |key| resolver.opt_resolve_container_value(field, &sname(key));
+    let mut attrs = parent.clone();
+    if let Some(t) = get("T")? {
+        let t = decode_text_string(t.as_byte_string()?);
+        attrs.name = match parent.name.is_empty() {
+            true => t,
+            false => format!("{}.{}", parent.name, t),
+        };
+    }
+    if let Some(ft) = get("FT")? {
+        attrs.field_type = Some(ft.name()?);
+    }
+    attrs.flags = get("Ff")?.or(parent.flags);
+    attrs.value = get("V")?.or(parent.value);
+
+    let kids = match get("Kids")? {
+        Some(kids) => kids
+            .arr()?
+            .iter()
+            .map(|kid| Ok((kid, resolver.resolve_reference(kid)?.as_dict()?)))
+            .collect::<Result<Vec<_>, ObjectValueError>>()?,
+        None => vec![],
+    };
+    if kids.iter().any(|(_, kid)| !is_widget(kid)) {
+        for (kid, _) in kids {
+            collect_fields(resolver, kid, &attrs, visited, r)?;
+        }
+        return Ok(());
+    }
+
+    // terminal field, merged with its widget if no kids
+    let rects = match kids.is_empty() {
+        true => get("Rect")?.into_iter().collect(),
+        false => kids
+            .iter()
+            .filter_map(|(_, kid)| kid.get(&sname("Rect")))
+            .collect::<Vec<_>>(),
+    };
+    let field_type = match attrs.field_type.as_ref().map(FieldType::try_from) {
+        Some(Err(_)) => {
+            warn!(
+                "form field {} of unknown type {:?}",
+                attrs.name, attrs.field_type
+            );
+            return Ok(());
+        }
+        ft => ft.transpose()?,
+    };
+    r.push(FormField {
+        name: attrs.name,
+        field_type,
+        flags: attrs
+            .flags
+            .map(FieldFlags::try_from)
+            .transpose()?
+            .unwrap_or_default(),
+        value: attrs
+            .value
+            .map(|v| field_value(resolver, v))
+            .transpose()?
+            .flatten(),
+        rects: rects
+            .into_iter()
+            .map(|o| Rectangle::try_from(resolver.resolve_reference(o)?))
+            .collect::<Result<_, _>>()?,
+    });
+    Ok(())
+}
+
+/// Value of `/V`, None if null.
+fn field_value(resolver: &ObjectResolver, v: &Object) -> AnyResult<Option<FieldValue>> {
+    let id = match v {
+        Object::Reference(r) => Some(r.id().id()),
+        _ => None,
+    };
+    Ok(Some(match resolver.resolve_reference(v)? {
+        Object::Null => return Ok(None),
+        Object::Name(n) => FieldValue::Name(n.clone()),
+        Object::Stream(s) => FieldValue::Text(decode_text_string(&s.decode(resolver)?)),
+        Object::Array(arr) => FieldValue::Texts(
+            arr.iter()
+                .map(|o| Ok(decode_text_string(o.as_byte_string()?)))
+                .collect::<Result<_, ObjectValueError>>()?,
+        ),
+        Object::Dictionary(_) => FieldValue::Signature(id),
+        o => FieldValue::Text(decode_text_string(o.as_byte_string()?)),
+    }))
+}
+
+impl<'a, 'b: 'a> Catalog<'a, 'b> {
+    /// Interactive form `/AcroForm` of catalog, None if the document has no
+    /// form.
+    pub fn acro_form(&self) -> AnyResult<Option<AcroFormDict<'a, 'b>>> {
+        Ok(self
+            .d
+            .acro_form()?
+            .map(|d| AcroFormDict::new(None, d, self.d.resolver()))
+            .transpose()?)
+    }
+}
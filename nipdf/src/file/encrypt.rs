@@ -1,6 +1,6 @@
-use crate::object::{ObjectId, TrailerDict};
+use crate::object::{ObjectId, ObjectValueError, TrailerDict};
 use ahash::{HashMap, HashMapExt};
-use anyhow::Result as AnyResult;
+use anyhow::{Result as AnyResult, anyhow};
 use arc4::Arc4;
 use log::error;
 use md5::{Digest, Md5};
@@ -16,6 +16,8 @@ pub enum Algorithm {
     Key40AndMore = 2,
     Unpublished = 3,
     DefinedInDoc = 4,
+    /// Crypt filters defined in doc, AES with 256-bit key.
+    DefinedInDocAes256 = 5,
 }
 
 #[derive(TryFromIntObject, PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
@@ -23,6 +25,7 @@ pub enum StandardHandlerRevision {
     V2 = 2,
     V3 = 3,
     V4 = 4,
+    V6 = 6,
 }
 
 #[derive(TryFromNameObject, PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord, Default)]
@@ -31,6 +34,7 @@ pub enum DecryptMethod {
     None,
     V2,
     AESV2,
+    AESV3,
 }
 
 const fn identity() -> Name {
@@ -66,14 +70,24 @@ pub trait EncryptDictTrait {
     #[try_from]
     fn revison(&self) -> StandardHandlerRevision;
 
-    /// 32-byte long string.
+    /// 32-byte long string, 48-byte for revision 6.
     #[key("O")]
     fn owner_password_hash(&self) -> &[u8];
 
-    /// 32-byte long string.
+    /// 32-byte long string, 48-byte for revision 6.
     #[key("U")]
     fn user_password_hash(&self) -> &[u8];
 
+    /// 32-byte file encryption key encrypted by owner password, revision 6
+    /// only.
+    #[key("OE")]
+    fn owner_encrypted_key(&self) -> &[u8];
+
+    /// 32-byte file encryption key encrypted by user password, revision 6
+    /// only.
+    #[key("UE")]
+    fn user_encrypted_key(&self) -> &[u8];
+
     #[key("CF")]
     #[one_or_more]
     #[nested]
@@ -150,11 +164,13 @@ impl CryptFilters {
 }
 
 impl<'a, 'b> EncryptDict<'a, 'b> {
-    pub fn crypt_filters(&self) -> CryptFilters {
+    /// Crypt filters of the document, Identity if failed to parse, error if
+    /// algorithm `/V` not supported.
+    pub fn crypt_filters(&self) -> AnyResult<CryptFilters> {
         use anyhow::Result;
 
         fn _do(this: &EncryptDict) -> Result<CryptFilters> {
-            if this.revison()? != StandardHandlerRevision::V4 {
+            if this.revison()? < StandardHandlerRevision::V4 {
                 return Ok(CryptFilters::rc4());
             }
 
@@ -167,6 +183,7 @@ impl<'a, 'b> EncryptDict<'a, 'b> {
                             DecryptMethod::None => CryptFilter::Identity,
                             DecryptMethod::V2 => CryptFilter::Rc4,
                             DecryptMethod::AESV2 => CryptFilter::Aes,
+                            DecryptMethod::AESV3 => CryptFilter::Aes256,
                         })
                     })
                 })
@@ -178,17 +195,25 @@ impl<'a, 'b> EncryptDict<'a, 'b> {
             ))
         }
 
+        let algorithm = self.algorithm()?;
         if !matches!(
-            self.algorithm().unwrap(),
-            Algorithm::Key40 | Algorithm::Key40AndMore | Algorithm::DefinedInDoc,
+            algorithm,
+            Algorithm::Key40
+                | Algorithm::Key40AndMore
+                | Algorithm::DefinedInDoc
+                | Algorithm::DefinedInDocAes256,
         ) {
-            todo!("Algorithm: {:?}", self.algorithm().unwrap());
+            return Err(ObjectValueError::Unsupported(vec![format!(
+                "encrypt algorithm {:?}",
+                algorithm
+            )])
+            .into());
         }
 
-        _do(self).unwrap_or_else(|e| {
+        Ok(_do(self).unwrap_or_else(|e| {
             error!("failed to parse crypt filters, use Identity: {}", e);
             CryptFilters::identity()
-        })
+        }))
     }
 }
 
@@ -211,11 +236,12 @@ fn pad_trunc_password(s: &[u8]) -> [u8; 32] {
 pub trait VecLike {
     fn drain(&mut self, range: std::ops::Range<usize>) -> ArrayVec<[u8; 16]>;
     fn as_mut_slice(&mut self) -> &mut [u8];
+    fn truncate(&mut self, len: usize);
 }
 
 pub trait Decryptor {
     fn new(key: &[u8], id: ObjectId) -> Self;
-    fn decrypt<V: VecLike>(&self, data: &mut V);
+    fn decrypt<V: VecLike>(&self, data: &mut V) -> Result<(), ObjectValueError>;
 }
 
 impl<A> VecLike for TinyVec<A>
@@ -229,6 +255,10 @@ where
     fn as_mut_slice(&mut self) -> &mut [u8] {
         self.as_mut_slice()
     }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
 }
 
 impl VecLike for Vec<u8> {
@@ -239,6 +269,10 @@ impl VecLike for Vec<u8> {
     fn as_mut_slice(&mut self) -> &mut [u8] {
         self.as_mut_slice()
     }
+
+    fn truncate(&mut self, len: usize) {
+        self.truncate(len)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -246,14 +280,21 @@ pub enum CryptFilter {
     Identity,
     Rc4,
     Aes,
+    Aes256,
 }
 
 impl CryptFilter {
-    pub fn decrypt(&self, key: &[u8], id: ObjectId, data: &mut impl VecLike) {
+    pub fn decrypt(
+        &self,
+        key: &[u8],
+        id: ObjectId,
+        data: &mut impl VecLike,
+    ) -> Result<(), ObjectValueError> {
         match self {
-            CryptFilter::Identity => {}
+            CryptFilter::Identity => Ok(()),
             CryptFilter::Rc4 => Rc4Decryptor::new(key, id).decrypt(data),
             CryptFilter::Aes => AesDecryptor::new(key, id).decrypt(data),
+            CryptFilter::Aes256 => Aes256Decryptor::new(key, id).decrypt(data),
         }
     }
 }
@@ -272,8 +313,9 @@ impl Decryptor for Rc4Decryptor {
         Self(key)
     }
 
-    fn decrypt<V: VecLike>(&self, data: &mut V) {
+    fn decrypt<V: VecLike>(&self, data: &mut V) -> Result<(), ObjectValueError> {
         Arc4::with_key(&self.0).encrypt(data.as_mut_slice());
+        Ok(())
     }
 }
 
@@ -296,16 +338,50 @@ impl Decryptor for AesDecryptor {
     /// the initialization vector is a 16-byte random  number that is stored as the first 16 bytes
     /// of the encrypted data.
     /// Pad the data using the PKCS#5 padding scheme.
-    fn decrypt<V: VecLike>(&self, data: &mut V) {
+    fn decrypt<V: VecLike>(&self, data: &mut V) -> Result<(), ObjectValueError> {
         use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
         type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
 
-        let iv_data = data.drain(0..16);
-        let mut iv = [0u8; 16];
-        iv.copy_from_slice(&iv_data[..]);
+        let iv = take_iv(data)?;
         Aes128CbcDec::new(self.0.as_ref().into(), &iv.into())
             .decrypt_padded_mut::<Pkcs7>(data.as_mut_slice())
-            .unwrap();
+            .map_err(|_| ObjectValueError::DecryptError)?;
+        Ok(())
+    }
+}
+
+/// Remove the 16-byte initialization vector from the start of `data`.
+fn take_iv(data: &mut impl VecLike) -> Result<[u8; 16], ObjectValueError> {
+    if data.as_mut_slice().len() < 16 {
+        return Err(ObjectValueError::DecryptError);
+    }
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&data.drain(0..16)[..]);
+    Ok(iv)
+}
+
+/// AESV3 uses file encryption key as is, not derived from object id.
+struct Aes256Decryptor([u8; 32]);
+
+impl Decryptor for Aes256Decryptor {
+    fn new(key: &[u8], _id: ObjectId) -> Self {
+        let mut k = [0u8; 32];
+        k.copy_from_slice(&key[..32]);
+        Self(k)
+    }
+
+    /// Same as `AesDecryptor`, but in Aes256, padding removed.
+    fn decrypt<V: VecLike>(&self, data: &mut V) -> Result<(), ObjectValueError> {
+        use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+        type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+        let iv = take_iv(data)?;
+        let n = Aes256CbcDec::new(&self.0.into(), &iv.into())
+            .decrypt_padded_mut::<Pkcs7>(data.as_mut_slice())
+            .map_err(|_| ObjectValueError::DecryptError)?
+            .len();
+        data.truncate(n);
+        Ok(())
     }
 }
 
@@ -430,5 +506,95 @@ impl Authorizer {
     }
 }
 
+/// Standard security handler of revision 6, AES-256 key derived by
+/// SHA-256/384/512 hash, see PDF 2.0 7.6.4.3.3 and 7.6.4.4.
+pub struct Aes256Authorizer {
+    owner_hash: [u8; 48],
+    user_hash: [u8; 48],
+    owner_key: [u8; 32],
+    user_key: [u8; 32],
+}
+
+impl Aes256Authorizer {
+    pub fn new(d: &EncryptDict) -> AnyResult<Self> {
+        Ok(Self {
+            owner_hash: copy_array(d.owner_password_hash()?, "O")?,
+            user_hash: copy_array(d.user_password_hash()?, "U")?,
+            owner_key: copy_array(d.owner_encrypted_key()?, "OE")?,
+            user_key: copy_array(d.user_encrypted_key()?, "UE")?,
+        })
+    }
+
+    /// Try authorize by owner password first, if failed, try user password.
+    /// Returns file encryption key if succeed. Password is UTF-8, longer
+    /// than 127 bytes truncated, SASLprep not applied.
+    pub fn authorize(&self, password: &[u8]) -> Option<Box<[u8]>> {
+        use aes::cipher::{BlockDecryptMut, KeyIvInit, block_padding::NoPadding};
+        type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+        // algorithm 2.A, `/U` and `/O` are 32-byte hash, 8-byte validation
+        // salt and 8-byte key salt
+        let password = &password[..password.len().min(127)];
+        let (owner, user) = (&self.owner_hash, &self.user_hash);
+        let is_owner = hash_r6(password, &owner[32..40], user) == owner[..32];
+        let (salt, udata, encrypted_key) = if is_owner {
+            (&owner[40..], &user[..], &self.owner_key)
+        } else if hash_r6(password, &user[32..40], &[]) == user[..32] {
+            (&user[40..], &[][..], &self.user_key)
+        } else {
+            return None;
+        };
+
+        let key = hash_r6(password, salt, udata);
+        let mut r = *encrypted_key;
+        Aes256CbcDec::new(&key.into(), &[0u8; 16].into())
+            .decrypt_padded_mut::<NoPadding>(&mut r)
+            .unwrap();
+        Some(r.into())
+    }
+}
+
+/// Algorithm 2.B, hash `password` with `salt`, `udata` is 48-byte `/U` for
+/// owner password, empty for user password.
+fn hash_r6(password: &[u8], salt: &[u8], udata: &[u8]) -> [u8; 32] {
+    use aes::cipher::{BlockEncryptMut, KeyIvInit, block_padding::NoPadding};
+    use sha2::{Sha256, Sha384, Sha512};
+    type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+
+    let mut k = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(udata)
+        .finalize()
+        .to_vec();
+    let mut round = 0u32;
+    loop {
+        let mut e = [password, &k[..], udata].concat().repeat(64);
+        let n = e.len();
+        Aes128CbcEnc::new(k[..16].into(), k[16..32].into())
+            .encrypt_padded_mut::<NoPadding>(&mut e, n)
+            .unwrap();
+        // sum of first 16 bytes mod 3 equals the big-endian number mod 3
+        k = match e[..16].iter().map(|&b| u32::from(b)).sum::<u32>() % 3 {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && u32::from(e[n - 1]) + 32 <= round {
+            break;
+        }
+    }
+    // SHA-256 digest or longer
+    k[..32].try_into().unwrap()
+}
+
+/// Copy first `N` bytes of entry `key`, error if `s` is shorter.
+fn copy_array<const N: usize>(s: &[u8], key: &str) -> AnyResult<[u8; N]> {
+    s.get(..N)
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| anyhow!("/{} of encrypt dict shorter than {} bytes", key, N))
+}
+
 #[cfg(test)]
 mod tests;
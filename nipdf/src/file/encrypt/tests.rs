@@ -1,6 +1,7 @@
 use super::*;
-use crate::file::open_test_file_with_password;
+use crate::file::{FileError, open_test_file_with_password};
 use hex_literal::hex;
+use test_case::test_case;
 
 #[test]
 fn test_pad_trunc_password() {
@@ -82,3 +83,50 @@ fn revision_v4() {
 
     assert!(auth.authorize(b"").is_some());
 }
+
+/// `/V 5 /R 6` sample, user password "user", owner password "owner".
+#[test_case("aes256-r6.pdf", "user"; "user password")]
+#[test_case("aes256-r6.pdf", "owner"; "owner password")]
+#[test_case("aes256-r6-empty-user-password.pdf", ""; "empty user password")]
+fn revision_v6(file_name: &str, password: &str) {
+    let file = open_test_file_with_password(
+        format!("sample_files/file-structure/{}", file_name),
+        password,
+    )
+    .unwrap();
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    // stream decrypted, padding removed
+    assert_eq!(3, pages[0].content().unwrap().operations().len());
+    // string decrypted
+    let info = resolver.resolve(6).unwrap().as_dict().unwrap();
+    assert_eq!(
+        b"Hello",
+        info.get(&sname("Title")).unwrap().as_byte_string().unwrap()
+    );
+}
+
+#[test]
+fn revision_v6_wrong_password() {
+    assert!(matches!(
+        open_test_file_with_password("sample_files/file-structure/aes256-r6.pdf", "wrong"),
+        Err(FileError::InvalidPassword)
+    ));
+}
+
+#[test]
+fn aes256_decrypt_malformed() {
+    let decryptor = Aes256Decryptor::new(&[0u8; 32], ObjectId::new(1, 0));
+    // shorter than initialization vector
+    let mut data = vec![0u8; 8];
+    assert!(decryptor.decrypt(&mut data).is_err());
+    // all zero data decrypted to bad padding
+    let mut data = vec![0u8; 32];
+    assert!(decryptor.decrypt(&mut data).is_err());
+}
+
+#[test]
+fn copy_array_too_short() {
+    assert_eq!([1, 2], copy_array::<2>(&[1, 2, 3], "O").unwrap());
+    assert!(copy_array::<48>(&[0; 32], "U").is_err());
+}
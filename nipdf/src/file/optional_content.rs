@@ -0,0 +1,135 @@
+//! Visibility of optional content, decide whether content marked by
+//! optional content group (OCG) or membership dictionary (OCMD) is shown,
+//! see PDF 32000-1:2008 8.11.
+use super::{Layer, ObjectResolver};
+use crate::object::{Object, ObjectValueError, Resolver, RuntimeObjectId};
+use ahash::{HashSet, HashSetExt};
+use log::warn;
+use prescript::sname;
+
+/// Max nesting of `/VE` visibility expression, guard against loops made by
+/// indirect arrays.
+const MAX_VE_DEPTH: usize = 32;
+
+/// Visibility state of optional content groups, all groups visible by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionalContent {
+    hidden: HashSet<RuntimeObjectId>,
+}
+
+impl OptionalContent {
+    /// State of `layers`, such as layers returned by `Catalog::layers()`.
+    pub fn new(layers: &[Layer]) -> Self {
+        let mut hidden = HashSet::new();
+        hidden.extend(layers.iter().filter(|l| !l.visible).map(|l| l.id));
+        Self { hidden }
+    }
+
+    pub fn set_visible(&mut self, ocg: RuntimeObjectId, visible: bool) {
+        if visible {
+            self.hidden.remove(&ocg);
+        } else {
+            self.hidden.insert(ocg);
+        }
+    }
+
+    pub fn is_group_visible(&self, ocg: RuntimeObjectId) -> bool {
+        !self.hidden.contains(&ocg)
+    }
+
+    /// Visibility of `oc`, the reference to OCG or OCMD, as value of `/OC` of
+    /// XObject or marked content property list. OCMD visibility decided by
+    /// `/VE` expression if exists, otherwise by `/P` policy on `/OCGs`.
+    pub fn is_visible(
+        &self,
+        resolver: &ObjectResolver,
+        oc: &Object,
+    ) -> Result<bool, ObjectValueError> {
+        let d = resolver.resolve_reference(oc)?.as_dict()?;
+        match resolver.opt_resolve_container_value(d, &sname("Type"))? {
+            Some(t) if t.name()? == sname("OCMD") => {}
+            _ => return Ok(self.is_ocg_visible(oc)),
+        }
+
+        if let Some(ve) = resolver.opt_resolve_container_value(d, &sname("VE"))? {
+            return self.eval_visibility_expression(resolver, ve, 0);
+        }
+
+        // `/OCGs` is a group or an array of groups
+        let ocgs: Vec<_> = match d.get(&sname("OCGs")) {
+            Some(o) => match resolver.resolve_reference(o)? {
+                Object::Array(arr) => arr.iter().collect(),
+                _ => vec![o],
+            },
+            None => vec![],
+        };
+        let states: Vec<_> = ocgs
+            .into_iter()
+            .filter(|o| !matches!(o, Object::Null))
+            .map(|o| self.is_ocg_visible(o))
+            .collect();
+        // OCMD without groups has no effect
+        if states.is_empty() {
+            return Ok(true);
+        }
+
+        let policy = match resolver.opt_resolve_container_value(d, &sname("P"))? {
+            Some(p) => p.name()?,
+            None => sname("AnyOn"),
+        };
+        Ok(match policy.as_str() {
+            "AllOn" => states.iter().all(|v| *v),
+            "AnyOff" => states.iter().any(|v| !*v),
+            "AllOff" => states.iter().all(|v| !*v),
+            "AnyOn" => states.iter().any(|v| *v),
+            p => {
+                warn!("unknown OCMD /P policy: {}, treat as AnyOn", p);
+                states.iter().any(|v| *v)
+            }
+        })
+    }
+
+    /// OCG referenced by `o` visible, inline dictionary always visible
+    /// because it can not be toggled.
+    fn is_ocg_visible(&self, o: &Object) -> bool {
+        match o {
+            Object::Reference(r) => self.is_group_visible(r.id().id()),
+            _ => true,
+        }
+    }
+
+    /// Evaluate `/VE` array, such as `[/And 1 0 R [/Not 2 0 R]]`.
+    fn eval_visibility_expression(
+        &self,
+        resolver: &ObjectResolver,
+        ve: &Object,
+        depth: usize,
+    ) -> Result<bool, ObjectValueError> {
+        if depth > MAX_VE_DEPTH {
+            return Err(ObjectValueError::UnexpectedType);
+        }
+        let Object::Array(arr) = ve else {
+            return Ok(self.is_ocg_visible(ve));
+        };
+        let Some((op, operands)) = arr.split_first() else {
+            return Err(ObjectValueError::UnexpectedType);
+        };
+        let mut operands = operands.iter().map(|o| match o {
+            Object::Reference(_) => match resolver.resolve_reference(o)? {
+                // indirect array is a nested expression, not a group
+                arr @ Object::Array(_) => self.eval_visibility_expression(resolver, arr, depth + 1),
+                _ => Ok(self.is_ocg_visible(o)),
+            },
+            _ => self.eval_visibility_expression(resolver, o, depth + 1),
+        });
+        match op.name()?.as_str() {
+            "Not" => match (operands.next(), operands.next()) {
+                (Some(v), None) => Ok(!v?),
+                _ => Err(ObjectValueError::UnexpectedType),
+            },
+            "And" => operands.try_fold(true, |acc, v| v.map(|v| acc && v)),
+            "Or" => operands.try_fold(false, |acc, v| v.map(|v| acc || v)),
+            _ => Err(ObjectValueError::UnexpectedType),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Document outline (bookmarks), see PDF 32000-1:2008 12.3.3.
+use super::{Catalog, DestView, ObjectResolver, decode_text_string};
+use crate::object::{Dictionary, Object, PdfObject, RuntimeObjectId};
+use ahash::{HashSet, HashSetExt};
+use anyhow::Result as AnyResult;
+use bitflags::bitflags;
+use log::warn;
+use nipdf_macro::{TryFromIntObjectForBitflags, pdf_object};
+use prescript::sname;
+
+bitflags! {
+    /// Style of outline item title, see PDF 32000-1:2008 Table 154
+    #[derive(TryFromIntObjectForBitflags, Debug, PartialEq, Eq, Copy, Clone, Default)]
+    pub struct OutlineFlags: u32 {
+        const ITALIC = 1;
+        const BOLD = 1 << 1;
+    }
+}
+
+#[pdf_object(())]
+trait OutlineItemDictTrait {
+    /// Positive if item open, negative if collapsed, absolute value is the
+    /// count of visible descendants.
+    #[or_default]
+    fn count(&self) -> i32;
+    #[key("C")]
+    fn color(&self) -> Option<Vec<f32>>;
+    #[key("F")]
+    #[try_from]
+    #[or_default]
+    fn flags(&self) -> OutlineFlags;
+    #[key("A")]
+    fn action(&self) -> Option<&'b Dictionary>;
+}
+
+/// Item of document outline, see [`Catalog::outlines()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineItem {
+    pub title: String,
+    /// Target page and view, from `/Dest` or `/D` of `/GoTo` action.
+    pub dest: Option<(RuntimeObjectId, DestView)>,
+    /// Children shown initially, `/Count` is positive. Item collapsed if
+    /// `/Count` negative or absent.
+    pub open: bool,
+    /// RGB color of title, default black.
+    pub color: [f32; 3],
+    pub flags: OutlineFlags,
+    pub kids: Vec<OutlineItem>,
+}
+
+impl<'a, 'b: 'a> Catalog<'a, 'b> {
+    /// Outline item tree in `/Outlines` of catalog, top level items returned.
+    ///
+    /// Returns empty vec if the document has no outline.
+    pub fn outlines(&self) -> AnyResult<Vec<OutlineItem>> {
+        let Some(root) = self.d.outlines()? else {
+            return Ok(vec![]);
+        };
+        let mut visited = HashSet::new();
+        self.outline_kids(self.d.resolver(), root, &mut visited)
+    }
+
+    /// Items linked by `/First` and `/Next` of `parent`, items visited twice
+    /// are skipped to break reference cycles.
+    fn outline_kids(
+        &self,
+        resolver: &'b ObjectResolver<'a>,
+        parent: &'b Dictionary,
+        visited: &mut HashSet<RuntimeObjectId>,
+    ) -> AnyResult<Vec<OutlineItem>> {
+        let mut r = vec![];
+        let mut next = parent.get(&sname("First"));
+        while let Some(Object::Reference(id)) = next {
+            let id = id.id().id();
+            if !visited.insert(id) {
+                warn!("outline item {:?} visited twice", id);
+                break;
+            }
+            let d = resolver.resolve(id)?.as_dict()?;
+            let item = OutlineItemDict::new(None, d, resolver)?;
+            r.push(self.outline_item(resolver, &item, visited)?);
+            next = d.get(&sname("Next"));
+        }
+        Ok(r)
+    }
+
+    fn outline_item(
+        &self,
+        resolver: &'b ObjectResolver<'a>,
+        item: &OutlineItemDict<'a, 'b>,
+        visited: &mut HashSet<RuntimeObjectId>,
+    ) -> AnyResult<OutlineItem> {
+        let d = item.dict();
+        let title = match resolver.opt_resolve_container_value(d, &sname("Title"))? {
+            Some(t) => decode_text_string(t.as_byte_string()?),
+            None => String::new(),
+        };
+        let dest = resolver.opt_resolve_container_value(d, &sname("Dest"))?;
+        let dest = match (dest, item.action()?) {
+            (Some(dest), _) => Some(dest),
+            (None, Some(action)) => {
+                let is_goto = resolver
+                    .opt_resolve_container_value(action, &sname("S"))?
+                    .is_some_and(|s| s.name().is_ok_and(|s| s == sname("GoTo")));
+                if is_goto {
+                    resolver.opt_resolve_container_value(action, &sname("D"))?
+                } else {
+                    None
+                }
+            }
+            (None, None) => None,
+        };
+        let dest = match dest {
+            Some(dest) => self.resolve_dest(dest)?,
+            None => None,
+        };
+        let color = match item.color()?.as_deref() {
+            Some(&[r, g, b]) => [r, g, b],
+            _ => [0.0; 3],
+        };
+        Ok(OutlineItem {
+            title,
+            dest,
+            open: item.count()? > 0,
+            color,
+            flags: item.flags()?,
+            kids: self.outline_kids(resolver, d, visited)?,
+        })
+    }
+}
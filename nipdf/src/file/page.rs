@@ -1,21 +1,30 @@
 use crate::{
-    function::Domains,
+    file::{Catalog, JavaScript, additional_actions_javascript},
+    function::{Domains, FunctionDict},
     graphics::{
-        ColorArgs, ColorSpaceArgs, LineCapStyle, LineJoinStyle, Operation, PatternDict, Point,
-        RenderingIntent, parse_operations, shading::ShadingDict, trans::FormToUserSpace,
+        BlendMode, ColorArgs, ColorSpaceArgs, LineCapStyle, LineJoinStyle, NameOrDict, Operation,
+        PatternDict, Point, RenderingIntent, TextRenderingMode, parse_operations,
+        shading::ShadingDict, trans::FormToUserSpace,
+    },
+    object::{
+        Dictionary, ImageDict, ImageMask, ImageMetadata, Object, ObjectValueError, PdfObject,
+        RuntimeObjectId, Stream,
     },
-    object::{Dictionary, ImageMask, Object, ObjectValueError, PdfObject, RuntimeObjectId, Stream},
     text::FontDict,
 };
-use ahash::{HashMap, HashMapExt};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use bitflags::bitflags;
 use educe::Educe;
-use log::error;
-use nipdf_macro::{TryFromNameObject, pdf_object};
+use log::{error, warn};
+use nipdf_macro::{TryFromIntObjectForBitflags, TryFromNameObject, pdf_object};
 use nom::Finish;
 use prescript::{Name, sname};
 use std::{cell::LazyCell, iter::once};
 
+mod appearance;
 pub mod paint;
+mod text_runs;
+pub use text_runs::{TextRun, text_separator};
 
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Rectangle {
@@ -65,6 +74,17 @@ impl Rectangle {
             self.upper_y * v,
         )
     }
+
+    /// Return None if two rectangles not overlap, or overlap area is empty.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let r = Self {
+            left_x: self.left_x.max(other.left_x),
+            lower_y: self.lower_y.max(other.lower_y),
+            right_x: self.right_x.min(other.right_x),
+            upper_y: self.upper_y.min(other.upper_y),
+        };
+        (r.width() > 0.0 && r.height() > 0.0).then_some(r)
+    }
 }
 
 /// Convert from raw array, auto re-order to (left_x, lower_y, right_x, upper_y),
@@ -120,6 +140,61 @@ pub trait GraphicsStateParameterDictTrait {
     fn text_knockout_flag(&self) -> Option<bool>;
     #[key("FL")]
     fn flatness(&self) -> Option<f32>;
+    #[key("BM")]
+    #[try_from]
+    fn blend_mode(&self) -> Option<BlendMode>;
+    /// Use `soft_mask()` instead, this one fails if it is `/None`.
+    #[key("SMask")]
+    #[nested]
+    fn soft_mask_dict(&self) -> Option<SoftMaskDict<'a, 'b>>;
+}
+
+impl<'a, 'b> GraphicsStateParameterDict<'a, 'b> {
+    /// Soft mask dictionary, None if `/SMask` is `/None` or not exist.
+    pub fn soft_mask(&self) -> anyhow::Result<Option<SoftMaskDict<'a, 'b>>> {
+        if let Some(Object::Name(_)) = self.dict().get(&sname("SMask")) {
+            return Ok(None);
+        }
+        self.soft_mask_dict()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromNameObject)]
+pub enum SoftMaskType {
+    Alpha,
+    Luminosity,
+}
+
+#[pdf_object(Some("Mask"))]
+pub trait SoftMaskDictTrait {
+    #[key("S")]
+    #[try_from]
+    fn subtype(&self) -> SoftMaskType;
+
+    /// Transparency group form XObject to compute the mask.
+    #[key("G")]
+    #[nested]
+    fn group(&self) -> XObjectDict<'a, 'b>;
+
+    /// Backdrop color in group color space, used by luminosity mask.
+    #[key("BC")]
+    fn backdrop_color(&self) -> Option<Vec<f32>>;
+
+    /// Use `transfer_function()` instead, this one fails if it is `/Identity`.
+    #[key("TR")]
+    #[nested]
+    fn transfer_function_dict(&self) -> Option<FunctionDict<'a, 'b>>;
+}
+
+impl<'a, 'b> SoftMaskDict<'a, 'b> {
+    /// Function maps luminosity or alpha to mask value, None if `/TR` is
+    /// `/Identity` or not exist.
+    pub fn transfer_function(&self) -> anyhow::Result<Option<FunctionDict<'a, 'b>>> {
+        if let Some(Object::Name(_)) = self.dict().get(&sname("TR")) {
+            return Ok(None);
+        }
+        self.transfer_function_dict()
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromNameObject)]
@@ -148,6 +223,9 @@ pub trait XObjectDictTrait {
     #[or_default]
     fn interpolate(&self) -> bool;
 
+    #[try_from]
+    fn color_space(&self) -> Option<ColorSpaceArgs>;
+
     #[or_default]
     fn image_mask(&self) -> bool;
 
@@ -229,7 +307,164 @@ pub trait ResourceDictTrait {
     fn properties(&self) -> Option<&'b Dictionary>;
 }
 
-#[pdf_object(["Pages", "Page"])]
+bitflags! {
+    /// Annotation flags, see PDF 32000-1:2008 12.5.3
+    #[derive(TryFromIntObjectForBitflags, Debug, PartialEq, Eq, Copy, Clone, Default)]
+    pub struct AnnotationFlags: u32 {
+        const INVISIBLE = 1;
+        const HIDDEN = 1 << 1;
+        const PRINT = 1 << 2;
+        /// Do not scale annotation appearance to match page magnification
+        const NO_ZOOM = 1 << 3;
+        /// Do not rotate annotation appearance to match page rotation
+        const NO_ROTATE = 1 << 4;
+        const NO_VIEW = 1 << 5;
+        const READ_ONLY = 1 << 6;
+        const LOCKED = 1 << 7;
+        const TOGGLE_NO_VIEW = 1 << 8;
+        const LOCKED_CONTENTS = 1 << 9;
+    }
+}
+
+#[pdf_object(Some("Annot"))]
+pub trait AnnotationDictTrait {
+    fn subtype(&self) -> Name;
+    #[try_from]
+    fn rect(&self) -> Rectangle;
+    #[key("F")]
+    #[try_from]
+    #[or_default]
+    fn flags(&self) -> AnnotationFlags;
+    #[key("AP")]
+    fn appearance(&self) -> Option<&'b Dictionary>;
+    /// Appearance state, selects appearance of `/AP` sub-dictionaries.
+    #[key("AS")]
+    fn appearance_state(&self) -> Option<Name>;
+    #[key("A")]
+    fn action(&self) -> Option<&'b Dictionary>;
+    #[key("C")]
+    fn color(&self) -> Option<Vec<f32>>;
+    #[key("IC")]
+    fn interior_color(&self) -> Option<Vec<f32>>;
+    #[key("BS")]
+    fn border_style(&self) -> Option<&'b Dictionary>;
+    #[key("DA")]
+    fn default_appearance(&self) -> Option<&str>;
+    /// Annotation this annotation replies to, markup annotations only.
+    #[key("IRT")]
+    fn in_reply_to(&self) -> Option<&'b Dictionary>;
+}
+
+impl<'a, 'b> AnnotationDict<'a, 'b> {
+    /// Normal appearance `/N` of `/AP`. If `/N` is a dictionary of appearance
+    /// states, such as check box, the stream of state `/AS`. None if no
+    /// appearance, or the state not found.
+    pub fn normal_appearance(&self) -> anyhow::Result<Option<XObjectDict<'a, 'b>>> {
+        let Some(ap) = self.appearance()? else {
+            return Ok(None);
+        };
+        let resolver = self.resolver();
+        let states = match ap.get(&sname("N")) {
+            None => return Ok(None),
+            Some(Object::Reference(r)) => match resolver.resolve(r.id().id())? {
+                Object::Stream(_) => return Ok(Some(resolver.resolve_pdf_object(r.id().id())?)),
+                o => o.as_dict()?,
+            },
+            Some(o) => o.as_dict()?,
+        };
+        let Some(state) = self.appearance_state()? else {
+            return Ok(None);
+        };
+        Ok(states
+            .get(&state)
+            .map(|o| resolver.resolve_pdf_object2(o))
+            .transpose()?)
+    }
+
+    /// JavaScript of additional actions `/AA`, such as widget annotation of
+    /// form field, name of [`JavaScript`] is the trigger key.
+    pub fn additional_javascript(&self) -> Result<Vec<JavaScript>, ObjectValueError> {
+        additional_actions_javascript(self.resolver(), self.dict())
+    }
+}
+
+/// How to display destination page, see PDF 32000-1:2008 12.3.2.2.
+/// `None` coordinate or zoom means keep current value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DestView {
+    XYZ {
+        left: Option<f32>,
+        top: Option<f32>,
+        zoom: Option<f32>,
+    },
+    Fit,
+    FitH {
+        top: Option<f32>,
+    },
+    FitV {
+        left: Option<f32>,
+    },
+    FitR(Rectangle),
+    FitB,
+    FitBH {
+        top: Option<f32>,
+    },
+    FitBV {
+        left: Option<f32>,
+    },
+}
+
+impl DestView {
+    /// Parse from explicit destination array items after page.
+    pub(crate) fn parse(args: &[Object]) -> Result<Self, ObjectValueError> {
+        let num = |i: usize| match args.get(i) {
+            None | Some(Object::Null) => Ok(None),
+            Some(o) => o.as_number().map(Some),
+        };
+        let required = |i: usize| num(i)?.ok_or(ObjectValueError::UnexpectedType);
+        let Some(Object::Name(kind)) = args.first() else {
+            return Err(ObjectValueError::UnexpectedType);
+        };
+        Ok(match kind.as_str() {
+            "XYZ" => Self::XYZ {
+                left: num(1)?,
+                top: num(2)?,
+                // zoom 0 has the same meaning as null
+                zoom: num(3)?.filter(|z| *z != 0.0),
+            },
+            "Fit" => Self::Fit,
+            "FitH" => Self::FitH { top: num(1)? },
+            "FitV" => Self::FitV { left: num(1)? },
+            "FitR" => Self::FitR(Rectangle::from_lbrt(
+                required(1)?,
+                required(2)?,
+                required(3)?,
+                required(4)?,
+            )),
+            "FitB" => Self::FitB,
+            "FitBH" => Self::FitBH { top: num(1)? },
+            "FitBV" => Self::FitBV { left: num(1)? },
+            _ => return Err(ObjectValueError::UnexpectedType),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    Uri(String),
+    /// Page index and view of destination
+    GoTo(usize, DestView),
+}
+
+/// Clickable area of the page, and its target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub rect: Rectangle,
+    pub target: LinkTarget,
+}
+
+/// Page tree node, `/Type` not validated, see `PageDict::is_leaf()`.
+#[pdf_object(())]
 pub(crate) trait PageDictTrait {
     #[nested]
     fn kids(&self) -> Vec<Self>;
@@ -242,14 +477,33 @@ pub(crate) trait PageDictTrait {
     #[one_or_more]
     fn contents(&self) -> Vec<&Stream>;
     #[key("Type")]
-    fn type_name(&self) -> Name;
+    fn type_name(&self) -> Option<Name>;
     #[or_default]
     fn rotate(&self) -> i32;
+    fn struct_parents(&self) -> Option<i32>;
+    #[nested]
+    fn annots(&self) -> Vec<AnnotationDict<'a, 'b>>;
+    /// Count of pages in the sub-tree, only in intermediate node.
+    fn count(&self) -> Option<u32>;
 }
 
 impl<'a, 'b> PageDict<'a, 'b> {
+    /// Node is a page if `/Type` is `/Page`. `/Type` is required, but some
+    /// files omit it, then node without `/Kids` is treated as page.
     pub fn is_leaf(&self) -> bool {
-        self.type_name().unwrap() == sname("Page")
+        match self.type_name().ok().flatten() {
+            Some(t) => t == sname("Page"),
+            None => !self.dict().contains_key(&sname("Kids")),
+        }
+    }
+
+    /// Count of pages in the sub-tree of the node, 1 if it is a page.
+    pub fn page_count(&self) -> anyhow::Result<usize> {
+        Ok(if self.is_leaf() {
+            1
+        } else {
+            self.count()?.unwrap_or_default() as usize
+        })
     }
 }
 
@@ -269,6 +523,17 @@ impl<'a, 'b: 'a> Page<'a, 'b> {
         once(&self.d).chain(self.parents_to_root.iter())
     }
 
+    /// Page dictionary, inherited attributes not included.
+    pub(crate) fn dict(&self) -> &Dictionary {
+        self.d.dict()
+    }
+
+    /// Value of inheritable attribute `key`, such as `/Resources`, from the
+    /// page or its nearest ancestor.
+    pub(crate) fn inherited_attr(&self, key: &Name) -> Option<&Object> {
+        self.iter_to_root().find_map(|d| d.dict().get(key))
+    }
+
     pub fn media_box(&self) -> Rectangle {
         self.iter_to_root()
             .find_map(|d| d.media_box().unwrap())
@@ -279,15 +544,11 @@ impl<'a, 'b: 'a> Page<'a, 'b> {
         self.d.rotate().unwrap()
     }
 
-    /// Return None if crop_box not exist, or empty.
+    /// Return None if crop_box not exist, or empty. Effective crop box is
+    /// the intersection with media box, see PDF 32000-1:2008 14.11.2.
     pub fn crop_box(&self) -> Option<Rectangle> {
-        let r = self.iter_to_root().find_map(|d| d.crop_box().unwrap());
-        if let Some(r) = r {
-            if r.width() == 0.0 || r.height() == 0.0 {
-                return None;
-            }
-        }
-        r
+        let r = self.iter_to_root().find_map(|d| d.crop_box().unwrap())?;
+        r.intersect(&self.media_box())
     }
 
     pub fn resources(&self) -> ResourceDict<'_, '_> {
@@ -311,30 +572,288 @@ impl<'a, 'b: 'a> Page<'a, 'b> {
         Ok(PageContent { bufs })
     }
 
+    /// Whether page content paints nothing, by scanning its operations: no
+    /// path painting, shading, image or XObject, and text shown only in
+    /// invisible or clip render modes. Cheap heuristic without rasterizing,
+    /// marks can not be seen, such as white on white or clipped out, are not
+    /// detected. Annotations are not checked.
+    pub fn is_blank(&self) -> Result<bool, ObjectValueError> {
+        // text render mode is part of graphics state, saved by `q`, restored by `Q`
+        let mut invisible_text = vec![false];
+        for op in self.content()?.operations() {
+            match op {
+                Operation::SaveGraphicsState => {
+                    invisible_text.push(*invisible_text.last().unwrap())
+                }
+                Operation::RestoreGraphicsState if invisible_text.len() > 1 => {
+                    invisible_text.pop();
+                }
+                Operation::SetTextRenderingMode(mode) => {
+                    *invisible_text.last_mut().unwrap() =
+                        matches!(mode, TextRenderingMode::Invisible | TextRenderingMode::Clip);
+                }
+                Operation::ShowText(_)
+                | Operation::ShowTexts(_)
+                | Operation::MoveToNextLineAndShowText(_)
+                | Operation::SetSpacingMoveToNextLineAndShowText(..)
+                    if !invisible_text.last().unwrap() =>
+                {
+                    return Ok(false);
+                }
+                Operation::Stroke
+                | Operation::CloseAndStroke
+                | Operation::FillNonZero
+                | Operation::FillNonZeroDeprecated
+                | Operation::FillEvenOdd
+                | Operation::FillAndStrokeNonZero
+                | Operation::FillAndStrokeEvenOdd
+                | Operation::CloseFillAndStrokeNonZero
+                | Operation::CloseFillAndStrokeEvenOdd
+                | Operation::PaintShading(_)
+                | Operation::PaintInlineImage(_)
+                | Operation::PaintXObject(_) => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// Ids of indirect objects the page depends on: objects referenced from
+    /// page resources and contents, collected transitively. `/Parent` links
+    /// are not followed, missing objects are ignored.
+    pub fn referenced_object_ids(&self) -> Result<HashSet<RuntimeObjectId>, ObjectValueError> {
+        let parent = sname("Parent");
+        let roots = self
+            .inherited_attr(&sname("Resources"))
+            .into_iter()
+            .chain(self.d.dict().get(&sname("Contents")));
+        self.d
+            .resolver()
+            .referenced_object_ids(roots, |k| *k != parent)
+    }
+
+    /// Annotations of the page, `/Annots` entry.
+    pub fn annotations(&self) -> anyhow::Result<Vec<AnnotationDict<'a, 'b>>> {
+        self.d.annots()
+    }
+
+    /// Annotations painted with the page, annotations flagged `Hidden` or
+    /// `NoView` are skipped. `/Popup` annotations, shown only on interaction,
+    /// and replies (`/IRT`), shown in the thread of the annotation they reply
+    /// to, are skipped unless `popups_and_replies` is true.
+    pub fn painted_annotations(
+        &self,
+        popups_and_replies: bool,
+    ) -> anyhow::Result<Vec<AnnotationDict<'a, 'b>>> {
+        let mut r = vec![];
+        for annot in self.annotations()? {
+            if annot
+                .flags()?
+                .intersects(AnnotationFlags::HIDDEN | AnnotationFlags::NO_VIEW)
+            {
+                continue;
+            }
+            if !popups_and_replies
+                && (annot.subtype()? == sname("Popup") || annot.in_reply_to()?.is_some())
+            {
+                continue;
+            }
+            r.push(annot);
+        }
+        Ok(r)
+    }
+
+    /// Links of the page, from `/Link` annotations with URI or go-to action,
+    /// or `/Dest` entry. Named destinations are resolved by
+    /// [`Catalog::resolve_dest()`]. Links of other actions, or destination not
+    /// found, are ignored.
+    pub fn links(&self, catalog: &Catalog) -> anyhow::Result<Vec<Link>> {
+        let resolver = self.d.resolver();
+        let mut page_ids = None;
+        let mut r = vec![];
+        for annot in self.annotations()? {
+            if annot.subtype()? != sname("Link") {
+                continue;
+            }
+
+            let dest = match annot.action()? {
+                Some(action) => {
+                    let s = resolver.resolve_container_value(action, &sname("S"))?;
+                    match s.name()?.as_str() {
+                        "URI" => {
+                            let uri = resolver.resolve_container_value(action, &sname("URI"))?;
+                            let uri = String::from_utf8_lossy(uri.as_byte_string()?).into_owned();
+                            r.push(Link {
+                                rect: annot.rect()?,
+                                target: LinkTarget::Uri(uri),
+                            });
+                            continue;
+                        }
+                        "GoTo" => resolver.resolve_container_value(action, &sname("D"))?,
+                        _ => continue,
+                    }
+                }
+                None => match resolver.opt_resolve_container_value(annot.dict(), &sname("Dest"))? {
+                    Some(dest) => dest,
+                    None => continue,
+                },
+            };
+            let Some((page_id, view)) = catalog.resolve_dest(dest)? else {
+                warn!("link destination not found: {:?}", dest);
+                continue;
+            };
+            let page_ids = match &mut page_ids {
+                Some(ids) => ids,
+                None => {
+                    page_ids.insert(catalog.pages()?.iter().map(|p| p.id()).collect::<Vec<_>>())
+                }
+            };
+            let Some(page_index) = page_ids.iter().position(|id| *id == page_id) else {
+                warn!("link destination page not found: {:?}", page_id);
+                continue;
+            };
+            r.push(Link {
+                rect: annot.rect()?,
+                target: LinkTarget::GoTo(page_index, view),
+            });
+        }
+        Ok(r)
+    }
+
+    /// Key of the page in structure tree `/ParentTree`, the `/StructParents` entry.
+    pub fn struct_parents(&self) -> Option<i32> {
+        self.d.struct_parents().unwrap()
+    }
+
+    /// Marked-content identifiers (MCID) of marked-content sequences in page
+    /// content, in content order. Property list can be inline dictionary or
+    /// name in `/Properties` resources.
+    pub fn marked_content_ids(&self) -> anyhow::Result<Vec<i32>> {
+        let resources = self.resources();
+        let mut r = vec![];
+        for op in self.content()?.operations() {
+            let Operation::BeginMarkedContentWithProperties(_, props) = op else {
+                continue;
+            };
+            let props = marked_content_properties(&resources, &props)?;
+            if let Some(mcid) = props.and_then(|d| d.get(&sname("MCID"))) {
+                r.push(mcid.int()?);
+            }
+        }
+        Ok(r)
+    }
+
+    /// Estimate rendering cost of the page without rasterizing it, by scanning
+    /// page content operations and resources.
+    pub fn complexity(&self) -> anyhow::Result<Complexity> {
+        let resources = self.resources();
+        let x_objects = resources.x_object()?;
+        let mut r = Complexity {
+            fonts: resources.font()?.len(),
+            has_shading: !resources.shading()?.is_empty(),
+            has_pattern: !resources.pattern()?.is_empty(),
+            ..Default::default()
+        };
+
+        let ops = self.content()?.operations();
+        r.operations = ops.len();
+        for op in &ops {
+            match op {
+                Operation::PaintXObject(name) => {
+                    let Some(x) = x_objects.get(&name.0) else {
+                        continue;
+                    };
+                    if x.subtype()? == XObjectType::Image {
+                        let img = ImageDict::new(x.id(), x.dict(), x.resolver())?;
+                        r.add_image(img.width()?, img.height()?);
+                    }
+                }
+                Operation::PaintInlineImage(img) => {
+                    let meta = img.meta();
+                    r.add_image(meta.width()?, meta.height()?);
+                }
+                Operation::PaintShading(_) => r.has_shading = true,
+                _ => {}
+            }
+        }
+        Ok(r)
+    }
+
     /// Parse page tree to get all pages
     pub(crate) fn parse(root: PageDict<'a, 'b>) -> Result<Vec<Self>, ObjectValueError> {
         let mut pages = Vec::new();
         let mut parents = Vec::new();
+        let mut visited = HashSet::new();
         fn handle<'a, 'b: 'a, 'c>(
             node: PageDict<'a, 'b>,
             pages: &'c mut Vec<Page<'a, 'b>>,
             parents: &'c mut Vec<PageDict<'a, 'b>>,
+            visited: &'c mut HashSet<RuntimeObjectId>,
         ) -> Result<(), ObjectValueError> {
+            // malformed page tree may link back to its ancestor, or list a node
+            // more than once, skip visited node to break the cycle
+            if let Some(id) = node.id() {
+                if !visited.insert(id) {
+                    warn!("page tree node {} already visited, skipped", id);
+                    return Ok(());
+                }
+            }
+
+            // `/Count` of intermediate node is not trusted, pages are
+            // collected by walking `/Kids`, which may mix pages and sub-trees
             if node.is_leaf() {
                 pages.push(Page::from_leaf(&node, &parents[..])?);
             } else {
                 let kids = node.kids().unwrap();
                 parents.push(node);
                 for kid in kids {
-                    handle(kid, pages, parents)?;
+                    handle(kid, pages, parents, visited)?;
                 }
+                parents.pop();
             }
             Ok(())
         }
-        handle(root, &mut pages, &mut parents)?;
+        handle(root, &mut pages, &mut parents, &mut visited)?;
         Ok(pages)
     }
 
+    /// Find page at `index` of page tree, descends only into the sub-tree
+    /// contains it, guided by `/Count` of intermediate nodes. Unlike
+    /// `parse()`, result is wrong if `/Count` is incorrect, and cyclic tree
+    /// is an error.
+    pub(crate) fn parse_at(root: PageDict<'a, 'b>, index: usize) -> anyhow::Result<Self> {
+        if index >= root.page_count()? {
+            return Err(ObjectValueError::PageIndexOutOfRange(index).into());
+        }
+
+        let mut rest = index;
+        let mut parents = Vec::new();
+        let mut visited = HashSet::new();
+        let mut node = root;
+        'descend: loop {
+            if let Some(id) = node.id() {
+                if !visited.insert(id) {
+                    return Err(ObjectValueError::CyclicReference(id).into());
+                }
+            }
+
+            if node.is_leaf() {
+                return Ok(Self::from_leaf(&node, &parents[..])?);
+            }
+            let kids = node.kids()?;
+            parents.push(node);
+            for kid in kids {
+                let n = kid.page_count()?;
+                if rest < n {
+                    node = kid;
+                    continue 'descend;
+                }
+                rest -= n;
+            }
+            return Err(ObjectValueError::PageIndexOutOfRange(index).into());
+        }
+    }
+
     fn from_leaf(
         d: &PageDict<'a, 'b>,
         parents: &[PageDict<'a, 'b>],
@@ -350,6 +869,28 @@ impl<'a, 'b: 'a> Page<'a, 'b> {
     }
 }
 
+/// Cheap metrics about how expensive a page is to render, see `Page::complexity()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Complexity {
+    /// Count of operations in page content stream.
+    pub operations: usize,
+    /// Count of painted images, both image XObject and inline image.
+    pub images: usize,
+    /// Total pixels of painted images.
+    pub image_pixels: u64,
+    /// Count of fonts defined in page resources.
+    pub fonts: usize,
+    pub has_shading: bool,
+    pub has_pattern: bool,
+}
+
+impl Complexity {
+    fn add_image(&mut self, width: u32, height: u32) {
+        self.images += 1;
+        self.image_pixels += u64::from(width) * u64::from(height);
+    }
+}
+
 pub struct PageContent {
     bufs: Vec<Vec<u8>>,
 }
@@ -359,23 +900,22 @@ impl PageContent {
         Self { bufs }
     }
 
-    pub fn operations(self) -> Vec<Operation> {
-        let mut data: Option<Vec<u8>> = None;
-        for buf in self.bufs.into_iter() {
-            if let Some(data) = data.as_mut() {
-                data.extend_from_slice(&buf);
-            } else {
-                data = Some(buf);
-            }
-        }
+    /// Content streams joined by newline. Content can split at any token
+    /// boundary into array of streams, joined with whitespace to keep the
+    /// tokens apart, see PDF 32000-1:2008 7.8.2.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bufs.join(&b'\n')
+    }
 
-        if let Some(data) = data {
-            let (input, ops) = parse_operations(&data).finish().unwrap();
-            assert!(input.is_empty(), "buf should be empty: {:?}", input);
-            ops
-        } else {
-            vec![]
-        }
+    pub fn operations(self) -> Vec<Operation> {
+        let data = match self.bufs.len() {
+            0 => return vec![],
+            1 => self.bufs.into_iter().next().unwrap(),
+            _ => self.to_bytes(),
+        };
+        let (input, ops) = parse_operations(&data).finish().unwrap();
+        assert!(input.is_empty(), "buf should be empty: {:?}", input);
+        ops
     }
 
     pub fn as_ref(&self) -> impl Iterator<Item = &[u8]> {
@@ -383,5 +923,24 @@ impl PageContent {
     }
 }
 
+/// Property list of marked-content operator, inline dictionary or name in
+/// `/Properties` of `resources`.
+pub(crate) fn marked_content_properties<'b>(
+    resources: &ResourceDict<'_, 'b>,
+    props: &'b NameOrDict,
+) -> anyhow::Result<Option<&'b Dictionary>> {
+    Ok(match props {
+        NameOrDict::Dict(d) => Some(d),
+        NameOrDict::Name(name) => match resources.properties()? {
+            Some(properties) => resources
+                .resolver()
+                .opt_resolve_container_value(properties, name)?
+                .map(|o| o.as_dict())
+                .transpose()?,
+            None => None,
+        },
+    })
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,277 @@
+//! Synthesize appearance for annotations without `/AP`, such as `/FreeText`,
+//! `/Square` and `/Circle` annotations created by some tools, painted from
+//! their color and border properties. See PDF 32000-1:2008 12.5.6.
+use super::{AnnotationDict, Rectangle};
+use crate::{
+    file::decode_text_string,
+    graphics::{NameOfDict, Operation, Point, parse_operations, trans::TextPoint},
+    object::{LiteralString, PdfObject, TextString},
+};
+use anyhow::Result as AnyResult;
+use log::warn;
+use nom::Finish;
+use prescript::sname;
+
+/// Distance of bezier control points to approximate a quarter of ellipse.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Font size if `/DA` not sets font.
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+/// Padding between `/FreeText` border and its text.
+const TEXT_PADDING: f32 = 2.0;
+
+fn stroke_color(c: &[f32]) -> Option<Operation> {
+    match *c {
+        [g] => Some(Operation::SetStrokeGray([g])),
+        [r, g, b] => Some(Operation::SetStrokeRGB([r, g, b])),
+        [c, m, y, k] => Some(Operation::SetStrokeCMYK([c, m, y, k])),
+        // empty array means transparent
+        _ => None,
+    }
+}
+
+fn fill_color(c: &[f32]) -> Option<Operation> {
+    match *c {
+        [g] => Some(Operation::SetFillGray([g])),
+        [r, g, b] => Some(Operation::SetFillRGB([r, g, b])),
+        [c, m, y, k] => Some(Operation::SetFillCMYK([c, m, y, k])),
+        _ => None,
+    }
+}
+
+/// Append path of ellipse inscribed in `rect`.
+fn append_ellipse(rect: &Rectangle, ops: &mut Vec<Operation>) {
+    let (rx, ry) = (rect.width() / 2.0, rect.height() / 2.0);
+    let (cx, cy) = (rect.left_x + rx, rect.lower_y + ry);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    ops.push(Operation::MoveToNext(Point::new(cx + rx, cy)));
+    for (c1, c2, end) in [
+        ((cx + rx, cy + ky), (cx + kx, cy + ry), (cx, cy + ry)),
+        ((cx - kx, cy + ry), (cx - rx, cy + ky), (cx - rx, cy)),
+        ((cx - rx, cy - ky), (cx - kx, cy - ry), (cx, cy - ry)),
+        ((cx + kx, cy - ry), (cx + rx, cy - ky), (cx + rx, cy)),
+    ] {
+        ops.push(Operation::AppendBezierCurve(
+            c1.into(),
+            c2.into(),
+            end.into(),
+        ));
+    }
+    ops.push(Operation::ClosePath);
+}
+
+impl AnnotationDict<'_, '_> {
+    /// Border width and dash pattern from `/BS`, fallback to `/Border`,
+    /// default to solid border of width 1.
+    fn border_width_and_dash(&self) -> AnyResult<(f32, Option<Vec<f32>>)> {
+        let resolver = self.resolver();
+        if let Some(bs) = self.border_style()? {
+            let width = resolver
+                .opt_resolve_container_value(bs, &sname("W"))?
+                .map_or(Ok(1.0), |w| w.as_number())?;
+            let dashed = resolver
+                .opt_resolve_container_value(bs, &sname("S"))?
+                .is_some_and(|s| s.name().is_ok_and(|s| s == sname("D")));
+            let dash = if dashed {
+                Some(
+                    match resolver.opt_resolve_container_value(bs, &sname("D"))? {
+                        Some(d) => d
+                            .arr()?
+                            .iter()
+                            .map(|v| v.as_number())
+                            .collect::<Result<_, _>>()?,
+                        None => vec![3.0],
+                    },
+                )
+            } else {
+                None
+            };
+            return Ok((width, dash));
+        }
+
+        let Some(border) = resolver.opt_resolve_container_value(self.dict(), &sname("Border"))?
+        else {
+            return Ok((1.0, None));
+        };
+        let border = border.arr()?;
+        let width = border.get(2).map_or(Ok(1.0), |w| w.as_number())?;
+        let dash = border
+            .get(3)
+            .map(|d| {
+                d.arr()?
+                    .iter()
+                    .map(|v| v.as_number())
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok((width, dash))
+    }
+
+    /// Border width, and line width and dash operations, None if no border.
+    fn border_ops(&self) -> AnyResult<Option<(f32, Vec<Operation>)>> {
+        let (width, dash) = self.border_width_and_dash()?;
+        if width <= 0.0 {
+            return Ok(None);
+        }
+        let mut ops = vec![Operation::SetLineWidth(width)];
+        ops.extend(dash.map(|d| Operation::SetDashPattern(d, 0.0)));
+        Ok(Some((width, ops)))
+    }
+
+    /// Operations to paint the annotation in default user space, for
+    /// `/Square`, `/Circle` and `/FreeText` annotations without `/AP`.
+    ///
+    /// Returns `None` if the annotation has `/AP`, or its subtype not supported.
+    pub fn fallback_appearance(&self) -> AnyResult<Option<Vec<Operation>>> {
+        if self.appearance()?.is_some() {
+            return Ok(None);
+        }
+        Ok(match self.subtype()?.as_str() {
+            "Square" => Some(self.shape_appearance(false)?),
+            "Circle" => Some(self.shape_appearance(true)?),
+            "FreeText" => Some(self.free_text_appearance()?),
+            _ => None,
+        })
+    }
+
+    fn shape_appearance(&self, ellipse: bool) -> AnyResult<Vec<Operation>> {
+        let rect = self.rect()?;
+        let border = self.border_ops()?;
+        let stroke = match &border {
+            Some(_) => self.color()?.as_deref().and_then(stroke_color),
+            None => None,
+        };
+        let fill = self.interior_color()?.as_deref().and_then(fill_color);
+        let paint = match (stroke.is_some(), fill.is_some()) {
+            (true, true) => Operation::FillAndStrokeNonZero,
+            (true, false) => Operation::Stroke,
+            (false, true) => Operation::FillNonZero,
+            (false, false) => return Ok(vec![]),
+        };
+
+        let mut ops = vec![Operation::SaveGraphicsState];
+        // stroke inside of `/Rect`
+        let half = match (border, &stroke) {
+            (Some((width, border)), Some(_)) => {
+                ops.extend(border);
+                width / 2.0
+            }
+            _ => 0.0,
+        };
+        ops.extend(stroke);
+        ops.extend(fill);
+        let rect = Rectangle::from_lbrt(
+            rect.left_x + half,
+            rect.lower_y + half,
+            rect.right_x - half,
+            rect.upper_y - half,
+        );
+        if ellipse {
+            append_ellipse(&rect, &mut ops);
+        } else {
+            ops.push(Operation::AppendRectangle(
+                Point::new(rect.left_x, rect.lower_y),
+                rect.width(),
+                rect.height(),
+            ));
+        }
+        ops.push(paint);
+        ops.push(Operation::RestoreGraphicsState);
+        Ok(ops)
+    }
+
+    /// `/C` fills the background, border stroked in default black, text
+    /// painted by font and color of `/DA`, one line per line of `/Contents`.
+    fn free_text_appearance(&self) -> AnyResult<Vec<Operation>> {
+        let rect = self.rect()?;
+        let mut ops = vec![Operation::SaveGraphicsState];
+        if let Some(fill) = self.color()?.as_deref().and_then(fill_color) {
+            ops.push(fill);
+            ops.push(Operation::AppendRectangle(
+                Point::new(rect.left_x, rect.lower_y),
+                rect.width(),
+                rect.height(),
+            ));
+            ops.push(Operation::FillNonZero);
+        }
+        if let Some((width, border)) = self.border_ops()? {
+            let half = width / 2.0;
+            ops.extend(border);
+            ops.push(Operation::SetStrokeGray([0.0]));
+            ops.push(Operation::AppendRectangle(
+                Point::new(rect.left_x + half, rect.lower_y + half),
+                rect.width() - half * 2.0,
+                rect.height() - half * 2.0,
+            ));
+            ops.push(Operation::Stroke);
+        }
+
+        let contents = self
+            .resolver()
+            .opt_resolve_container_value(self.dict(), &sname("Contents"))?
+            .map(|c| c.as_byte_string().map(decode_text_string))
+            .transpose()?
+            .unwrap_or_default();
+        if !contents.is_empty() {
+            let da = match self.default_appearance()? {
+                Some(da) => parse_operations(da.as_bytes())
+                    .finish()
+                    .map(|(_, ops)| ops)
+                    .unwrap_or_else(|e| {
+                        warn!("parse /DA of annotation error: {:?}", e);
+                        vec![]
+                    }),
+                None => vec![],
+            };
+            let font_size = da.iter().rev().find_map(|op| match op {
+                Operation::SetFont(_, size) => Some(*size),
+                _ => None,
+            });
+
+            // clip text to `/Rect`
+            ops.push(Operation::AppendRectangle(
+                Point::new(rect.left_x, rect.lower_y),
+                rect.width(),
+                rect.height(),
+            ));
+            ops.push(Operation::ClipNonZero);
+            ops.push(Operation::EndPath);
+            ops.push(Operation::BeginText);
+            let font_size = match font_size {
+                Some(size) => size,
+                None => {
+                    // `/Helv` is the conventional name of Helvetica in `/AcroForm` `/DR`
+                    ops.push(Operation::SetFont(
+                        NameOfDict(sname("Helv")),
+                        DEFAULT_FONT_SIZE,
+                    ));
+                    DEFAULT_FONT_SIZE
+                }
+            };
+            ops.extend(da);
+            ops.push(Operation::SetLeading(font_size));
+            ops.push(Operation::MoveTextPosition(TextPoint::new(
+                rect.left_x + TEXT_PADDING,
+                rect.upper_y - TEXT_PADDING - font_size,
+            )));
+            let contents = contents.replace("\r\n", "\n");
+            for (i, line) in contents.split(['\r', '\n']).enumerate() {
+                // text shown by simple font, chars out of Latin-1 replaced
+                let bytes: Vec<u8> = line
+                    .chars()
+                    .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                    .collect();
+                let text = TextString::Text(LiteralString(bytes.as_slice().into()));
+                ops.push(if i == 0 {
+                    Operation::ShowText(text)
+                } else {
+                    Operation::MoveToNextLineAndShowText(text)
+                });
+            }
+            ops.push(Operation::EndText);
+        }
+        ops.push(Operation::RestoreGraphicsState);
+        Ok(ops)
+    }
+}
@@ -4,29 +4,38 @@ use crate::{
         NameOrDictByRef, NameOrStream, Operation, Point, parse_operations,
         trans::{GlyphLength, GlyphToTextSpace},
     },
-    object::{PdfObject, Stream},
+    object::{Object, ObjectValueError, PdfObject, Stream},
     text::{
         CIDFontType, CIDFontWidths, EncodingDict, EncodingDifferences, FontDescriptorDict,
         FontDescriptorFlags, FontDict, FontType, Type0FontDict, Type3FontDict,
     },
 };
-use anyhow::{Ok, Result as AnyResult, anyhow, bail};
+use anyhow::{Ok, Result as AnyResult, bail};
 use cff_parser::{File as CffFile, Font as CffFont};
 use either::Either;
 use font_kit::loaders::freetype::Font as FontKitFont;
-use fontdb::{Database, Family, Query, Source, Weight};
+use fontdb::{Database, FaceInfo, Family, ID, Query, Source, Weight};
 use heck::ToTitleCase;
 use log::{debug, error, info, warn};
 use num_traits::ToPrimitive;
 use ouroboros::self_referencing;
 use pathfinder_geometry::{line_segment::LineSegment2F, vector::Vector2F};
 use phf::phf_map;
+#[cfg(feature = "cjk")]
+use prescript::cmap::{CID, CIDToUnicode};
 use prescript::{
     Encoding, NOTDEF, Name,
     cmap::{CMap, CMapRegistry},
     name, sname,
 };
-use std::{collections::HashMap, ops::RangeInclusive, rc::Rc, sync::LazyLock};
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashMap,
+    ops::RangeInclusive,
+    rc::Rc,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tinyvec::{TinyVec, tiny_vec};
 use ttf_parser::{Face as TTFFace, GlyphId, OutlineBuilder};
 
 /// FontWidth used in Type1 and TrueType fonts
@@ -55,6 +64,20 @@ impl FirstLastFontWidth {
         }))
     }
 
+    /// Widths of char codes 0..=255, looked up by glyph name of `encoding` in
+    /// AFM font metrics.
+    fn from_afm(afm: &str, encoding: &Encoding) -> Self {
+        let glyph_widths = parse_afm_widths(afm);
+        let widths = (0..=255u8)
+            .map(|ch| glyph_widths.get(encoding.get_str(ch)).copied().unwrap_or(0))
+            .collect();
+        Self {
+            range: 0..=255,
+            widths,
+            default_width: 0,
+        }
+    }
+
     fn char_width(&self, ch: u32) -> GlyphLength {
         GlyphLength::new(if self.range.contains(&ch) {
             let idx = (ch - self.range.start()) as usize;
@@ -65,6 +88,27 @@ impl FirstLastFontWidth {
     }
 }
 
+/// Parse glyph name to width map from AFM file, only `WX` and `N` of char
+/// metrics are used.
+fn parse_afm_widths(afm: &str) -> HashMap<&str, u32> {
+    let mut r = HashMap::new();
+    for line in afm.lines().filter(|l| l.starts_with("C ")) {
+        let mut width = None;
+        let mut name = None;
+        for item in line.split(';').map(str::trim) {
+            if let Some(v) = item.strip_prefix("WX ") {
+                width = v.trim().parse().ok();
+            } else if let Some(v) = item.strip_prefix("N ") {
+                name = Some(v.trim());
+            }
+        }
+        if let (Some(name), Some(width)) = (name, width) {
+            r.insert(name, width);
+        }
+    }
+    r
+}
+
 struct FreeTypeFontWidth<'a> {
     font: &'a FontKitFont,
 }
@@ -195,17 +239,22 @@ impl<'a, 'b, 'c> EncodingParser<'a, 'b, 'c> {
     fn load_from_file(
         font_name: &str,
         font_data: &[u8],
-        is_cff: bool,
+        format: Type1FontFormat,
     ) -> AnyResult<Option<Encoding>> {
-        if is_cff {
-            info!("scan encoding from cff font. ({})", font_name);
-            let cff_file: CffFile = CffFile::open(font_data)?;
-            let font: CffFont = cff_file.iter()?.next().expect("no font in cff?");
-            Ok(Some(font.encodings()?))
-        } else {
-            info!("scan encoding from type1 font. ({})", font_name);
-            let type1_font = prescript::Font::parse(font_data)?;
-            Ok(type1_font.encoding().cloned())
+        match format {
+            Type1FontFormat::Cff => {
+                info!("scan encoding from cff font. ({})", font_name);
+                let cff_file: CffFile = CffFile::open(font_data)?;
+                let font: CffFont = cff_file.iter()?.next().expect("no font in cff?");
+                Ok(Some(font.encodings()?))
+            }
+            Type1FontFormat::Type1 => {
+                info!("scan encoding from type1 font. ({})", font_name);
+                let type1_font = prescript::Font::parse(font_data)?;
+                Ok(type1_font.encoding().cloned())
+            }
+            // glyphs of OpenType font mapped by cmap, no built-in encoding
+            Type1FontFormat::OpenType => Ok(None),
         }
     }
 
@@ -235,12 +284,21 @@ impl<'a, 'b, 'c> EncodingParser<'a, 'b, 'c> {
         encoding
     }
 
-    pub fn type1(&self, is_cff: bool, font_data: &[u8]) -> AnyResult<Encoding> {
+    pub fn type1(
+        &self,
+        format: Type1FontFormat,
+        font_data: &[u8],
+        shared: Option<&SharedFontCache>,
+    ) -> AnyResult<Encoding> {
         let encoding_pair = self.encoding_pair()?;
         let font_name = self.0.font_name()?;
+        let load = || Self::load_from_file(font_name.as_ref(), font_data, format).unwrap();
         let r = self
             .resolve_by_encoding_or_font_name(&encoding_pair, font_name.as_ref())
-            .or_else(|| Self::load_from_file(font_name.as_ref(), font_data, is_cff).unwrap())
+            .or_else(|| match shared {
+                Some(shared) => shared.encoding(font_data, load),
+                None => load(),
+            })
             .or_else(|| Self::guess_by_font_name(font_name.as_ref()))
             .unwrap_or_else(|| self.default_encoding().unwrap());
         Ok(self.apply_encoding_diff(r, &encoding_pair))
@@ -255,6 +313,20 @@ impl<'a, 'b, 'c> EncodingParser<'a, 'b, 'c> {
     }
 
     fn encoding_pair(&self) -> AnyResult<Option<EncodingPair>> {
+        // Some malformed files use CMap stream as /Encoding of simple font,
+        // ignore it and fallback to font built-in encoding.
+        if let Some(Object::Stream(_)) = self
+            .0
+            .resolver()
+            .opt_resolve_container_value(self.0.dict(), &sname("Encoding"))?
+        {
+            warn!(
+                "Stream /Encoding of simple font '{}' not supported, use built-in encoding",
+                self.0.font_name()?
+            );
+            return Ok(None);
+        }
+
         let encoding = self.0.encoding()?;
         let Some(encoding) = encoding else {
             return Ok(None);
@@ -287,23 +359,50 @@ struct Type1FontOp<'a> {
     font_width: Either<FirstLastFontWidth, FreeTypeFontWidth<'a>>,
     font: &'a FontKitFont,
     encoding: Encoding,
+    units_per_em: u16,
+}
+
+/// Units per em derived from FontMatrix of type1 font, FreeType do not
+/// always respect non-default FontMatrix.
+fn type1_units_per_em(font_data: &[u8]) -> Option<u16> {
+    let font = prescript::Font::parse(font_data)
+        .inspect_err(|e| info!("failed to parse type1 font for FontMatrix: {}", e))
+        .ok()?;
+    let scale = font.font_matrix()[3].abs();
+    #[allow(clippy::cast_possible_truncation)]
+    (scale > 0.0).then(|| (1.0 / scale).round() as u16)
 }
 
 impl<'a> Type1FontOp<'a> {
     fn new(
         font_dict: &FontDict,
         font: &'a FontKitFont,
-        is_cff: bool,
+        format: Type1FontFormat,
         font_data: &'a [u8],
+        shared: Option<&SharedFontCache>,
     ) -> AnyResult<Self> {
+        let encoding = EncodingParser(font_dict).type1(format, font_data, shared)?;
+        // standard 14 fonts may omit /Widths, use widths from their AFM metrics,
+        // advances of the substituted font may differ.
         let font_width = FirstLastFontWidth::from(font_dict)?
+            .or_else(|| {
+                standard_14_type1_font_metrics(font_dict.font_name().ok()?.as_ref())
+                    .map(|afm| FirstLastFontWidth::from_afm(afm, &encoding))
+            })
             .map_or_else(|| Either::Right(FreeTypeFontWidth::new(font)), Either::Left);
-        let encoding = EncodingParser(font_dict).type1(is_cff, font_data)?;
+        let units_per_em = (format == Type1FontFormat::Type1)
+            .then(|| match shared {
+                Some(shared) => shared.units_per_em(font_data, || type1_units_per_em(font_data)),
+                None => type1_units_per_em(font_data),
+            })
+            .flatten()
+            .unwrap_or_else(|| font.metrics().units_per_em.try_into().unwrap());
 
         Ok(Self {
             font_width,
             font,
             encoding,
+            units_per_em,
         })
     }
 }
@@ -340,28 +439,52 @@ impl<'a> FontOp for Type1FontOp<'a> {
     }
 
     fn units_per_em(&self) -> u16 {
-        self.font.metrics().units_per_em.try_into().unwrap()
+        self.units_per_em
+    }
+
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        glyph_name_to_unicode(self.encoding.get_str(ch.try_into().ok()?))
     }
 }
 
+/// Format of Type1 font program, outlines of all formats are loaded by
+/// font-kit, format decides where to read built-in encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type1FontFormat {
+    /// `/FontFile`, or standard 14 font file
+    Type1,
+    /// `/FontFile3` of `/Subtype /Type1C`, bare CFF, encoding read by cff-parser
+    Cff,
+    /// `/FontFile3` of `/Subtype /OpenType`
+    OpenType,
+}
+
 /// Font implementation using free-type/(font-kit), to handle Type1 fonts
 struct Type1Font<'a, 'b> {
     font_data: Vec<u8>,
-    is_cff: bool,
+    format: Type1FontFormat,
     font: FontKitFont,
     font_dict: FontDict<'a, 'b>,
+    shared: Option<Arc<SharedFontCache>>,
 }
 
 impl<'a, 'b> Type1Font<'a, 'b> {
-    fn new(is_cff: bool, data: Vec<u8>, font_dict: FontDict<'a, 'b>) -> AnyResult<Self> {
+    fn new(
+        format: Type1FontFormat,
+        data: Vec<u8>,
+        font_dict: FontDict<'a, 'b>,
+        shared: Option<Arc<SharedFontCache>>,
+        faces: Option<&FontFaces>,
+    ) -> AnyResult<Self> {
         debug_assert_eq!(data.capacity(), data.len());
 
-        let font = FontKitFont::from_bytes(data.clone().into(), 0)?;
+        let font = load_face(&data, faces)?;
         Ok(Self {
             font_data: data,
-            is_cff,
+            format,
             font,
             font_dict,
+            shared,
         })
     }
 }
@@ -375,8 +498,9 @@ impl<'a, 'b: 'a, P: PathSink> Font<P> for Type1Font<'a, 'b> {
         Ok(Box::new(Type1FontOp::new(
             &self.font_dict,
             &self.font,
-            self.is_cff,
+            self.format,
             self.font_data.as_slice(),
+            self.shared.as_deref(),
         )?))
     }
 
@@ -409,6 +533,13 @@ impl<'a> TTFParserFontOp<'a> {
 
 static GLYPH_NAME_TO_UNICODE: phf::Map<&'static str, u32> = include!("glyph_name_to_unicode.in");
 
+/// Unicode of glyph name by Adobe Glyph List, None if not listed.
+fn glyph_name_to_unicode(glyph_name: &str) -> Option<char> {
+    GLYPH_NAME_TO_UNICODE
+        .get(glyph_name)
+        .and_then(|u| char::from_u32(*u))
+}
+
 impl<'a> FontOp for TTFParserFontOp<'a> {
     fn decode_chars(&self, s: &[u8]) -> Vec<u32> {
         s.iter().map(|v| *v as u32).collect()
@@ -423,11 +554,8 @@ impl<'a> FontOp for TTFParserFontOp<'a> {
                 } else {
                     // If glyph_name not in font CMap, convert to unicode then resolve by unicode
                     // use Adobe Glyph List to convert glyph name to unicode
-                    if let Some(unicode) = GLYPH_NAME_TO_UNICODE.get(glyph_name) {
-                        if let Some(gid) = self
-                            .face
-                            .glyph_index(unsafe { char::from_u32_unchecked(*unicode) })
-                        {
+                    if let Some(unicode) = glyph_name_to_unicode(glyph_name) {
+                        if let Some(gid) = self.face.glyph_index(unicode) {
                             return gid.0;
                         }
                     }
@@ -456,6 +584,15 @@ impl<'a> FontOp for TTFParserFontOp<'a> {
     fn units_per_em(&self) -> u16 {
         self.units_per_em
     }
+
+    /// Unicode of glyph name in encoding, char code treated as unicode if
+    /// font has no encoding.
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        match &self.encoding {
+            Some(encoding) => glyph_name_to_unicode(encoding.get_str(ch.try_into().ok()?)),
+            None => char::from_u32(ch),
+        }
+    }
 }
 
 struct TTFParserGlyphRender<'a> {
@@ -508,12 +645,128 @@ impl<'a, 'b, P: PathSink> Font<P> for TTFParserFont<'a, 'b> {
     }
 }
 
+/// Font op renders glyphs using forced font, char codes decoded by document font,
+/// and use document font widths for spacing.
+struct ForcedFontOp<'a> {
+    inner: Box<dyn FontOp + 'a>,
+    face: TTFFace<'a>,
+}
+
+impl<'a> FontOp for ForcedFontOp<'a> {
+    fn decode_chars(&self, s: &[u8]) -> Vec<u32> {
+        self.inner.decode_chars(s)
+    }
+
+    fn decode_code_chars(&self, s: &[u8]) -> Vec<(u32, u32)> {
+        self.inner.decode_code_chars(s)
+    }
+
+    /// Lookup glyph in forced font by unicode of the char, from `/ToUnicode`
+    /// or encoding of document font. Char is used as its char code, which
+    /// holds except for CID fonts of non-identity CMap.
+    fn char_to_gid(&self, ch: u32) -> u16 {
+        self.inner
+            .char_to_unicode(ch, ch)
+            .and_then(|chars| chars.first().copied())
+            .and_then(|c| self.face.glyph_index(c))
+            .map_or_else(
+                || {
+                    warn!("Forced font glyph id not found for char: {}", ch);
+                    0
+                },
+                |gid| gid.0,
+            )
+    }
+
+    fn char_width(&self, ch: u32) -> GlyphLength {
+        self.inner.char_width(ch) / self.inner.units_per_em() as f32
+            * self.face.units_per_em() as f32
+    }
+
+    fn units_per_em(&self) -> u16 {
+        self.face.units_per_em()
+    }
+
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        self.inner.to_unicode(ch)
+    }
+
+    fn char_to_unicode(&self, code: u32, ch: u32) -> Option<TinyVec<[char; 4]>> {
+        self.inner.char_to_unicode(code, ch)
+    }
+}
+
+/// Replace glyphs of document font with a known font, for debugging.
+struct ForcedFont<'c, P> {
+    inner: Box<dyn Font<P> + 'c>,
+    data: Rc<[u8]>,
+    /// `/ToUnicode` of document font, wraps inner op so that glyphs are
+    /// looked up by it.
+    to_unicode: Option<Rc<CMap>>,
+}
+
+impl<'c, P: PathSink> Font<P> for ForcedFont<'c, P> {
+    fn font_type(&self) -> FontType {
+        self.inner.font_type()
+    }
+
+    fn create_op(&self, cmap_registry: &mut CMapRegistry) -> AnyResult<Box<dyn FontOp + '_>> {
+        let mut inner = self.inner.create_op(cmap_registry)?;
+        if let Some(to_unicode) = &self.to_unicode {
+            inner = Box::new(ToUnicodeFontOp {
+                inner,
+                to_unicode: Rc::clone(to_unicode),
+            });
+        }
+        Ok(Box::new(ForcedFontOp {
+            inner,
+            face: TTFFace::parse(&self.data, 0)?,
+        }))
+    }
+
+    fn create_glyph_render(&self) -> AnyResult<Box<dyn GlyphRender<P> + '_>> {
+        let face = TTFFace::parse(&self.data, 0)?;
+        Ok(Box::new(TTFParserGlyphRender { face }))
+    }
+}
+
 static SYSTEM_FONTS: LazyLock<Database> = LazyLock::new(|| {
     let mut db = Database::new();
     db.load_system_fonts();
     db
 });
 
+fn system_face_bytes(face: &FaceInfo) -> AnyResult<Vec<u8>> {
+    match face.source {
+        Source::File(ref path) => Ok(std::fs::read(path)?),
+        Source::Binary(ref bytes) => Ok(bytes.as_ref().as_ref().to_owned()),
+        Source::SharedFile(_, ref bytes) => Ok(bytes.as_ref().as_ref().to_owned()),
+    }
+}
+
+/// Data of system font of family or PostScript name `name`, for
+/// [`FontCache::new()`] `force_font`.
+pub fn system_font_data(name: &str) -> AnyResult<Vec<u8>> {
+    let families = [Family::Name(name)];
+    let q = Query {
+        families: &families,
+        ..Default::default()
+    };
+    let Some(id) = SYSTEM_FONTS.query(&q).or_else(|| {
+        SYSTEM_FONTS
+            .faces()
+            .find(|f| f.post_script_name == name)
+            .map(|f| f.id)
+    }) else {
+        bail!("font {} not found in system", name);
+    };
+    let face = SYSTEM_FONTS.face(id).unwrap();
+    if face.index != 0 {
+        bail!("font {} in font collection not supported", name);
+    }
+    system_face_bytes(face)
+}
+
 /// Remove suffix "MT"/"PSMT" from font name. And remove ",Bold", ",BoldItalic", ".BoldOblique",
 /// ",Italic", "-BoldItalic", "-Bold", "-Italic", "-BoldOblique", "-Oblique", "-BoldOblique"
 fn normalize_true_type_font_name(name: &str) -> String {
@@ -665,6 +918,230 @@ fn standard_14_type1_font_data(font_name: &str) -> Option<&'static [u8]> {
     }
 }
 
+/// Bundled fonts tried in order by [`FallbackFont`] before system fonts.
+const FALLBACK_BUNDLED_FONTS: [&str; 2] = ["Helvetica", "Symbol"];
+
+thread_local! {
+    /// Fallback font of current thread, loaded on first use. Its fonts can
+    /// not be sent to other threads.
+    static FALLBACK_FONT: OnceCell<Rc<FallbackFont>> = const { OnceCell::new() };
+}
+
+/// Fonts to substitute glyphs missing in document fonts, such as chars
+/// omitted by subset fonts. Bundled sans and symbol fonts tried first, then
+/// the first system font has the char, regular faces preferred.
+pub struct FallbackFont {
+    bundled: Vec<FontKitFont>,
+    /// System faces in lookup order.
+    system_faces: Vec<ID>,
+    loaded: RefCell<HashMap<ID, Rc<FontKitFont>>>,
+    /// System face found for char, None if no face has it.
+    chars: RefCell<HashMap<char, Option<ID>>>,
+}
+
+impl FallbackFont {
+    pub fn new() -> AnyResult<Self> {
+        let bundled = FALLBACK_BUNDLED_FONTS
+            .iter()
+            .map(|name| {
+                let data = standard_14_type1_font_data(name).unwrap();
+                FontKitFont::from_bytes(data.to_vec().into(), 0)
+            })
+            .collect::<Result<_, _>>()?;
+        let mut faces: Vec<_> = SYSTEM_FONTS.faces().collect();
+        faces.sort_by_key(|f| {
+            (
+                f.style != fontdb::Style::Normal,
+                f.monospaced,
+                f.weight.0.abs_diff(Weight::NORMAL.0),
+            )
+        });
+        Ok(Self {
+            bundled,
+            system_faces: faces.into_iter().map(|f| f.id).collect(),
+            loaded: RefCell::default(),
+            chars: RefCell::default(),
+        })
+    }
+
+    /// Fallback font shared by renders of current thread, system faces
+    /// scanned and fonts loaded once per thread.
+    pub fn shared() -> AnyResult<Rc<Self>> {
+        FALLBACK_FONT.with(|f| {
+            if let Some(font) = f.get() {
+                return Ok(font.clone());
+            }
+            let font = Rc::new(Self::new()?);
+            Ok(f.get_or_init(|| font).clone())
+        })
+    }
+
+    /// Render glyph of `ch`, returns units per em of the font rendered it,
+    /// None if no font has a glyph for it.
+    pub fn render<P: PathSink>(&self, ch: char, sink: &mut P) -> AnyResult<Option<u16>> {
+        for font in &self.bundled {
+            if let Some(units_per_em) = Self::render_by(font, ch, sink)? {
+                return Ok(Some(units_per_em));
+            }
+        }
+        match self.system_font(ch)? {
+            Some(font) => Self::render_by(&font, ch, sink),
+            None => Ok(None),
+        }
+    }
+
+    fn render_by<P: PathSink>(
+        font: &FontKitFont,
+        ch: char,
+        sink: &mut P,
+    ) -> AnyResult<Option<u16>> {
+        let Some(gid) = font.glyph_for_char(ch).filter(|gid| *gid != 0) else {
+            return Ok(None);
+        };
+        Type1GlyphRender { font }.render(gid.try_into()?, sink)?;
+        Ok(Some(font.metrics().units_per_em.try_into()?))
+    }
+
+    fn system_font(&self, ch: char) -> AnyResult<Option<Rc<FontKitFont>>> {
+        let id = *self.chars.borrow_mut().entry(ch).or_insert_with(|| {
+            self.system_faces.iter().copied().find(|id| {
+                SYSTEM_FONTS
+                    .with_face_data(*id, |data, index| {
+                        TTFFace::parse(data, index)
+                            .is_ok_and(|f| f.glyph_index(ch).is_some_and(|gid| gid.0 != 0))
+                    })
+                    .unwrap_or(false)
+            })
+        });
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        if let Some(font) = self.loaded.borrow().get(&id) {
+            return Ok(Some(font.clone()));
+        }
+
+        let face = SYSTEM_FONTS.face(id).unwrap();
+        debug!("load fallback font for {:?}: {:?}", ch, &face.source);
+        let font = Rc::new(FontKitFont::from_bytes(
+            system_face_bytes(face)?.into(),
+            face.index,
+        )?);
+        self.loaded.borrow_mut().insert(id, font.clone());
+        Ok(Some(font))
+    }
+}
+
+/// AFM font metrics of standard 14 font, widths of bundled font files.
+fn standard_14_type1_font_metrics(font_name: &str) -> Option<&'static str> {
+    let font_name = normalize_font_name(font_name);
+
+    match font_name {
+        "Courier" => Some(include_str!("../../../../fonts/n022003l.afm")),
+        "Courier-Bold" => Some(include_str!("../../../../fonts/n022004l.afm")),
+        "Courier-BoldOblique" => Some(include_str!("../../../../fonts/n022024l.afm")),
+        "Courier-Oblique" => Some(include_str!("../../../../fonts/n022023l.afm")),
+        "Helvetica" => Some(include_str!("../../../../fonts/n019003l.afm")),
+        "Helvetica-Bold" => Some(include_str!("../../../../fonts/n019004l.afm")),
+        "Helvetica-BoldOblique" => Some(include_str!("../../../../fonts/n019024l.afm")),
+        "Helvetica-Oblique" => Some(include_str!("../../../../fonts/n019023l.afm")),
+        "Symbol" => Some(include_str!("../../../../fonts/s050000l.afm")),
+        "Times-Bold" => Some(include_str!("../../../../fonts/n021004l.afm")),
+        "Times-BoldItalic" => Some(include_str!("../../../../fonts/n021024l.afm")),
+        "Times-Italic" => Some(include_str!("../../../../fonts/n021023l.afm")),
+        "Times-Roman" => Some(include_str!("../../../../fonts/n021003l.afm")),
+        "ZapfDingbats" => Some(include_str!("../../../../fonts/d050000l.afm")),
+        _ => None,
+    }
+}
+
+/// Font data shared by [`FontCache`]s of pages loaded on different threads,
+/// such as pages rendered in parallel. Built-in encodings and units per em
+/// of Type1 fonts are keyed by font bytes, so fonts embedded in each page or
+/// standard 14 fonts are scanned once. Bytes of system fonts are read once.
+/// FreeType faces can not be sent to other threads, see [`FontFaces`].
+#[derive(Debug, Default)]
+pub struct SharedFontCache {
+    encodings: Mutex<HashMap<Arc<[u8]>, Option<Encoding>>>,
+    units_per_em: Mutex<HashMap<Arc<[u8]>, Option<u16>>>,
+    system_fonts: Mutex<HashMap<ID, Arc<[u8]>>>,
+}
+
+impl SharedFontCache {
+    fn cached<V: Clone>(
+        map: &Mutex<HashMap<Arc<[u8]>, V>>,
+        font_data: &[u8],
+        load: impl FnOnce() -> V,
+    ) -> V {
+        if let Some(r) = map.lock().unwrap().get(font_data) {
+            return r.clone();
+        }
+        // scan without lock, other threads may scan the same font meanwhile
+        let r = load();
+        map.lock().unwrap().insert(font_data.into(), r.clone());
+        r
+    }
+
+    fn encoding(
+        &self,
+        font_data: &[u8],
+        load: impl FnOnce() -> Option<Encoding>,
+    ) -> Option<Encoding> {
+        Self::cached(&self.encodings, font_data, load)
+    }
+
+    fn units_per_em(&self, font_data: &[u8], load: impl FnOnce() -> Option<u16>) -> Option<u16> {
+        Self::cached(&self.units_per_em, font_data, load)
+    }
+
+    fn system_face_bytes(&self, face: &FaceInfo) -> AnyResult<Vec<u8>> {
+        if let Some(r) = self.system_fonts.lock().unwrap().get(&face.id) {
+            return Ok(r.to_vec());
+        }
+        let r = system_face_bytes(face)?;
+        self.system_fonts
+            .lock()
+            .unwrap()
+            .insert(face.id, r.as_slice().into());
+        Ok(r)
+    }
+}
+
+/// FreeType faces parsed by [`FontCache`]s on one thread, keyed by font
+/// bytes. Faces are bound to FreeType library of the thread, can not be sent
+/// to other threads, owner creates one for each thread, such as each worker
+/// of parallel renders.
+#[derive(Default)]
+pub struct FontFaces(RefCell<HashMap<Arc<[u8]>, FontKitFont>>);
+
+impl std::fmt::Debug for FontFaces {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FontFaces")
+            .field(&self.0.borrow().len())
+            .finish()
+    }
+}
+
+impl FontFaces {
+    /// FreeType face of `font_data`, parsed once, cloned face shares the
+    /// parsed one.
+    fn face(&self, font_data: &[u8]) -> AnyResult<FontKitFont> {
+        if let Some(face) = self.0.borrow().get(font_data) {
+            return Ok(face.clone());
+        }
+        let face = FontKitFont::from_bytes(font_data.to_vec().into(), 0)?;
+        self.0.borrow_mut().insert(font_data.into(), face.clone());
+        Ok(face)
+    }
+}
+
+/// FreeType face of `font_data`, reused from `faces` if not None.
+fn load_face(font_data: &[u8], faces: Option<&FontFaces>) -> AnyResult<FontKitFont> {
+    match faces {
+        Some(faces) => faces.face(font_data),
+        None => Ok(FontKitFont::from_bytes(font_data.to_vec().into(), 0)?),
+    }
+}
+
 #[self_referencing]
 struct FontCacheInner<'c, P: PathSink + 'static> {
     fonts: HashMap<Name, Box<dyn Font<P> + 'c>>,
@@ -681,7 +1158,10 @@ pub struct FontCache<'c, P: PathSink + 'static> {
 }
 
 impl<'c, P: PathSink + 'static> FontCache<'c, P> {
-    fn load_true_type_from_os(desc: &FontDescriptorDict) -> AnyResult<Vec<u8>> {
+    fn load_true_type_from_os(
+        desc: &FontDescriptorDict,
+        shared: Option<&SharedFontCache>,
+    ) -> AnyResult<Vec<u8>> {
         let font_name = desc.font_name()?;
         let font_name = normalize_true_type_font_name(&font_name);
         let font_name = font_name.to_title_case();
@@ -724,10 +1204,9 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
         let face = SYSTEM_FONTS.face(id).unwrap();
         debug!("loaded ttf font: {:?}", &face.source);
         assert_eq!(face.index, 0, "Only one face supported");
-        match face.source {
-            Source::File(ref path) => Ok(std::fs::read(path)?),
-            Source::Binary(ref bytes) => Ok(bytes.as_ref().as_ref().to_owned()),
-            Source::SharedFile(_, ref bytes) => Ok(bytes.as_ref().as_ref().to_owned()),
+        match shared {
+            Some(shared) => shared.system_face_bytes(face),
+            None => system_face_bytes(face),
         }
     }
 
@@ -739,6 +1218,8 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
         font_type: FontType,
         font: FontDict<'a, 'b>,
         desc: FontDescriptorDict<'a, 'b>,
+        shared: Option<&SharedFontCache>,
+        faces: Option<&FontFaces>,
     ) -> AnyResult<Box<dyn Font<P> + 'b>> {
         let (is_embed, ttf_bytes) = match desc.font_file2()? {
             Some(stream) => {
@@ -752,14 +1233,16 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                             desc.font_name()?,
                             e
                         );
-                        (false, Self::load_true_type_from_os(&desc)?)
+                        (false, Self::load_true_type_from_os(&desc, shared)?)
                     }
                 }
             }
-            None => (false, Self::load_true_type_from_os(&desc)?),
+            None => (false, Self::load_true_type_from_os(&desc, shared)?),
         };
         if font_type == FontType::Type0 {
-            Ok(Box::new(CIDFontType2Font::new(is_embed, ttf_bytes, font)?))
+            Ok(Box::new(CIDFontType2Font::new(
+                is_embed, ttf_bytes, font, faces,
+            )?))
         } else {
             Ok(Box::new(TTFParserFont::new(
                 font.subtype()?,
@@ -773,7 +1256,11 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
     /// by TrueType fonts scanned from current OS. Because Type1 fonts are not
     /// supported by swash, and the only crate support Type1 fonts is `font`, which
     /// I am not familiar with.
-    fn load_type1_font<'a, 'b>(font: FontDict<'a, 'b>) -> AnyResult<Type1Font<'a, 'b>>
+    fn load_type1_font<'a, 'b>(
+        font: FontDict<'a, 'b>,
+        shared: Option<&Arc<SharedFontCache>>,
+        faces: Option<&FontFaces>,
+    ) -> AnyResult<Type1Font<'a, 'b>>
     where
         'a: 'c,
         'b: 'c,
@@ -783,22 +1270,34 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
         let desc = f.font_descriptor()?;
         let font_data = desc
             .map(|desc| -> AnyResult<_> {
-                let r = desc
-                    .font_file()
-                    .map(|s| s.map(|s| (false, s)))
-                    .transpose()
-                    .or_else(
-                        || desc.font_file3().map(|s| s.map(|s| (true, s))).transpose(), /* if Compact Font Format*/
-                    )
-                    .transpose();
-                r
+                if let Some(s) = desc.font_file()? {
+                    return Ok(Some((Type1FontFormat::Type1, s)));
+                }
+                let Some(s) = desc.font_file3()? else {
+                    return Ok(None);
+                };
+                let subtype = desc
+                    .resolver()
+                    .opt_resolve_container_value(s.as_dict(), &sname("Subtype"))?
+                    .map(|o| o.name())
+                    .transpose()?;
+                let format = match subtype.as_ref().map(|n| n.as_str()) {
+                    Some("OpenType") => Type1FontFormat::OpenType,
+                    Some("Type1C") => Type1FontFormat::Cff,
+                    subtype => {
+                        // other subtypes are for CID fonts, try as CFF
+                        warn!("Unexpected /FontFile3 subtype of Type1 font: {:?}", subtype);
+                        Type1FontFormat::Cff
+                    }
+                };
+                Ok(Some((format, s)))
             })
             .transpose()?
             .flatten();
-        let (is_cff, mut bytes) = match font_data {
+        let (format, mut bytes) = match font_data {
             Some(s) => (s.0, Self::load_embed_font_bytes(f.resolver(), s.1)?),
             None => (
-                false,
+                Type1FontFormat::Type1,
                 if let Some(font_data) = standard_14_type1_font_data(font_name.as_ref()) {
                     font_data.to_owned()
                 } else {
@@ -807,10 +1306,14 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
             ),
         };
         bytes.shrink_to_fit();
-        Type1Font::new(is_cff, bytes, font)
+        Type1Font::new(format, bytes, font, shared.cloned(), faces)
     }
 
-    fn scan_font<'a, 'b>(font: FontDict<'a, 'b>) -> AnyResult<Option<Box<dyn Font<P> + 'c>>>
+    fn scan_font<'a, 'b>(
+        font: FontDict<'a, 'b>,
+        shared: Option<&Arc<SharedFontCache>>,
+        faces: Option<&FontFaces>,
+    ) -> AnyResult<Option<Box<dyn Font<P> + 'c>>>
     where
         'a: 'c,
         'b: 'c,
@@ -824,6 +1327,8 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                     FontType::TrueType,
                     font,
                     desc,
+                    shared.map(Arc::as_ref),
+                    faces,
                 )?))
             }
 
@@ -843,6 +1348,7 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                         Ok(Some(Box::new(CIDFontType0Font::new(
                             font,
                             Self::load_embed_font_bytes(descentdant_font.resolver(), stream)?,
+                            faces,
                         )?)))
                     }
                     CIDFontType::CIDFontType2 => {
@@ -852,12 +1358,14 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                             FontType::Type0,
                             font,
                             desc,
+                            shared.map(Arc::as_ref),
+                            faces,
                         )?))
                     }
                 }
             }
 
-            FontType::Type1 => Self::load_type1_font(font.clone())
+            FontType::Type1 => Self::load_type1_font(font.clone(), shared, faces)
                 .map(|v| Some(Box::new(v) as Box<dyn Font<P> + 'c>))
                 .or_else(|err| {
                     info!(
@@ -869,6 +1377,8 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                         FontType::Type1,
                         font,
                         desc,
+                        shared.map(Arc::as_ref),
+                        faces,
                     )?))
                 }),
 
@@ -880,7 +1390,17 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
         }
     }
 
-    pub fn new<'a, 'b>(resource: &'c ResourceDict<'a, 'b>) -> anyhow::Result<Self>
+    /// Load fonts of `resource`. If `force_font` is not None, it is TrueType/OpenType
+    /// font data used to render glyphs of all fonts except Type3 fonts, glyphs
+    /// looked up by unicode of chars.
+    /// Font data parsed before is reused from `shared` and FreeType faces from
+    /// `faces` if not None.
+    pub fn new<'a, 'b>(
+        resource: &'c ResourceDict<'a, 'b>,
+        force_font: Option<Rc<[u8]>>,
+        shared: Option<&Arc<SharedFontCache>>,
+        faces: Option<&FontFaces>,
+    ) -> anyhow::Result<Self>
     where
         'a: 'c,
         'b: 'c,
@@ -888,9 +1408,27 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
     {
         let font_res = resource.font()?;
         let mut fonts = HashMap::with_capacity(font_res.len());
+        let mut to_unicodes = HashMap::new();
         for (k, v) in font_res.into_iter() {
             info!("load font: {:?}", k);
-            let font = Self::scan_font(v)?;
+            let mut to_unicode = v.to_unicode().unwrap_or_else(|e| {
+                warn!("failed to parse ToUnicode of font {}: {}", k, e);
+                None
+            });
+            let mut font = Self::scan_font(v, shared, faces)?;
+            if let Some(data) = &force_font {
+                font = font.map(|inner| match inner.font_type() {
+                    FontType::Type3 => inner,
+                    _ => Box::new(ForcedFont {
+                        inner,
+                        data: Rc::clone(data),
+                        to_unicode: to_unicode.take(),
+                    }) as Box<dyn Font<P> + 'c>,
+                });
+            }
+            if let Some(to_unicode) = to_unicode {
+                to_unicodes.insert(k.clone(), to_unicode);
+            }
             if let Some(font) = font {
                 fonts.insert(k, font);
             }
@@ -904,7 +1442,14 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
                     let mut ops = HashMap::with_capacity(fonts.len());
                     for (k, v) in fonts {
                         debug!("Create {} font_op", k.as_str());
-                        ops.insert(k.clone(), v.create_op(&mut cmap_registry)?);
+                        let mut op = v.create_op(&mut cmap_registry)?;
+                        if let Some(to_unicode) = to_unicodes.remove(k) {
+                            op = Box::new(ToUnicodeFontOp {
+                                inner: op,
+                                to_unicode,
+                            });
+                        }
+                        ops.insert(k.clone(), op);
                     }
                     Ok(ops)
                 },
@@ -935,25 +1480,124 @@ impl<'c, P: PathSink + 'static> FontCache<'c, P> {
 pub trait FontOp {
     /// Decode char codes to chars, possible using some encoding
     fn decode_chars(&self, s: &[u8]) -> Vec<u32>;
+
+    /// Char code and char of each char of `s`, chars same as
+    /// `decode_chars()`. Char code differs from char only for CID fonts of
+    /// non-identity CMap, whose chars are CIDs.
+    fn decode_code_chars(&self, s: &[u8]) -> Vec<(u32, u32)> {
+        self.decode_chars(s)
+            .into_iter()
+            .map(|ch| (ch, ch))
+            .collect()
+    }
+
     fn char_to_gid(&self, ch: u32) -> u16;
-    /// Return glyph width for specified char
+    /// Return glyph width for specified char, in glyph space of
+    /// `units_per_em()`, `/Widths` in 1/1000 text space are converted to it.
+    /// Char and word spacing are text space, not scaled by units per em.
     fn char_width(&self, ch: u32) -> GlyphLength;
     fn units_per_em(&self) -> u16 {
         1000
     }
+
+    /// Map char (CID for CID fonts) to unicode, returns None if unknown.
+    fn to_unicode(&self, _ch: u32) -> Option<char> {
+        None
+    }
+
+    /// Unicode chars of char code decoded as `ch`, read from `/ToUnicode`
+    /// of the font by the code if exists, otherwise same as `to_unicode(ch)`.
+    /// One code may map to several chars, such as ligature "ffi".
+    fn char_to_unicode(&self, _code: u32, ch: u32) -> Option<TinyVec<[char; 4]>> {
+        self.to_unicode(ch).map(|c| tiny_vec!([char; 4] => c))
+    }
+}
+
+/// Font op maps chars to unicode by `/ToUnicode` of the font, chars not in
+/// it fall back to `inner`.
+struct ToUnicodeFontOp<'a> {
+    inner: Box<dyn FontOp + 'a>,
+    to_unicode: Rc<CMap>,
+}
+
+impl FontOp for ToUnicodeFontOp<'_> {
+    fn decode_chars(&self, s: &[u8]) -> Vec<u32> {
+        self.inner.decode_chars(s)
+    }
+
+    fn decode_code_chars(&self, s: &[u8]) -> Vec<(u32, u32)> {
+        self.inner.decode_code_chars(s)
+    }
+
+    fn char_to_gid(&self, ch: u32) -> u16 {
+        self.inner.char_to_gid(ch)
+    }
+
+    fn char_width(&self, ch: u32) -> GlyphLength {
+        self.inner.char_width(ch)
+    }
+
+    fn units_per_em(&self) -> u16 {
+        self.inner.units_per_em()
+    }
+
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        self.inner.to_unicode(ch)
+    }
+
+    fn char_to_unicode(&self, code: u32, ch: u32) -> Option<TinyVec<[char; 4]>> {
+        self.to_unicode
+            .to_unicode(code)
+            .map(|s| s.chars().collect())
+            .or_else(|| self.inner.char_to_unicode(code, ch))
+    }
+}
+
+/// Fallback CID to unicode map resolved from CIDSystemInfo, used if
+/// `font` has no ToUnicode.
+#[cfg(feature = "cjk")]
+fn cid_to_unicode(
+    cmap_registry: &mut CMapRegistry,
+    font: &Type0FontDict,
+    cid_font: &crate::text::CIDFontDict,
+) -> AnyResult<Option<Rc<CIDToUnicode>>> {
+    if font.to_unicode()?.is_some() {
+        return Ok(None);
+    }
+    let Some(info) = cid_font.cid_system_info()? else {
+        return Ok(None);
+    };
+    Ok(cmap_registry.cid_to_unicode(info.registry()?, info.ordering()?))
+}
+
+#[cfg(feature = "cjk")]
+fn cid_char_to_unicode(m: Option<&CIDToUnicode>, ch: u32) -> Option<char> {
+    m?.get(CID(ch.try_into().ok()?))
 }
 
 struct CIDFontType0FontOp {
     widths: Option<CIDFontWidths>,
     default_width: u32,
+    #[cfg(feature = "cjk")]
+    cid_to_unicode: Option<Rc<CIDToUnicode>>,
 }
 
 impl CIDFontType0FontOp {
-    fn new(font: &Type0FontDict) -> AnyResult<Self> {
-        if let NameOrStream::Name(encoding) = font.encoding()? {
-            assert_eq!(encoding, "Identity-H");
-        } else {
-            todo!("Only IdentityH encoding supported");
+    #[cfg_attr(
+        not(feature = "cjk"),
+        allow(unused_variables, clippy::needless_pass_by_ref_mut)
+    )]
+    fn new(cmap_registry: &mut CMapRegistry, font: &Type0FontDict) -> AnyResult<Self> {
+        let encoding = match font.encoding()? {
+            NameOrStream::Name(encoding) if encoding == "Identity-H" => None,
+            NameOrStream::Name(encoding) => Some(encoding.to_string()),
+            NameOrStream::Stream(_) => Some("CMap stream".to_owned()),
+        };
+        if let Some(encoding) = encoding {
+            bail!(ObjectValueError::Unsupported(vec![format!(
+                "CIDFontType0 encoding {}",
+                encoding
+            )]));
         }
         let cid_fonts = font.descendant_fonts()?;
         let cid_font = &cid_fonts[0];
@@ -961,6 +1605,8 @@ impl CIDFontType0FontOp {
         Ok(Self {
             widths,
             default_width: cid_font.dw()?,
+            #[cfg(feature = "cjk")]
+            cid_to_unicode: cid_to_unicode(cmap_registry, font, cid_font)?,
         })
     }
 }
@@ -989,6 +1635,11 @@ impl FontOp for CIDFontType0FontOp {
             .unwrap_or(self.default_width) as f32;
         GlyphLength::new(char_width)
     }
+
+    #[cfg(feature = "cjk")]
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        cid_char_to_unicode(self.cid_to_unicode.as_deref(), ch)
+    }
 }
 
 /// CID -> GID, GID is u16. stored in [u8], each u16 is big endian
@@ -1019,6 +1670,8 @@ struct CIDFontType2FontOp<'a> {
     cmap: Option<Rc<CMap>>,
     cid_to_gid: Option<CIDToGIDMap>,
     cid_is_gid: bool,
+    #[cfg(feature = "cjk")]
+    cid_to_unicode: Option<Rc<CIDToUnicode>>,
 }
 
 impl<'a> CIDFontType2FontOp<'a> {
@@ -1066,6 +1719,8 @@ impl<'a> CIDFontType2FontOp<'a> {
             cmap,
             cid_is_gid: is_embed && cid_to_gid.is_none(),
             cid_to_gid,
+            #[cfg(feature = "cjk")]
+            cid_to_unicode: cid_to_unicode(cmap_registry, font, cid_font)?,
         })
     }
 }
@@ -1096,6 +1751,22 @@ impl<'a> FontOp for CIDFontType2FontOp<'a> {
         )
     }
 
+    /// Char codes taken by code space of the CMap, in the same way as
+    /// `decode_chars()` maps them to CIDs.
+    fn decode_code_chars(&self, s: &[u8]) -> Vec<(u32, u32)> {
+        let chars = self.decode_chars(s);
+        let Some(cmap) = self.cmap.as_ref() else {
+            return chars.into_iter().map(|ch| (ch, ch)).collect();
+        };
+        let mut codes = Vec::with_capacity(chars.len());
+        let mut s = s;
+        while let Some((code, n)) = cmap.map_next(s) {
+            codes.push(code);
+            s = &s[n..];
+        }
+        codes.into_iter().zip(chars).collect()
+    }
+
     fn char_to_gid(&self, ch: u32) -> u16 {
         if self.cid_is_gid {
             return ch.try_into().unwrap();
@@ -1131,6 +1802,11 @@ impl<'a> FontOp for CIDFontType2FontOp<'a> {
         GlyphLength::new(char_width)
     }
 
+    #[cfg(feature = "cjk")]
+    fn to_unicode(&self, ch: u32) -> Option<char> {
+        cid_char_to_unicode(self.cid_to_unicode.as_deref(), ch)
+    }
+
     fn units_per_em(&self) -> u16 {
         self.units_per_em
     }
@@ -1143,8 +1819,12 @@ struct CIDFontType0Font<'a, 'b> {
 }
 
 impl<'a, 'b> CIDFontType0Font<'a, 'b> {
-    fn new(font_dict: FontDict<'a, 'b>, data: Vec<u8>) -> AnyResult<Self> {
-        let font = FontKitFont::from_bytes(data.into(), 0)?;
+    fn new(
+        font_dict: FontDict<'a, 'b>,
+        data: Vec<u8>,
+        faces: Option<&FontFaces>,
+    ) -> AnyResult<Self> {
+        let font = load_face(&data, faces)?;
         Ok(Self { font_dict, font })
     }
 }
@@ -1157,8 +1837,13 @@ struct CIDFontType2Font<'a, 'b> {
 }
 
 impl<'a, 'b> CIDFontType2Font<'a, 'b> {
-    fn new(font_is_embed: bool, data: Vec<u8>, font_dict: FontDict<'a, 'b>) -> AnyResult<Self> {
-        let font = FontKitFont::from_bytes(data.clone().into(), 0)?;
+    fn new(
+        font_is_embed: bool,
+        data: Vec<u8>,
+        font_dict: FontDict<'a, 'b>,
+        faces: Option<&FontFaces>,
+    ) -> AnyResult<Self> {
+        let font = load_face(&data, faces)?;
         Ok(Self {
             data,
             font,
@@ -1194,8 +1879,11 @@ impl<'a, 'b, P: PathSink + 'static> Font<P> for CIDFontType0Font<'a, 'b> {
         FontType::Type0
     }
 
-    fn create_op(&self, _cmap_registry: &mut CMapRegistry) -> AnyResult<Box<dyn FontOp + '_>> {
-        Ok(Box::new(CIDFontType0FontOp::new(&self.font_dict.type0()?)?))
+    fn create_op(&self, cmap_registry: &mut CMapRegistry) -> AnyResult<Box<dyn FontOp + '_>> {
+        Ok(Box::new(CIDFontType0FontOp::new(
+            cmap_registry,
+            &self.font_dict.type0()?,
+        )?))
     }
 
     fn create_glyph_render(&self) -> AnyResult<Box<dyn GlyphRender<P> + '_>> {
@@ -1228,7 +1916,8 @@ impl<'a> Type3FontOp<'a> {
             font_width: FirstLastFontWidth::from(font_dict)?.unwrap(),
             name_to_gid,
             encoding,
-            units_per_em: (1.0 / matrix.m11).abs().to_u16().unwrap(),
+            // degenerated FontMatrix, such as m11 is 0
+            units_per_em: (1.0 / matrix.m11).abs().to_u16().unwrap_or(1000),
         })
     }
 }
@@ -1244,7 +1933,7 @@ impl<'a> FontOp for Type3FontOp<'a> {
             *gid
         } else {
             info!("glyph id not found for char: {:?}/{}", ch, gid_name);
-            u16::MAX
+            TYPE3_NOTDEF_GID
         }
     }
 
@@ -1257,6 +1946,9 @@ impl<'a> FontOp for Type3FontOp<'a> {
     }
 }
 
+/// Glyph id of empty glyph, used for char whose glyph not in `/CharProcs`.
+const TYPE3_NOTDEF_GID: u16 = 0;
+
 pub struct Type3Font<'a, 'b> {
     name_to_gid: HashMap<Name, u16>,
     glyphs: Box<[Type3Glyph]>,
@@ -1270,8 +1962,13 @@ impl<'a, 'b> Type3Font<'a, 'b> {
         for (name, stream) in procs.iter() {
             debug!("parse Type3 glyph: {}", name.as_str());
             let data = stream.decode(d.resolver())?;
-            let (_, ops) = parse_operations(&data[..])
-                .map_err(|e| anyhow!("parse type3 operation error: {}", e))?;
+            // glyph failed to parse renders nothing, other glyphs still usable
+            let ops = parse_operations(&data[..])
+                .map(|(_, ops)| ops)
+                .unwrap_or_else(|e| {
+                    warn!("parse type3 glyph {} error: {}", name.as_str(), e);
+                    vec![]
+                });
             r.push((name.clone(), Type3Glyph(ops.into())))
         }
 
@@ -1281,7 +1978,9 @@ impl<'a, 'b> Type3Font<'a, 'b> {
     pub fn new(dict: FontDict<'a, 'b>) -> AnyResult<Self> {
         let type3 = dict.type3()?;
         let glyph_and_names = Self::parse_glyphs(&type3)?;
-        let mut glyphs = Vec::with_capacity(glyph_and_names.len());
+        let mut glyphs = Vec::with_capacity(glyph_and_names.len() + 1);
+        // empty glyph of `TYPE3_NOTDEF_GID`
+        glyphs.push(Type3Glyph(Box::new([])));
         let mut glyph_ids = HashMap::with_capacity(glyph_and_names.len());
         for (name, glyph) in glyph_and_names {
             let gid = glyphs.len().try_into().unwrap();
@@ -1339,6 +2038,10 @@ impl<'a, 'b, P: PathSink + 'static> Font<P> for Type3Font<'a, 'b> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        file::XRefTable,
+        object::{BufPos, Dictionary, ObjectId},
+    };
     use test_case::test_case;
 
     #[test]
@@ -1355,6 +2058,192 @@ mod tests {
         assert_eq!(15.0, font_width.char_width('e' as u32).0);
     }
 
+    /// Greek chars not in bundled sans font are in bundled symbol font, found
+    /// without system fonts.
+    #[test]
+    fn fallback_font_bundled_symbol() {
+        let font = FallbackFont::new().unwrap();
+        let has_glyph = |f: &FontKitFont| f.glyph_for_char('α').is_some_and(|gid| gid != 0);
+        assert!(!has_glyph(&font.bundled[0]));
+        assert!(has_glyph(&font.bundled[1]));
+    }
+
+    #[test]
+    fn type1_units_per_em_from_font_matrix() {
+        let data = include_str!("../../../../../prescript/src/type1/p052024l.pfa");
+        assert_eq!(Some(1000), type1_units_per_em(data.as_bytes()));
+
+        let data = data.replace(
+            "/FontMatrix [0.001 0 0 0.001 0 0 ]",
+            "/FontMatrix [0.0005 0 0 0.0005 0 0 ]",
+        );
+        assert_eq!(Some(2000), type1_units_per_em(data.as_bytes()));
+    }
+
+    #[test]
+    fn stream_encoding_of_simple_font() {
+        let xref = XRefTable::empty();
+        let mut resolver = ObjectResolver::empty(&xref);
+        let font = Dictionary::from_iter([
+            (sname("Type"), sname("Font").into()),
+            (sname("Subtype"), sname("Type1").into()),
+            (sname("BaseFont"), sname("Helvetica").into()),
+            (sname("Encoding"), Object::new_ref(2)),
+        ]);
+        resolver.setup_object(1, Object::Dictionary(font));
+        resolver.setup_object(
+            2,
+            Object::Stream(Rc::new(Stream::new(
+                Dictionary::new(),
+                BufPos::new(0, None),
+                ObjectId::new(2, 0),
+            ))),
+        );
+
+        let font: FontDict = resolver.resolve_pdf_object(1).unwrap();
+        let parser = EncodingParser(&font);
+        assert!(parser.encoding_pair().unwrap().is_none());
+        assert_eq!(None, parser.ttf().unwrap());
+    }
+
+    #[test]
+    fn standard_14_font_afm_widths() {
+        let xref = XRefTable::empty();
+        let mut resolver = ObjectResolver::empty(&xref);
+        let font = Dictionary::from_iter([
+            (sname("Type"), sname("Font").into()),
+            (sname("Subtype"), sname("Type1").into()),
+            (sname("BaseFont"), sname("Helvetica").into()),
+        ]);
+        resolver.setup_object(1, Object::Dictionary(font));
+        let font: FontDict = resolver.resolve_pdf_object(1).unwrap();
+
+        let data = standard_14_type1_font_data("Helvetica").unwrap();
+        let ft_font = FontKitFont::from_bytes(data.to_vec().into(), 0).unwrap();
+        let op = Type1FontOp::new(&font, &ft_font, Type1FontFormat::Type1, data, None).unwrap();
+        assert_eq!(GlyphLength::new(944.0), op.char_width('W' as u32));
+        assert_eq!(GlyphLength::new(278.0), op.char_width(' ' as u32));
+    }
+
+    /// `/ToUnicode` CMap of one byte codes, `bfchar` entries.
+    fn to_unicode_cmap(bfchar: &str) -> Rc<CMap> {
+        let data = format!(
+            "/CIDInit /ProcSet findresource begin 12 dict begin begincmap
+/CMapName /Test-UCS def
+1 begincodespacerange <00> <FF> endcodespacerange
+1 beginbfchar {bfchar} endbfchar
+endcmap CMapName currentdict /CMap defineresource pop end end"
+        );
+        CMapRegistry::new().add_cmap_file(data.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn to_unicode_font_op() {
+        let xref = XRefTable::empty();
+        let mut resolver = ObjectResolver::empty(&xref);
+        let font = Dictionary::from_iter([
+            (sname("Type"), sname("Font").into()),
+            (sname("Subtype"), sname("Type1").into()),
+            (sname("BaseFont"), sname("Helvetica").into()),
+        ]);
+        resolver.setup_object(1, Object::Dictionary(font));
+        let font: FontDict = resolver.resolve_pdf_object(1).unwrap();
+
+        let data = standard_14_type1_font_data("Helvetica").unwrap();
+        let ft_font = FontKitFont::from_bytes(data.to_vec().into(), 0).unwrap();
+        let op = ToUnicodeFontOp {
+            inner: Box::new(
+                Type1FontOp::new(&font, &ft_font, Type1FontFormat::Type1, data, None).unwrap(),
+            ),
+            to_unicode: to_unicode_cmap("<41> <006600660069>"),
+        };
+        // ligature maps to several chars
+        assert_eq!(
+            Some(tiny_vec!([char; 4] => 'f', 'f', 'i')),
+            op.char_to_unicode(0x41, 0x41)
+        );
+        // not in `/ToUnicode`, falls back to font encoding
+        assert_eq!(
+            Some(tiny_vec!([char; 4] => 'B')),
+            op.char_to_unicode(0x42, 0x42)
+        );
+        assert_eq!(Some('A'), op.to_unicode(0x41));
+    }
+
+    /// Font op knows nothing about unicode of its chars.
+    struct NoUnicodeFontOp;
+
+    impl FontOp for NoUnicodeFontOp {
+        fn decode_chars(&self, s: &[u8]) -> Vec<u32> {
+            s.iter().map(|b| u32::from(*b)).collect()
+        }
+
+        fn char_to_gid(&self, _ch: u32) -> u16 {
+            0
+        }
+
+        fn char_width(&self, _ch: u32) -> GlyphLength {
+            GlyphLength::new(500.0)
+        }
+    }
+
+    #[test]
+    fn forced_font_op_lookup_glyph_by_unicode() {
+        // skipped if the font not installed
+        let Some(data) = system_font_data("DejaVu Sans").ok() else {
+            return;
+        };
+        let face = TTFFace::parse(&data, 0).unwrap();
+        let op = ForcedFontOp {
+            inner: Box::new(ToUnicodeFontOp {
+                inner: Box::new(NoUnicodeFontOp),
+                to_unicode: to_unicode_cmap("<01> <005A>"),
+            }),
+            face: face.clone(),
+        };
+        assert_eq!(face.glyph_index('Z').unwrap().0, op.char_to_gid(1));
+        // char code not treated as unicode
+        assert_eq!(0, op.char_to_gid(0x41));
+        assert_eq!(Some(tiny_vec!([char; 4] => 'Z')), op.char_to_unicode(1, 1));
+        assert_eq!(
+            GlyphLength::new(0.5 * f32::from(face.units_per_em())),
+            op.char_width(1)
+        );
+    }
+
+    #[test]
+    fn type1c_encoding_from_cff() {
+        let xref = XRefTable::empty();
+        let mut resolver = ObjectResolver::empty(&xref);
+        let font = Dictionary::from_iter([
+            (sname("Type"), sname("Font").into()),
+            (sname("Subtype"), sname("Type1").into()),
+            (sname("BaseFont"), sname("ABCDEF+Minus").into()),
+        ]);
+        resolver.setup_object(1, Object::Dictionary(font));
+        let font: FontDict = resolver.resolve_pdf_object(1).unwrap();
+
+        let data = include_bytes!("../../../../../cff-parser/src/68.cff");
+        let encoding = EncodingParser(&font)
+            .type1(Type1FontFormat::Cff, data, None)
+            .unwrap();
+        assert_eq!("minus", encoding.get_str(0));
+
+        // scanned once, reused by font of the same bytes
+        let shared = SharedFontCache::default();
+        let encoding = EncodingParser(&font)
+            .type1(Type1FontFormat::Cff, data, Some(&shared))
+            .unwrap();
+        assert_eq!("minus", encoding.get_str(0));
+        let cached = shared.encoding(data, || unreachable!()).unwrap();
+        assert_eq!("minus", cached.get_str(0));
+
+        // face parsed once, clones share it
+        let faces = FontFaces::default();
+        let face = faces.face(data).unwrap();
+        assert_eq!(face.native_font(), faces.face(data).unwrap().native_font());
+    }
+
     #[test_case("s" => "s"; "no need to normalize")]
     #[test_case("TimesNewRomanPSMT" => "TimesNewRoman"; "PSMT")]
     fn test_normalize_true_type_font_name(s: &str) -> String {
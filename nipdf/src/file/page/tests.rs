@@ -1,8 +1,9 @@
 use super::*;
 use crate::{
-    file::{ObjectResolver, XRefTable},
+    file::{ObjectResolver, XRefTable, open_test_file},
     object::{Array, Object},
 };
+use assert_approx_eq::assert_approx_eq;
 use test_case::test_case;
 
 #[test_case(1.0, 2, 3.0, 4.0 => (1.0, 2.0, 3.0, 4.0); "normal")]
@@ -18,21 +19,31 @@ fn rectangle_from_array(
     (rect.left_x, rect.lower_y, rect.right_x, rect.upper_y)
 }
 
-#[test_case(1, vec![(1, vec![2]), (2, vec![])]=> vec![2u32]; "one page")]
-#[test_case(1, vec![
-    (1, vec![2, 3, 4]),
-    (2, vec![]),
-    (3, vec![5, 6]),
-    (4, vec![7, 8]),
-    (5, vec![]),
-    (6, vec![]),
-    (7, vec![9]),
-    (8, vec![]),
-    (9, vec![]),
-]=> vec![2, 5, 6, 9, 8]; "complex tree")]
-fn parse_page_tree(root_id: u32, tree: Vec<(u32, Vec<u32>)>) -> Vec<u32> {
-    let xref = XRefTable::empty();
-    let mut resolver = ObjectResolver::empty(&xref);
+#[test_case((0., 0., 100., 100.), (-50., -20., 150., 80.) => Some((0., 0., 100., 80.)); "overlap")]
+#[test_case((0., 0., 100., 100.), (10., 10., 20., 20.) => Some((10., 10., 20., 20.)); "inside")]
+#[test_case((0., 0., 100., 100.), (100., 0., 200., 100.) => None; "touch")]
+#[test_case((0., 0., 100., 100.), (200., 200., 300., 300.) => None; "disjoint")]
+fn rectangle_intersect(
+    a: (f32, f32, f32, f32),
+    b: (f32, f32, f32, f32),
+) -> Option<(f32, f32, f32, f32)> {
+    let a = Rectangle::from_lbrt(a.0, a.1, a.2, a.3);
+    let b = Rectangle::from_lbrt(b.0, b.1, b.2, b.3);
+    a.intersect(&b)
+        .map(|r| (r.left_x, r.lower_y, r.right_x, r.upper_y))
+}
+
+/// Setup page tree nodes of `(id, kids)`, node without kids is page.
+fn setup_page_tree(resolver: &mut ObjectResolver, tree: &[(u32, Vec<u32>)]) {
+    fn count(tree: &[(u32, Vec<u32>)], id: u32) -> i32 {
+        let kids = &tree.iter().find(|(i, _)| *i == id).unwrap().1;
+        if kids.is_empty() {
+            1
+        } else {
+            kids.iter().map(|kid| count(tree, *kid)).sum()
+        }
+    }
+
     for (id, kids) in tree {
         let mut dict = HashMap::new();
         dict.insert(
@@ -43,13 +54,398 @@ fn parse_page_tree(root_id: u32, tree: Vec<(u32, Vec<u32>)>) -> Vec<u32> {
             sname("MediaBox"),
             Object::Array(vec![0.0.into(), 0.0.into(), 0.0.into(), 0.0.into()].into()),
         );
-        dict.insert(
-            sname("Kids"),
-            Object::Array(kids.into_iter().map(Object::new_ref).collect::<Array>()),
-        );
-        resolver.setup_object(id, Object::Dictionary(Dictionary::from(dict)));
+        if !kids.is_empty() {
+            dict.insert(
+                sname("Kids"),
+                Object::Array(kids.iter().copied().map(Object::new_ref).collect::<Array>()),
+            );
+            dict.insert(sname("Count"), count(tree, *id).into());
+        }
+        resolver.setup_object(*id, Object::Dictionary(Dictionary::from(dict)));
     }
+}
+
+fn complex_page_tree() -> Vec<(u32, Vec<u32>)> {
+    vec![
+        (1, vec![2, 3, 4]),
+        (2, vec![]),
+        (3, vec![5, 6]),
+        (4, vec![7, 8]),
+        (5, vec![]),
+        (6, vec![]),
+        (7, vec![9]),
+        (8, vec![]),
+        (9, vec![]),
+    ]
+}
+
+#[test_case(1, vec![(1, vec![2]), (2, vec![])]=> vec![2u32]; "one page")]
+#[test_case(1, complex_page_tree() => vec![2, 5, 6, 9, 8]; "complex tree")]
+fn parse_page_tree(root_id: u32, tree: Vec<(u32, Vec<u32>)>) -> Vec<u32> {
+    let xref = XRefTable::empty();
+    let mut resolver = ObjectResolver::empty(&xref);
+    setup_page_tree(&mut resolver, &tree);
 
     let pages = Page::parse(resolver.resolve_pdf_object(root_id).unwrap());
     pages.unwrap().into_iter().map(|p| p.id().0).collect()
 }
+
+#[test]
+fn parse_page_at() {
+    let xref = XRefTable::empty();
+    let mut resolver = ObjectResolver::empty(&xref);
+    setup_page_tree(&mut resolver, &complex_page_tree());
+
+    let root = || resolver.resolve_pdf_object::<PageDict>(1).unwrap();
+    assert_eq!(5, root().page_count().unwrap());
+    let pages: Vec<_> = (0..5)
+        .map(|i| Page::parse_at(root(), i).unwrap().id().0)
+        .collect();
+    assert_eq!(vec![2, 5, 6, 9, 8], pages);
+    // parents of page 9 are 7, 4 and root
+    let page = Page::parse_at(root(), 3).unwrap();
+    assert_eq!(
+        vec![9, 7, 4, 1],
+        page.iter_to_root()
+            .map(|d| d.id().unwrap().0)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(&ObjectValueError::PageIndexOutOfRange(5)),
+        Page::parse_at(root(), 5).unwrap_err().downcast_ref()
+    );
+}
+
+#[test]
+fn page_complexity() {
+    let file = open_test_file("sample_files/page/image-and-text.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    assert_eq!(
+        Complexity {
+            operations: 11,
+            images: 1,
+            image_pixels: 24,
+            fonts: 1,
+            has_shading: false,
+            has_pattern: false,
+        },
+        pages[0].complexity().unwrap()
+    );
+}
+
+#[test]
+fn marked_content_ids() {
+    let file = open_test_file("sample_files/page/marked-content-mcid.pdf");
+    let resolver = file.resolver().unwrap();
+    let catalog = file.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    assert_eq!(Some(0), pages[0].struct_parents());
+    assert_eq!(vec![0, 1], pages[0].marked_content_ids().unwrap());
+
+    let elements = catalog.struct_elements(&pages[0]).unwrap();
+    assert_eq!(2, elements.len());
+    assert_eq!(RuntimeObjectId(6), elements[&0]);
+    assert_eq!(RuntimeObjectId(7), elements[&1]);
+}
+
+#[test]
+fn page_annotations() {
+    let file = open_test_file("sample_files/page/annotation-no-rotate.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let annots = pages[0].annotations().unwrap();
+    assert_eq!(2, annots.len());
+
+    assert_eq!(sname("Text"), annots[0].subtype().unwrap());
+    assert_eq!(
+        Rectangle::from_lbrt(10.0, 70.0, 30.0, 80.0),
+        annots[0].rect().unwrap()
+    );
+    assert_eq!(
+        AnnotationFlags::NO_ZOOM | AnnotationFlags::NO_ROTATE,
+        annots[0].flags().unwrap()
+    );
+    assert!(annots[0].appearance().unwrap().is_some());
+
+    // inline annotation dictionary, flags default to empty
+    assert_eq!(sname("Square"), annots[1].subtype().unwrap());
+    assert_eq!(AnnotationFlags::empty(), annots[1].flags().unwrap());
+    assert!(annots[1].appearance().unwrap().is_none());
+}
+
+#[test]
+fn normal_appearance() {
+    let file = open_test_file("sample_files/page/annotation-appearance.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let annots = pages[0].annotations().unwrap();
+    let id = |i: usize| annots[i].normal_appearance().unwrap().unwrap().id();
+
+    // state `/On` selected by `/AS`
+    assert_eq!(Some(sname("On")), annots[0].appearance_state().unwrap());
+    assert_eq!(Some(RuntimeObjectId(7)), id(0));
+    assert_eq!(Some(RuntimeObjectId(10)), id(1));
+}
+
+#[test]
+fn painted_annotations() {
+    let file = open_test_file("sample_files/page/annotation-popup.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let subtypes = |popups_and_replies| {
+        pages[0]
+            .painted_annotations(popups_and_replies)
+            .unwrap()
+            .iter()
+            .map(|a| a.subtype().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // text annotation, its popup, a reply and a hidden square
+    assert_eq!(4, pages[0].annotations().unwrap().len());
+    assert_eq!(vec![sname("Text")], subtypes(false));
+    assert_eq!(
+        vec![sname("Text"), sname("Popup"), sname("Text")],
+        subtypes(true)
+    );
+}
+
+#[test]
+fn annotation_fallback_appearance() {
+    use crate::{
+        graphics::{NameOfDict, trans::TextPoint},
+        object::{LiteralString, TextString},
+    };
+
+    let file = open_test_file("sample_files/page/annotation-no-appearance.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let annots = pages[0].annotations().unwrap();
+    assert_eq!(4, annots.len());
+
+    // dashed border drawn inside of `/Rect`
+    assert_eq!(
+        Some(vec![
+            Operation::SaveGraphicsState,
+            Operation::SetLineWidth(2.0),
+            Operation::SetDashPattern(vec![4.0, 2.0], 0.0),
+            Operation::SetStrokeRGB([1.0, 0.0, 0.0]),
+            Operation::SetFillRGB([0.0, 1.0, 0.0]),
+            Operation::AppendRectangle(Point::new(21.0, 21.0), 58.0, 38.0),
+            Operation::FillAndStrokeNonZero,
+            Operation::RestoreGraphicsState,
+        ]),
+        annots[0].fallback_appearance().unwrap()
+    );
+
+    // border width from `/Border`, no `/IC` stroke only
+    let ops = annots[1].fallback_appearance().unwrap().unwrap();
+    assert_eq!(
+        &[
+            Operation::SaveGraphicsState,
+            Operation::SetLineWidth(4.0),
+            Operation::SetStrokeGray([0.0]),
+            Operation::MoveToNext(Point::new(178.0, 40.0)),
+        ],
+        &ops[..4]
+    );
+    assert_eq!(
+        &[
+            Operation::ClosePath,
+            Operation::Stroke,
+            Operation::RestoreGraphicsState
+        ],
+        &ops[ops.len() - 3..]
+    );
+
+    let text = |s: &[u8]| TextString::Text(LiteralString(s.into()));
+    assert_eq!(
+        Some(vec![
+            Operation::SaveGraphicsState,
+            Operation::SetFillRGB([1.0, 1.0, 0.0]),
+            Operation::AppendRectangle(Point::new(20.0, 100.0), 160.0, 60.0),
+            Operation::FillNonZero,
+            Operation::AppendRectangle(Point::new(20.0, 100.0), 160.0, 60.0),
+            Operation::ClipNonZero,
+            Operation::EndPath,
+            Operation::BeginText,
+            Operation::SetFont(NameOfDict(sname("Helv")), 10.0),
+            Operation::SetFillRGB([0.0, 0.0, 1.0]),
+            Operation::SetLeading(10.0),
+            Operation::MoveTextPosition(TextPoint::new(22.0, 148.0)),
+            Operation::ShowText(text(b"Hello")),
+            Operation::MoveToNextLineAndShowText(text(b"World")),
+            Operation::EndText,
+            Operation::RestoreGraphicsState,
+        ]),
+        annots[2].fallback_appearance().unwrap()
+    );
+
+    // has `/AP`
+    assert_eq!(None, annots[3].fallback_appearance().unwrap());
+}
+
+/// Pages: no `/Contents`, invisible text only, visible text after `Q`
+/// restores text render mode, filled rect.
+#[test]
+fn page_is_blank() {
+    let file = open_test_file("sample_files/page/blank-pages.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let blank: Vec<_> = pages.iter().map(|p| p.is_blank().unwrap()).collect();
+    assert_eq!(vec![true, true, false, false], blank);
+}
+
+#[test]
+fn referenced_object_ids() {
+    let file = open_test_file("sample_files/page/referenced-objects.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let ids = pages[0].referenced_object_ids().unwrap();
+    // form xobject references itself, font descriptor has a `/Parent` link
+    // back to the page, object 11 is not used by the page
+    let expected = [4, 5, 6, 7, 8, 9, 10]
+        .into_iter()
+        .map(RuntimeObjectId)
+        .collect::<HashSet<_>>();
+    assert_eq!(expected, ids);
+}
+
+#[test]
+fn contents_single_stream_or_array() {
+    let file = open_test_file("sample_files/page/contents-single-or-array.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    // single stream, array of streams split at token boundary without
+    // whitespace, and reference to array
+    let contents = pages
+        .iter()
+        .map(|p| p.content().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(b"0 0 1 rg 10 10 50 50 re f", &contents[0].to_bytes()[..]);
+    assert_eq!(b"0 0 1 rg\n10 10 50 50 re f", &contents[1].to_bytes()[..]);
+    assert_eq!(contents[1].to_bytes(), contents[2].to_bytes());
+
+    let ops = contents
+        .into_iter()
+        .map(|c| c.operations())
+        .collect::<Vec<_>>();
+    assert_eq!(3, ops[0].len());
+    assert_eq!(ops[0], ops[1]);
+    assert_eq!(ops[0], ops[2]);
+}
+
+#[test]
+fn page_links() {
+    let file = open_test_file("sample_files/page/links.pdf");
+    let resolver = file.resolver().unwrap();
+    let catalog = file.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let links = pages[0].links(&catalog).unwrap();
+    assert_eq!(
+        vec![
+            Link {
+                rect: Rectangle::from_lbrt(10.0, 10.0, 50.0, 20.0),
+                target: LinkTarget::Uri("https://example.com/".to_owned()),
+            },
+            Link {
+                rect: Rectangle::from_lbrt(10.0, 30.0, 50.0, 40.0),
+                target: LinkTarget::GoTo(1, DestView::XYZ {
+                    left: Some(10.0),
+                    top: Some(180.0),
+                    zoom: None,
+                }),
+            },
+            // named destination in catalog `/Dests`
+            Link {
+                rect: Rectangle::from_lbrt(10.0, 50.0, 50.0, 60.0),
+                target: LinkTarget::GoTo(1, DestView::Fit),
+            },
+            // named destination in `/Dests` name tree
+            Link {
+                rect: Rectangle::from_lbrt(10.0, 70.0, 50.0, 80.0),
+                target: LinkTarget::GoTo(
+                    1,
+                    DestView::FitR(Rectangle::from_lbrt(10.0, 20.0, 110.0, 120.0))
+                ),
+            },
+        ],
+        links
+    );
+    assert!(pages[1].links(&catalog).unwrap().is_empty());
+}
+
+#[test]
+fn extract_text() {
+    let file = open_test_file("sample_files/page/extract-text.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let runs = pages[0].extract_text().unwrap();
+    let texts: Vec<_> = runs.iter().map(|r| r.text.as_str()).collect();
+    // small TJ adjustment ignored, large one becomes space; `/ToUnicode`
+    // maps "A" to "fi", "B" not in it falls back to encoding
+    assert_eq!(vec!["Hello", "World again", "Next", "fiB", "Form"], texts);
+
+    let origins: Vec<_> = runs.iter().map(|r| (r.origin.x, r.origin.y)).collect();
+    // "Hello" in 12pt Helvetica is 2278 / 1000 * 12 wide
+    assert_approx_eq!(37.336, origins[1].0, 1e-3);
+    assert_eq!(
+        vec![(10.0, 20.0), (10.0, 6.0), (102.0, 104.0), (50.0, 0.0)],
+        [origins[0], origins[2], origins[3], origins[4]]
+    );
+    assert_eq!("Helvetica", runs[0].font_name);
+    assert_approx_eq!(37.336, runs[0].end.x, 1e-3);
+    assert_eq!(20.0, runs[0].end.y);
+    // "fiB" scaled by CTM
+    assert_eq!(
+        vec![12.0, 12.0, 12.0, 20.0, 8.0],
+        runs.iter().map(|r| r.font_size).collect::<Vec<_>>()
+    );
+}
+
+/// "AB" of 10pt Helvetica at (10, 50) with -2 char spacing, "A" and "B" are
+/// 6.67 wide, "B" starts at 14.67.
+#[test]
+fn extract_text_negative_char_spacing() {
+    let file = open_test_file("sample_files/page/extract-text-char-spacing.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let runs = pages[0].extract_text().unwrap();
+    assert_eq!(1, runs.len());
+    let run = &runs[0];
+    // char spacing after the last glyph not included
+    assert_approx_eq!(21.34, run.end.x, 1e-3);
+    assert_eq!(50.0, run.end.y);
+    let bbox = run.bbox;
+    assert_approx_eq!(10.0, bbox.left_x, 1e-3);
+    assert_approx_eq!(21.34, bbox.right_x, 1e-3);
+    assert_approx_eq!(48.0, bbox.lower_y, 1e-3);
+    assert_approx_eq!(58.0, bbox.upper_y, 1e-3);
+}
+
+/// `/ActualText` replaces ligature glyph, inline or in `/Properties`
+/// resources, TJ adjustment in sequence not become space, and sequence
+/// painting no glyphs.
+#[test]
+fn extract_actual_text() {
+    let file = open_test_file("sample_files/page/extract-actual-text.pdf");
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    let runs = pages[0].extract_text().unwrap();
+    let texts: Vec<_> = runs.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(vec!["Dif", "fi", "cult", "X", "Logo"], texts);
+    assert_eq!((10.0, 60.0), (runs[4].origin.x, runs[4].origin.y));
+    assert_eq!("Difficult\nX\nLogo", pages[0].plain_text().unwrap());
+}
+
+#[test_case("extract-text.pdf" => "HelloWorld again\nNext\nfiB\nForm"; "simple fonts")]
+#[test_case("extract-text-type0.pdf" => "AB\nBA B"; "type0 two bytes codes")]
+#[test_case("type1c-custom-encoding.pdf" => "\u{2212}"; "built-in encoding")]
+#[test_case("extract-text-cmap.pdf" => "XY"; "to unicode by code not cid")]
+fn plain_text(file_name: &str) -> String {
+    let file = open_test_file(format!("sample_files/page/{}", file_name));
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    pages[0].plain_text().unwrap()
+}
@@ -0,0 +1,507 @@
+//! Extract positioned text of page content, runs the text state machine of
+//! PDF 32000-1:2008 9.3 and 9.4 without painting glyphs.
+use super::{
+    Page, PageContent, Rectangle, ResourceDict, XObjectType, marked_content_properties,
+    paint::fonts::{FontCache, FontFaces, PathSink, SharedFontCache},
+};
+use crate::{
+    file::decode_text_string,
+    graphics::{
+        Operation, Point,
+        trans::{
+            TextPoint, TextSpace, TextToUserSpace, ThousandthsOfText, UserToUserSpace,
+            glyph_advance, glyph_width, move_text_space_pos, move_text_space_right,
+        },
+    },
+    object::{ObjectValueError, PdfObject, TextStringOrNumber},
+};
+use ahash::{HashMap, HashMapExt};
+use euclid::{Length, Scale, Vector2D, default::Box2D};
+use log::warn;
+use prescript::{Name, sname};
+use std::sync::Arc;
+
+/// TJ adjustment moves text right more than this ratio of font size is
+/// treated as a space between words.
+const SPACE_GAP: f32 = 0.25;
+
+/// Run starts off the baseline of previous run more than this ratio of font
+/// size starts a new line.
+const LINE_OFFSET: f32 = 0.5;
+
+/// Max nesting of form XObjects, guards against forms painting themselves.
+const MAX_FORM_DEPTH: usize = 16;
+
+/// Text shown by a text showing operation, returned by
+/// [`Page::extract_text()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    /// Unicode of shown chars, chars of unknown unicode skipped. TJ
+    /// adjustments wider than a quarter of font size become a space.
+    pub text: String,
+    /// Origin of the first glyph in default user space of the page, the
+    /// coordinate space of `/MediaBox`, text rise included.
+    pub origin: Point,
+    /// Right edge of the last glyph on the baseline, in default user space.
+    /// Char and word spacing after the glyph not included, even if negative.
+    pub end: Point,
+    /// Bounding box of shown glyphs in default user space, each glyph from
+    /// 0.2 font size below the baseline to 0.8 above, horizontally its width
+    /// without char and word spacing. Empty at `origin` if no glyph shown.
+    pub bbox: Rectangle,
+    /// PostScript name of the font, subset tag removed.
+    pub font_name: String,
+    /// Font size in default user space, `Tf` size scaled by text matrix and
+    /// CTM, such as 12 for `1 Tf` and `12 0 0 12 0 0 Tm`.
+    pub font_size: f32,
+}
+
+/// Separator between two pieces of shown text, decided by distance from the
+/// end of previous text to the origin of the next, along and perpendicular to
+/// the baseline, in font size of previous text. `'\n'` if off the baseline
+/// more than half of font size, `' '` if gap wider than a quarter of font size.
+/// Used by [`Page::plain_text()`] and text search of renderer.
+pub fn text_separator(gap: f32, offset: f32) -> Option<char> {
+    if offset.abs() > LINE_OFFSET {
+        Some('\n')
+    } else if gap > SPACE_GAP {
+        Some(' ')
+    } else {
+        None
+    }
+}
+
+/// Glyph paths never rendered, only font ops are used.
+struct NoPath;
+
+impl PathSink for NoPath {
+    fn move_to(&mut self, _to: Point) {}
+    fn line_to(&mut self, _to: Point) {}
+    fn quad_to(&mut self, _ctrl: Point, _to: Point) {}
+    fn cubic_to(&mut self, _ctrl1: Point, _ctrl2: Point, _to: Point) {}
+    fn close(&mut self) {}
+}
+
+/// Fonts of a resource dictionary.
+struct Fonts<'c> {
+    cache: FontCache<'c, NoPath>,
+    /// Font name by resource name.
+    names: HashMap<Name, String>,
+}
+
+impl<'c> Fonts<'c> {
+    fn new<'a, 'b>(
+        resources: &'c ResourceDict<'a, 'b>,
+        shared: &Arc<SharedFontCache>,
+        faces: &FontFaces,
+    ) -> anyhow::Result<Self>
+    where
+        'a: 'c,
+        'b: 'c,
+        'b: 'a,
+    {
+        let mut names = HashMap::new();
+        for (k, font) in resources.font()? {
+            names.insert(k, font.font_name()?);
+        }
+        Ok(Self {
+            cache: FontCache::new(resources, None, Some(shared), Some(faces))?,
+            names,
+        })
+    }
+}
+
+/// Part of graphics state used by text extraction, saved by `q`.
+#[derive(Clone)]
+struct TextState {
+    ctm: UserToUserSpace,
+    matrix: TextToUserSpace,
+    line_matrix: TextToUserSpace,
+    font: Option<Name>,
+    font_size: f32,
+    char_spacing: Length<f32, TextSpace>,
+    word_spacing: Length<f32, TextSpace>,
+    horiz_scaling: f32,
+    leading: f32,
+    rise: f32,
+}
+
+impl TextState {
+    fn new(ctm: UserToUserSpace) -> Self {
+        Self {
+            ctm,
+            matrix: TextToUserSpace::identity(),
+            line_matrix: TextToUserSpace::identity(),
+            font: None,
+            font_size: 0.0,
+            char_spacing: Length::new(0.0),
+            word_spacing: Length::new(0.0),
+            horiz_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+
+    fn move_text_position(&mut self, p: TextPoint) {
+        self.matrix = move_text_space_pos(&self.line_matrix, p);
+        self.line_matrix = self.matrix;
+    }
+
+    fn move_to_start_of_next_line(&mut self) {
+        self.move_text_position(TextPoint::new(0.0, -self.leading));
+    }
+
+    fn font_size(&self) -> f32 {
+        let v = self
+            .matrix
+            .transform_vector(Vector2D::new(0.0, self.font_size))
+            .cast_unit();
+        self.ctm.transform_vector(v).length()
+    }
+
+    fn origin(&self) -> Point {
+        self.to_user_space(0.0, 0.0)
+    }
+
+    /// Point `(x, y)` of text space relative to current position, text rise
+    /// included, in default user space.
+    fn to_user_space(&self, x: f32, y: f32) -> Point {
+        let p = self
+            .matrix
+            .transform_point(TextPoint::new(x, self.rise + y))
+            .cast_unit();
+        self.ctm.transform_point(p).cast_unit()
+    }
+
+    /// Box of glyph of `width` in text space at current position.
+    fn glyph_box(&self, width: f32) -> Box2D<f32> {
+        let (descent, ascent) = (-0.2 * self.font_size, 0.8 * self.font_size);
+        Box2D::from_points([
+            self.to_user_space(0.0, descent),
+            self.to_user_space(width, descent),
+            self.to_user_space(0.0, ascent),
+            self.to_user_space(width, ascent),
+        ])
+    }
+}
+
+/// Part of text shown by a text showing operation.
+enum Shown<'o> {
+    Text(&'o [u8]),
+    Adjust(Length<f32, ThousandthsOfText>),
+}
+
+/// Marked-content sequence being extracted.
+enum MarkedContent {
+    Plain,
+    /// Text of `/ActualText` property replaces text of glyphs shown in the
+    /// sequence, None once output.
+    ActualText(Option<String>),
+}
+
+struct TextExtractor {
+    runs: Vec<TextRun>,
+    stack: Vec<TextState>,
+    /// Marked-content sequences, innermost last.
+    marked: Vec<MarkedContent>,
+    /// Font data loaded by fonts of page and forms, forms may be painted
+    /// many times.
+    font_cache: Arc<SharedFontCache>,
+    faces: FontFaces,
+}
+
+impl TextExtractor {
+    fn state(&mut self) -> &mut TextState {
+        self.stack.last_mut().unwrap()
+    }
+
+    fn exec(
+        &mut self,
+        resources: &ResourceDict,
+        ops: Vec<Operation>,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        let fonts = Fonts::new(resources, &self.font_cache, &self.faces)?;
+        for op in ops {
+            match op {
+                Operation::SaveGraphicsState => {
+                    let state = self.state().clone();
+                    self.stack.push(state);
+                }
+                Operation::RestoreGraphicsState if self.stack.len() > 1 => {
+                    self.stack.pop();
+                }
+                Operation::ModifyCTM(ctm) => {
+                    let state = self.state();
+                    state.ctm = ctm.then(&state.ctm);
+                }
+                Operation::BeginText => {
+                    let state = self.state();
+                    state.matrix = TextToUserSpace::identity();
+                    state.line_matrix = TextToUserSpace::identity();
+                }
+                Operation::SetCharacterSpacing(v) => self.state().char_spacing = v,
+                Operation::SetWordSpacing(v) => self.state().word_spacing = v,
+                Operation::SetHorizontalScaling(v) => self.state().horiz_scaling = v / 100.0,
+                Operation::SetLeading(v) => self.state().leading = v,
+                Operation::SetTextRise(v) => self.state().rise = v,
+                Operation::SetFont(name, size) => {
+                    let state = self.state();
+                    state.font = Some(name.0);
+                    state.font_size = size;
+                }
+                Operation::MoveTextPosition(p) => self.state().move_text_position(p),
+                Operation::MoveTextPositionAndSetLeading(p) => {
+                    let state = self.state();
+                    state.leading = -p.y;
+                    state.move_text_position(p);
+                }
+                Operation::SetTextMatrix(m) => {
+                    let state = self.state();
+                    state.matrix = m;
+                    state.line_matrix = m;
+                }
+                Operation::MoveToStartOfNextLine => self.state().move_to_start_of_next_line(),
+                Operation::ShowText(s) => self.show(&fonts, [Shown::Text(s.to_bytes()?)]),
+                Operation::MoveToNextLineAndShowText(s) => {
+                    self.state().move_to_start_of_next_line();
+                    self.show(&fonts, [Shown::Text(s.to_bytes()?)]);
+                }
+                Operation::SetSpacingMoveToNextLineAndShowText(aw, ac, s) => {
+                    let state = self.state();
+                    state.word_spacing = Length::new(aw);
+                    state.char_spacing = Length::new(ac);
+                    state.move_to_start_of_next_line();
+                    self.show(&fonts, [Shown::Text(s.as_bytes())]);
+                }
+                Operation::ShowTexts(texts) => {
+                    let shown = texts
+                        .iter()
+                        .map(|t| match t {
+                            TextStringOrNumber::TextString(s) => s.to_bytes().map(Shown::Text),
+                            TextStringOrNumber::Number(n) => Ok(Shown::Adjust(*n)),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.show(&fonts, shown);
+                }
+                Operation::PaintXObject(name) => self.paint_form(resources, &name.0, depth)?,
+                Operation::BeginMarkedContent(_) => self.marked.push(MarkedContent::Plain),
+                Operation::BeginMarkedContentWithProperties(_, props) => {
+                    let actual_text = marked_content_properties(resources, &props)?
+                        .and_then(|d| d.get(&sname("ActualText")))
+                        .map(|t| t.as_byte_string().map(decode_text_string))
+                        .transpose()?;
+                    self.marked.push(match actual_text {
+                        Some(t) => MarkedContent::ActualText(Some(t)),
+                        None => MarkedContent::Plain,
+                    });
+                }
+                Operation::EndMarkedContent => self.end_marked_content(&fonts),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn paint_form(
+        &mut self,
+        resources: &ResourceDict,
+        name: &Name,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        if depth >= MAX_FORM_DEPTH {
+            warn!("form XObject nested too deep, skip: {}", name);
+            return Ok(());
+        }
+        let x_objects = resources.x_object()?;
+        let Some(x_object) = x_objects.get(name) else {
+            warn!("XObject not found: {}", name);
+            return Ok(());
+        };
+        if x_object.subtype()? != XObjectType::Form {
+            return Ok(());
+        }
+
+        let form = x_object.as_form()?;
+        let content = x_object.as_stream()?.decode(resources.resolver())?;
+        let ops = PageContent::new(vec![content.into_owned()]).operations();
+        let form_resources = form.resources()?;
+        let state = self.state().clone();
+        let ctm = form
+            .matrix()?
+            .then(&state.ctm)
+            .with_source()
+            .with_destination();
+        let saved = std::mem::replace(&mut self.stack, vec![TextState::new(ctm)]);
+        let r = self.exec(form_resources.as_ref().unwrap_or(resources), ops, depth + 1);
+        self.stack = saved;
+        r
+    }
+
+    /// `/ActualText` of outermost sequence, None if not in such sequence.
+    fn actual_text(&mut self) -> Option<&mut Option<String>> {
+        self.marked.iter_mut().find_map(|m| match m {
+            MarkedContent::ActualText(t) => Some(t),
+            MarkedContent::Plain => None,
+        })
+    }
+
+    /// `/ActualText` not output by glyphs shown in the sequence, such as
+    /// sequence of images, becomes a run at current text position.
+    fn end_marked_content(&mut self, fonts: &Fonts) {
+        let Some(MarkedContent::ActualText(Some(text))) = self.marked.pop() else {
+            return;
+        };
+        if self.actual_text().is_some() || text.is_empty() {
+            return;
+        }
+        let state = self.state();
+        let font_name = state.font.as_ref().and_then(|f| fonts.names.get(f));
+        let origin = state.origin();
+        let run = TextRun {
+            text,
+            origin,
+            end: origin,
+            bbox: Rectangle::from_lbrt(origin.x, origin.y, origin.x, origin.y),
+            font_name: font_name.cloned().unwrap_or_default(),
+            font_size: state.font_size(),
+        };
+        self.runs.push(run);
+    }
+
+    fn show<'o>(&mut self, fonts: &Fonts, shown: impl IntoIterator<Item = Shown<'o>>) {
+        // glyphs in `/ActualText` sequence not output, the text is output
+        // with the first shown glyphs
+        let (actual_text, in_actual_text) = match self.actual_text() {
+            Some(t) => (t.take(), true),
+            None => (None, false),
+        };
+        let state = self.stack.last_mut().unwrap();
+        let Some(font) = state.font.clone() else {
+            warn!("show text without font");
+            return;
+        };
+        let Some(op) = fonts.cache.get_op(&font) else {
+            warn!("font not found: {}", font);
+            return;
+        };
+
+        let em_ratio = Scale::new(1.0 / f32::from(op.units_per_em()));
+        let origin = state.origin();
+        let mut bbox: Option<Box2D<f32>> = None;
+        let mut run = TextRun {
+            text: actual_text.unwrap_or_default(),
+            origin,
+            end: origin,
+            bbox: Rectangle::default(),
+            font_name: fonts.names.get(&font).cloned().unwrap_or_default(),
+            font_size: state.font_size(),
+        };
+        for part in shown {
+            match part {
+                Shown::Text(s) => {
+                    for (code, ch) in op.decode_code_chars(s) {
+                        if let Some(unicode) =
+                            op.char_to_unicode(code, ch).filter(|_| !in_actual_text)
+                        {
+                            run.text.extend(unicode);
+                        }
+                        // glyph box and end exclude spacing after the glyph
+                        let width = glyph_width(
+                            op.char_width(ch),
+                            em_ratio,
+                            state.font_size,
+                            state.horiz_scaling,
+                        );
+                        let glyph = state.glyph_box(width.0);
+                        bbox = Some(bbox.map_or(glyph, |b| b.union(&glyph)));
+                        run.end = state.to_user_space(width.0, 0.0);
+                        let advance = glyph_advance(
+                            op.char_width(ch),
+                            em_ratio,
+                            state.font_size,
+                            state.char_spacing,
+                            state.word_spacing,
+                            ch == 32,
+                            state.horiz_scaling,
+                        );
+                        state.matrix = move_text_space_right(&state.matrix, advance);
+                    }
+                }
+                Shown::Adjust(n) => {
+                    let gap = -n.0 / 1000.0;
+                    if gap > SPACE_GAP
+                        && !in_actual_text
+                        && !run.text.is_empty()
+                        && !run.text.ends_with(' ')
+                    {
+                        run.text.push(' ');
+                    }
+                    let n = n * (state.font_size * state.horiz_scaling) * Scale::new(1.0 / 1000.0);
+                    state.matrix = move_text_space_right(&state.matrix, -n);
+                }
+            }
+        }
+        let bbox = bbox.unwrap_or_else(|| Box2D::from_points([origin]));
+        run.bbox = Rectangle::from_lbrt(bbox.min.x, bbox.min.y, bbox.max.x, bbox.max.y);
+        if !run.text.is_empty() {
+            self.runs.push(run);
+        }
+    }
+}
+
+impl Page<'_, '_> {
+    /// Text shown by text showing operations of page content and forms it
+    /// paints, one run per operation in content order. Invisible text is
+    /// included, annotations are not.
+    ///
+    /// Unicode of chars read from `/ToUnicode` of the font if exists,
+    /// otherwise from font encoding. `/ToUnicode` looked up by char code,
+    /// not by CID of CID fonts. Text of glyphs in
+    /// marked-content sequence of `/ActualText` property, such as ligatures,
+    /// is replaced by the property, output in run of the first shown glyphs.
+    pub fn extract_text(&self) -> Result<Vec<TextRun>, ObjectValueError> {
+        let mut extractor = TextExtractor {
+            runs: vec![],
+            stack: vec![TextState::new(UserToUserSpace::identity())],
+            marked: vec![],
+            font_cache: Arc::default(),
+            faces: FontFaces::default(),
+        };
+        let resources = self.resources();
+        extractor
+            .exec(&resources, self.content()?.operations(), 0)
+            .map_err(|e| {
+                warn!("extract text: {}", e);
+                match e.downcast::<ObjectValueError>() {
+                    Ok(e) => e,
+                    Err(_) => ObjectValueError::UnexpectedType,
+                }
+            })?;
+        Ok(extractor.runs)
+    }
+
+    /// Readable text of the page, text of [`Page::extract_text()`] joined in
+    /// content order. A run starts off the baseline of previous run by half
+    /// of font size starts a new line, a gap wider than a quarter of font
+    /// size between runs on the same line becomes a space. Horizontal text
+    /// assumed.
+    pub fn plain_text(&self) -> Result<String, ObjectValueError> {
+        let mut r = String::new();
+        let mut prev: Option<TextRun> = None;
+        for run in self.extract_text()? {
+            if let Some(prev) = &prev {
+                let size = prev.font_size;
+                let gap = (run.origin.x - prev.end.x) / size;
+                match text_separator(gap, (run.origin.y - prev.end.y) / size) {
+                    Some('\n') => r.push('\n'),
+                    Some(sep) if !r.ends_with(' ') && !run.text.starts_with(' ') => r.push(sep),
+                    _ => {}
+                }
+            }
+            r.push_str(&run.text);
+            prev = Some(run);
+        }
+        Ok(r)
+    }
+}
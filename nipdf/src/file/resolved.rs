@@ -0,0 +1,51 @@
+//! `File` bundled with its `ObjectResolver`, saves the caller from keeping
+//! the file alive in a separate binding for the borrowed resolver.
+use super::{Catalog, File, FileError, ObjectResolver};
+use crate::object::ObjectValueError;
+use ouroboros::self_referencing;
+
+#[self_referencing]
+struct Inner {
+    file: File,
+    #[borrows(file)]
+    #[covariant]
+    resolver: ObjectResolver<'this>,
+}
+
+/// Parsed file and a resolver ready to use, created by [`File::open_resolver()`].
+pub struct ResolvedFile {
+    inner: Inner,
+}
+
+impl ResolvedFile {
+    pub fn file(&self) -> &File {
+        self.inner.borrow_file()
+    }
+
+    pub fn resolver(&self) -> &ObjectResolver<'_> {
+        self.inner.borrow_resolver()
+    }
+
+    pub fn catalog(&self) -> Result<Catalog<'_, '_>, ObjectValueError> {
+        self.file().catalog(self.resolver())
+    }
+
+    pub fn version(&self) -> Result<Option<String>, ObjectValueError> {
+        self.file().version(self.resolver())
+    }
+
+    pub fn into_file(self) -> File {
+        self.inner.into_heads().file
+    }
+}
+
+impl File {
+    /// Parse file and create its resolver in one call.
+    pub fn open_resolver(buf: Vec<u8>, user_password: &str) -> Result<ResolvedFile, FileError> {
+        let file = Self::parse(buf, user_password)?;
+        let inner = Inner::new(file, |file| {
+            ObjectResolver::new(&file.data, &file.xref, file.encrypt_info.clone())
+        });
+        Ok(ResolvedFile { inner })
+    }
+}
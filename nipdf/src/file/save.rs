@@ -0,0 +1,273 @@
+//! Re-save pdf file, keep only objects reachable from trailer, or split
+//! pages into single page files.
+use super::{File, ObjectResolver, Page};
+use crate::object::{Dictionary, Object, RuntimeObjectId};
+use ahash::HashMap;
+use anyhow::{Result as AnyResult, bail};
+use prescript::{Name, sname};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// New ids of referenced objects, None to keep object ids.
+type Renumber<'m> = Option<&'m HashMap<RuntimeObjectId, u32>>;
+
+/// Append `name` to `out`, escape delimiters and non-regular characters
+/// using `#xx` form, see PDF 32000-1:2008 7.3.5.
+fn write_name(name: &Name, out: &mut Vec<u8>) {
+    out.push(b'/');
+    for &b in name.as_bytes() {
+        if (b'!'..=b'~').contains(&b) && !b"#%()/<>[]{}".contains(&b) {
+            out.push(b);
+        } else {
+            write!(out, "#{:02X}", b).unwrap();
+        }
+    }
+}
+
+/// Append string as hex string, hex form needs no escaping.
+fn write_hex_string(s: &[u8], out: &mut Vec<u8>) {
+    out.push(b'<');
+    for b in s {
+        write!(out, "{:02X}", b).unwrap();
+    }
+    out.push(b'>');
+}
+
+/// Append `o` to `out`. Stream data is copied un-decoded, `/Length` is
+/// replaced by the actual data length, because it may be an indirect object.
+/// If `renumber` is set, references are written with new ids, reference to
+/// object not in `renumber` written as `null`.
+fn write_object(
+    o: &Object,
+    resolver: &ObjectResolver,
+    renumber: Renumber,
+    out: &mut Vec<u8>,
+) -> AnyResult<()> {
+    match o {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Bool(v) => write!(out, "{}", v)?,
+        Object::Integer(v) => write!(out, "{}", v)?,
+        Object::Number(v) => write!(out, "{}", v)?,
+        Object::LiteralString(s) => write_hex_string(s.as_bytes(), out),
+        Object::HexString(s) => write_hex_string(s.as_bytes(), out),
+        Object::Name(name) => write_name(name, out),
+        Object::Array(arr) => {
+            out.push(b'[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                write_object(item, resolver, renumber, out)?;
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(d) => {
+            out.extend_from_slice(b"<<");
+            write_dict_entries(d, resolver, renumber, out)?;
+            out.extend_from_slice(b">>");
+        }
+        Object::Stream(s) => {
+            let data = s.raw(resolver)?;
+            let length = data.len().try_into()?;
+            let mut d = s.as_dict().clone();
+            d.update(|d| {
+                d.insert(sname("Length"), Object::Integer(length));
+            });
+            write_object(&Object::Dictionary(d), resolver, renumber, out)?;
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(data);
+            out.extend_from_slice(b"\nendstream");
+        }
+        Object::Reference(r) => match renumber {
+            None => write!(out, "{} {} R", r.id().id().0, r.id().generation())?,
+            Some(ids) => match ids.get(&r.id().id()) {
+                Some(id) => write!(out, "{} 0 R", id)?,
+                None => out.extend_from_slice(b"null"),
+            },
+        },
+    }
+    Ok(())
+}
+
+/// Append entries of `d` without enclosing `<<` and `>>`, keys sorted to
+/// make output stable.
+fn write_dict_entries(
+    d: &Dictionary,
+    resolver: &ObjectResolver,
+    renumber: Renumber,
+    out: &mut Vec<u8>,
+) -> AnyResult<()> {
+    let mut entries: Vec<_> = d.iter().collect();
+    entries.sort_unstable_by_key(|(k, _)| k.as_str());
+    for (k, v) in entries {
+        write_name(k, out);
+        out.push(b' ');
+        write_object(v, resolver, renumber, out)?;
+    }
+    Ok(())
+}
+
+/// Append cross reference table, `offsets` are `(id, offset, generation)`
+/// sorted by id, ids not in `offsets` are free. Returns offset of the table.
+fn write_xref(offsets: &[(u32, usize, u16)], size: u32, out: &mut Vec<u8>) -> AnyResult<usize> {
+    let xref_offset = out.len();
+    writeln!(out, "xref\n0 {}", size)?;
+    let mut offsets = offsets.iter().peekable();
+    for i in 0..size {
+        match offsets.next_if(|(id, _, _)| *id == i) {
+            Some((_, offset, generation)) => writeln!(out, "{:010} {:05} n\r", offset, generation)?,
+            None => writeln!(out, "{:010} {:05} f\r", 0, if i == 0 { 65535 } else { 0 })?,
+        }
+    }
+    Ok(xref_offset)
+}
+
+impl File {
+    /// Serialize file, write only objects reachable from trailer `/Root` and
+    /// `/Info`, orphaned objects, such as objects left by incremental updates,
+    /// are dropped. Object ids are kept, objects in object streams are written
+    /// as plain indirect objects, and cross reference table is used.
+    pub fn pruned_bytes(&self) -> AnyResult<Vec<u8>> {
+        if self.encrypt_info.is_some() {
+            bail!("prune encrypted file not supported");
+        }
+
+        let resolver = self.resolver()?;
+        let roots: Vec<_> = [Some(self.root_id), self.info_id]
+            .into_iter()
+            .flatten()
+            .map(|id| Object::new_ref(id.0))
+            .collect();
+        let mut ids: Vec<_> = resolver
+            .referenced_object_ids(&roots, |_| true)?
+            .into_iter()
+            .filter(|id| resolver.resolve(*id).is_ok())
+            .collect();
+        ids.sort_unstable_by_key(|id| id.0);
+
+        let mut out = self.header()?;
+        let mut offsets = Vec::with_capacity(ids.len());
+        for &id in &ids {
+            let generation = self.xref.generation(id).unwrap_or_default();
+            offsets.push((id.0, out.len(), generation));
+            writeln!(out, "{} {} obj", id.0, generation)?;
+            write_object(resolver.resolve(id)?, &resolver, None, &mut out)?;
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let size = ids.last().map_or(1, |id| id.0 + 1);
+        let xref_offset = write_xref(&offsets, size, &mut out)?;
+
+        let reference = |id: RuntimeObjectId| {
+            format!(
+                "{} {} R",
+                id.0,
+                self.xref.generation(id).unwrap_or_default()
+            )
+        };
+        write!(
+            out,
+            "trailer\n<</Size {} /Root {}",
+            size,
+            reference(self.root_id)
+        )?;
+        if let Some(info_id) = self.info_id {
+            write!(out, " /Info {}", reference(info_id))?;
+        }
+        write!(out, ">>\nstartxref\n{}\n%%EOF\n", xref_offset)?;
+        Ok(out)
+    }
+
+    /// Save to `path` with only reachable objects, see [`File::pruned_bytes()`].
+    pub fn save_pruned(&self, path: impl AsRef<Path>) -> AnyResult<()> {
+        std::fs::write(path, self.pruned_bytes()?)?;
+        Ok(())
+    }
+
+    /// File header, version line followed by binary comment, mark the file
+    /// contains binary data.
+    fn header(&self) -> AnyResult<Vec<u8>> {
+        let mut out = Vec::new();
+        writeln!(out, "%PDF-{}", self.head_ver.as_deref().unwrap_or("1.7"))?;
+        out.extend_from_slice(b"%\xE2\xE3\xCF\xD3\n");
+        Ok(out)
+    }
+
+    /// Serialize `page` as a standalone file of one page. Page dictionary is
+    /// copied with inherited `/MediaBox`, `/CropBox`, `/Rotate` and
+    /// `/Resources`, other entries such as `/Annots` are dropped. Objects
+    /// referenced from resources and contents are copied and renumbered.
+    fn single_page_bytes(&self, resolver: &ObjectResolver, page: &Page) -> AnyResult<Vec<u8>> {
+        let mut ids: Vec<_> = page
+            .referenced_object_ids()?
+            .into_iter()
+            .filter(|id| resolver.resolve(*id).is_ok())
+            .collect();
+        ids.sort_unstable_by_key(|id| id.0);
+        // 1 is catalog, 2 page tree, 3 page, copied objects start from 4
+        let renumber: HashMap<_, _> = ids.iter().copied().zip(4..).collect();
+
+        let mut page_dict = Dictionary::new();
+        page_dict.update(|d| {
+            for key in ["MediaBox", "CropBox", "Rotate", "Resources"] {
+                let key = sname(key);
+                if let Some(v) = page.inherited_attr(&key) {
+                    d.insert(key, v.clone());
+                }
+            }
+            if let Some(v) = page.dict().get(&sname("Contents")) {
+                d.insert(sname("Contents"), v.clone());
+            }
+        });
+
+        let mut out = self.header()?;
+        let mut offsets = Vec::with_capacity(ids.len() + 3);
+        offsets.push((1, out.len(), 0));
+        out.extend_from_slice(b"1 0 obj\n<</Type /Catalog /Pages 2 0 R>>\nendobj\n");
+        offsets.push((2, out.len(), 0));
+        out.extend_from_slice(b"2 0 obj\n<</Type /Pages /Kids [3 0 R] /Count 1>>\nendobj\n");
+        // `/Parent` written as is, references in other entries renumbered
+        offsets.push((3, out.len(), 0));
+        out.extend_from_slice(b"3 0 obj\n<</Type /Page /Parent 2 0 R");
+        write_dict_entries(&page_dict, resolver, Some(&renumber), &mut out)?;
+        out.extend_from_slice(b">>\nendobj\n");
+        for (id, new_id) in ids.iter().zip(4..) {
+            offsets.push((new_id, out.len(), 0));
+            writeln!(out, "{} 0 obj", new_id)?;
+            write_object(resolver.resolve(*id)?, resolver, Some(&renumber), &mut out)?;
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let size = offsets.last().map_or(1, |(id, _, _)| id + 1);
+        let xref_offset = write_xref(&offsets, size, &mut out)?;
+        write!(
+            out,
+            "trailer\n<</Size {} /Root 1 0 R>>\nstartxref\n{}\n%%EOF\n",
+            size, xref_offset
+        )?;
+        Ok(out)
+    }
+
+    /// Write each page as a single page file named `page-<n>.pdf` in
+    /// `out_dir`, `n` starts from 1. Page contents, resources and page boxes
+    /// are kept, other page entries such as annotations are dropped.
+    /// Returns paths of written files in page order.
+    pub fn split_pages(&self, out_dir: impl AsRef<Path>) -> AnyResult<Vec<PathBuf>> {
+        if self.encrypt_info.is_some() {
+            bail!("split encrypted file not supported");
+        }
+
+        let resolver = self.resolver()?;
+        let pages = self.catalog(&resolver)?.pages()?;
+        let out_dir = out_dir.as_ref();
+        let mut r = Vec::with_capacity(pages.len());
+        for (i, page) in pages.iter().enumerate() {
+            let path = out_dir.join(format!("page-{}.pdf", i + 1));
+            std::fs::write(&path, self.single_page_bytes(&resolver, page)?)?;
+            r.push(path);
+        }
+        Ok(r)
+    }
+}
@@ -4,14 +4,15 @@ use crate::{
     parser::parse_dict,
 };
 use prescript::sname;
-use std::path::PathBuf;
+use std::{path::PathBuf, str::from_utf8};
+use test_case::test_case;
 
 #[test]
 fn xref_table_resolve_object_buf() {
     let buf = b"1234567890";
     let mut id_offset = IDOffsetMap::default();
-    id_offset.insert(1.into(), ObjectPos::Offset(5));
-    id_offset.insert(2.into(), ObjectPos::Offset(3));
+    id_offset.insert(1.into(), ObjectPos::Offset(5, 0));
+    id_offset.insert(2.into(), ObjectPos::Offset(3, 0));
     let xref_table = XRefTable::new(id_offset);
 
     assert_eq!(
@@ -29,9 +30,9 @@ fn xref_table_resolve_object_buf() {
 fn object_resolver() {
     let buf = b"   2 0 obj 5 endobj 1 0 obj null endobj 3 0 obj 2 0 R endobj";
     let mut id_offset = IDOffsetMap::default();
-    id_offset.insert(1.into(), ObjectPos::Offset(20));
-    id_offset.insert(2.into(), ObjectPos::Offset(3));
-    id_offset.insert(3.into(), ObjectPos::Offset(40));
+    id_offset.insert(1.into(), ObjectPos::Offset(20, 0));
+    id_offset.insert(2.into(), ObjectPos::Offset(3, 0));
+    id_offset.insert(3.into(), ObjectPos::Offset(40, 0));
     let xref_table = XRefTable::new(id_offset);
     let resolver = ObjectResolver::new(buf, &xref_table, None);
 
@@ -110,6 +111,83 @@ endobj
     Ok(())
 }
 
+/// Node 2 lists itself as kid, `/Limits` of node 3 and 5 have one key only.
+#[test]
+fn walk_tree_cyclic_and_short_limits() -> AnyResult<()> {
+    let buf = br#"1 0 obj
+<</Kids [2 0 R 3 0 R]>>
+endobj
+2 0 obj
+<</Kids [2 0 R 2 0 R] /Names [(a) 1]>>
+endobj
+3 0 obj
+<</Limits [(c)] /Names [(c) 3]>>
+endobj
+4 0 obj
+<</Kids [4 0 R 5 0 R]>>
+endobj
+5 0 obj
+<</Limits [1] /Nums [1 (x)]>>
+endobj
+"#;
+    let xref = XRefTable::from_buf(buf);
+    let resolver = ObjectResolver::new(buf, &xref, None);
+    let names = resolver.resolve(1)?.as_dict()?;
+    assert_eq!(
+        Some(&Object::Integer(3)),
+        lookup_name_tree(&resolver, names, b"c")?
+    );
+    assert_eq!(None, lookup_name_tree(&resolver, names, b"b")?);
+    let mut pairs = vec![];
+    collect_name_tree(&resolver, names, &mut pairs)?;
+    assert_eq!(
+        vec![&b"a"[..], &b"c"[..]],
+        pairs.iter().map(|(k, _)| *k).collect::<Vec<_>>()
+    );
+
+    let nums = resolver.resolve(4)?.as_dict()?;
+    assert_eq!(
+        b"x",
+        lookup_number_tree(&resolver, nums, 1)?
+            .unwrap()
+            .as_byte_string()?
+    );
+    Ok(())
+}
+
+/// Action 1 chains itself twice by `/Next`, field 2 lists itself as kid.
+#[test]
+fn javascript_cyclic() -> AnyResult<()> {
+    let buf = br#"1 0 obj
+<</S /JavaScript /JS (a) /Next [1 0 R 1 0 R]>>
+endobj
+2 0 obj
+<</T (f) /AA <</K 1 0 R>> /Kids [2 0 R 2 0 R]>>
+endobj
+"#;
+    let xref = XRefTable::from_buf(buf);
+    let resolver = ObjectResolver::new(buf, &xref, None);
+    let mut scripts = vec![];
+    collect_javascript(
+        &resolver,
+        &Object::new_ref(1),
+        &mut HashSet::new(),
+        &mut scripts,
+    )?;
+    assert_eq!(vec!["a"], scripts);
+
+    let mut r = vec![];
+    collect_field_javascript(
+        &resolver,
+        &Object::new_ref(2),
+        "",
+        &mut HashSet::new(),
+        &mut r,
+    )?;
+    assert_eq!(1, r.len());
+    Ok(())
+}
+
 #[test]
 fn resolve_one_or_more_pdf_object() {
     // object is dictionary
@@ -169,3 +247,396 @@ fn parse_file() {
     let resolver = f.resolver().unwrap();
     assert_eq!(Some("1.5".to_owned()), f.version(&resolver).unwrap());
 }
+
+#[test]
+fn iter_objects() {
+    let f = open_test_file("sample_files/file-structure/xref-stream.pdf");
+    let resolver = f.resolver().unwrap();
+    let objects = resolver
+        .iter_objects()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let ids = objects.iter().map(|(id, _, _)| id.0).collect::<Vec<_>>();
+    assert!(ids.is_sorted());
+    let mut expected = f.xref.iter_ids().map(|id| id.0).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(expected, ids);
+    // objects inside object stream are included
+    assert!(
+        f.xref
+            .iter_ids()
+            .any(|id| matches!(f.xref.id_offset[&id], ObjectPos::InStream(..)))
+    );
+    assert!(objects.iter().all(|(_, generation, _)| *generation == 0));
+}
+
+#[test]
+fn xref_entries() {
+    let entries = |path: &str| {
+        open_test_file(path)
+            .xref()
+            .entries()
+            .into_iter()
+            .map(|(id, entry)| (id.0, entry))
+            .collect::<Vec<_>>()
+    };
+
+    // classic xref table
+    let classic = entries("sample_files/page/two-pages.pdf");
+    assert_eq!(9, classic.len());
+    assert_eq!((0, Entry::in_file(0, 65535, false)), classic[0]);
+    assert_eq!((3, Entry::in_file(184, 0, true)), classic[3]);
+
+    // xref stream, objects in object stream 7
+    assert_eq!(
+        vec![
+            (0, Entry::in_file(0, 65535, false)),
+            (1, Entry::in_file(152, 0, true)),
+            (2, Entry::in_stream(RuntimeObjectId(7), 2)),
+            (3, Entry::in_file(197, 0, true)),
+            (4, Entry::in_stream(RuntimeObjectId(7), 0)),
+            (5, Entry::in_file(15, 0, true)),
+            (6, Entry::in_stream(RuntimeObjectId(7), 1)),
+            (7, Entry::in_file(364, 0, true)),
+        ],
+        entries("sample_files/file-structure/xref-stream.pdf")
+    );
+}
+
+#[test]
+fn cyclic_reference() {
+    let f = open_test_file("sample_files/bizarre/cyclic-reference.pdf");
+    let resolver = f.resolver().unwrap();
+
+    // page tree node links to itself
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    assert_eq!(1, pages.len());
+    assert_eq!(RuntimeObjectId(3), pages[0].id());
+
+    // reference chain loops: 5 -> 6 -> 5
+    let page = resolver.resolve(3).unwrap().as_dict().unwrap();
+    assert_eq!(
+        Err(ObjectValueError::CyclicReference(5.into())),
+        resolver.resolve_container_value(page, &sname("StructParents"))
+    );
+
+    // dictionary refers to itself is fine, resolve only one level
+    let piece_info = resolver
+        .resolve_container_value(page, &sname("PieceInfo"))
+        .unwrap()
+        .as_dict()
+        .unwrap();
+    assert_eq!(
+        piece_info,
+        resolver
+            .resolve_container_value(piece_info, &sname("Self"))
+            .unwrap()
+            .as_dict()
+            .unwrap()
+    );
+}
+
+#[test]
+fn recursive_pages_tree() {
+    let f = open_test_file("sample_files/bizarre/recursive-pages-tree.pdf");
+    let resolver = f.resolver().unwrap();
+
+    // node 3 lists its parent 2 in `/Kids`, and 2 lists 3 twice
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    assert_eq!(
+        vec![RuntimeObjectId(4), RuntimeObjectId(5)],
+        pages.iter().map(|p| p.id()).collect::<Vec<_>>()
+    );
+    // inherits from its own ancestors only
+    assert_eq!(
+        Rectangle::from_xywh(0., 0., 300., 300.),
+        pages[0].media_box()
+    );
+    assert_eq!(
+        Rectangle::from_xywh(0., 0., 200., 200.),
+        pages[1].media_box()
+    );
+}
+
+#[test]
+fn pages_tree_without_count() {
+    let f = open_test_file("sample_files/bizarre/pages-tree-no-count.pdf");
+    let resolver = f.resolver().unwrap();
+
+    // root has no `/Count`, sub tree 4 claims 7 pages, page 6 has no `/Type`
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    assert_eq!(
+        vec![RuntimeObjectId(3), RuntimeObjectId(5), RuntimeObjectId(6)],
+        pages.iter().map(|p| p.id()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn collection_documents() {
+    let f = open_test_file("sample_files/page/portfolio.pdf");
+    let resolver = f.resolver().unwrap();
+    let docs = f
+        .catalog(&resolver)
+        .unwrap()
+        .collection_documents()
+        .unwrap();
+    // "notes.txt" is not pdf
+    assert_eq!(
+        vec!["a.pdf", "b.pdf"],
+        docs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>()
+    );
+
+    let sizes: Vec<_> = docs
+        .into_iter()
+        .map(|d| {
+            let f = File::parse(d.data, "").unwrap();
+            let resolver = f.resolver().unwrap();
+            let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+            pages[0].media_box().width()
+        })
+        .collect();
+    assert_eq!(vec![100., 200.], sizes);
+
+    let f = open_test_file("sample_files/page/links.pdf");
+    let resolver = f.resolver().unwrap();
+    assert!(
+        f.catalog(&resolver)
+            .unwrap()
+            .collection_documents()
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn javascript() {
+    let f = open_test_file("sample_files/page/javascript.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let js = |name: &str, source: &str| JavaScript {
+        name: name.to_owned(),
+        source: source.to_owned(),
+    };
+
+    // script in stream, and action chained by `/Next`
+    assert_eq!(
+        vec![
+            js("b", "stream();"),
+            js("init", "app.alert(1);"),
+            js("init", "next();"),
+        ],
+        catalog.document_javascript().unwrap()
+    );
+
+    // widget kid has no `/T`, non JavaScript action ignored
+    let field = |source: &JavaScript| ("form.name".to_owned(), source.clone());
+    assert_eq!(
+        vec![
+            field(&js("K", "keystroke();")),
+            field(&js("Fo", "focus();"))
+        ],
+        catalog.field_javascript().unwrap()
+    );
+
+    let annots = catalog.pages().unwrap()[0].annotations().unwrap();
+    assert_eq!(
+        vec![js("Fo", "focus();")],
+        annots[0].additional_javascript().unwrap()
+    );
+
+    let f = open_test_file("sample_files/page/links.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    assert!(catalog.document_javascript().unwrap().is_empty());
+    assert!(catalog.field_javascript().unwrap().is_empty());
+}
+
+#[test]
+fn layers() {
+    let f = open_test_file("sample_files/page/layers.pdf");
+    let resolver = f.resolver().unwrap();
+    let layers = f.catalog(&resolver).unwrap().layers().unwrap();
+    // `/D` turns "Annotations" off, others use base state `/ON`
+    assert_eq!(
+        vec![("Background", true), ("Annotations", false), ("Grid", true),],
+        layers
+            .iter()
+            .map(|l| (l.name.as_str(), l.visible))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(RuntimeObjectId(5), layers[1].id);
+}
+
+#[test]
+fn outlines() {
+    let f = open_test_file("sample_files/page/outlines.pdf");
+    let resolver = f.resolver().unwrap();
+    let outlines = f.catalog(&resolver).unwrap().outlines().unwrap();
+    assert_eq!(2, outlines.len());
+
+    let ch1 = &outlines[0];
+    assert_eq!("Chapter 1", ch1.title);
+    assert!(ch1.open);
+    assert_eq!([0.0; 3], ch1.color);
+    assert_eq!(OutlineFlags::empty(), ch1.flags);
+    assert_eq!(Some((RuntimeObjectId(3), DestView::Fit)), ch1.dest);
+    assert_eq!(1, ch1.kids.len());
+    assert_eq!("Section 1.1", ch1.kids[0].title);
+    assert_eq!(OutlineFlags::ITALIC, ch1.kids[0].flags);
+
+    // collapsed by negative `/Count`, destination from `/GoTo` action
+    let ch2 = &outlines[1];
+    assert_eq!("Chapter 2", ch2.title);
+    assert!(!ch2.open);
+    assert_eq!([1.0, 0.0, 0.0], ch2.color);
+    assert_eq!(OutlineFlags::BOLD, ch2.flags);
+    assert_eq!(Some((RuntimeObjectId(4), DestView::Fit)), ch2.dest);
+    assert_eq!(
+        vec!["Section 2.1"],
+        ch2.kids
+            .iter()
+            .map(|k| k.title.as_str())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn acro_form() {
+    let f = open_test_file("sample_files/page/acro-form.pdf");
+    let resolver = f.resolver().unwrap();
+    let form = f.catalog(&resolver).unwrap().acro_form().unwrap().unwrap();
+    assert!(!form.need_appearances().unwrap());
+    assert_eq!(Some("/Helv 0 Tf 0 g"), form.default_appearance().unwrap());
+    // `bad` of unknown field type and `loop` listing itself as kids skipped
+    let fields = form.fields().unwrap();
+    assert_eq!(
+        vec![
+            "name",
+            "address.city",
+            "address.zip",
+            "gender",
+            "agree",
+            "color",
+            "sig"
+        ],
+        fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>()
+    );
+
+    let name = &fields[0];
+    assert_eq!(Some(FieldType::Text), name.field_type);
+    assert_eq!(Some(FieldValue::Text("John".to_owned())), name.value);
+    assert_eq!(vec![Rectangle::from_xywh(10., 180., 90., 10.)], name.rects);
+
+    // field type inherited from parent, value from stream
+    let city = &fields[1];
+    assert_eq!(Some(FieldType::Text), city.field_type);
+    assert_eq!(Some(FieldValue::Text("Paris".to_owned())), city.value);
+    let zip = &fields[2];
+    assert_eq!(FieldFlags::MULTILINE, zip.flags);
+    assert_eq!(None, zip.value);
+
+    // radio group, widgets are kids
+    let gender = &fields[3];
+    assert_eq!(Some(FieldType::Button), gender.field_type);
+    assert_eq!(
+        FieldFlags::RADIO | FieldFlags::NO_TOGGLE_TO_OFF,
+        gender.flags
+    );
+    assert_eq!(Some(FieldValue::Name(sname("F"))), gender.value);
+    assert_eq!(2, gender.rects.len());
+
+    assert_eq!(Some(FieldValue::Name(sname("Yes"))), fields[4].value);
+
+    let color = &fields[5];
+    assert_eq!(Some(FieldType::Choice), color.field_type);
+    assert_eq!(FieldFlags::MULTI_SELECT, color.flags);
+    assert_eq!(
+        Some(FieldValue::Texts(vec!["Red".to_owned(), "Blue".to_owned()])),
+        color.value
+    );
+
+    let sig = &fields[6];
+    assert_eq!(Some(FieldType::Signature), sig.field_type);
+    assert_eq!(None, sig.value);
+
+    let f = open_test_file("sample_files/page/links.pdf");
+    let resolver = f.resolver().unwrap();
+    assert!(f.catalog(&resolver).unwrap().acro_form().unwrap().is_none());
+}
+
+/// Layer A on, B off by default. Object 7 is OCMD of `/AnyOn` on A and B,
+/// 8 `/AllOn`, 9 has visibility expression `A and not B`.
+#[test_case(false => (true, false, true); "default")]
+#[test_case(true => (true, true, false); "B on")]
+fn optional_content_membership(b_on: bool) -> (bool, bool, bool) {
+    let f = open_test_file("sample_files/page/optional-content-membership.pdf");
+    let resolver = f.resolver().unwrap();
+    let mut oc = f.catalog(&resolver).unwrap().optional_content().unwrap();
+    assert!(oc.is_group_visible(RuntimeObjectId(5)));
+    assert!(!oc.is_group_visible(RuntimeObjectId(6)));
+    oc.set_visible(RuntimeObjectId(6), b_on);
+    let visible = |id| oc.is_visible(&resolver, &Object::new_ref(id)).unwrap();
+    (visible(7), visible(8), visible(9))
+}
+
+#[test]
+fn pruned_bytes() {
+    let f = open_test_file("sample_files/page/referenced-objects.pdf");
+    let pruned = File::parse(f.pruned_bytes().unwrap(), "").unwrap();
+    let resolver = pruned.resolver().unwrap();
+    // object 11 is not referenced
+    let ids = resolver
+        .iter_objects()
+        .map(|r| r.unwrap().0.0)
+        .collect::<Vec<_>>();
+    assert_eq!((1..=10).collect::<Vec<_>>(), ids);
+
+    let pages = pruned.catalog(&resolver).unwrap().pages().unwrap();
+    assert_eq!(
+        b"/Fm0 Do\n".as_slice(),
+        pages[0].content().unwrap().as_ref().next().unwrap()
+    );
+}
+
+#[test]
+fn split_pages() {
+    let f = open_test_file("sample_files/page/two-pages.pdf");
+    let out_dir = std::env::temp_dir().join(format!("nipdf-split-pages-{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let paths = f.split_pages(&out_dir).unwrap();
+    assert_eq!(2, paths.len());
+
+    for (path, (text, rotate)) in paths.iter().zip([("One", 0), ("Two", 90)]) {
+        let f = File::parse(std::fs::read(path).unwrap(), "").unwrap();
+        let resolver = f.resolver().unwrap();
+        let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+        assert_eq!(1, pages.len());
+        let page = &pages[0];
+        let content = page.content().unwrap().to_bytes();
+        assert!(from_utf8(&content).unwrap().contains(text));
+        // inherited attributes copied to page
+        assert_eq!(Rectangle::from_xywh(0., 0., 200., 100.), page.media_box());
+        assert!(page.resources().font().unwrap().contains_key(&sname("F1")));
+        assert_eq!(rotate, page.rotate());
+        // link annotation to other page dropped
+        assert!(page.annotations().unwrap().is_empty());
+    }
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn open_resolver() {
+    let data = std::fs::read(test_file("sample_files/page/referenced-objects.pdf")).unwrap();
+    let f = File::open_resolver(data, "").unwrap();
+    let pages = f.catalog().unwrap().pages().unwrap();
+    assert_eq!(1, pages.len());
+    let page = f
+        .resolver()
+        .resolve(pages[0].id())
+        .unwrap()
+        .as_dict()
+        .unwrap();
+    assert_eq!(sname("Page"), page[&sname("Type")].name().unwrap());
+    assert!(f.into_file().pruned_bytes().is_ok());
+}
@@ -230,8 +230,14 @@ pub struct PostScriptFunction {
 
 impl PostScriptFunction {
     pub fn new(signature: Signature, script: Box<[u8]>) -> Self {
+        let to_pairs =
+            |domains: &Domains| domains.iter().map(|d| (d.start, d.end)).collect::<Vec<_>>();
         Self {
-            f: PdfFunc::new(script, signature.n_returns().unwrap()),
+            f: PdfFunc::new(
+                script,
+                to_pairs(&signature.domain),
+                to_pairs(signature.range.as_ref().unwrap()),
+            ),
             signature,
         }
     }
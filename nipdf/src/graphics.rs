@@ -77,6 +77,49 @@ pub enum RenderingIntent {
     Perceptual,
 }
 
+/// Blend mode of ExtGState `/BM`, see PDF 32000-1:2008 11.3.5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    /// Deprecated alias of `Normal`
+    Compatible,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+/// `/BM` is a name, or an array of names, use the first one recognized,
+/// fallback to `Normal` if none of them recognized.
+impl TryFrom<&Object> for BlendMode {
+    type Error = ObjectValueError;
+
+    fn try_from(o: &Object) -> Result<Self, Self::Error> {
+        let parse = |o: &Object| -> Result<Self, ObjectValueError> {
+            o.name()?
+                .as_str()
+                .parse()
+                .map_err(|_| ObjectValueError::GraphicsOperationSchemaError)
+        };
+        match o {
+            Object::Array(arr) => Ok(arr.iter().find_map(|o| parse(o).ok()).unwrap_or_default()),
+            _ => parse(o),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, TryFromIntObject)]
 pub enum TextRenderingMode {
     Fill = 0,
@@ -102,16 +145,15 @@ impl AsRef<[f32]> for ColorArgs {
 }
 
 impl<'b> ConvertFromObject<'b> for ColorArgs {
+    /// Takes all operands, at least one.
     fn convert_from_object(objects: &'b mut Vec<Object>) -> Result<Self, ObjectValueError> {
-        let mut result = Vec::with_capacity(objects.len());
-        while let Some(o) = objects.pop() {
-            if let Ok(num) = o.as_number() {
-                result.push(num);
-            } else {
-                todo!("color args: {:?}", o);
-            }
+        if objects.is_empty() {
+            return Err(ObjectValueError::GraphicsOperationSchemaError);
         }
-        result.reverse();
+        let result = objects
+            .drain(..)
+            .map(|o| o.as_number())
+            .collect::<Result<_, _>>()?;
         Ok(Self(result))
     }
 }
@@ -668,27 +710,27 @@ pub fn parse_operations(mut input: &[u8]) -> ParseResult<'_, Vec<Operation>> {
                         operands.push(o);
                     }
                     ObjectOrOperator::Operator(op) => {
-                        let opt_op = create_operation(op, &mut operands).unwrap_or_else(|e| {
-                            // possible because not enough operands
-                            warn!("Invalid operation '{}': {:?}", op, e);
-                            None
-                        });
-                        match opt_op {
-                            Some(Operation::BeginCompatibilitySection) => {}
-                            Some(Operation::EndCompatibilitySection) => {}
-                            Some(Operation::BeginInlineImage) => {
+                        match create_operation(op, &mut operands) {
+                            Ok(Some(Operation::BeginCompatibilitySection)) => {}
+                            Ok(Some(Operation::EndCompatibilitySection)) => {}
+                            Ok(Some(Operation::BeginInlineImage)) => {
                                 let inline_image;
                                 (input, inline_image) = parse_inline_image
                                     .map(Operation::PaintInlineImage)
                                     .parse(input)?;
                                 r.push(inline_image);
                             }
-                            Some(op) => r.push(op),
-                            None => {
+                            Ok(Some(op)) => r.push(op),
+                            Ok(None) => {
                                 warn!("Unknown operation: {:?}", op);
                             }
+                            // not enough operands, or operand of wrong type
+                            Err(e) => {
+                                warn!("Skip operation '{}' of invalid operands: {:?}", op, e);
+                            }
                         }
-                        // Some pdf files has bug that has extra operands
+                        // operands not consumed are extra ones before the
+                        // operands of the operator, never belong to next one
                         operands.clear();
                     }
                 }
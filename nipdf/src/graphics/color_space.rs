@@ -1,16 +1,20 @@
 use super::ColorSpaceArgs;
 use crate::{
     file::{ObjectResolver, ResourceDict},
-    function::{Domain, Domains, Function, FunctionDict, NFunc},
+    function::{Domain, Domains, Function, FunctionDict, FunctionValue, NFunc, Signature},
     graphics::ICCStreamDict,
-    object::Object,
+    object::{Object, ObjectValueError},
 };
 use anyhow::{Result as AnyResult, anyhow, bail};
 use educe::Educe;
 use nipdf_macro::pdf_object;
 use num_traits::ToPrimitive;
 use prescript::sname;
-use std::{fmt::Debug, iter::repeat, rc::Rc};
+use std::{
+    fmt::Debug,
+    iter::{once, repeat},
+    rc::Rc,
+};
 use tinyvec::TinyVec;
 
 /// Color component composes a color.
@@ -130,7 +134,8 @@ where
     u8: ColorCompConvertTo<T>,
 {
     /// Create from color space args.
-    /// Panic if need resolve resource but resources is None.
+    /// Color space name not device color space resolved from `resources`,
+    /// returns error if `resources` is None.
     pub fn from_args<'a>(
         args: &ColorSpaceArgs,
         resolver: &ObjectResolver<'a>,
@@ -148,11 +153,14 @@ where
                 "DeviceCMYK" => Ok(Self::DeviceCMYK),
                 "Pattern" => Ok(Self::Pattern(Box::new(PatternColorSpace(None)))),
                 _ => {
-                    let color_spaces = resources.unwrap().color_space()?;
-                    let args = color_spaces
-                        .get(name)
-                        .ok_or_else(|| anyhow!("ColorSpace::from_args() color space not found"))?;
-                    Self::from_args(args, resolver, resources)
+                    let resources = resources.ok_or_else(|| {
+                        anyhow!("ColorSpace::from_args() resources required to resolve {name}")
+                    })?;
+                    let color_spaces = resources.color_space()?;
+                    let args = color_spaces.get(name).ok_or_else(|| {
+                        anyhow!("ColorSpace::from_args() color space {name} not found")
+                    })?;
+                    Self::from_args(args, resolver, Some(resources))
                 }
             },
             ColorSpaceArgs::Array(arr) => match arr[0].name()?.as_str() {
@@ -172,6 +180,17 @@ where
                 }
                 "Separation" => {
                     assert_eq!(4, arr.len());
+                    // colorant `All` paints on all separations, alternate color
+                    // space and tint transform are ignored
+                    if arr[1].name()? == sname("All") {
+                        return Ok(Self::Separation(Box::new(SeparationColorSpace {
+                            alt: Self::DeviceGray,
+                            f: Rc::new(AllColorantsTint(Signature::new(
+                                Domains(vec![Domain::new(0.0, 1.0)]),
+                                None,
+                            ))),
+                        })));
+                    }
                     let alternate = ColorSpaceArgs::try_from(&arr[2])?;
                     let functions: Vec<FunctionDict> =
                         resolver.resolve_one_or_more_pdf_object(&arr[3])?;
@@ -265,7 +284,7 @@ where
                         black_point,
                     }))
                 }
-                s => todo!("ColorSpace::from_args() {} color space", s),
+                s => Err(ObjectValueError::Unsupported(vec![format!("{} color space", s)]).into()),
             },
         }
     }
@@ -581,6 +600,20 @@ where
     }
 }
 
+/// Tint transform of Separation colorant `All` to DeviceGray, tint 1.0 is
+/// black.
+struct AllColorantsTint(Signature);
+
+impl Function for AllColorantsTint {
+    fn signature(&self) -> &Signature {
+        &self.0
+    }
+
+    fn inner_call(&self, args: FunctionValue) -> AnyResult<FunctionValue> {
+        Ok(once(1.0 - args[0]).collect())
+    }
+}
+
 #[derive(Clone, Educe)]
 #[educe(Debug)]
 pub struct DeviceNColorSpace<T: Debug + PartialEq> {
@@ -727,6 +760,13 @@ pub struct LabColorSpace {
     black_point: [f32; 3],
 }
 
+impl LabColorSpace {
+    /// Ranges of L, a and b components, L is 0 to 100, a and b from `/Range`.
+    pub fn ranges(&self) -> [Domain; 3] {
+        self.ranges
+    }
+}
+
 pub trait LabColorInput {
     fn map_input(self, range: Domain) -> f32;
 }
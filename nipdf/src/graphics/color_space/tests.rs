@@ -183,6 +183,24 @@ endobj
     Ok(())
 }
 
+#[test]
+fn separation_all() -> AnyResult<()> {
+    // tint transform maps to cyan, ignored for colorant All
+    let buf = br#"
+1 0 obj
+[/Separation /All /DeviceCMYK 2 0 R]
+endobj
+2 0 obj
+<</FunctionType 2/Domain [0 1]/C0 [0 0 0 0]/C1 [1 0 0 0]/N 1>>
+endobj
+"#;
+    let color_space = parse_color_space(buf)?;
+    assert_eq!([0.0, 0.0, 0.0, 1.0], color_space.to_rgba(&[1.0]));
+    assert_eq!([1.0, 1.0, 1.0, 1.0], color_space.to_rgba(&[0.0]));
+    assert_eq!([0.75, 0.75, 0.75, 1.0], color_space.to_rgba(&[0.25]));
+    Ok(())
+}
+
 #[test_case(b"1 0 obj
 [/Indexed /DeviceRGB 1 2 0 R]
 endobj
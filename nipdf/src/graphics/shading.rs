@@ -1,11 +1,14 @@
 use super::Point;
 use crate::{
     file::Rectangle,
-    function::{Domain, FunctionDict, default_domain},
+    function::{Domain, Domains, FunctionDict, default_domain},
     graphics::{ColorArgs, ColorSpaceArgs},
     object::{Object, ObjectValueError},
 };
+use anyhow::{Result as AnyResult, bail};
+use bitstream_io::{BigEndian, BitRead, BitReader};
 use nipdf_macro::{TryFromIntObject, pdf_object};
+use std::io;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, TryFromIntObject)]
 pub enum ShadingType {
@@ -143,6 +146,304 @@ pub trait RadialShadingDictTrait {
     fn extend(&self) -> Extend;
 }
 
+/// Shading dictionary of type 4 to 7, vertex and patch data stored in
+/// shading stream.
+#[pdf_object(())]
+pub trait MeshShadingDictTrait {
+    fn bits_per_coordinate(&self) -> u32;
+    fn bits_per_component(&self) -> u32;
+    /// Required by all mesh types except lattice-form (type 5).
+    fn bits_per_flag(&self) -> Option<u32>;
+    /// Required by lattice-form (type 5).
+    fn vertices_per_row(&self) -> Option<u32>;
+
+    #[try_from]
+    fn decode(&self) -> Domains;
+
+    #[one_or_more]
+    #[nested]
+    fn function(&self) -> Vec<FunctionDict<'a, 'b>>;
+}
+
+/// Vertex of mesh shadings. `color` is color components, or a single
+/// parametric value `t` if shading has `/Function`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshVertex {
+    pub point: Point,
+    pub color: Vec<f32>,
+}
+
+/// Gouraud-shaded triangle, decoded from type 4 to 7 shadings.
+pub type MeshTriangle = [MeshVertex; 3];
+
+/// Number of rows and columns of the grid that a patch is divided into.
+const PATCH_DIVISIONS: usize = 16;
+
+/// Position in 4x4 control point grid of patch points in stream order,
+/// the first 12 points are the boundary, the rest are tensor-product
+/// interior points.
+const PATCH_POINT_ORDER: [(usize, usize); 16] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (1, 3),
+    (2, 3),
+    (3, 3),
+    (3, 2),
+    (3, 1),
+    (3, 0),
+    (2, 0),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (2, 2),
+    (2, 1),
+];
+
+/// Reads bit-packed values of mesh shading stream, mapped by `/Decode`.
+struct MeshReader<'c> {
+    r: BitReader<&'c [u8], BigEndian>,
+    bits_per_coordinate: u32,
+    bits_per_component: u32,
+    bits_per_flag: u32,
+    decode: Vec<Domain>,
+}
+
+/// Read value of `bits` width, and map it into range `d`.
+fn read_value(r: &mut BitReader<&[u8], BigEndian>, bits: u32, d: Domain) -> io::Result<f32> {
+    let v: u32 = r.read(bits)?;
+    let max = ((1u64 << bits) - 1) as f32;
+    Ok((v as f32).mul_add((d.end - d.start) / max, d.start))
+}
+
+impl MeshReader<'_> {
+    fn read_flag(&mut self) -> io::Result<u32> {
+        self.r.read(self.bits_per_flag)
+    }
+
+    fn read_point(&mut self) -> io::Result<Point> {
+        let x = read_value(&mut self.r, self.bits_per_coordinate, self.decode[0])?;
+        let y = read_value(&mut self.r, self.bits_per_coordinate, self.decode[1])?;
+        Ok(Point::new(x, y))
+    }
+
+    fn read_color(&mut self) -> io::Result<Vec<f32>> {
+        self.decode[2..]
+            .iter()
+            .map(|d| read_value(&mut self.r, self.bits_per_component, *d))
+            .collect()
+    }
+
+    fn read_vertex(&mut self) -> io::Result<MeshVertex> {
+        let point = self.read_point()?;
+        let color = self.read_color()?;
+        Ok(MeshVertex { point, color })
+    }
+}
+
+impl<'a, 'b> MeshShadingDict<'a, 'b> {
+    /// Decode shading stream to triangles, patches of type 6 and 7 are divided
+    /// into triangles. Incomplete data at stream end is ignored.
+    pub fn triangles(&self, shading_type: ShadingType) -> AnyResult<Vec<MeshTriangle>> {
+        let decode = self.decode()?.0;
+        if decode.len() < 3 {
+            bail!("mesh shading /Decode should have at least 3 ranges");
+        }
+        let Some(id) = self.id else {
+            bail!("mesh shading should be a stream");
+        };
+        let resolver = self.d.resolver();
+        let stream = resolver.resolve(id)?.stream()?;
+        let data = stream.decode(resolver)?;
+        let mut r = MeshReader {
+            r: BitReader::new(&data[..]),
+            bits_per_coordinate: self.bits_per_coordinate()?,
+            bits_per_component: self.bits_per_component()?,
+            bits_per_flag: 0,
+            decode,
+        };
+
+        Ok(match shading_type {
+            ShadingType::LatticeForm => {
+                let Some(per_row) = self.vertices_per_row()?.filter(|n| *n >= 2) else {
+                    bail!("lattice-form shading /VerticesPerRow should be at least 2");
+                };
+                lattice_form_triangles(&mut r, per_row as usize)
+            }
+            t => {
+                let Some(bits_per_flag) = self.bits_per_flag()? else {
+                    bail!("mesh shading /BitsPerFlag required");
+                };
+                r.bits_per_flag = bits_per_flag;
+                match t {
+                    ShadingType::FreeForm => free_form_triangles(&mut r),
+                    ShadingType::CoonsPatchMesh => patch_triangles(&mut r, 12),
+                    ShadingType::TensorProductPatchMesh => patch_triangles(&mut r, 16),
+                    t => bail!("not mesh shading type: {:?}", t),
+                }
+            }
+        })
+    }
+}
+
+/// Type 4, flag 0 starts a new triangle, flag 1 and 2 form a triangle with
+/// the last edge or the first and last vertex of previous triangle.
+fn free_form_triangles(r: &mut MeshReader) -> Vec<MeshTriangle> {
+    let mut triangles: Vec<MeshTriangle> = vec![];
+    let mut pending = Vec::with_capacity(3);
+    while let (Ok(flag), Ok(v)) = (r.read_flag(), r.read_vertex()) {
+        r.r.byte_align();
+        if flag == 0 || !pending.is_empty() {
+            pending.push(v);
+            if pending.len() == 3 {
+                let [a, b, c] = std::mem::take(&mut pending).try_into().unwrap();
+                triangles.push([a, b, c]);
+            }
+            continue;
+        }
+
+        let Some([va, vb, vc]) = triangles.last().cloned() else {
+            continue;
+        };
+        match flag {
+            1 => triangles.push([vb, vc, v]),
+            2 => triangles.push([va, vc, v]),
+            _ => (),
+        }
+    }
+    triangles
+}
+
+/// Type 5, each two vertices of adjacent rows form two triangles.
+fn lattice_form_triangles(r: &mut MeshReader, per_row: usize) -> Vec<MeshTriangle> {
+    let mut vertices = vec![];
+    while let Ok(v) = r.read_vertex() {
+        vertices.push(v);
+    }
+
+    let rows: Vec<_> = vertices.chunks_exact(per_row).collect();
+    let mut triangles = vec![];
+    for pair in rows.windows(2) {
+        for (top, bottom) in pair[0].windows(2).zip(pair[1].windows(2)) {
+            triangles.push([top[0].clone(), top[1].clone(), bottom[0].clone()]);
+            triangles.push([top[1].clone(), bottom[0].clone(), bottom[1].clone()]);
+        }
+    }
+    triangles
+}
+
+/// Patch control points in stream order, and its four corner colors.
+type Patch = (Vec<Point>, Vec<Vec<f32>>);
+
+/// Type 6 and 7, `n_points` is 12 for Coons patch, 16 for tensor-product
+/// patch. Flag 1 to 3 shares an edge and its two corner colors with previous
+/// patch.
+fn patch_triangles(r: &mut MeshReader, n_points: usize) -> Vec<MeshTriangle> {
+    fn read_patch(
+        r: &mut MeshReader,
+        n_points: usize,
+        prev: Option<&Patch>,
+    ) -> io::Result<Option<Patch>> {
+        let flag = r.read_flag()? as usize;
+        let mut points = Vec::with_capacity(n_points);
+        let mut colors = Vec::with_capacity(4);
+        if flag != 0 {
+            let Some((prev_points, prev_colors)) = prev.filter(|_| flag <= 3) else {
+                return Ok(None);
+            };
+            points.extend((0..4).map(|i| prev_points[(flag * 3 + i) % 12]));
+            colors.push(prev_colors[flag].clone());
+            colors.push(prev_colors[(flag + 1) % 4].clone());
+        }
+        while points.len() < n_points {
+            points.push(r.read_point()?);
+        }
+        while colors.len() < 4 {
+            colors.push(r.read_color()?);
+        }
+        r.r.byte_align();
+        Ok(Some((points, colors)))
+    }
+
+    let mut triangles = vec![];
+    let mut prev = None;
+    loop {
+        match read_patch(r, n_points, prev.as_ref()) {
+            Ok(Some(patch)) => {
+                divide_patch(&patch.0, &patch.1, &mut triangles);
+                prev = Some(patch);
+            }
+            // skip patch if flag invalid, its data already read
+            Ok(None) => prev = None,
+            Err(_) => break,
+        }
+    }
+    triangles
+}
+
+/// Divide patch into `PATCH_DIVISIONS` x `PATCH_DIVISIONS` grid of triangles,
+/// vertex color bilinear interpolated from corner colors.
+fn divide_patch(points: &[Point], colors: &[Vec<f32>], triangles: &mut Vec<MeshTriangle>) {
+    let mut p = [[Point::zero(); 4]; 4];
+    for (pt, (i, j)) in points.iter().zip(PATCH_POINT_ORDER) {
+        p[i][j] = *pt;
+    }
+    if points.len() == 12 {
+        // Coons patch, interior control points of the equivalent tensor-product patch
+        let inner = |k: usize| if k == 0 { 1 } else { 2 };
+        for (i, j) in [(0, 0), (0, 3), (3, 3), (3, 0)] {
+            let v = |i: usize, j: usize| p[i][j].to_vector();
+            let (fi, fj, ii, ij) = (3 - i, 3 - j, inner(i), inner(j));
+            let pt = (v(i, j) * -4.0 + (v(i, ij) + v(ii, j)) * 6.0 - (v(i, fj) + v(fi, j)) * 2.0
+                + (v(ii, fj) + v(fi, ij)) * 3.0
+                - v(fi, fj))
+                / 9.0;
+            p[ii][ij] = pt.to_point();
+        }
+    }
+
+    let bernstein = |t: f32| {
+        let s = 1.0 - t;
+        [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+    };
+    let vertex = |u: f32, v: f32| {
+        let (bu, bv) = (bernstein(u), bernstein(v));
+        let mut point = Point::zero();
+        for (row, bu) in p.iter().zip(bu) {
+            for (pt, bv) in row.iter().zip(bv) {
+                point += pt.to_vector() * (bu * bv);
+            }
+        }
+        // corner colors are c00, c03, c33, c30
+        let color = (0..colors[0].len())
+            .map(|k| {
+                let c0 = (colors[3][k] - colors[0][k]).mul_add(u, colors[0][k]);
+                let c1 = (colors[2][k] - colors[1][k]).mul_add(u, colors[1][k]);
+                (c1 - c0).mul_add(v, c0)
+            })
+            .collect();
+        MeshVertex { point, color }
+    };
+
+    let n = PATCH_DIVISIONS;
+    let grid: Vec<Vec<MeshVertex>> = (0..=n)
+        .map(|a| {
+            (0..=n)
+                .map(|b| vertex(a as f32 / n as f32, b as f32 / n as f32))
+                .collect()
+        })
+        .collect();
+    for a in 0..n {
+        for b in 0..n {
+            let (v00, v01) = (&grid[a][b], &grid[a][b + 1]);
+            let (v10, v11) = (&grid[a + 1][b], &grid[a + 1][b + 1]);
+            triangles.push([v00.clone(), v01.clone(), v10.clone()]);
+            triangles.push([v01.clone(), v10.clone(), v11.clone()]);
+        }
+    }
+}
+
 #[pdf_object(())]
 pub trait ShadingDictTrait {
     #[try_from]
@@ -165,6 +466,9 @@ pub trait ShadingDictTrait {
 
     #[self_as]
     fn radial(&self) -> RadialShadingDict<'a, 'b>;
+
+    #[self_as]
+    fn mesh(&self) -> MeshShadingDict<'a, 'b>;
 }
 
 #[cfg(test)]
@@ -33,3 +33,130 @@ fn axias_coords_try_from() {
         end: Point::new(3., 4.),
     });
 }
+
+fn mesh_vertex(x: f32, y: f32, c: f32) -> MeshVertex {
+    MeshVertex {
+        point: Point::new(x, y),
+        color: vec![c],
+    }
+}
+
+fn mesh_triangles(buf: &[u8]) -> AnyResult<Vec<MeshTriangle>> {
+    use crate::file::{ObjectResolver, XRefTable};
+
+    let xref = XRefTable::from_buf(buf);
+    let resolver = ObjectResolver::new(buf, &xref, None);
+    let d: ShadingDict = resolver.resolve_pdf_object(1)?;
+    d.mesh()?.triangles(d.shading_type()?)
+}
+
+#[test]
+fn free_form_mesh() -> AnyResult<()> {
+    // flag 0 triangle, flag 1 and flag 2 vertex, incomplete vertex at end
+    let triangles = mesh_triangles(
+        b"1 0 obj<</ShadingType 4/ColorSpace/DeviceGray/BitsPerCoordinate 8/BitsPerComponent 8\
+        /BitsPerFlag 8/Decode[0 255 0 255 0 255]/Length 22>>stream\n\
+        \x00\x00\x00\x00\x00\xff\x00\x80\x00\x00\xff\xff\x01\xff\xff\x40\x02\x80\x80\x00\x00\x01\n\
+        endstream\nendobj",
+    )?;
+    let (a, b, c) = (
+        mesh_vertex(0., 0., 0.),
+        mesh_vertex(255., 0., 128.),
+        mesh_vertex(0., 255., 255.),
+    );
+    let (d, e) = (mesh_vertex(255., 255., 64.), mesh_vertex(128., 128., 0.));
+    assert_eq!(triangles, vec![
+        [a, b.clone(), c.clone()],
+        [b.clone(), c, d.clone()],
+        [b, d, e],
+    ]);
+    Ok(())
+}
+
+#[test]
+fn lattice_form_mesh() -> AnyResult<()> {
+    let triangles = mesh_triangles(
+        b"1 0 obj<</ShadingType 5/ColorSpace/DeviceGray/BitsPerCoordinate 8/BitsPerComponent 8\
+        /VerticesPerRow 2/Decode[0 255 0 255 0 1]/Length 12>>stream\n\
+        \x00\x00\x00\xff\x00\xff\x00\xff\x00\xff\xff\xff\n\
+        endstream\nendobj",
+    )?;
+    let (a, b) = (mesh_vertex(0., 0., 0.), mesh_vertex(255., 0., 1.));
+    let (c, d) = (mesh_vertex(0., 255., 0.), mesh_vertex(255., 255., 1.));
+    assert_eq!(triangles, vec![[a, b.clone(), c.clone()], [b, c, d]]);
+    Ok(())
+}
+
+/// Shading stream object 1 of `dict` entries, `data` is its stream content.
+fn mesh_stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut buf = format!("1 0 obj<<{dict}/Length {}>>stream\n", data.len()).into_bytes();
+    buf.extend_from_slice(data);
+    buf.extend_from_slice(b"\nendstream\nendobj");
+    buf
+}
+
+const GRID: [u8; 4] = [0, 30, 60, 90];
+
+/// Patch control points of `order` on grid `(xs[i], ys[j])`.
+fn patch_points(order: &[(usize, usize)], xs: [u8; 4], ys: [u8; 4]) -> Vec<u8> {
+    order.iter().flat_map(|&(i, j)| [xs[i], ys[j]]).collect()
+}
+
+#[test]
+fn coons_patch_mesh() -> AnyResult<()> {
+    // flag 0 patch, flag 2 patch shares edge p33 to p30 and colors c2, c3,
+    // incomplete patch at end
+    let mut data = vec![0];
+    data.extend(patch_points(&PATCH_POINT_ORDER[..12], GRID, GRID));
+    data.extend([0, 60, 120, 180, 2]);
+    data.extend(patch_points(
+        &PATCH_POINT_ORDER[4..12],
+        [90, 120, 150, 180],
+        [90, 60, 30, 0],
+    ));
+    data.extend([240, 255, 0, 1, 2]);
+    let triangles = mesh_triangles(&mesh_stream(
+        "/ShadingType 6/ColorSpace/DeviceGray/BitsPerCoordinate 8/BitsPerComponent 8\
+        /BitsPerFlag 8/Decode[0 255 0 255 0 255]",
+        &data,
+    ))?;
+
+    let n = PATCH_DIVISIONS;
+    let per_patch = n * n * 2;
+    assert_eq!(triangles.len(), per_patch * 2);
+    // corners of first patch
+    assert_eq!(triangles[0][0], mesh_vertex(0., 0., 0.));
+    assert_eq!(triangles[(n - 1) * 2 + 1][0], mesh_vertex(0., 90., 60.));
+    assert_eq!(triangles[per_patch - 1][2], mesh_vertex(90., 90., 120.));
+    // corners of second patch, the first two from the shared edge
+    let second = &triangles[per_patch..];
+    assert_eq!(second[0][0], mesh_vertex(90., 90., 120.));
+    assert_eq!(second[(n - 1) * 2 + 1][0], mesh_vertex(90., 0., 180.));
+    assert_eq!(second[per_patch - 1][2], mesh_vertex(180., 0., 240.));
+    assert_eq!(second[(n - 1) * n * 2][2], mesh_vertex(180., 90., 255.));
+    Ok(())
+}
+
+#[test]
+fn tensor_product_patch_mesh() -> AnyResult<()> {
+    // flag 0 patch, flag 5 is invalid and skipped
+    let mut data = vec![0];
+    data.extend(patch_points(&PATCH_POINT_ORDER, GRID, GRID));
+    data.extend([0, 60, 120, 180, 5]);
+    let triangles = mesh_triangles(&mesh_stream(
+        "/ShadingType 7/ColorSpace/DeviceGray/BitsPerCoordinate 8/BitsPerComponent 8\
+        /BitsPerFlag 8/Decode[0 255 0 255 0 255]",
+        &data,
+    ))?;
+
+    let n = PATCH_DIVISIONS;
+    assert_eq!(triangles.len(), n * n * 2);
+    assert_eq!(triangles[0][0], mesh_vertex(0., 0., 0.));
+    assert_eq!(triangles[n * n * 2 - 1][2], mesh_vertex(90., 90., 120.));
+    // center of patch, color bilinear interpolated from corners
+    assert_eq!(
+        triangles[(n / 2 * n + n / 2) * 2][0],
+        mesh_vertex(45., 45., 90.)
+    );
+    Ok(())
+}
@@ -66,6 +66,25 @@ fn test_parse_operation(s: &str) -> Operation {
     result.pop().unwrap()
 }
 
+#[test_case("1 0 0 1 0 cm 0 0 m" => vec![
+        Operation::MoveToNext(Point::new(0f32, 0f32)),
+    ];
+    "missing operand of cm"
+)]
+#[test_case("9 1 0 0 1 0 0 cm" => vec![
+        Operation::ModifyCTM(UserToUserSpace::new(1f32, 0f32, 0f32, 1f32, 0f32, 0f32)),
+    ];
+    "extra operand of cm"
+)]
+#[test_case("cm 1 w" => vec![Operation::SetLineWidth(1f32)]; "cm without operands")]
+#[test_case("/P sc sc (a) SC 1 g" => vec![Operation::SetFillGray([1f32])]; "invalid color operands")]
+#[test_case("J j Tr ri 1 w" => vec![Operation::SetLineWidth(1f32)]; "enum operators without operands")]
+fn skip_operation_of_invalid_operands(s: &str) -> Vec<Operation> {
+    let (buf, result) = parse_operations(s.as_bytes()).unwrap();
+    assert!(buf.is_empty());
+    result
+}
+
 #[test]
 fn test_ignore_bx_ex() {
     let (buf, result) = parse_operations(b"BX\nq\nEX\nQ").unwrap();
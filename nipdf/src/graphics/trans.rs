@@ -1,7 +1,7 @@
 //! Lib to translate coordinates. Including CTM,
 //! line matrix, pattern etc, and User space to screen space.
 
-use euclid::{Length, Point2D, Transform2D};
+use euclid::{Length, Point2D, Scale, Transform2D};
 use num_traits::AsPrimitive;
 
 pub enum UserSpace {}
@@ -19,6 +19,7 @@ pub type GlyphLength = Length<f32, GlyphSpace>;
 pub type TextPoint = Point2D<f32, TextSpace>;
 pub type GlyphToTextSpace = Transform2D<f32, GlyphSpace, TextSpace>;
 pub type GlyphToUserSpace = Transform2D<f32, GlyphSpace, UserSpace>;
+pub type GlyphToDeviceSpace = Transform2D<f32, GlyphSpace, DeviceSpace>;
 pub type UserToUserSpace = Transform2D<f32, UserSpace, UserSpace>;
 pub type UserToLogicDeviceSpace = Transform2D<f32, UserSpace, LogicDeviceSpace>;
 pub type UserToDeviceSpace = Transform2D<f32, UserSpace, DeviceSpace>;
@@ -54,6 +55,38 @@ pub fn move_text_space_right(
     move_text_space_pos(transform, TextPoint::new(x_text_space.0, 0.0))
 }
 
+/// Horizontal advance in text space after painting a glyph, `tx` of PDF spec 9.4.4:
+/// `(w0 * Tfs + Tc + Tw) * Th`, `Tw` only applies if `is_space`.
+///
+/// `char_width` in glyph space, scaled to text space by `em_ratio` (1 / units_per_em).
+pub fn glyph_advance(
+    char_width: GlyphLength,
+    em_ratio: Scale<f32, GlyphSpace, TextSpace>,
+    font_size: f32,
+    char_spacing: Length<f32, TextSpace>,
+    word_spacing: Length<f32, TextSpace>,
+    is_space: bool,
+    horiz_scaling: f32,
+) -> Length<f32, TextSpace> {
+    let mut w = char_width * em_ratio * font_size + char_spacing;
+    if is_space {
+        w += word_spacing;
+    }
+    w * horiz_scaling
+}
+
+/// Width of glyph in text space: `w0 * Tfs * Th`. Unlike [`glyph_advance`], char and word
+/// spacing not included, they are space after the glyph, not part of it. Use it to compute
+/// tight glyph bounding box, such as for text extraction, even if char spacing is negative.
+pub fn glyph_width(
+    char_width: GlyphLength,
+    em_ratio: Scale<f32, GlyphSpace, TextSpace>,
+    font_size: f32,
+    horiz_scaling: f32,
+) -> Length<f32, TextSpace> {
+    char_width * em_ratio * font_size * horiz_scaling
+}
+
 /// Adjust transform to moves position in text space.
 pub fn move_text_space_pos(transform: &TextToUserSpace, p: TextPoint) -> TextToUserSpace {
     transform.pre_translate(p.to_vector())
@@ -130,3 +130,43 @@ fn test_move_text_space_right() {
     ));
     f((0., 0.), (0. * 3., 10. * 2.));
 }
+
+#[test]
+fn test_glyph_advance() {
+    let em_ratio = Scale::new(1.0 / 1000.0);
+    let adv = |is_space, horiz_scaling| {
+        glyph_advance(
+            Length::new(500.0),
+            em_ratio,
+            12.0,
+            Length::new(1.0),
+            Length::new(2.0),
+            is_space,
+            horiz_scaling,
+        )
+        .0
+    };
+    assert_eq!(7.0, adv(false, 1.0));
+    assert_eq!(9.0, adv(true, 1.0));
+    assert_eq!(4.5, adv(true, 0.5));
+}
+
+#[test]
+fn test_glyph_width() {
+    let em_ratio = Scale::new(1.0 / 1000.0);
+    let char_width = Length::new(500.0);
+    // negative char spacing for tight tracking, the glyph itself not shrinks
+    let char_spacing = Length::new(-1.0);
+    let advance = glyph_advance(
+        char_width,
+        em_ratio,
+        12.0,
+        char_spacing,
+        Length::new(0.0),
+        false,
+        1.0,
+    );
+    assert_eq!(5.0, advance.0);
+    assert_eq!(6.0, glyph_width(char_width, em_ratio, 12.0, 1.0).0);
+    assert_eq!(3.0, glyph_width(char_width, em_ratio, 12.0, 0.5).0);
+}
@@ -0,0 +1,935 @@
+//! Decoder of `JBIG2Decode` filter, stream data is JBIG2 embedded stream
+//! (ISO/IEC 14492 Annex D.3), no file header, segments of `/JBIG2Globals`
+//! are decoded before the page ones.
+//!
+//! Supports generic region (arithmetic and MMR coded), and arithmetic coded
+//! symbol dictionary and text region without refinement. Other segments,
+//! such as halftone, refinement and Huffman coded symbols, are skipped with
+//! a warning.
+use crate::ccitt::{Algorithm, Decoder as CCITTDecoder, Flags};
+use ahash::HashMap;
+use image::{GrayImage, Luma};
+use log::warn;
+use std::rc::Rc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("Unexpected end of data")]
+    UnexpectedEof,
+    #[error("Page information segment missing")]
+    NoPageInfo,
+    #[error("Invalid segment {0}: {1}")]
+    InvalidSegment(u32, &'static str),
+    #[error("Bitmap too large: {0}x{1}")]
+    BitmapTooLarge(u32, u32),
+    #[error("MMR decode error: {0}")]
+    Mmr(#[from] crate::ccitt::DecodeError),
+}
+
+type Result<T> = std::result::Result<T, DecodeError>;
+
+/// Qe value, next index of MPS and LPS, and switch flag of Table E.1.
+#[rustfmt::skip]
+const QE_TABLE: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true), (0x3401, 2, 6, false), (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false), (0x0521, 5, 29, false), (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true), (0x5401, 8, 14, false), (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false), (0x3001, 11, 17, false), (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false), (0x1601, 29, 21, false), (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false), (0x5101, 17, 15, false), (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false), (0x3401, 20, 18, false), (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false), (0x2401, 23, 20, false), (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false), (0x1801, 26, 23, false), (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false), (0x1201, 29, 26, false), (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false), (0x09C1, 32, 29, false), (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false), (0x0441, 35, 32, false), (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false), (0x0141, 38, 35, false), (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false), (0x0049, 41, 38, false), (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false), (0x0009, 44, 41, false), (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false), (0x5601, 46, 46, false),
+];
+
+/// MQ arithmetic decoder of Annex E.3, a context is stored as a byte of
+/// `index << 1 | mps`.
+struct ArithDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    c_high: u32,
+    c_low: u32,
+    a: u32,
+    ct: u32,
+}
+
+impl<'a> ArithDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let mut r = Self {
+            data,
+            pos: 0,
+            c_high: u32::from(data.first().copied().unwrap_or(0xff)),
+            c_low: 0,
+            a: 0,
+            ct: 0,
+        };
+        r.byte_in();
+        r.c_high = ((r.c_high << 7) & 0xffff) | ((r.c_low >> 9) & 0x7f);
+        r.c_low = (r.c_low << 7) & 0xffff;
+        r.ct -= 7;
+        r.a = 0x8000;
+        r
+    }
+
+    /// Read past the end of data as 0xff.
+    fn byte_at(&self, pos: usize) -> u32 {
+        u32::from(self.data.get(pos).copied().unwrap_or(0xff))
+    }
+
+    fn byte_in(&mut self) {
+        if self.byte_at(self.pos) == 0xff {
+            if self.byte_at(self.pos + 1) > 0x8f {
+                self.c_low += 0xff00;
+                self.ct = 8;
+            } else {
+                self.pos += 1;
+                self.c_low += self.byte_at(self.pos) << 9;
+                self.ct = 7;
+            }
+        } else {
+            self.pos += 1;
+            self.c_low += self.byte_at(self.pos) << 8;
+            self.ct = 8;
+        }
+        if self.c_low > 0xffff {
+            self.c_high += self.c_low >> 16;
+            self.c_low &= 0xffff;
+        }
+    }
+
+    fn decode(&mut self, contexts: &mut [u8], cx: usize) -> u8 {
+        let mut index = contexts[cx] >> 1;
+        let mut mps = contexts[cx] & 1;
+        let (qe, nmps, nlps, switch) = QE_TABLE[usize::from(index)];
+        let mut a = self.a - qe;
+        let d;
+        if self.c_high < qe {
+            // LPS exchange
+            if a < qe {
+                d = mps;
+                index = nmps;
+            } else {
+                d = 1 ^ mps;
+                if switch {
+                    mps = d;
+                }
+                index = nlps;
+            }
+            a = qe;
+        } else {
+            self.c_high -= qe;
+            if a & 0x8000 != 0 {
+                self.a = a;
+                return mps;
+            }
+            // MPS exchange
+            if a < qe {
+                d = 1 ^ mps;
+                if switch {
+                    mps = d;
+                }
+                index = nlps;
+            } else {
+                d = mps;
+                index = nmps;
+            }
+        }
+
+        // renormalize
+        loop {
+            if self.ct == 0 {
+                self.byte_in();
+            }
+            a <<= 1;
+            self.c_high = ((self.c_high << 1) & 0xffff) | ((self.c_low >> 15) & 1);
+            self.c_low = (self.c_low << 1) & 0xffff;
+            self.ct -= 1;
+            if a & 0x8000 != 0 {
+                break;
+            }
+        }
+        self.a = a;
+        contexts[cx] = index << 1 | mps;
+        d
+    }
+}
+
+/// Contexts of an integer arithmetic decoding procedure of Annex A.2.
+struct IntDecoder([u8; 512]);
+
+impl IntDecoder {
+    fn new() -> Self {
+        Self([0; 512])
+    }
+
+    /// Returns None for out-of-band value.
+    fn decode(&mut self, decoder: &mut ArithDecoder<'_>) -> Option<i32> {
+        let mut prev = 1usize;
+        let mut read_bits = |n: u32| {
+            let mut v = 0u32;
+            for _ in 0..n {
+                let bit = decoder.decode(&mut self.0, prev);
+                prev = if prev < 256 {
+                    (prev << 1) | usize::from(bit)
+                } else {
+                    (((prev << 1) | usize::from(bit)) & 511) | 256
+                };
+                v = (v << 1) | u32::from(bit);
+            }
+            v
+        };
+
+        let sign = read_bits(1);
+        let v = if read_bits(1) == 0 {
+            read_bits(2)
+        } else if read_bits(1) == 0 {
+            read_bits(4) + 4
+        } else if read_bits(1) == 0 {
+            read_bits(6) + 20
+        } else if read_bits(1) == 0 {
+            read_bits(8) + 84
+        } else if read_bits(1) == 0 {
+            read_bits(12) + 340
+        } else {
+            read_bits(32).wrapping_add(4436)
+        } as i32;
+        match (sign, v) {
+            (0, v) => Some(v),
+            (_, 0) => None,
+            (_, v) => Some(-v),
+        }
+    }
+}
+
+/// Symbol ID decoding procedure of Annex A.3.
+struct IaidDecoder {
+    code_len: u32,
+    contexts: Vec<u8>,
+}
+
+impl IaidDecoder {
+    fn new(code_len: u32) -> Self {
+        Self {
+            code_len,
+            contexts: vec![0; 1 << (code_len + 1)],
+        }
+    }
+
+    fn decode(&mut self, decoder: &mut ArithDecoder<'_>) -> usize {
+        let mut prev = 1usize;
+        for _ in 0..self.code_len {
+            let bit = decoder.decode(&mut self.contexts, prev);
+            prev = (prev << 1) | usize::from(bit);
+        }
+        prev - (1 << self.code_len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CombinationOperator {
+    Or,
+    And,
+    Xor,
+    Xnor,
+    Replace,
+}
+
+impl CombinationOperator {
+    fn new(v: u8) -> Self {
+        match v {
+            0 => Self::Or,
+            1 => Self::And,
+            2 => Self::Xor,
+            3 => Self::Xnor,
+            _ => Self::Replace,
+        }
+    }
+
+    fn apply(self, dest: u8, src: u8) -> u8 {
+        match self {
+            Self::Or => dest | src,
+            Self::And => dest & src,
+            Self::Xor => dest ^ src,
+            Self::Xnor => !(dest ^ src) & 1,
+            Self::Replace => src,
+        }
+    }
+}
+
+/// Bitmap of one byte per pixel, 1 is black.
+#[derive(Debug, Clone)]
+struct Bitmap {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Max pixels of a bitmap, sizes read from segments are not trusted.
+const MAX_BITMAP_PIXELS: usize = 1024 * 1024 * 100;
+
+/// Pixels of bitmap of the size, error if exceeds `MAX_BITMAP_PIXELS`.
+fn bitmap_pixels(width: u32, height: u32) -> Result<usize> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .filter(|n| *n <= MAX_BITMAP_PIXELS)
+        .ok_or(DecodeError::BitmapTooLarge(width, height))
+}
+
+impl Bitmap {
+    fn new(width: u32, height: u32, pixel: u8) -> Result<Self> {
+        Ok(Self {
+            width,
+            height,
+            data: vec![pixel; bitmap_pixels(width, height)?],
+        })
+    }
+
+    /// Returns 0 if out of bitmap.
+    fn get(&self, x: i32, y: i32) -> u8 {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            0
+        } else {
+            self.data[y as usize * self.width as usize + x as usize]
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, v: u8) {
+        self.data[y as usize * self.width as usize + x as usize] = v;
+    }
+
+    fn row(&self, y: u32) -> &[u8] {
+        let w = self.width as usize;
+        &self.data[y as usize * w..(y as usize + 1) * w]
+    }
+
+    /// Combine `src` at (`x`, `y`) with operator `op`, parts out of bitmap
+    /// are ignored.
+    fn compose(&mut self, src: &Bitmap, x: i32, y: i32, op: CombinationOperator) {
+        for sy in 0..src.height {
+            let dy = y + sy as i32;
+            if dy < 0 || dy as u32 >= self.height {
+                continue;
+            }
+            for sx in 0..src.width {
+                let dx = x + sx as i32;
+                if dx < 0 || dx as u32 >= self.width {
+                    continue;
+                }
+                let v = op.apply(self.get(dx, dy), src.get(sx as i32, sy as i32));
+                self.set(dx as u32, dy as u32, v);
+            }
+        }
+    }
+
+    /// Grow height to `height`, new rows filled by `pixel`.
+    fn grow(&mut self, height: u32, pixel: u8) -> Result<()> {
+        if height > self.height {
+            self.data.resize(bitmap_pixels(self.width, height)?, pixel);
+            self.height = height;
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let r = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += n;
+        Ok(r)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+/// Segment data length of unknown, only allowed for immediate generic
+/// region.
+const UNKNOWN_LENGTH: u32 = 0xffff_ffff;
+
+#[derive(Debug)]
+struct SegmentHeader {
+    number: u32,
+    kind: u8,
+    referred: Vec<u32>,
+    data_len: u32,
+}
+
+impl SegmentHeader {
+    /// Parse segment header of 7.2.
+    fn parse(r: &mut Reader<'_>) -> Result<Self> {
+        let number = r.u32()?;
+        let flags = r.u8()?;
+        let kind = flags & 0x3f;
+        let large_page_association = flags & 0x40 != 0;
+
+        let b = r.u8()?;
+        let referred_count = if b >> 5 == 7 {
+            r.pos -= 1;
+            let count = r.u32()? & 0x1fff_ffff;
+            // retention flags
+            r.bytes((count as usize + 1).div_ceil(8))?;
+            count
+        } else {
+            u32::from(b >> 5)
+        };
+        // count not trusted, each referred number takes at least one byte
+        let remaining = r.data.len().saturating_sub(r.pos);
+        let mut referred = Vec::with_capacity((referred_count as usize).min(remaining));
+        for _ in 0..referred_count {
+            referred.push(match number {
+                0..=256 => r.u8()?.into(),
+                257..=65536 => r.u16()?.into(),
+                _ => r.u32()?,
+            });
+        }
+
+        if large_page_association {
+            r.u32()?;
+        } else {
+            r.u8()?;
+        }
+        let data_len = r.u32()?;
+        Ok(Self {
+            number,
+            kind,
+            referred,
+            data_len,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RegionInfo {
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    op: CombinationOperator,
+}
+
+impl RegionInfo {
+    /// Parse region segment information field of 7.4.1.
+    fn parse(r: &mut Reader<'_>) -> Result<Self> {
+        Ok(Self {
+            width: r.u32()?,
+            height: r.u32()?,
+            x: r.u32()?,
+            y: r.u32()?,
+            op: CombinationOperator::new(r.u8()? & 0x7),
+        })
+    }
+}
+
+/// Read adaptive template pixels, 4 pairs for template 0, 1 pair for others.
+fn read_at_pixels(r: &mut Reader<'_>, template: u8) -> Result<Vec<(i32, i32)>> {
+    let n = if template == 0 { 4 } else { 1 };
+    (0..n)
+        .map(|_| Ok((r.i8()?.into(), r.i8()?.into())))
+        .collect()
+}
+
+/// Context bits of generic region templates, from the least significant
+/// bit, `None` for adaptive template pixels in order.
+#[rustfmt::skip]
+const TEMPLATES: [&[Option<(i32, i32)>]; 4] = [
+    &[
+        Some((-1, 0)), Some((-2, 0)), Some((-3, 0)), Some((-4, 0)), None,
+        Some((2, -1)), Some((1, -1)), Some((0, -1)), Some((-1, -1)), Some((-2, -1)),
+        None, None, Some((1, -2)), Some((0, -2)), Some((-1, -2)), None,
+    ],
+    &[
+        Some((-1, 0)), Some((-2, 0)), Some((-3, 0)), None,
+        Some((2, -1)), Some((1, -1)), Some((0, -1)), Some((-1, -1)), Some((-2, -1)),
+        Some((2, -2)), Some((1, -2)), Some((0, -2)), Some((-1, -2)),
+    ],
+    &[
+        Some((-1, 0)), Some((-2, 0)), None,
+        Some((1, -1)), Some((0, -1)), Some((-1, -1)), Some((-2, -1)),
+        Some((1, -2)), Some((0, -2)), Some((-1, -2)),
+    ],
+    &[
+        Some((-1, 0)), Some((-2, 0)), Some((-3, 0)), Some((-4, 0)), None,
+        Some((1, -1)), Some((0, -1)), Some((-1, -1)), Some((-2, -1)), Some((-3, -1)),
+    ],
+];
+
+/// Context of typical prediction pseudo pixel SLTP, by template.
+const SLTP_CONTEXTS: [usize; 4] = [0x9b25, 0x0795, 0x00e5, 0x0195];
+
+/// Contexts of generic region decoding, large enough for all templates.
+fn new_generic_contexts() -> Vec<u8> {
+    vec![0; 1 << 16]
+}
+
+/// Generic region decoding procedure of 6.2.5, arithmetic coded.
+fn decode_generic(
+    decoder: &mut ArithDecoder<'_>,
+    contexts: &mut [u8],
+    width: u32,
+    height: u32,
+    template: u8,
+    at: &[(i32, i32)],
+    tpgdon: bool,
+) -> Result<Bitmap> {
+    let mut at = at.iter();
+    let offsets: Vec<(i32, i32)> = TEMPLATES[template as usize]
+        .iter()
+        .map(|p| p.unwrap_or_else(|| *at.next().unwrap()))
+        .collect();
+
+    let mut r = Bitmap::new(width, height, 0)?;
+    let mut ltp = 0;
+    for y in 0..height {
+        if tpgdon {
+            ltp ^= decoder.decode(contexts, SLTP_CONTEXTS[template as usize]);
+            if ltp == 1 {
+                if y > 0 {
+                    let w = width as usize;
+                    let start = y as usize * w;
+                    r.data.copy_within(start - w..start, start);
+                }
+                continue;
+            }
+        }
+
+        for x in 0..width {
+            let (x, y) = (x as i32, y as i32);
+            let cx = offsets.iter().enumerate().fold(0, |cx, (i, (dx, dy))| {
+                cx | usize::from(r.get(x + dx, y + dy)) << i
+            });
+            let v = decoder.decode(contexts, cx);
+            r.set(x as u32, y as u32, v);
+        }
+    }
+    Ok(r)
+}
+
+/// MMR coded generic region of segment `number`, it is CCITT Group 4.
+fn decode_mmr(number: u32, data: &[u8], width: u32, height: u32) -> Result<Bitmap> {
+    let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(DecodeError::InvalidSegment(number, "MMR region too large"));
+    };
+    let decoder = CCITTDecoder {
+        algorithm: Algorithm::Group4,
+        width: w,
+        rows: Some(h),
+        flags: Flags::default(),
+    };
+    let bits = decoder.decode(data)?;
+    let mut r = Bitmap::new(width, height, 0)?;
+    for (i, p) in r.data.iter_mut().enumerate() {
+        // CCITT decoded bit 1 is white
+        let white = bits.get(i / 8).is_none_or(|b| b & (0x80 >> (i % 8)) != 0);
+        *p = u8::from(!white);
+    }
+    Ok(r)
+}
+
+struct Page {
+    bitmap: Bitmap,
+    default_pixel: u8,
+    default_op: CombinationOperator,
+    /// Regions use their own combination operator.
+    op_override: bool,
+    /// Page height is unknown, determined by end of stripe segments.
+    height_unknown: bool,
+}
+
+/// Decode state of segments from globals and page stream.
+#[derive(Default)]
+struct Decoder {
+    page: Option<Page>,
+    /// Exported symbols of symbol dictionary segments by segment number,
+    /// None if the dictionary not supported.
+    symbols: HashMap<u32, Option<Rc<[Bitmap]>>>,
+}
+
+impl Decoder {
+    /// Decode segments of `data` until end of data or end of page.
+    fn decode_segments(&mut self, data: &[u8]) -> Result<()> {
+        let mut r = Reader::new(data);
+        while !r.is_empty() {
+            let header = SegmentHeader::parse(&mut r)?;
+            let data = if header.data_len == UNKNOWN_LENGTH {
+                if header.kind != 38 && header.kind != 39 {
+                    return Err(DecodeError::InvalidSegment(
+                        header.number,
+                        "unknown data length",
+                    ));
+                }
+                // region decoded until end of data, see
+                // `Self::generic_region()`
+                let d = &r.data[r.pos..];
+                r.pos = r.data.len();
+                d
+            } else {
+                r.bytes(header.data_len as usize)?
+            };
+
+            match header.kind {
+                0 => self.symbol_dictionary(&header, data)?,
+                6 | 7 => self.text_region(&header, data)?,
+                38 | 39 => self.generic_region(&header, data, &mut r)?,
+                48 => self.page_info(data)?,
+                49 => break,
+                50 => self.end_of_stripe(data)?,
+                // end of file, profiles, tables and extension
+                51 | 52 | 53 | 62 => {}
+                kind => warn!(
+                    "JBIG2 segment type {} not supported, segment {} skipped",
+                    kind, header.number
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn page_info(&mut self, data: &[u8]) -> Result<()> {
+        let mut r = Reader::new(data);
+        let width = r.u32()?;
+        let height = r.u32()?;
+        // x and y resolution
+        r.bytes(8)?;
+        let flags = r.u8()?;
+        let default_pixel = (flags >> 2) & 1;
+        let height_unknown = height == UNKNOWN_LENGTH;
+        self.page = Some(Page {
+            bitmap: Bitmap::new(
+                width,
+                if height_unknown { 0 } else { height },
+                default_pixel,
+            )?,
+            default_pixel,
+            default_op: CombinationOperator::new((flags >> 3) & 0x3),
+            op_override: flags & 0x40 != 0,
+            height_unknown,
+        });
+        Ok(())
+    }
+
+    fn end_of_stripe(&mut self, data: &[u8]) -> Result<()> {
+        let row = Reader::new(data).u32()?;
+        let page = self.page.as_mut().ok_or(DecodeError::NoPageInfo)?;
+        if page.height_unknown {
+            page.bitmap
+                .grow(row.saturating_add(1), page.default_pixel)?;
+        }
+        Ok(())
+    }
+
+    /// Combine region bitmap to page.
+    fn paint_region(&mut self, info: &RegionInfo, bitmap: &Bitmap) -> Result<()> {
+        let page = self.page.as_mut().ok_or(DecodeError::NoPageInfo)?;
+        if page.height_unknown {
+            page.bitmap
+                .grow(info.y.saturating_add(bitmap.height), page.default_pixel)?;
+        }
+        let op = if page.op_override {
+            info.op
+        } else {
+            page.default_op
+        };
+        page.bitmap
+            .compose(bitmap, info.x as i32, info.y as i32, op);
+        Ok(())
+    }
+
+    /// Decode immediate generic region segment of 7.4.6, `r` is the segment
+    /// reader, moved to the segment end if data length is unknown.
+    fn generic_region(
+        &mut self,
+        header: &SegmentHeader,
+        data: &[u8],
+        r: &mut Reader<'_>,
+    ) -> Result<()> {
+        let mut sr = Reader::new(data);
+        let mut info = RegionInfo::parse(&mut sr)?;
+        let flags = sr.u8()?;
+        let mmr = flags & 1 != 0;
+        let template = (flags >> 1) & 0x3;
+        let tpgdon = flags & 0x8 != 0;
+        let at = if mmr {
+            vec![]
+        } else {
+            read_at_pixels(&mut sr, template)?
+        };
+
+        let mut data = &data[sr.pos..];
+        if header.data_len == UNKNOWN_LENGTH {
+            // data ends with end sequence and row count (7.2.7)
+            let end: &[u8] = if mmr { &[0, 0] } else { &[0xff, 0xac] };
+            let Some(end_pos) = data.windows(2).position(|w| w == end) else {
+                return Err(DecodeError::InvalidSegment(
+                    header.number,
+                    "end of generic region not found",
+                ));
+            };
+            let mut rows = Reader::new(&data[end_pos + 2..]);
+            info.height = rows.u32()?;
+            r.pos = r.data.len() - data.len() + end_pos + 2 + 4;
+            data = &data[..end_pos];
+        }
+
+        let bitmap = if mmr {
+            decode_mmr(header.number, data, info.width, info.height)?
+        } else {
+            let mut decoder = ArithDecoder::new(data);
+            decode_generic(
+                &mut decoder,
+                &mut new_generic_contexts(),
+                info.width,
+                info.height,
+                template,
+                &at,
+                tpgdon,
+            )?
+        };
+        self.paint_region(&info, &bitmap)
+    }
+
+    /// Exported symbols of referred symbol dictionaries, None if any of them
+    /// not supported.
+    fn referred_symbols(&self, header: &SegmentHeader) -> Option<Vec<Bitmap>> {
+        let mut r = vec![];
+        for n in &header.referred {
+            match self.symbols.get(n) {
+                Some(Some(symbols)) => r.extend(symbols.iter().cloned()),
+                Some(None) => return None,
+                // not a symbol dictionary
+                None => {}
+            }
+        }
+        Some(r)
+    }
+
+    /// Decode symbol dictionary segment of 7.4.2, only arithmetic coded
+    /// without refinement/aggregate supported.
+    fn symbol_dictionary(&mut self, header: &SegmentHeader, data: &[u8]) -> Result<()> {
+        let mut r = Reader::new(data);
+        let flags = r.u16()?;
+        let huffman = flags & 1 != 0;
+        let ref_agg = flags & 2 != 0;
+        let template = ((flags >> 10) & 0x3) as u8;
+        let Some(in_symbols) = self
+            .referred_symbols(header)
+            .filter(|_| !huffman && !ref_agg)
+        else {
+            warn!(
+                "JBIG2 symbol dictionary segment {} skipped, Huffman coding and refinement not supported",
+                header.number
+            );
+            self.symbols.insert(header.number, None);
+            return Ok(());
+        };
+        if flags & 0x100 != 0 {
+            warn!("JBIG2 symbol dictionary bitmap coding context reuse not supported");
+        }
+        let at = read_at_pixels(&mut r, template)?;
+        let n_exported = r.u32()?;
+        let n_new = r.u32()?;
+
+        let mut decoder = ArithDecoder::new(&data[r.pos..]);
+        let mut gb_contexts = new_generic_contexts();
+        let (mut iadh, mut iadw, mut iaex) =
+            (IntDecoder::new(), IntDecoder::new(), IntDecoder::new());
+        // count not trusted, capacity bounded by data size
+        let mut new_symbols = Vec::with_capacity((n_new as usize).min(data.len()));
+        let mut height = 0i32;
+        while new_symbols.len() < n_new as usize {
+            let Some(dh) = iadh.decode(&mut decoder) else {
+                return Err(DecodeError::InvalidSegment(header.number, "OOB height"));
+            };
+            height += dh;
+            let mut width = 0i32;
+            while let Some(dw) = iadw.decode(&mut decoder) {
+                width += dw;
+                if width < 0 || height < 0 || new_symbols.len() >= n_new as usize {
+                    return Err(DecodeError::InvalidSegment(header.number, "invalid symbol"));
+                }
+                new_symbols.push(decode_generic(
+                    &mut decoder,
+                    &mut gb_contexts,
+                    width as u32,
+                    height as u32,
+                    template,
+                    &at,
+                    false,
+                )?);
+            }
+        }
+
+        // export flags in runs, starts from not exported
+        let total = in_symbols.len() + new_symbols.len();
+        let mut exported = Vec::with_capacity((n_exported as usize).min(total));
+        let mut export = false;
+        let mut i = 0;
+        while i < total {
+            let run = iaex
+                .decode(&mut decoder)
+                .filter(|&n| n >= 0 && i + n as usize <= total)
+                .ok_or(DecodeError::InvalidSegment(
+                    header.number,
+                    "invalid export run",
+                ))? as usize;
+            if export {
+                exported.extend(
+                    in_symbols
+                        .iter()
+                        .chain(new_symbols.iter())
+                        .skip(i)
+                        .take(run)
+                        .cloned(),
+                );
+            }
+            i += run;
+            export = !export;
+        }
+        self.symbols.insert(header.number, Some(exported.into()));
+        Ok(())
+    }
+
+    /// Decode immediate text region segment of 7.4.3, only arithmetic coded
+    /// without refinement supported.
+    fn text_region(&mut self, header: &SegmentHeader, data: &[u8]) -> Result<()> {
+        let mut r = Reader::new(data);
+        let info = RegionInfo::parse(&mut r)?;
+        let flags = r.u16()?;
+        let huffman = flags & 1 != 0;
+        let refine = flags & 2 != 0;
+        let Some(symbols) = self
+            .referred_symbols(header)
+            .filter(|s| !huffman && !refine && !s.is_empty())
+        else {
+            warn!(
+                "JBIG2 text region segment {} skipped, Huffman coding and refinement not supported, or no symbols",
+                header.number
+            );
+            return Ok(());
+        };
+        let log_strips = (flags >> 2) & 0x3;
+        let strips = 1i32 << log_strips;
+        let ref_corner = (flags >> 4) & 0x3;
+        let transposed = flags & 0x40 != 0;
+        let op = CombinationOperator::new(((flags >> 7) & 0x3) as u8);
+        let default_pixel = ((flags >> 9) & 1) as u8;
+        // 5 bits signed
+        let ds_offset = (((flags >> 10) & 0x1f) as i32 ^ 0x10) - 0x10;
+        let n_instances = r.u32()?;
+
+        let (right, bottom) = (ref_corner & 2 != 0, ref_corner & 1 == 0);
+        let mut code_len = 0;
+        while (1usize << code_len) < symbols.len() {
+            code_len += 1;
+        }
+        let mut decoder = ArithDecoder::new(&data[r.pos..]);
+        let (mut iadt, mut iafs, mut iads, mut iait) = (
+            IntDecoder::new(),
+            IntDecoder::new(),
+            IntDecoder::new(),
+            IntDecoder::new(),
+        );
+        let mut iaid = IaidDecoder::new(code_len);
+        let invalid = || DecodeError::InvalidSegment(header.number, "invalid text region");
+
+        let mut bitmap = Bitmap::new(info.width, info.height, default_pixel)?;
+        let mut strip_t = -iadt.decode(&mut decoder).ok_or_else(invalid)? * strips;
+        let mut first_s = 0;
+        let mut n = 0;
+        while n < n_instances {
+            strip_t += iadt.decode(&mut decoder).ok_or_else(invalid)? * strips;
+            first_s += iafs.decode(&mut decoder).ok_or_else(invalid)?;
+            let mut cur_s = first_s;
+            loop {
+                let cur_t = if strips > 1 {
+                    iait.decode(&mut decoder).ok_or_else(invalid)?
+                } else {
+                    0
+                };
+                let t = strip_t + cur_t;
+                let symbol = symbols.get(iaid.decode(&mut decoder)).ok_or_else(invalid)?;
+                let (w, h) = (symbol.width as i32, symbol.height as i32);
+                // extent along S direction
+                let extent = if transposed { h } else { w };
+                // for these reference corners, S points to the symbol end
+                let s_at_end = if transposed { bottom } else { right };
+                if s_at_end {
+                    cur_s += extent - 1;
+                }
+                // S is the horizontal axis if not transposed
+                let (x, y) = if transposed { (t, cur_s) } else { (cur_s, t) };
+                let x = if right { x - (w - 1) } else { x };
+                let y = if bottom { y - (h - 1) } else { y };
+                bitmap.compose(symbol, x, y, op);
+                if !s_at_end {
+                    cur_s += extent - 1;
+                }
+
+                n += 1;
+                let Some(ds) = iads.decode(&mut decoder) else {
+                    break;
+                };
+                cur_s += ds + ds_offset;
+            }
+        }
+        self.paint_region(&info, &bitmap)
+    }
+}
+
+/// Decode JBIG2 embedded stream `data`, with optional global segments
+/// `globals`. Returns page image, black is 0 and white is 255.
+pub fn decode(globals: Option<&[u8]>, data: &[u8]) -> Result<GrayImage> {
+    let mut decoder = Decoder::default();
+    if let Some(globals) = globals {
+        decoder.decode_segments(globals)?;
+    }
+    decoder.decode_segments(data)?;
+
+    let page = decoder.page.ok_or(DecodeError::NoPageInfo)?;
+    let bitmap = page.bitmap;
+    Ok(GrayImage::from_fn(bitmap.width, bitmap.height, |x, y| {
+        Luma([if bitmap.row(y)[x as usize] == 1 {
+            0
+        } else {
+            255
+        }])
+    }))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,84 @@
+use super::*;
+
+/// Render image as lines of `#` for black and `.` for white.
+fn to_art(img: &GrayImage) -> String {
+    let mut r = String::new();
+    for row in img.rows() {
+        r.extend(row.map(|p| if p.0[0] == 0 { '#' } else { '.' }));
+        r.push('\n');
+    }
+    r
+}
+
+/// Test sequence of ISO/IEC 14492 Annex H.2, all bits coded in context 0.
+#[test]
+fn arith_decoder() {
+    let encoded = hex_literal::hex!(
+        "84 C7 3B FC E1 A1 43 04 02 20 00 00 41 0D BB 86"
+        "F4 31 7F FF 88 FF 37 47 1A DB 6A DF FF AC"
+    );
+    let expected = hex_literal::hex!(
+        "00 02 00 51 00 00 00 C0 03 52 87 2A AA AA AA AA"
+        "82 C0 20 00 FC D7 9E F6 BF 7F ED 90 4F 46 A3 BF"
+    );
+
+    let mut decoder = ArithDecoder::new(&encoded);
+    let mut contexts = [0u8];
+    let decoded: Vec<u8> = (0..expected.len())
+        .map(|_| (0..8).fold(0, |b, _| b << 1 | decoder.decode(&mut contexts, 0)))
+        .collect();
+    assert_eq!(&expected[..], &decoded[..]);
+}
+
+/// Generic regions of all templates, typical prediction, combination
+/// operators and unknown data length.
+#[test]
+fn generic_region() {
+    let img = decode(None, include_bytes!("generic-region")).unwrap();
+    assert_eq!((64, 40), img.dimensions());
+    assert_eq!(include_str!("generic-region.exp"), to_art(&img));
+}
+
+/// Text region of symbols from symbol dictionary in globals, halftone region
+/// skipped.
+#[test]
+fn symbol_text_region() {
+    let img = decode(
+        Some(include_bytes!("symbol-globals")),
+        include_bytes!("symbol-text-region"),
+    )
+    .unwrap();
+    assert_eq!((48, 30), img.dimensions());
+    assert_eq!(include_str!("symbol-text-region.exp"), to_art(&img));
+}
+
+/// Text region skipped if referred symbol dictionary missing.
+#[test]
+fn text_region_without_globals() {
+    let img = decode(None, include_bytes!("symbol-text-region")).unwrap();
+    assert!(img.pixels().all(|p| p.0[0] == 255));
+}
+
+#[test]
+fn page_info_missing() {
+    assert!(matches!(decode(None, &[]), Err(DecodeError::NoPageInfo)));
+}
+
+/// Page size of page information segment not trusted, error instead of
+/// allocating huge bitmap.
+#[test]
+fn page_info_too_large() {
+    #[rustfmt::skip]
+    let data = [
+        // segment 0, page information, page 1, 19 bytes
+        0, 0, 0, 0, 48, 0, 1, 0, 0, 0, 19,
+        // width, height 0x10000
+        0, 1, 0, 0, 0, 1, 0, 0,
+        // resolution, flags and striping
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    assert!(matches!(
+        decode(None, &data),
+        Err(DecodeError::BitmapTooLarge(0x10000, 0x10000))
+    ));
+}
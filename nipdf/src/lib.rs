@@ -1,5 +1,6 @@
 mod ascii85;
 mod ccitt;
+mod jbig2;
 pub mod file;
 pub mod function;
 pub mod graphics;
@@ -542,8 +542,6 @@ impl<'a, 'b, T: TypeValidator, R: 'a + Resolver> SchemaDict<'b, T, R> {
 
     pub fn opt_f32_arr(&self, id: &Name) -> Result<Option<Vec<f32>>, ObjectValueError> {
         self.opt_arr_map(id, |o| o.as_number())
-            .map(|o| o.unwrap_or_default())
-            .map(Some)
     }
 
     pub fn required_arr_map<V>(
@@ -823,8 +821,12 @@ mod xref;
 pub use xref::{Entry as XRefEntry, Section as XRefSection, *};
 
 mod frame;
+#[cfg(feature = "serde")]
+mod ser;
 use crate::{file::DataContainer, graphics::trans::ThousandthsOfText, parser};
 pub use frame::*;
+#[cfg(feature = "serde")]
+pub use ser::ObjectWithResolver;
 
 #[derive(Clone, PartialEq, Debug, thiserror::Error)]
 pub enum ObjectValueError {
@@ -844,6 +846,8 @@ pub enum ObjectValueError {
     UnknownFilter,
     #[error("Filter decode error")]
     FilterDecodeError,
+    #[error("Decrypt error")]
+    DecryptError,
     #[error("Stream not image")]
     StreamNotImage,
     #[error("Stream is not bytes")]
@@ -862,6 +866,14 @@ pub enum ObjectValueError {
     GraphicsOperationSchemaError,
     #[error("Dict key not found")]
     DictKeyNotFound,
+    #[error("Cyclic reference of object {0}")]
+    CyclicReference(RuntimeObjectId),
+    #[error("Render cancelled")]
+    Cancelled,
+    #[error("Page index out of range")]
+    PageIndexOutOfRange(usize),
+    #[error("Unsupported features: {}", .0.join(", "))]
+    Unsupported(Vec<String>),
 }
 
 impl<'a> From<parser::ParseError<'a>> for ObjectValueError {
@@ -0,0 +1,139 @@
+//! `serde::Serialize` for `Object` and `Dictionary`, enabled by `serde` feature.
+//!
+//! Mapping:
+//!
+//! - `null`, boolean, integer and number map to their JSON counterparts
+//! - name maps to string with leading `/`, such as `"/Type"`
+//! - literal and hex string map to `{"string": "..."}` if it is valid UTF-8,
+//!   otherwise `{"hex": "..."}` with hex encoded bytes
+//! - array maps to array, dictionary maps to map, keys without leading `/`,
+//!   sorted to make output stable
+//! - reference maps to `{"ref": [id, generation]}`
+//! - stream maps to `{"dict": {...}, "data": "..."}`, `data` is base64 of
+//!   the raw (un-decoded) stream data. `data` is only available if serialized
+//!   with `ObjectResolver` by `Object::with_resolver()`, omitted otherwise.
+//!
+//! Shared arrays and dictionaries are serialized by value.
+use super::{Dictionary, Object};
+use crate::file::ObjectResolver;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{
+    Serialize, Serializer,
+    ser::{Error, SerializeMap, SerializeSeq},
+};
+use std::str::from_utf8;
+
+/// Serialize `Object` with stream data resolved by `resolver`.
+#[derive(Clone, Copy)]
+pub struct ObjectWithResolver<'o, 'r, 'a> {
+    object: &'o Object,
+    resolver: Option<&'r ObjectResolver<'a>>,
+}
+
+impl Object {
+    /// Wrap to serialize with stream data, see module doc for the mapping.
+    pub fn with_resolver<'o, 'r, 'a>(
+        &'o self,
+        resolver: &'r ObjectResolver<'a>,
+    ) -> ObjectWithResolver<'o, 'r, 'a> {
+        ObjectWithResolver {
+            object: self,
+            resolver: Some(resolver),
+        }
+    }
+}
+
+struct DictWithResolver<'o, 'r, 'a> {
+    dict: &'o Dictionary,
+    resolver: Option<&'r ObjectResolver<'a>>,
+}
+
+impl Serialize for DictWithResolver<'_, '_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = self.dict.iter().collect();
+        entries.sort_unstable_by_key(|(k, _)| k.as_str());
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (k, v) in entries {
+            map.serialize_entry(k.as_str(), &ObjectWithResolver {
+                object: v,
+                resolver: self.resolver,
+            })?;
+        }
+        map.end()
+    }
+}
+
+fn serialize_string<S: Serializer>(s: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    match from_utf8(s) {
+        Ok(s) => map.serialize_entry("string", s)?,
+        Err(_) => map.serialize_entry("hex", &hex::encode(s))?,
+    }
+    map.end()
+}
+
+impl Serialize for ObjectWithResolver<'_, '_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.object {
+            Object::Null => serializer.serialize_unit(),
+            Object::Bool(v) => serializer.serialize_bool(*v),
+            Object::Integer(v) => serializer.serialize_i32(*v),
+            Object::Number(v) => serializer.serialize_f32(*v),
+            Object::LiteralString(s) => serialize_string(s.as_bytes(), serializer),
+            Object::HexString(s) => serialize_string(s.as_bytes(), serializer),
+            Object::Name(name) => serializer.collect_str(&format_args!("/{}", name.as_str())),
+            Object::Array(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+                for item in arr.iter() {
+                    seq.serialize_element(&ObjectWithResolver {
+                        object: item,
+                        resolver: self.resolver,
+                    })?;
+                }
+                seq.end()
+            }
+            Object::Dictionary(d) => DictWithResolver {
+                dict: d,
+                resolver: self.resolver,
+            }
+            .serialize(serializer),
+            Object::Stream(s) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("dict", &DictWithResolver {
+                    dict: s.as_dict(),
+                    resolver: self.resolver,
+                })?;
+                if let Some(resolver) = self.resolver {
+                    let data = s.raw(resolver).map_err(S::Error::custom)?;
+                    map.serialize_entry("data", &STANDARD.encode(data))?;
+                }
+                map.end()
+            }
+            Object::Reference(r) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("ref", &(r.id().id().0, r.id().generation()))?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ObjectWithResolver {
+            object: self,
+            resolver: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for Dictionary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DictWithResolver {
+            dict: self,
+            resolver: None,
+        }
+        .serialize(serializer)
+    }
+}
@@ -2,7 +2,7 @@ use super::{Dictionary, Object, ObjectId, ObjectValueError};
 use crate::{
     ccitt::{Algorithm as CCITTAlgorithm, Flags},
     file::{EncryptInfo, ObjectResolver, ResourceDict},
-    function::Domains,
+    function::{Domain, Domains},
     graphics::{
         ColorSpaceArgs,
         color_space::{
@@ -13,11 +13,12 @@ use crate::{
     object::PdfObject,
     parser::is_white_space,
 };
+use ahash::HashMap;
 use anyhow::Result as AnyResult;
 use bitstream_io::{BigEndian, BitReader};
 use image::{DynamicImage, GrayImage, Luma, RgbImage, Rgba, RgbaImage};
 use jpeg_decoder::PixelFormat;
-use log::error;
+use log::{error, warn};
 use nipdf_macro::pdf_object;
 use num_traits::ToPrimitive;
 use prescript::{Name, sname};
@@ -47,6 +48,7 @@ const S_FILTER_ASCII85_DECODE: &str = "ASCII85Decode";
 const S_FILTER_ASCII_HEX_DECODE: &str = "ASCIIHexDecode";
 const S_FILTER_RUN_LENGTH_DECODE: &str = "RunLengthDecode";
 const S_FILTER_JPX_DECODE: &str = "JPXDecode";
+const S_FILTER_JBIG2_DECODE: &str = "JBIG2Decode";
 
 const FILTER_CRYPT: Name = sname(S_FILTER_CRYPT);
 #[cfg(test)]
@@ -57,6 +59,7 @@ const FILTER_DCT_DECODE: Name = sname("DCTDecode");
 const FILTER_ASCII85_DECODE: Name = sname("ASCII85Decode");
 // const FILTER_RUN_LENGTH_DECODE: Name = sname("RunLengthDecode");
 const FILTER_JPX_DECODE: Name = sname("JPXDecode");
+const FILTER_JBIG2_DECODE: Name = sname("JBIG2Decode");
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct BufPos {
@@ -245,13 +248,49 @@ pub trait ImageMetadata {
     fn decode(&self) -> AnyResult<Option<Domains>>;
 }
 
+/// Ranges image samples of `cs` mapped to, by `/Decode`. Only Lab needs
+/// it, its components are not in 0 to 1, default `/Decode` maps samples to
+/// L 0 to 100 and a, b in `/Range`. Other color spaces use normalized
+/// samples as is.
+fn lab_sample_decode(cs: &ColorSpace, decode: Option<Domains>) -> Option<Vec<Domain>> {
+    let ColorSpace::Lab(lab) = cs else {
+        return None;
+    };
+    Some(match decode {
+        Some(decode) if decode.len() == 3 => decode.0,
+        Some(decode) => {
+            warn!("invalid /Decode of Lab image, use default: {:?}", decode);
+            lab.ranges().to_vec()
+        }
+        None => lab.ranges().to_vec(),
+    })
+}
+
+/// Map normalized sample values to ranges of `decode`.
+fn apply_sample_decode(c: &mut [f32], decode: &[Domain]) {
+    for (v, d) in c.iter_mut().zip(decode) {
+        *v = (d.end - d.start).mul_add(*v, d.start);
+    }
+}
+
 fn decode_image<'a, M: ImageMetadata>(
     data: FilterDecodedData<'a>,
     img_meta: &M,
     resolver: &ObjectResolver<'a>,
     resources: Option<&ResourceDict<'a, '_>>,
 ) -> Result<DynamicImage, ObjectValueError> {
-    fn decode_one_bit(w: u32, h: u32, data: &[u8], row_padding: bool) -> DynamicImage {
+    /// Image data shorter than width, height and bits per component needs.
+    fn truncated(e: std::io::Error) -> ObjectValueError {
+        error!("Failed to decode image, data truncated: {}", e);
+        ObjectValueError::FilterDecodeError
+    }
+
+    fn decode_one_bit(
+        w: u32,
+        h: u32,
+        data: &[u8],
+        row_padding: bool,
+    ) -> Result<DynamicImage, ObjectValueError> {
         use bitstream_io::read::BitRead;
 
         let mut img = GrayImage::new(w, h);
@@ -265,16 +304,25 @@ fn decode_image<'a, M: ImageMetadata>(
         let mut r = BitReader::<_, BigEndian>::new(data as &[u8]);
         for y in 0..h {
             for x in 0..w {
-                img.put_pixel(x, y, Luma([if r.read_bit().unwrap() { 255u8 } else { 0 }]));
+                let bit = r.read_bit().map_err(truncated)?;
+                img.put_pixel(x, y, Luma([if bit { 255u8 } else { 0 }]));
+            }
+            // padding of last row may be omitted
+            if y + 1 < h {
+                r.skip(row_padding_bits).map_err(truncated)?;
             }
-            r.skip(row_padding_bits).unwrap();
         }
-        DynamicImage::ImageLuma8(img)
+        Ok(DynamicImage::ImageLuma8(img))
     }
 
     let color_space = img_meta.color_space().unwrap();
     let color_space =
         color_space.map(|args| ColorSpace::from_args(&args, resolver, resources).unwrap());
+    // take() it if color key mask applied on raw sample values
+    let mut color_key = match img_meta.mask().unwrap() {
+        Some(ImageMask::ColorKey(color_key)) => Some(color_key),
+        _ => None,
+    };
     let mut r = match data {
         FilterDecodedData::Image(img) => {
             if let Some(color_space) = color_space.as_ref() {
@@ -285,15 +333,25 @@ fn decode_image<'a, M: ImageMetadata>(
         }
 
         FilterDecodedData::CmykImage((width, height, pixels)) => {
-            let cs = DeviceCMYK;
+            // Use PDF /ColorSpace if it is not DeviceCMYK, such as DeviceN of 4 components
+            let cs = color_space
+                .as_ref()
+                .filter(|cs| **cs != ColorSpace::DeviceCMYK && cs.components() == 4);
             DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
                 let i = (y * width + x) as usize * 4;
-                Rgba(cs.to_rgba(&[
+                let c = [
                     255 - pixels[i],
                     255 - pixels[i + 1],
                     255 - pixels[i + 2],
                     255 - pixels[i + 3],
-                ]))
+                ];
+                match cs {
+                    Some(cs) => {
+                        let c: [f32; 4] = convert_color_to(&c);
+                        Rgba(color_to_rgba(cs, &c))
+                    }
+                    None => Rgba(DeviceCMYK.to_rgba(&c)),
+                }
             }))
         }
 
@@ -302,28 +360,87 @@ fn decode_image<'a, M: ImageMetadata>(
                 &color_space,
                 img_meta.bits_per_component().unwrap().unwrap(),
             ) {
-                (_, 1) => decode_one_bit(
+                // image mask or 1 bit gray, other color spaces need sample
+                // unpacked per component
+                (None | Some(ColorSpace::DeviceGray), 1) => decode_one_bit(
                     img_meta.width().unwrap(),
                     img_meta.height().unwrap(),
                     data.borrow(),
                     true,
-                ),
+                )?,
                 (Some(cs), 8) => {
                     let n_colors = cs.components();
+                    let color_key = color_key.take();
+                    let decode = lab_sample_decode(cs, img_meta.decode().unwrap());
                     let mut img =
                         RgbaImage::new(img_meta.width().unwrap(), img_meta.height().unwrap());
                     for (p, dest_p) in data.chunks(n_colors).zip(img.pixels_mut()) {
-                        let c: TinyVec<[f32; 4]> = p.iter().map(|v| v.into_color_comp()).collect();
-                        let color: [u8; 4] = color_to_rgba(cs, c.as_slice());
+                        let mut c: TinyVec<[f32; 4]> =
+                            p.iter().map(|v| v.into_color_comp()).collect();
+                        if let Some(decode) = &decode {
+                            apply_sample_decode(&mut c, decode);
+                        }
+                        let mut color: [u8; 4] = color_to_rgba(cs, c.as_slice());
+                        if color_key.as_ref().is_some_and(|k| {
+                            samples_match_color_key(k, p.iter().map(|&v| v.into()))
+                        }) {
+                            color[3] = 0;
+                        }
                         *dest_p = Rgba(color);
                     }
                     DynamicImage::ImageRgba8(img)
                 }
-                _ => todo!(
-                    "unsupported interoperate decoded stream data as image: {:?} {}",
-                    color_space,
-                    img_meta.bits_per_component().unwrap().unwrap()
-                ),
+                (Some(cs), bpc @ (1 | 2 | 4 | 16)) => {
+                    use bitstream_io::read::BitRead;
+
+                    let (w, h) = (img_meta.width().unwrap(), img_meta.height().unwrap());
+                    let n_colors = cs.components();
+                    let color_key = color_key.take();
+                    let max = ((1u32 << bpc) - 1) as f32;
+                    let is_indexed = matches!(cs, ColorSpace::Indexed(_));
+                    let decode = lab_sample_decode(cs, img_meta.decode().unwrap());
+                    let mut img = RgbaImage::new(w, h);
+                    let mut r = BitReader::<_, BigEndian>::new(data.borrow() as &[u8]);
+                    for y in 0..h {
+                        for x in 0..w {
+                            let samples: TinyVec<[u16; 4]> = (0..n_colors)
+                                .map(|_| r.read::<u16>(u32::from(bpc)))
+                                .collect::<Result<_, _>>()
+                                .map_err(truncated)?;
+                            // index of Indexed color space not normalized
+                            let mut c: TinyVec<[f32; 4]> = samples
+                                .iter()
+                                .map(|&v| {
+                                    if is_indexed {
+                                        f32::from(v) / 255.0
+                                    } else {
+                                        f32::from(v) / max
+                                    }
+                                })
+                                .collect();
+                            if let Some(decode) = &decode {
+                                apply_sample_decode(&mut c, decode);
+                            }
+                            let mut color: [u8; 4] = color_to_rgba(cs, c.as_slice());
+                            if color_key.as_ref().is_some_and(|k| {
+                                samples_match_color_key(k, samples.iter().copied())
+                            }) {
+                                color[3] = 0;
+                            }
+                            img.put_pixel(x, y, Rgba(color));
+                        }
+                        // each row starts at byte boundary
+                        r.byte_align();
+                    }
+                    DynamicImage::ImageRgba8(img)
+                }
+                _ => {
+                    return Err(ObjectValueError::Unsupported(vec![format!(
+                        "image of color space {:?} and {:?} bits per component",
+                        color_space,
+                        img_meta.bits_per_component().ok().flatten()
+                    )]));
+                }
             }
         }
 
@@ -334,13 +451,16 @@ fn decode_image<'a, M: ImageMetadata>(
                 img_meta.height().unwrap(),
                 &data,
                 false,
-            )
+            )?
         }
     };
 
-    if let Some(ImageMask::ColorKey(color_key)) = img_meta.mask().unwrap() {
+    // raw sample values not available, compare on converted rgb color
+    if let Some(color_key) = color_key {
         let Some(cs) = color_space else {
-            todo!("Color Space not defined when process color key mask");
+            return Err(ObjectValueError::Unsupported(vec![
+                "color key mask without color space".to_owned(),
+            ]));
         };
         let mut img = r.into_rgba8();
         let color_key = color_key_range(&color_key, &cs);
@@ -562,7 +682,9 @@ fn predictor_decode(
             (params.columns * params.colors * params.bits_per_component + 7) as usize / 8,
             (params.colors * params.bits_per_component + 7) as usize / 8,
         ),
-        2 => todo!("predictor 2/tiff"),
+        2 => Err(ObjectValueError::Unsupported(vec![
+            "TIFF predictor".to_owned(),
+        ])),
         _ => {
             error!("Unknown predictor: {}", params.predictor);
             Err(ObjectValueError::FilterDecodeError)
@@ -604,7 +726,7 @@ fn crypt_filter(
     let name = params
         .and_then(|d| d.get("Name").map(|o| o.name()))
         .transpose()?;
-    encrypt_info.stream_decrypt(name, id, &mut buf);
+    encrypt_info.stream_decrypt(name, id, &mut buf)?;
     Ok(buf)
 }
 
@@ -697,8 +819,10 @@ fn decode_dct<'a>(
             GrayImage::from_vec(info.width as u32, info.height as u32, pixels).unwrap(),
         ))),
         PixelFormat::L16 => {
-            todo!("Convert to DynamicImage::ImageLuma16")
-            // Problem is jpeg-decoder returns pixels in Vec<u8>, but DynamicImage::ImageLuma16
+            Err(ObjectValueError::Unsupported(vec![
+                "16-bit gray JPEG".to_owned(),
+            ]))
+            // TODO: convert to DynamicImage::ImageLuma16, problem is jpeg-decoder returns pixels in Vec<u8>, but DynamicImage::ImageLuma16
             // expect Vec<u16> I don't known is {little,big}-endian, or native-endian in
             // pixels
         }
@@ -790,10 +914,9 @@ trait CCITTFaxDecodeParamsDictTrait {
 impl<'a: 'b, 'b> TryFrom<&CCITTFaxDecodeParamsDict<'a, 'b>> for Flags {
     type Error = anyhow::Error;
 
+    /// EOLs of Group 3 are optional whatever `/EndOfLine` is, and lines
+    /// ended by EOL early are filled blank, `/DamagedRowsBeforeError` ignored.
     fn try_from(params: &CCITTFaxDecodeParamsDict<'a, 'b>) -> Result<Self, Self::Error> {
-        assert!(!params.end_of_line()?);
-        assert_eq!(0, params.damaged_rows_before_error()?);
-
         Ok(Flags {
             encoded_byte_align: params.encoded_byte_align()?,
             inverse_black_white: params.black_is1()?,
@@ -892,13 +1015,62 @@ fn decode_ccitt<'a: 'b, 'b>(
     let decoder = Decoder {
         algorithm: params.k().unwrap(),
         width: params.columns().unwrap(),
-        rows: Some(params.rows().unwrap()),
+        rows: Some(params.rows().unwrap()).filter(|&rows| rows > 0),
         flags: (&params).try_into().unwrap(),
     };
     let image = handle_filter_error(decoder.decode(input), &FILTER_CCITT_FAX)?;
     Ok(image)
 }
 
+/// Decode JBIG2 image, global segments are in stream of `/JBIG2Globals` of
+/// `params`.
+fn decode_jbig2<'a>(
+    input: &[u8],
+    params: Option<&Dictionary>,
+    resolver: Option<&ObjectResolver<'a>>,
+) -> Result<FilterDecodedData<'a>, ObjectValueError> {
+    let globals = match (params, resolver) {
+        (Some(params), Some(resolver)) => resolver
+            .opt_resolve_container_value(params, &sname("JBIG2Globals"))?
+            .map(|o| o.stream()?.decode(resolver))
+            .transpose()?,
+        _ => None,
+    };
+    let img = handle_filter_error(
+        crate::jbig2::decode(globals.as_deref(), input),
+        &FILTER_JBIG2_DECODE,
+    )?;
+    Ok(FilterDecodedData::Image(DynamicImage::ImageLuma8(img)))
+}
+
+/// Decoder of custom filter, called with stream data and its `/DecodeParms`.
+pub type FilterDecoder = dyn Fn(&[u8], Option<&Dictionary>) -> AnyResult<Vec<u8>>;
+
+/// Custom filter decoders by filter name, consulted before built-in filters,
+/// so can handle vendor filters or override built-in ones. Decryption by
+/// `/Crypt` filter can not be overridden. Set to resolver by
+/// `ObjectResolver::set_filter_registry()`, inline image streams not
+/// affected.
+#[derive(Default)]
+pub struct FilterRegistry {
+    decoders: HashMap<Name, Box<FilterDecoder>>,
+}
+
+impl FilterRegistry {
+    /// Register `decoder` for filter `name`, replace the previous one.
+    pub fn register(
+        &mut self,
+        name: Name,
+        decoder: impl Fn(&[u8], Option<&Dictionary>) -> AnyResult<Vec<u8>> + 'static,
+    ) {
+        self.decoders.insert(name, Box::new(decoder));
+    }
+
+    fn get(&self, name: &Name) -> Option<&FilterDecoder> {
+        self.decoders.get(name).map(|d| d.as_ref())
+    }
+}
+
 fn filter<'a: 'b, 'b>(
     buf: Cow<'a, [u8]>,
     resolver: Option<&ObjectResolver<'a>>,
@@ -907,6 +1079,15 @@ fn filter<'a: 'b, 'b>(
     id: Option<ObjectId>,
     encrypt_info: Option<&EncryptInfo>,
 ) -> Result<FilterDecodedData<'a>, ObjectValueError> {
+    if let Some(decoder) = resolver
+        .and_then(|r| r.filter_registry())
+        .filter(|_| filter_name != &FILTER_CRYPT)
+        .and_then(|registry| registry.get(filter_name))
+    {
+        return handle_filter_error(decoder(&buf, params), filter_name)
+            .map(FilterDecodedData::bytes);
+    }
+
     let empty_dict = LazyCell::new(Dictionary::new);
     #[allow(clippy::match_ref_pats)]
     match filter_name.as_str() {
@@ -933,6 +1114,7 @@ fn filter<'a: 'b, 'b>(
         S_FILTER_ASCII_HEX_DECODE => decode_ascii_hex(&buf).map(FilterDecodedData::bytes),
         S_FILTER_RUN_LENGTH_DECODE => Ok(FilterDecodedData::bytes(decode_run_length(&buf, params))),
         S_FILTER_JPX_DECODE => decode_jpx(buf, params),
+        S_FILTER_JBIG2_DECODE => decode_jbig2(&buf, params, resolver),
         S_FILTER_LZW_DECODE => decode_lzw(
             &buf,
             LZWDeflateDecodeParams::new(params.unwrap_or_else(|| &*empty_dict), resolver)?,
@@ -946,34 +1128,41 @@ fn filter<'a: 'b, 'b>(
 }
 
 fn image_transform_color_space(img: DynamicImage, to: &ColorSpace) -> AnyResult<DynamicImage> {
-    fn image_color_space(img: &DynamicImage) -> ColorSpace {
+    fn image_color_space(img: &DynamicImage) -> Result<ColorSpace, ObjectValueError> {
         match img {
-            DynamicImage::ImageLuma8(_) => ColorSpace::DeviceGray,
-            DynamicImage::ImageRgb8(_) => ColorSpace::DeviceRGB,
-            _ => todo!("unsupported image color space: {:?}", img),
+            DynamicImage::ImageLuma8(_) => Ok(ColorSpace::DeviceGray),
+            DynamicImage::ImageRgb8(_) => Ok(ColorSpace::DeviceRGB),
+            _ => Err(ObjectValueError::Unsupported(vec![format!(
+                "image color space of {:?}",
+                img.color()
+            )])),
         }
     }
 
-    fn transform(img: DynamicImage, from: &ColorSpace, to: &ColorSpace) -> AnyResult<DynamicImage> {
-        fn convert_cs(img: GrayImage, cs: &impl ColorSpaceTrait<f32>) -> AnyResult<RgbaImage> {
-            let mut r = RgbaImage::new(img.width(), img.height());
-            for (p, dest_p) in img.pixels().zip(r.pixels_mut()) {
-                let color: [u8; 4] = color_to_rgba(cs, &[p[0].into_color_comp()]);
-                *dest_p = Rgba(color);
-            }
-            Ok(r)
+    /// Interpret image raw components as colors of `cs`, PDF /ColorSpace takes
+    /// precedence over the color space image decoder assumed.
+    fn convert_cs(img: &DynamicImage, cs: &ColorSpace) -> RgbaImage {
+        let mut r = RgbaImage::new(img.width(), img.height());
+        for (p, dest_p) in img.as_bytes().chunks(cs.components()).zip(r.pixels_mut()) {
+            let c: TinyVec<[f32; 4]> = p.iter().map(|v| v.into_color_comp()).collect();
+            *dest_p = Rgba(color_to_rgba(cs, c.as_slice()));
         }
+        r
+    }
 
-        if let (ColorSpace::DeviceGray, ColorSpace::Separation(sep)) = (from, to) {
-            return Ok(DynamicImage::ImageRgba8(convert_cs(
-                img.into_luma8(),
-                sep.as_ref(),
-            )?));
+    fn transform(img: DynamicImage, from: &ColorSpace, to: &ColorSpace) -> AnyResult<DynamicImage> {
+        if ColorSpaceTrait::<f32>::components(from) == to.components() {
+            return Ok(DynamicImage::ImageRgba8(convert_cs(&img, to)));
         }
-        todo!("transform image color space from {:?} to {:?}", from, to);
+
+        warn!(
+            "image components not match color space, ignore color space: {:?} {:?}",
+            from, to
+        );
+        Ok(img)
     }
 
-    let from = image_color_space(&img);
+    let from = image_color_space(&img)?;
     if &from == to {
         return Ok(img);
     }
@@ -1031,6 +1220,35 @@ impl Stream {
         self._decode(resolver).and_then(|v| v.into_bytes())
     }
 
+    /// Embedded JPEG file of `DCTDecode` image stream, stream data decoded
+    /// by all filters except the last `DCTDecode` one. Returns None if the
+    /// last filter is not `DCTDecode`.
+    pub fn decode_jpeg<'a>(
+        &self,
+        resolver: &ObjectResolver<'a>,
+    ) -> Result<Option<Cow<'a, [u8]>>, ObjectValueError> {
+        if self.0.contains_key(&KEY_FFILTER) {
+            return Err(ObjectValueError::ExternalStreamNotSupported);
+        }
+
+        let mut filters = FilterDict::new(&self.0, Some(resolver))?.filters()?;
+        if filters.pop() != Some(FILTER_DCT_DECODE) {
+            return Ok(None);
+        }
+
+        let mut d = self.0.clone();
+        d.update(|d| {
+            d.insert(
+                KEY_FILTER,
+                Object::Array(filters.into_iter().map(Object::Name).collect()),
+            );
+        });
+        let raw: Cow<'a, [u8]> = self.raw(resolver)?.into();
+        decode_stream(&d, raw, Some(resolver), None, Some(self.2))?
+            .into_bytes()
+            .map(Some)
+    }
+
     fn buf_range(
         &self,
         resolver: Option<&ObjectResolver>,
@@ -1102,6 +1320,17 @@ fn color_key_range(range: &Domains, cs: &ColorSpace) -> ColorKey {
     (color_to_rgba(cs, &min[..]), color_to_rgba(cs, &max[..]))
 }
 
+/// Return true if all raw sample components in color key mask `ranges` inclusive.
+/// Ranges are in sample value range `0..2^bpc-1`, compared before normalization
+/// and `/Decode`, see PDF 32000-1:2008 8.9.6.4.
+fn samples_match_color_key(ranges: &Domains, samples: impl IntoIterator<Item = u16>) -> bool {
+    ranges
+        .0
+        .iter()
+        .zip(samples)
+        .all(|(range, v)| range.start <= f32::from(v) && f32::from(v) <= range.end)
+}
+
 /// Return true if rgb color in color_key range inclusive, alpha part not compared.
 fn color_matches_color_key(color_key: ColorKey, color: [u8; 4]) -> bool {
     color_key.0[0..2] <= color[0..2] && color[0..2] <= color_key.1[0..2]
@@ -48,10 +48,14 @@ impl<'a> ImageMetadata for InlineStreamDict<'a> {
     }
 
     fn bits_per_component(&self) -> AnyResult<Option<u8>> {
-        self.alt_get(&sname("BitsPerComponent"), &sname("BPC"), |o| {
+        let r = self.alt_get(&sname("BitsPerComponent"), &sname("BPC"), |o| {
             o.int().map(|v| v.try_into().unwrap())
-        })
-        .map_err(|e| e.into())
+        })?;
+        // BitsPerComponent is optional for image mask, it is always 1
+        if r.is_none() && self.image_mask()? {
+            return Ok(Some(1));
+        }
+        Ok(r)
     }
 
     fn color_space(&self) -> AnyResult<Option<crate::graphics::ColorSpaceArgs>> {
@@ -1,7 +1,8 @@
 use super::*;
 use crate::{
-    file::{decode_stream, test_file},
+    file::{decode_stream, open_test_file, test_file},
     function::Domain,
+    graphics::color_space::IndexedColorSpace,
     object::Name,
 };
 use miniz_oxide::deflate::compress_to_vec;
@@ -199,3 +200,255 @@ fn deflate_recover_truncated_zlib_data() {
     let s = from_utf8(&data).unwrap();
     assert_eq!(s, exp);
 }
+
+#[test]
+fn image_color_space_overrides_jpeg() {
+    // 1 component jpeg decoded as gray image, but PDF /ColorSpace is Indexed
+    let img = DynamicImage::ImageLuma8(GrayImage::from_raw(2, 1, vec![0, 1]).unwrap());
+    let cs = ColorSpace::Indexed(Box::new(IndexedColorSpace {
+        base: ColorSpace::DeviceRGB,
+        data: vec![255, 0, 0, 0, 0, 255],
+    }));
+    let img = image_transform_color_space(img, &cs).unwrap().into_rgba8();
+    assert_eq!(
+        vec![255, 0, 0, 255, 0, 0, 255, 255],
+        img.into_raw(),
+        "pixels mapped through indexed color space"
+    );
+
+    // same color space, keep image untouched
+    let img = DynamicImage::ImageRgb8(RgbImage::from_raw(1, 1, vec![1, 2, 3]).unwrap());
+    let img = image_transform_color_space(img, &ColorSpace::DeviceRGB).unwrap();
+    assert_eq!(
+        DynamicImage::ImageRgb8(RgbImage::from_raw(1, 1, vec![1, 2, 3]).unwrap()),
+        img
+    );
+
+    // components not match, ignore PDF color space
+    let img = DynamicImage::ImageRgb8(RgbImage::from_raw(1, 1, vec![1, 2, 3]).unwrap());
+    let img = image_transform_color_space(img, &ColorSpace::DeviceGray).unwrap();
+    assert_eq!(
+        DynamicImage::ImageRgb8(RgbImage::from_raw(1, 1, vec![1, 2, 3]).unwrap()),
+        img
+    );
+}
+
+#[test]
+fn image_color_space_resolved_from_resources() {
+    let f = open_test_file("sample_files/color-space/indexed-by-resource-name.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let resources = pages[0].resources();
+    let x_objects = resources.x_object().unwrap();
+    let img = x_objects[&sname("Im1")].as_stream().unwrap();
+
+    let decoded = img.decode_image(&resolver, Some(&resources)).unwrap();
+    assert_eq!(
+        vec![255, 0, 0, 255, 0, 255, 0, 255],
+        decoded.into_rgba8().into_raw()
+    );
+
+    // resources required to resolve color space name
+    assert!(
+        ColorSpace::<f32>::from_args(&ColorSpaceArgs::Name(sname("CS0")), &resolver, None).is_err()
+    );
+}
+
+#[test]
+fn color_key_mask_on_raw_samples() {
+    let f = open_test_file("sample_files/xobject/color-key-mask-4bpc.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let resources = pages[0].resources();
+    let x_objects = resources.x_object().unwrap();
+    let img = x_objects[&sname("Im1")].as_stream().unwrap();
+
+    // 4 bpc indexes 1, 2, 3, 4, `/Mask [2 3]` masks indexes 2 and 3
+    let decoded = img.decode_image(&resolver, Some(&resources)).unwrap();
+    let pixels = decoded
+        .into_rgba8()
+        .pixels()
+        .map(|p| p.0)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![[17, 0, 238, 255], [34, 0, 221, 0], [51, 0, 204, 0], [
+            68, 0, 187, 255
+        ]],
+        pixels
+    );
+}
+
+#[test_case(&[2, 3] => true; "in range")]
+#[test_case(&[1, 3] => false; "first component out of range")]
+#[test_case(&[2, 4] => false; "second component out of range")]
+fn test_samples_match_color_key(samples: &[u16]) -> bool {
+    let ranges = Domains(vec![Domain::new(2., 15.), Domain::new(0., 3.)]);
+    samples_match_color_key(&ranges, samples.iter().copied())
+}
+
+/// Lab samples mapped by default `/Decode` to L 0..100 and a, b in `/Range`,
+/// or by explicit `/Decode`, before converted to RGB.
+#[test]
+fn decode_lab_image() {
+    let f = open_test_file("sample_files/color-space/lab-image.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let resources = pages[0].resources();
+    let x_objects = resources.x_object().unwrap();
+    let pixels = |name| {
+        let img = x_objects[&sname(name)].as_stream().unwrap();
+        let decoded = img.decode_image(&resolver, Some(&resources)).unwrap();
+        decoded
+            .into_rgba8()
+            .pixels()
+            .map(|p| p.0)
+            .collect::<Vec<_>>()
+    };
+
+    // 8 bpc, L 100, 0 and 50
+    assert_eq!(
+        vec![[255, 255, 255, 255], [0, 0, 0, 255], [110, 110, 110, 255]],
+        pixels("Im1")
+    );
+    // 4 bpc, `/Decode [100 0 -8 7 -8 7]` inverts L
+    assert_eq!(vec![[255, 255, 255, 255], [0, 0, 0, 255]], pixels("Im2"));
+}
+
+#[test]
+fn decode_sub_byte_images() {
+    let f = open_test_file("sample_files/xobject/sub-byte-images.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let resources = pages[0].resources();
+    let x_objects = resources.x_object().unwrap();
+    let pixels = |name| {
+        let img = x_objects[&sname(name)].as_stream().unwrap();
+        let decoded = img.decode_image(&resolver, Some(&resources)).unwrap();
+        decoded
+            .into_rgba8()
+            .pixels()
+            .map(|p| p.0)
+            .collect::<Vec<_>>()
+    };
+    let (red, blue, black) = ([255, 0, 0, 255], [0, 0, 255, 255], [0, 0, 0, 255]);
+
+    // 1 bpc gray, rows padded to byte
+    let (w, b) = ([255, 255, 255, 255], black);
+    assert_eq!(vec![w, b, w, b, w, b], pixels("Im1"));
+    // 1 bpc indexed
+    assert_eq!(vec![red, blue, red, blue, red, red], pixels("Im2"));
+    // 4 bpc indexed
+    let green = [0, 255, 0, 255];
+    assert_eq!(vec![red, green, blue, blue, black, red], pixels("Im3"));
+    // 2 bpc gray
+    let gray = |v| [v, v, v, 255];
+    assert_eq!(
+        vec![gray(0), gray(85), gray(170), gray(255), gray(255), gray(0)],
+        pixels("Im4")
+    );
+    // 1 bpc rgb
+    assert_eq!(vec![red, [0, 255, 255, 255]], pixels("Im5"));
+}
+
+/// Image data shorter than its size needs is a decode error, not panic.
+#[test_case(1, 16, 1; "1 bpc gray")]
+#[test_case(4, 4, 2; "4 bpc gray")]
+fn decode_truncated_image(bpc: u8, width: u32, len: usize) {
+    use crate::file::{ObjectResolver, XRefTable};
+
+    let mut buf = format!(
+        "1 0 obj<</Subtype/Image/Width {width}/Height 2/ColorSpace/DeviceGray\
+         /BitsPerComponent {bpc}/Length {len}>>stream\n"
+    )
+    .into_bytes();
+    buf.extend(vec![0x5a; len]);
+    buf.extend_from_slice(b"\nendstream\nendobj");
+    let xref = XRefTable::from_buf(&buf);
+    let resolver = ObjectResolver::new(&buf, &xref, None);
+    let img = resolver.resolve(1).unwrap().stream().unwrap();
+    assert!(matches!(
+        img.decode_image(&resolver, None),
+        Err(ObjectValueError::FilterDecodeError)
+    ));
+}
+
+#[test]
+fn custom_filter_registry() {
+    let f = open_test_file("sample_files/filters/custom-filter.pdf");
+    let mut resolver = f.resolver().unwrap();
+    let stream = resolver.resolve(4).unwrap().stream().unwrap().clone();
+    assert!(matches!(
+        stream.decode(&resolver),
+        Err(ObjectValueError::UnknownFilter)
+    ));
+
+    let mut registry = FilterRegistry::default();
+    registry.register(sname("Rot13Decode"), |data, _| {
+        Ok(data
+            .iter()
+            .map(|b| match b {
+                b'a'..=b'z' => (b - b'a' + 13) % 26 + b'a',
+                b'A'..=b'Z' => (b - b'A' + 13) % 26 + b'A',
+                _ => *b,
+            })
+            .collect())
+    });
+    resolver.set_filter_registry(Rc::new(registry));
+    assert_eq!(
+        b"Hello, custom filter!",
+        stream.decode(&resolver).unwrap().as_ref()
+    );
+}
+
+#[test]
+fn decode_jpeg_strips_last_dct_filter() {
+    let f = open_test_file("sample_files/filters/dct-ascii-hex.pdf");
+    let resolver = f.resolver().unwrap();
+    let img = resolver.resolve(4).unwrap().stream().unwrap();
+    let jpeg = img.decode_jpeg(&resolver).unwrap().unwrap();
+    assert_eq!(&[0xff, 0xd8], &jpeg[..2], "JPEG SOI marker");
+    assert_eq!(&[0xff, 0xd9], &jpeg[jpeg.len() - 2..], "JPEG EOI marker");
+
+    // not DCTDecode
+    let s_mask = resolver.resolve(5).unwrap().stream().unwrap();
+    assert_eq!(None, s_mask.decode_jpeg(&resolver).unwrap());
+}
+
+#[test]
+fn decode_jbig2_image() {
+    use md5::{Digest, Md5};
+
+    let f = open_test_file("sample_files/filters/jbig2.pdf");
+    let resolver = f.resolver().unwrap();
+    let img = resolver.resolve(5).unwrap().stream().unwrap();
+    let decoded = img.decode_image(&resolver, None).unwrap().into_luma8();
+    assert_eq!((48, 30), decoded.dimensions());
+    assert_eq!(
+        "e4d89a5ec32f6f6c48770ba048abd3ce",
+        hex::encode(Md5::digest(decoded.as_raw()))
+    );
+}
+
+/// Group 3 1D with `/EndOfLine` and `/EncodedByteAlign`.
+#[test]
+fn decode_ccitt_group3_eol() {
+    let f = open_test_file("sample_files/filters/ccitt-group3-eol.pdf");
+    let resolver = f.resolver().unwrap();
+    let img = resolver.resolve(5).unwrap().stream().unwrap();
+    let decoded = img.decode_image(&resolver, None).unwrap().into_luma8();
+    let rows: Vec<String> = decoded
+        .rows()
+        .map(|row| row.map(|p| if p.0[0] == 0 { 'x' } else { '.' }).collect())
+        .collect();
+    assert_eq!(
+        vec![
+            "....xxxxxxxx....",
+            "xxxx............",
+            "..xx............",
+            "xxxxxxxxxxxxxxxx",
+        ],
+        rows
+    );
+}
@@ -139,3 +139,39 @@ fn f32_arr_try_from_object() {
     let arr2: [f32; 2] = (&o).try_into().unwrap();
     assert_eq!([1.0f32, 2.0f32], arr2);
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_object() {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use serde_json::json;
+
+    let f = crate::file::open_test_file("sample_files/page/text-rect-image.pdf");
+    let resolver = f.resolver().unwrap();
+
+    let page = resolver.resolve(3).unwrap();
+    assert_eq!(
+        json!({
+            "Contents": {"ref": [4, 0]},
+            "MediaBox": [0, 0, 200, 100],
+            "Parent": {"ref": [2, 0]},
+            "Resources": {"Font": {"F1": {"ref": [5, 0]}}},
+            "Type": "/Page",
+        }),
+        serde_json::to_value(page).unwrap()
+    );
+
+    let s: Object = LiteralString::new(b"(Hi)").into();
+    assert_eq!(json!({"string": "Hi"}), serde_json::to_value(&s).unwrap());
+    let s: Object = HexString::new(b"<FF00>").into();
+    assert_eq!(json!({"hex": "ff00"}), serde_json::to_value(&s).unwrap());
+
+    // stream data only available with resolver
+    let content = resolver.resolve(4).unwrap();
+    let v = serde_json::to_value(content).unwrap();
+    assert_eq!(json!({"dict": {"Length": 122}}), v);
+    let v = serde_json::to_value(content.with_resolver(&resolver)).unwrap();
+    let data = STANDARD.decode(v["data"].as_str().unwrap()).unwrap();
+    assert_eq!(122, data.len());
+    assert!(data.starts_with(b"1 0 0 rg 10 10 60 60 re f"));
+}
@@ -26,7 +26,8 @@ pub fn parse_header(buf: &[u8]) -> ParseResult<Option<&str>> {
 
     fn new_header(buf: &[u8]) -> std::result::Result<Option<&str>, FileError> {
         assert_eq!(3, buf.len());
-        if buf[0] != b'1' {
+        // PDF 2.0 files, such as AES-256 encrypted ones, are mostly compatible
+        if !matches!(buf[0], b'1' | b'2') {
             Err(FileError::UnsupportedVersion(
                 String::from_utf8_lossy(buf).to_string(),
             ))
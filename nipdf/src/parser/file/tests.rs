@@ -4,11 +4,16 @@ use insta::assert_debug_snapshot;
 use test_case::test_case;
 use test_log::test;
 
+#[test_case(b"%PDF-1.7\n%comment\n" => Some("1.7"); "1.7")]
+#[test_case(b"%PDF-2.0\n" => Some("2.0"); "2.0")]
+#[test_case(b"%comment\n" => None; "no header")]
+fn parse_file_header(buf: &[u8]) -> Option<&str> {
+    parse_header(buf).unwrap().1
+}
+
 #[test]
-fn parse_file_header() {
-    let buf = b"%PDF-1.7\n%comment\n";
-    let (_, header) = parse_header(buf).unwrap();
-    assert_eq!(header, Some("1.7"));
+fn parse_file_header_unsupported_version() {
+    assert!(parse_header(b"%PDF-3.0\n").is_err());
 }
 
 #[test_case(None, b"hello", b"world"; "not exist")]
@@ -9,7 +9,12 @@ use bitflags::bitflags;
 use log::warn;
 use nipdf_macro::{TryFromIntObjectForBitflags, TryFromNameObject, pdf_object};
 use num_traits::ToPrimitive;
-use prescript::{Encoding, Name, name};
+use prescript::{
+    Encoding, Name,
+    cmap::{CMap, CMapRegistry},
+    name,
+};
+use std::rc::Rc;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, TryFromNameObject)]
 pub enum FontType {
@@ -57,6 +62,9 @@ pub trait FontDictTrait {
     fn widths(&self) -> Vec<u32>;
 
     fn base_font(&self) -> Name;
+
+    #[key("ToUnicode")]
+    fn to_unicode_stream(&self) -> Option<&'b Stream>;
 }
 
 #[pdf_object(("Font", "Type0"))]
@@ -134,6 +142,14 @@ impl<'a, 'b> FontDict<'a, 'b> {
         };
         desc.missing_width()
     }
+
+    /// Parsed `/ToUnicode` CMap, None if font has no `/ToUnicode`.
+    pub fn to_unicode(&self) -> AnyResult<Option<Rc<CMap>>> {
+        Ok(match self.to_unicode_stream()? {
+            Some(s) => Some(CMapRegistry::new().add_cmap_file(&s.decode(self.d.resolver())?)?),
+            None => None,
+        })
+    }
 }
 
 #[pdf_object(("Font", "TrueType"))]
@@ -177,7 +193,8 @@ pub enum CIDFontWidthGroup {
 #[derive(Debug, PartialEq)]
 pub struct CIDFontWidths(Vec<CIDFontWidthGroup>);
 impl CIDFontWidths {
-    /// Return None if ch out of range
+    /// Return None if ch out of range. If ranges overlap, the first group
+    /// containing `ch` wins, the same as Acrobat.
     pub(crate) fn char_width(&self, ch: u32) -> Option<u32> {
         for group in &self.0 {
             match group {
@@ -201,6 +218,12 @@ impl<'b> TryFrom<&'b Object> for CIDFontWidths {
     type Error = ObjectValueError;
 
     fn try_from(obj: &'b Object) -> Result<Self, Self::Error> {
+        let to_width = |o: &Object| {
+            o.as_number()?
+                .to_u16()
+                .ok_or(ObjectValueError::UnexpectedType)
+        };
+
         let mut widths = Vec::new();
         let Object::Array(arr) = obj else {
             return Err(Self::Error::UnexpectedType);
@@ -214,20 +237,19 @@ impl<'b> TryFrom<&'b Object> for CIDFontWidths {
                 Object::Array(arr) => {
                     let mut width = Vec::with_capacity(arr.len());
                     for num in arr.iter() {
-                        let num = num.as_number()?.to_u16().unwrap();
-                        width.push(num);
+                        width.push(to_width(num)?);
                     }
                     widths.push(CIDFontWidthGroup::NConsecutive((first as u32, width)));
                 }
-                Object::Integer(last) => {
+                last => {
+                    let last = last.as_int()?;
                     let width = iter.next().ok_or(Self::Error::UnexpectedType)?;
                     widths.push(CIDFontWidthGroup::FirstLast {
                         first: first as u32,
-                        last: *last as u32,
-                        width: width.as_number()?.to_u16().unwrap(),
+                        last: last as u32,
+                        width: to_width(width)?,
                     });
                 }
-                _ => return Err(Self::Error::UnexpectedType),
             }
         }
         Ok(CIDFontWidths(widths))
@@ -250,6 +272,17 @@ pub trait CIDFontDictTrait {
     #[key("CIDToGIDMap")]
     #[default_fn(NameOrStream::identity)]
     fn cid_to_gid_map(&self) -> NameOrStream<'b>;
+    #[nested]
+    #[key("CIDSystemInfo")]
+    fn cid_system_info(&self) -> Option<CIDSystemInfoDict<'a, 'b>>;
+}
+
+/// Identifies character collection of CID font.
+#[pdf_object(())]
+pub trait CIDSystemInfoDictTrait {
+    fn registry(&self) -> &str;
+    fn ordering(&self) -> &str;
+    fn supplement(&self) -> u32;
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, TryFromNameObject)]
@@ -385,7 +418,12 @@ impl<'b> TryFrom<&'b Object> for EncodingDifferences<'b> {
         for o in iter {
             match o {
                 Object::Name(name) => {
-                    map.insert(code.try_into().unwrap(), name.as_str());
+                    match u8::try_from(code) {
+                        Ok(ch) => {
+                            map.insert(ch, name.as_str());
+                        }
+                        Err(_) => warn!("skip Differences code out of 0..255: {}", code),
+                    }
                     code += 1;
                 }
                 Object::Integer(num) => {
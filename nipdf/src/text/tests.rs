@@ -24,4 +24,61 @@ fn try_from_object_encoding_differences() {
     assert_eq!(res.0[&1], "A");
     assert_eq!(res.0[&3], "B");
     assert_eq!(res.0[&4], "C");
+
+    // codes out of 0..255 skipped
+    let obj = Object::Array(
+        vec![
+            Object::Integer(300),
+            Object::Name(sname("X")),
+            Object::Integer(255),
+            Object::Name(sname("Y")),
+            Object::Name(sname("Z")),
+            Object::Integer(65),
+            Object::Name(sname("B")),
+        ]
+        .into(),
+    );
+    let res = EncodingDifferences::try_from(&obj).unwrap();
+    assert_eq!(res.0.len(), 2);
+    assert_eq!(res.0[&255], "Y");
+    assert_eq!(res.0[&65], "B");
+    let encoding = res.apply_differences(Encoding::STANDARD);
+    assert_eq!("B", encoding.get_str(65));
+    assert_eq!("B", encoding.get_str(66));
+}
+
+#[test]
+fn cid_font_widths() {
+    // `c [w1 w2 ...]` and `c_first c_last w` forms, range overlaps first list,
+    // real number last cid and width
+    let obj = Object::Array(
+        vec![
+            Object::Integer(1),
+            Object::Array(vec![Object::Integer(500), Object::Number(600.0)].into()),
+            Object::Integer(2),
+            Object::Number(10.0),
+            Object::Integer(300),
+            Object::Integer(20),
+            Object::Array(vec![Object::Integer(700)].into()),
+        ]
+        .into(),
+    );
+    let widths = CIDFontWidths::try_from(&obj).unwrap();
+    assert_eq!(None, widths.char_width(0));
+    assert_eq!(Some(500), widths.char_width(1));
+    assert_eq!(Some(600), widths.char_width(2));
+    assert_eq!(Some(300), widths.char_width(3));
+    assert_eq!(Some(300), widths.char_width(10));
+    assert_eq!(None, widths.char_width(11));
+    assert_eq!(Some(700), widths.char_width(20));
+    assert_eq!(None, widths.char_width(21));
+
+    // negative width
+    let obj =
+        Object::Array(vec![Object::Integer(1), Object::Integer(2), Object::Integer(-1)].into());
+    assert!(CIDFontWidths::try_from(&obj).is_err());
+
+    // missing width of range form
+    let obj = Object::Array(vec![Object::Integer(1), Object::Integer(2)].into());
+    assert!(CIDFontWidths::try_from(&obj).is_err());
 }
@@ -1,4 +1,5 @@
-//! Cmap to map CharCode to CID, used in Type0/CID font
+//! Cmap to map CharCode to CID, used in Type0/CID font, and to unicode by
+//! `bfchar`/`bfrange` entries, used as `/ToUnicode` of fonts.
 
 use crate::{
     Name,
@@ -41,6 +42,16 @@ impl CharCode {
         }
     }
 
+    /// Code of `n_bytes` bytes by its big-endian value, None if `v` not
+    /// fits in `n_bytes`.
+    fn from_value(v: u32, n_bytes: usize) -> Option<Self> {
+        let bytes = v.to_be_bytes();
+        let (high, low) = bytes.split_at(4 - n_bytes);
+        high.iter()
+            .all(|b| *b == 0)
+            .then(|| Self::from_str_buf(low))
+    }
+
     pub fn n_bytes(&self) -> usize {
         match self {
             Self::One(_) => 1,
@@ -51,15 +62,6 @@ impl CharCode {
     }
 }
 
-fn parse_cid_from_str_buf(s: &[u8]) -> CID {
-    let bytes = match s.len() {
-        1 => [0, s[0]],
-        2 => [s[0], s[1]],
-        _ => unreachable!(),
-    };
-    CID(u16::from_be_bytes(bytes))
-}
-
 impl AsRef<[u8]> for CharCode {
     fn as_ref(&self) -> &[u8] {
         use std::slice::from_raw_parts;
@@ -125,11 +127,14 @@ struct CodeRange(ArrayVec<[ByteRange; 4]>);
 
 impl CodeRange {
     fn from_str_buf(lower: &[u8], upper: &[u8]) -> Option<Self> {
-        if lower.len() != upper.len() {
+        if lower.len() != upper.len() || !(1..=4).contains(&lower.len()) {
             return None;
         }
         let mut r = ArrayVec::new();
         for (l, u) in lower.iter().copied().zip(upper.iter().copied()) {
+            if l > u {
+                return None;
+            }
             r.push(ByteRange::new(l, u));
         }
         Some(Self(r))
@@ -177,6 +182,21 @@ impl CodeRange {
     fn n_bytes(&self) -> usize {
         self.0.len()
     }
+
+    /// Iterate all codes in range, in offset order.
+    #[cfg(feature = "cjk")]
+    fn codes(&self) -> impl Iterator<Item = CharCode> + '_ {
+        let len = |r: &ByteRange| r.upper as u32 - r.lower as u32 + 1;
+        let n: u32 = self.0.iter().map(len).product();
+        (0..n).map(move |mut offset| {
+            let mut bytes = [0u8; 4];
+            for (i, r) in self.0.iter().enumerate().rev() {
+                bytes[i] = r.lower + u8::try_from(offset % len(r)).unwrap();
+                offset /= len(r);
+            }
+            CharCode::from(&bytes[..self.n_bytes()])
+        })
+    }
 }
 
 struct CodeRangeParser;
@@ -273,22 +293,6 @@ impl EntryParser<IncRangeMap> for IncRangeMapParser {
     }
 }
 
-struct BFIncRangeMapParser;
-
-impl EntryParser<IncRangeMap> for BFIncRangeMapParser {
-    fn parse_entry<P>(&self, m: &mut Machine<P>) -> Result<IncRangeMap, MachineError> {
-        let cid = parse_cid_from_str_buf(&m.pop()?.string()?.borrow());
-        let s_upper = m.pop()?.string()?;
-        let s_lower = m.pop()?.string()?;
-        let range = CodeRange::from_str_buf(&s_lower.borrow(), &s_upper.borrow())
-            .context(TypeCheckSnafu)?;
-        Ok(IncRangeMap {
-            range,
-            start_cid: cid,
-        })
-    }
-}
-
 /// Maps a range of codes to CID, all codes in range map to `cid`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct RangeMapToOne {
@@ -348,14 +352,132 @@ impl EntryParser<SingleCodeMap> for SingleCodeMapParser {
     }
 }
 
-struct BFSingleCodeMapParser;
+/// UTF-16BE units of `bfchar`/`bfrange` destination string.
+fn utf16_units(s: &[u8]) -> Box<[u16]> {
+    s.chunks_exact(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect()
+}
 
-impl EntryParser<SingleCodeMap> for BFSingleCodeMapParser {
-    fn parse_entry<P>(&self, m: &mut Machine<P>) -> Result<SingleCodeMap, MachineError> {
-        let cid = parse_cid_from_str_buf(&m.pop()?.string()?.borrow());
-        let s_code = m.pop()?.string()?;
-        let code = CharCode::from_str_buf(&s_code.borrow());
-        Ok(SingleCodeMap::new(code, cid))
+/// String of UTF-16 `units`, None if empty.
+fn utf16_string(units: &[u16]) -> Option<String> {
+    (!units.is_empty()).then(|| String::from_utf16_lossy(units))
+}
+
+/// Maps a single code to unicode, one code may map to several chars, such
+/// as ligatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UnicodeCharMap {
+    code: CharCode,
+    dest: Box<[u16]>,
+}
+
+impl UnicodeCharMap {
+    fn map(&self, code: CharCode) -> Option<String> {
+        (code == self.code).then(|| utf16_string(&self.dest))?
+    }
+}
+
+/// Destination of `bfrange` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum UnicodeRangeDest {
+    /// First code in range maps to the units, later codes increment the last
+    /// unit by offset.
+    Inc(Box<[u16]>),
+    /// Nth code in range maps to nth item.
+    Array(Box<[Box<[u16]>]>),
+}
+
+/// Maps a range of codes to unicode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UnicodeRangeMap {
+    range: CodeRange,
+    dest: UnicodeRangeDest,
+}
+
+impl UnicodeRangeMap {
+    fn map(&self, code: CharCode) -> Option<String> {
+        let offset = self.range.offset(code)?;
+        match &self.dest {
+            UnicodeRangeDest::Inc(units) => {
+                let (last, prefix) = units.split_last()?;
+                let mut units = prefix.to_vec();
+                units.push(last.checked_add(offset)?);
+                utf16_string(&units)
+            }
+            UnicodeRangeDest::Array(dests) => utf16_string(dests.get(usize::from(offset))?),
+        }
+    }
+}
+
+/// Parse `bfchar` entry, None if code is not 1-4 bytes or destination is not
+/// a string.
+struct UnicodeCharMapParser;
+
+impl EntryParser<Option<UnicodeCharMap>> for UnicodeCharMapParser {
+    fn parse_entry<P>(&self, m: &mut Machine<P>) -> Result<Option<UnicodeCharMap>, MachineError> {
+        let dest = m.pop()?.opt_string();
+        let code = m.pop()?.opt_string();
+        Ok(code.zip(dest).and_then(|(code, dest)| {
+            let code = code.borrow();
+            (1..=4).contains(&code.len()).then(|| UnicodeCharMap {
+                code: CharCode::from_str_buf(&code),
+                dest: utf16_units(&dest.borrow()),
+            })
+        }))
+    }
+}
+
+/// Parse `bfrange` entry, None if range or destination is malformed.
+struct UnicodeRangeMapParser;
+
+impl EntryParser<Option<UnicodeRangeMap>> for UnicodeRangeMapParser {
+    fn parse_entry<P>(&self, m: &mut Machine<P>) -> Result<Option<UnicodeRangeMap>, MachineError> {
+        let dest = m.pop()?;
+        let s_upper = m.pop()?.opt_string();
+        let s_lower = m.pop()?.opt_string();
+        let dest = if let Some(s) = dest.opt_string() {
+            UnicodeRangeDest::Inc(utf16_units(&s.borrow()))
+        } else if let Some(arr) = dest.opt_array() {
+            UnicodeRangeDest::Array(
+                arr.borrow()
+                    .iter()
+                    .map(|v| {
+                        v.opt_string()
+                            .map_or_else(Box::default, |s| utf16_units(&s.borrow()))
+                    })
+                    .collect(),
+            )
+        } else {
+            return Ok(None);
+        };
+        Ok(s_lower
+            .zip(s_upper)
+            .and_then(|(lower, upper)| CodeRange::from_str_buf(&lower.borrow(), &upper.borrow()))
+            .map(|range| UnicodeRangeMap { range, dest }))
+    }
+}
+
+/// Unicode maps of `bfchar` and `bfrange` entries, single code maps has
+/// higher priority than range maps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct UnicodeMapper {
+    ranges: Box<[UnicodeRangeMap]>,
+    chars: Box<[UnicodeCharMap]>,
+}
+
+impl UnicodeMapper {
+    /// Map code by its value, each entry takes `code` in its own bytes length.
+    fn map(&self, code: u32) -> Option<String> {
+        let find_in_chars = self
+            .chars
+            .iter()
+            .filter_map(|m| m.map(CharCode::from_value(code, m.code.n_bytes())?));
+        let find_in_ranges = self
+            .ranges
+            .iter()
+            .filter_map(|m| m.map(CharCode::from_value(code, m.range.n_bytes())?));
+        find_in_chars.chain(find_in_ranges).next()
     }
 }
 
@@ -376,6 +498,21 @@ impl<R: CodeMap> CodeMap for Mapper<R> {
     }
 }
 
+#[cfg(feature = "cjk")]
+impl Mapper<IncRangeMap> {
+    /// Iterate all (code, cid) pairs, single code maps first.
+    fn mappings(&self) -> impl Iterator<Item = (CharCode, CID)> + '_ {
+        let chars = self.chars.iter().map(|m| (m.code, m.cid));
+        let ranges = self.ranges.iter().flat_map(|m| {
+            m.range
+                .codes()
+                .zip(m.start_cid.0..)
+                .map(|(code, cid)| (code, CID(cid)))
+        });
+        chars.chain(ranges)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
 pub struct CIDSystemInfo {
     registry: String,
@@ -559,11 +696,49 @@ static PREDEFINED_CMAPS: phf::Map<&'static str, &'static [u8]> = phf_map! {
     "Identity-V" => IDENTITY_V,
 };
 
+/// Maps CID of Adobe CJK character collection to unicode char, built by
+/// inverting predefined `Uni*-UTF16-H` CMap.
+#[cfg(feature = "cjk")]
+#[derive(Debug, PartialEq, Eq)]
+pub struct CIDToUnicode(HashMap<CID, char>);
+
+#[cfg(feature = "cjk")]
+impl CIDToUnicode {
+    /// Invert `cmap` whose char codes are UTF-16BE, if several chars map to
+    /// the same CID, the one with smallest code point wins.
+    fn from_unicode_cmap(cmap: &CMap) -> Self {
+        let mut r = HashMap::new();
+        let mut cmap = Some(cmap);
+        while let Some(c) = cmap {
+            for (code, cid) in c.cid_map.mappings() {
+                let units = code
+                    .as_ref()
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]));
+                let Some(Ok(ch)) = char::decode_utf16(units).next() else {
+                    continue;
+                };
+                r.entry(cid)
+                    .and_modify(|v: &mut char| *v = (*v).min(ch))
+                    .or_insert(ch);
+            }
+            cmap = c.use_map.as_deref();
+        }
+        Self(r)
+    }
+
+    pub fn get(&self, cid: CID) -> Option<char> {
+        self.0.get(&cid).copied()
+    }
+}
+
 /// CMapRegistry contains all CMaps, access by CMap Name.
 #[derive(Debug)]
 pub struct CMapRegistry {
     predefined: HashMap<&'static str, OnceCell<Rc<CMap>>>,
     files: HashMap<Name, Rc<CMap>>,
+    #[cfg(feature = "cjk")]
+    cid_to_unicode: HashMap<&'static str, Option<Rc<CIDToUnicode>>>,
 }
 
 impl Default for CMapRegistry {
@@ -581,6 +756,8 @@ impl CMapRegistry {
                 .map(|k| (k, OnceCell::new())))
             .collect(),
             files: HashMap::new(),
+            #[cfg(feature = "cjk")]
+            cid_to_unicode: HashMap::new(),
         }
     }
 
@@ -600,6 +777,30 @@ impl CMapRegistry {
             .or_else(|| self.files.get(name).cloned())
     }
 
+    /// Get CID to unicode map of Adobe CJK character collection, returns
+    /// `None` if `registry` is not `Adobe` or `ordering` is not one of `GB1`,
+    /// `CNS1`, `Japan1` and `Korea1`.
+    #[cfg(feature = "cjk")]
+    pub fn cid_to_unicode(&mut self, registry: &str, ordering: &str) -> Option<Rc<CIDToUnicode>> {
+        if registry != "Adobe" {
+            return None;
+        }
+        let cmap_name = match ordering {
+            "GB1" => "UniGB-UTF16-H",
+            "CNS1" => "UniCNS-UTF16-H",
+            "Japan1" => "UniJIS-UTF16-H",
+            "Korea1" => "UniKS-UTF16-H",
+            _ => return None,
+        };
+        if !self.cid_to_unicode.contains_key(cmap_name) {
+            let r = self
+                .get(&sname(cmap_name))
+                .map(|cmap| Rc::new(CIDToUnicode::from_unicode_cmap(&cmap)));
+            self.cid_to_unicode.insert(cmap_name, r);
+        }
+        self.cid_to_unicode[cmap_name].clone()
+    }
+
     fn parse_cmap_file(&self, file: &[u8]) -> Result<CMap, MachineError> {
         let p = CMapMachinePlugin {
             registry: self,
@@ -610,12 +811,14 @@ impl CMapRegistry {
             cid_char_entries: Default::default(),
             notdef_range_entries: Default::default(),
             notdef_char_entries: Default::default(),
+            unicode_range_entries: Default::default(),
+            unicode_char_entries: Default::default(),
             use_cmap: None,
         };
         let mut m = Machine::<CMapMachinePlugin>::with_plugin(file, p);
         m.execute()?;
         let mut p = m.take_plugin();
-        Ok(p.parsed.take().expect("CMap not defined in cmap file"))
+        p.parsed.take().context(UndefinedSnafu)
     }
 
     /// Add a CMap file, parse it and add to registry.
@@ -640,6 +843,7 @@ pub struct CMap {
     code_space: CodeSpace,
     cid_map: Mapper<IncRangeMap>,
     notdef_map: Mapper<RangeMapToOne>,
+    unicode_map: UnicodeMapper,
     use_map: Option<Rc<CMap>>,
 }
 
@@ -661,6 +865,38 @@ impl CMap {
         r
     }
 
+    /// Code space ranges, each item is (lower, upper) bytes of the range,
+    /// nth byte of a code in the range is between nth byte of lower and upper.
+    pub fn code_space_ranges(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.code_space
+            .0
+            .iter()
+            .map(|r| r.0.iter().map(|b| (b.lower, b.upper)).unzip())
+            .collect()
+    }
+
+    /// Take next char code from `codes` by code space, returns the code and
+    /// the number of bytes consumed. Code not in code space consumes bytes of
+    /// the shortest code space range.
+    ///
+    /// Returns None if `codes` is empty.
+    pub fn map_next(&self, codes: &[u8]) -> Option<(u32, usize)> {
+        if codes.is_empty() {
+            return None;
+        }
+        if let (true, Some(use_map)) = (self.code_space.0.is_empty(), &self.use_map) {
+            return use_map.map_next(codes);
+        }
+
+        let (rest, code) = self.code_space.next_code(codes);
+        let code = code
+            .into_inner()
+            .as_ref()
+            .iter()
+            .fold(0, |r, b| r << 8 | *b as u32);
+        Some((code, codes.len() - rest.len()))
+    }
+
     /// Get next cid, update codes buffer, without map notdef.
     /// If use_map not null, recover codes buffer, call next_cid.
     fn next_cid<'a>(&self, codes: &'a [u8]) -> (&'a [u8], Either<CharCode, CID>) {
@@ -676,6 +912,15 @@ impl CMap {
             .into_inner()
     }
 
+    /// Unicode of char `code` by `bfchar` and `bfrange` entries, used as
+    /// `/ToUnicode` of fonts, see PDF 32000-1:2008 9.10.3. Returns None if
+    /// `code` not mapped.
+    pub fn to_unicode(&self, code: u32) -> Option<String> {
+        self.unicode_map
+            .map(code)
+            .or_else(|| self.use_map.as_ref()?.to_unicode(code))
+    }
+
     /// Map undef cid, if notdef_map failed, call use_map.map_undef() if has use_map
     fn map_undef(&self, ch: CharCode) -> CID {
         self.notdef_map.map(ch).unwrap_or_else(|| {
@@ -742,6 +987,8 @@ struct CMapMachinePlugin<'a> {
     cid_char_entries: EntriesParser<SingleCodeMap>,
     notdef_range_entries: EntriesParser<RangeMapToOne>,
     notdef_char_entries: EntriesParser<SingleCodeMap>,
+    unicode_range_entries: EntriesParser<UnicodeRangeMap>,
+    unicode_char_entries: EntriesParser<UnicodeCharMap>,
 }
 
 macro_rules! built_in_ops {
@@ -791,8 +1038,8 @@ impl<'a> MachinePlugin for CMapMachinePlugin<'a> {
                     ok()
                 },
                 "endbfrange" => |m| {
-                    let entries = m.p.entries_parsing.take().unwrap().on_end(BFIncRangeMapParser, m)?;
-                    m.p.cid_range_entries.extend(entries);
+                    let entries = m.p.entries_parsing.take().unwrap().on_end(UnicodeRangeMapParser, m)?;
+                    m.p.unicode_range_entries.extend(entries.into_iter().flatten().collect());
                     ok()
                 },
                 "begincidchar" => |m| {
@@ -809,8 +1056,8 @@ impl<'a> MachinePlugin for CMapMachinePlugin<'a> {
                     ok()
                 },
                 "endbfchar" => |m| {
-                    let entries = m.p.entries_parsing.take().unwrap().on_end(BFSingleCodeMapParser, m)?;
-                    m.p.cid_char_entries.extend(entries);
+                    let entries = m.p.entries_parsing.take().unwrap().on_end(UnicodeCharMapParser, m)?;
+                    m.p.unicode_char_entries.extend(entries.into_iter().flatten().collect());
                     ok()
                 },
                 "beginnotdefrange" => |m| {
@@ -838,8 +1085,15 @@ impl<'a> MachinePlugin for CMapMachinePlugin<'a> {
                     let d_ref = d.borrow();
                     let cmap_name = m.pop()?.name()?;
                     let cmap = CMap {
-                        cid_system_info: CIDSystemInfo::from_dict(&d_ref[&sname("CIDSystemInfo")].dict()?.borrow())?,
-                        w_mode: WriteMode::parse(d_ref[&sname("WMode")].int()?)?,
+                        // optional in `/ToUnicode` CMaps
+                        cid_system_info: match d_ref.get(&sname("CIDSystemInfo")) {
+                            Some(v) => CIDSystemInfo::from_dict(&v.dict()?.borrow())?,
+                            None => CIDSystemInfo::default(),
+                        },
+                        w_mode: match d_ref.get(&sname("WMode")) {
+                            Some(v) => WriteMode::parse(v.int()?)?,
+                            None => WriteMode::default(),
+                        },
                         name: cmap_name,
                         code_space: CodeSpace::new(m.p.code_space_entries.take()),
                         cid_map: Mapper {
@@ -850,6 +1104,10 @@ impl<'a> MachinePlugin for CMapMachinePlugin<'a> {
                             ranges: m.p.notdef_range_entries.take().into(),
                             chars: m.p.notdef_char_entries.take().into(),
                         },
+                        unicode_map: UnicodeMapper {
+                            ranges: m.p.unicode_range_entries.take().into(),
+                            chars: m.p.unicode_char_entries.take().into(),
+                        },
                         use_map: m.p.use_cmap.take(),
                     };
                     m.p.parsed = Some(cmap);
@@ -31,15 +31,6 @@ fn char_code_as_byte_slice() {
     assert_eq!(&[0xD8, 0x00, 0xDC, 0x00][..], four(0xD800DC00).as_ref());
 }
 
-#[test]
-fn test_parse_cid_from_str_buf() {
-    assert_eq!(parse_cid_from_str_buf(&[0x0u8]), CID(0));
-    assert_eq!(parse_cid_from_str_buf(&[0x1u8]), CID(1));
-    assert_eq!(parse_cid_from_str_buf(&[0x10u8]), CID(16));
-    // two bytes
-    assert_eq!(parse_cid_from_str_buf(&[0x81, 0x40]), CID(0x8140));
-}
-
 #[test]
 fn code_range_parse() {
     assert_eq!(
@@ -258,6 +249,7 @@ fn cmap() {
         code_space,
         cid_map,
         notdef_map,
+        unicode_map: Default::default(),
         use_map: None,
     };
 
@@ -310,6 +302,7 @@ fn use_map() {
         code_space: base_code_space,
         cid_map: base_cid_map,
         notdef_map: base_notdef_map,
+        unicode_map: Default::default(),
         use_map: None,
     };
 
@@ -320,6 +313,7 @@ fn use_map() {
         code_space,
         cid_map,
         notdef_map,
+        unicode_map: Default::default(),
         use_map: Some(Rc::new(use_map)),
     };
 
@@ -465,18 +459,24 @@ end
 fn parse_bf_char() {
     let mut reg = CMapRegistry::new();
 
+    // ligature, surrogate pair, and code longer than 4 bytes skipped
     let cmap_data = create_test_cmap_data(
         r#"
-2 beginbfchar
-<03> <00>
-<04> <01>
+4 beginbfchar
+<03> <0041>
+<04> <00660066>
+<8741> <D83DDE00>
+<0000000005> <0042>
 endbfchar
 "#,
     );
     let cmap = reg.add_cmap_file(&cmap_data).unwrap();
-    assert_eq!(2, cmap.cid_map.chars.len());
-    assert_eq!(SingleCodeMap::new(one(0x03), CID(0)), cmap.cid_map.chars[0]);
-    assert_eq!(SingleCodeMap::new(one(0x04), CID(1)), cmap.cid_map.chars[1]);
+    assert_eq!(3, cmap.unicode_map.chars.len());
+    assert!(cmap.cid_map.chars.is_empty());
+    assert_eq!(Some("A".to_owned()), cmap.to_unicode(3));
+    assert_eq!(Some("ff".to_owned()), cmap.to_unicode(4));
+    assert_eq!(Some("😀".to_owned()), cmap.to_unicode(0x8741));
+    assert_eq!(None, cmap.to_unicode(5));
 }
 
 #[test]
@@ -485,27 +485,78 @@ fn parse_bf_range() {
 
     let cmap_data = create_test_cmap_data(
         r#"
-2 beginbfrange
-<03> <05> <00>
-<06> <08> <01>
+3 beginbfrange
+<03> <05> <0041>
+<8740> <8741> [<0061> <00620063>]
+<06> <04> <0041>
 endbfrange
 "#,
     );
     let cmap = reg.add_cmap_file(&cmap_data).unwrap();
-    assert_eq!(2, cmap.cid_map.ranges.len());
+    assert_eq!(2, cmap.unicode_map.ranges.len());
+    assert!(cmap.cid_map.ranges.is_empty());
+    assert_eq!(Some("A".to_owned()), cmap.to_unicode(3));
+    assert_eq!(Some("C".to_owned()), cmap.to_unicode(5));
+    assert_eq!(None, cmap.to_unicode(6));
+    assert_eq!(Some("a".to_owned()), cmap.to_unicode(0x8740));
+    assert_eq!(Some("bc".to_owned()), cmap.to_unicode(0x8741));
+}
+
+#[test]
+fn parse_to_unicode_cmap_without_wmode() {
+    let mut reg = CMapRegistry::new();
+    let cmap = reg
+        .add_cmap_file(
+            br#"/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def
+/CMapName /Adobe-Identity-UCS def
+/CMapType 2 def
+1 begincodespacerange <0000> <FFFF> endcodespacerange
+1 beginbfchar <0001> <0041> endbfchar
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end"#,
+        )
+        .unwrap();
+    assert_eq!(WriteMode::Horizontal, cmap.w_mode);
+    assert_eq!(Some("A".to_owned()), cmap.to_unicode(1));
+}
+
+#[test]
+fn map_next_by_code_space() {
+    let mut reg = CMapRegistry::new();
+    let cmap = reg.add_cmap_file(&create_test_cmap_data("")).unwrap();
     assert_eq!(
-        IncRangeMap {
-            range: CodeRange::from_str_buf(&[0x03], &[0x05]).unwrap(),
-            start_cid: CID(0),
-        },
-        cmap.cid_map.ranges[0]
+        vec![
+            (vec![0x00], vec![0x80]),
+            (vec![0x87, 0x40], vec![0xfe, 0xfe])
+        ],
+        cmap.code_space_ranges()
     );
+
+    let mut codes = &[0x41u8, 0x87, 0x40, 0x20, 0x90, 0x10, 0x81, 0xa0][..];
+    let mut r = vec![];
+    while let Some((code, n)) = cmap.map_next(codes) {
+        r.push((code, n));
+        codes = &codes[n..];
+    }
+    assert_eq!(None, cmap.map_next(codes));
     assert_eq!(
-        IncRangeMap {
-            range: CodeRange::from_str_buf(&[0x06], &[0x08]).unwrap(),
-            start_cid: CID(1),
-        },
-        cmap.cid_map.ranges[1]
+        vec![
+            (0x41, 1),
+            (0x8740, 2),
+            (0x20, 1),
+            // partial matched, second byte out of range
+            (0x9010, 2),
+            // not in code space
+            (0x81, 1),
+            // partial matched at end of input
+            (0xa0, 1),
+        ],
+        r
     );
 }
 
@@ -534,3 +585,35 @@ fn identity_h_map() {
     let cmap = reg.get(&sname("Identity-H")).unwrap();
     assert_eq!(vec![CID(0x04)], cmap.map(&[0x0, 0x04u8]));
 }
+
+#[cfg(feature = "cjk")]
+#[test]
+fn cid_to_unicode_from_unicode_cmap() {
+    let mut reg = CMapRegistry::new();
+    let cmap_data = create_test_cmap_data(
+        r#"
+2 begincidrange
+<0041> <0043> 34
+<4e00> <4e01> 1200
+endcidrange
+3 begincidchar
+<3000> 1
+<0020> 1
+<d840dc0b> 13698
+endcidchar
+"#,
+    );
+    let cmap = reg.add_cmap_file(&cmap_data).unwrap();
+    let m = CIDToUnicode::from_unicode_cmap(&cmap);
+    assert_eq!(Some('A'), m.get(CID(34)));
+    assert_eq!(Some('C'), m.get(CID(36)));
+    assert_eq!(Some('\u{4e01}'), m.get(CID(1201)));
+    // smallest code point wins
+    assert_eq!(Some(' '), m.get(CID(1)));
+    // surrogate pair
+    assert_eq!(Some('\u{2000b}'), m.get(CID(13698)));
+    assert_eq!(None, m.get(CID(2)));
+
+    assert_eq!(None, reg.cid_to_unicode("Adobe", "Identity"));
+    assert_eq!(None, reg.cid_to_unicode("Testing", "Japan1"));
+}
@@ -6,6 +6,7 @@ mod encoding;
 mod pdf_fn;
 mod type1;
 pub use encoding::Encoding;
+pub use machine::DEFAULT_MAX_RECURSION;
 pub use pdf_fn::PdfFunc;
 pub use type1::Font;
 
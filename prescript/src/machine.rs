@@ -466,6 +466,8 @@ pub enum MachineError {
     RangeCheck,
     #[snafu(display("syntax error"))]
     SyntaxError { source: Whatever },
+    #[snafu(display("procedure nesting exceeds {limit}"))]
+    ExecStackOverflow { limit: usize },
 }
 
 pub type MachineResult<T> = Result<T, MachineError>;
@@ -585,12 +587,18 @@ impl MachinePlugin for () {
     }
 }
 
+/// Default max nesting of procedure execution, see [`Machine::set_max_recursion()`].
+pub const DEFAULT_MAX_RECURSION: usize = 100;
+
 /// PostScript machine to execute operations.
 pub struct Machine<'a, P> {
     file: Rc<RefCell<CurrentFile<'a>>>,
     variable_stack: VariableDictStack<'a, P>,
     stack: Vec<RuntimeValue<'a, P>>,
     fonts: Vec<(String, Dictionary)>,
+    /// Nesting of procedures in execution.
+    depth: usize,
+    max_recursion: usize,
     pub p: P,
 }
 
@@ -608,6 +616,14 @@ impl<'a, P> Machine<'a, P> {
     pub fn take_plugin(self) -> P {
         self.p
     }
+
+    /// Max nesting of procedure execution, such as procedure calls itself,
+    /// or bodies of `if` and `for` nested deeply. Execution fails with
+    /// [`MachineError::ExecStackOverflow`] if exceeded, instead of
+    /// overflowing the native stack on untrusted scripts.
+    pub fn set_max_recursion(&mut self, max_recursion: usize) {
+        self.max_recursion = max_recursion;
+    }
 }
 
 impl<'a> Machine<'a, ()> {
@@ -617,6 +633,8 @@ impl<'a> Machine<'a, ()> {
             variable_stack: VariableDictStack::new(),
             stack: Vec::new(),
             fonts: vec![],
+            depth: 0,
+            max_recursion: DEFAULT_MAX_RECURSION,
             p: (),
         }
     }
@@ -629,6 +647,8 @@ impl<'a, P: MachinePlugin> Machine<'a, P> {
             variable_stack: VariableDictStack::new(),
             stack: Vec::new(),
             fonts: vec![],
+            depth: 0,
+            max_recursion: DEFAULT_MAX_RECURSION,
             p,
         }
     }
@@ -759,14 +779,20 @@ impl<'a, P> Machine<'a, P> {
     }
 
     fn execute_procedure(&mut self, proc: Rc<RefCell<TokenArray>>) -> MachineResult<ExecState> {
-        for token in proc.borrow().iter().cloned() {
+        ensure!(self.depth < self.max_recursion, ExecStackOverflowSnafu {
+            limit: self.max_recursion
+        });
+        self.depth += 1;
+        let r = proc.borrow().iter().cloned().try_for_each(|token| {
             assert_eq!(
                 self.exec(token)?,
                 ExecState::Ok,
                 "procedure should not return StartEExec or EndEExec"
             );
-        }
-        Ok(ExecState::Ok)
+            Ok(())
+        });
+        self.depth -= 1;
+        r.map(|_| ExecState::Ok)
     }
 
     fn dump_stack(&self) {
@@ -880,9 +906,9 @@ fn system_dict<'a, P: MachinePlugin>() -> RuntimeDictionary<'a, P> {
         // any(n) ... any0 n index -> any(n) ... any0 any(n)
         sname("index") => |m| {
             let index = m.pop()?.int()?;
-            m.push(m.stack.get(m.stack.len() - index as usize - 1)
-                .context(StackUnderflowSnafu)?
-                .clone());
+            let index = usize::try_from(index).ok().context(RangeCheckSnafu)?;
+            ensure!(index < m.stack.len(), RangeCheckSnafu);
+            m.push(m.stack[m.stack.len() - index - 1].clone());
             ok()
         },
 
@@ -890,19 +916,16 @@ fn system_dict<'a, P: MachinePlugin>() -> RuntimeDictionary<'a, P> {
         sname("roll") => |m| {
             let j = m.pop()?.int()?;
             let n = m.pop()?.int()?;
-            let mut items = Vec::new();
-            for _ in 0..n {
-                items.push(m.pop()?);
-            }
-            items.reverse();
-            if j > 0 {
-                items.rotate_right(j as usize);
-            } else {
-                items.rotate_left(-j as usize);
-            }
-            for item in items {
-                m.push(item);
+            ensure!(n >= 0, RangeCheckSnafu);
+            if n == 0 {
+                return ok();
             }
+            let j = j.rem_euclid(n) as usize;
+            let n = n as usize;
+            ensure!(n <= m.stack.len(), StackUnderflowSnafu);
+
+            let start = m.stack.len() - n;
+            m.stack[start..].rotate_right(j);
             ok()
         },
 
@@ -1132,7 +1155,7 @@ fn system_dict<'a, P: MachinePlugin>() -> RuntimeDictionary<'a, P> {
         sname("idiv") => |m| {
             let b = m.pop()?.int()?;
             let a = m.pop()?.int()?;
-            m.push(a / b);
+            m.push(a.checked_div(b).context(RangeCheckSnafu)?);
             ok()
         },
 
@@ -1140,6 +1163,7 @@ fn system_dict<'a, P: MachinePlugin>() -> RuntimeDictionary<'a, P> {
         sname("div") => |m| {
             let b = m.pop()?.number()?;
             let a = m.pop()?.number()?;
+            ensure!(b.either(|b| b != 0, |b| b != 0.0), RangeCheckSnafu);
             match (a, b) {
                 (Either::Left(a), Either::Left(b)) => m.push(a as f32 / b as f32),
                 (Either::Right(a), Either::Right(b)) => m.push(a / b),
@@ -1153,7 +1177,7 @@ fn system_dict<'a, P: MachinePlugin>() -> RuntimeDictionary<'a, P> {
         sname("mod") => |m| {
             let b = m.pop()?.int()?;
             let a = m.pop()?.int()?;
-            m.push(a % b);
+            m.push(a.checked_rem(b).context(RangeCheckSnafu)?);
             ok()
         },
 
@@ -1,6 +1,7 @@
 use super::*;
 use crate::sname;
 use assert_approx_eq::assert_approx_eq;
+use test_case::test_case;
 use test_log::test;
 
 trait Assert<'a, P = ()> {
@@ -309,6 +310,8 @@ fn test_array() {
 #[test]
 fn test_index() {
     assert_op("1 2 3 4 5 3 index", Stack(rt_values![1, 2, 3, 4, 5, 2]));
+    assert_op("1 2 3 4 5 0 index", Stack(rt_values![1, 2, 3, 4, 5, 5]));
+    assert_op("1 2 3 4 5 4 index", Stack(rt_values![1, 2, 3, 4, 5, 1]));
 }
 
 #[test]
@@ -428,6 +431,19 @@ fn idiv() {
     assert_op("-10 -3 idiv", 3);
 }
 
+#[test_case("10 0 idiv"; "idiv by zero")]
+#[test_case("-2147483648 -1 idiv"; "idiv overflow")]
+#[test_case("10 0 mod"; "mod by zero")]
+#[test_case("10 0 div"; "div by integer zero")]
+#[test_case("10 0.0 div"; "div by real zero")]
+fn divide_error(s: &str) {
+    let mut machine = Machine::new(s.as_bytes());
+    assert!(matches!(
+        machine.execute().unwrap_err(),
+        MachineError::RangeCheck
+    ));
+}
+
 #[test]
 fn div() {
     assert_op("10 3 div", 3.3333333);
@@ -560,4 +576,39 @@ fn roll() {
     assert_op("1 2 3 3 -1 roll", Stack(rt_values![2, 3, 1]));
     assert_op("1 2 3 3 1 roll", Stack(rt_values![3, 1, 2]));
     assert_op("1 2 3 3 0 roll", Stack(rt_values![1, 2, 3]));
+    assert_op("1 2 3 3 4 roll", Stack(rt_values![3, 1, 2]));
+    assert_op("1 2 3 3 -4 roll", Stack(rt_values![2, 3, 1]));
+    assert_op("1 2 3 2 1 roll", Stack(rt_values![1, 3, 2]));
+    assert_op("1 2 3 0 1 roll", Stack(rt_values![1, 2, 3]));
+    assert_op("1 0 -1 roll", Stack(rt_values![1]));
+}
+
+#[test_case("1 2 -1 1 roll" => matches MachineError::RangeCheck; "negative n")]
+#[test_case("1 2 3 1 roll" => matches MachineError::StackUnderflow; "n exceeds stack")]
+#[test_case("1 2 -1 index" => matches MachineError::RangeCheck; "negative index")]
+#[test_case("1 2 2 index" => matches MachineError::RangeCheck; "index out of range")]
+#[test_case("0 index" => matches MachineError::RangeCheck; "index on empty stack")]
+fn stack_op_error(s: &str) -> MachineError {
+    let mut machine = Machine::new(s.as_bytes());
+    machine.execute().unwrap_err()
+}
+
+#[test]
+fn recursive_procedure_exceeds_max_recursion() {
+    let mut machine = Machine::new(b"/f { f } def f");
+    assert!(matches!(
+        machine.execute(),
+        Err(MachineError::ExecStackOverflow {
+            limit: DEFAULT_MAX_RECURSION
+        })
+    ));
+}
+
+#[test_case(4 => matches Ok(()); "within limit")]
+#[test_case(3 => matches Err(MachineError::ExecStackOverflow { limit: 3 }); "exceeds limit")]
+fn nested_procedure_max_recursion(max_recursion: usize) -> MachineResult<()> {
+    // four nested procedures
+    let mut machine = Machine::new(b"{ true { true { true { 1 } if } if } if } exec");
+    machine.set_max_recursion(max_recursion);
+    machine.execute()
 }
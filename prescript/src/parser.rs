@@ -305,7 +305,7 @@ fn procedure(input: &mut &[u8]) -> PResult<TokenArray> {
 
 /// Parses '[', ']', '<<', '>>' and convert them to String.
 fn special_name<'a>(input: &mut &'a [u8]) -> PResult<&'a str> {
-    let buf = take_while(1..=2, (b'[', ']', b"<<", b">>")).parse_next(input)?;
+    let buf = alt((b"<<", b">>", b"[", b"]")).parse_next(input)?;
     Ok(unsafe { from_utf8_unchecked(buf) })
 }
 
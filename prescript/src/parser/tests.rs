@@ -150,6 +150,7 @@ fn test_literal_name<'a>(buf: &'a str, remains: &'a str) -> &'a str {
 #[test_case("{}"=> tokens![]; "empty")]
 #[test_case("{ { } }"=> tokens![tokens![]]; "nested empty")]
 #[test_case("{ 10 1.5 ($) [/foo] }"=> tokens![10, 1.5, *b"$", name_token("["), "foo", name_token("]")]; "values")]
+#[test_case("{ [<01>] [[]] }"=> tokens![name_token("["), *b"\x01", name_token("]"), name_token("["), name_token("["), name_token("]"), name_token("]")]; "brackets")]
 fn test_procedure(buf: &str) -> TokenArray {
     procedure.parse(buf.as_bytes()).unwrap()
 }
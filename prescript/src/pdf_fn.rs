@@ -1,29 +1,66 @@
 //! Implement Pdf Type4 PostScript function
-use crate::machine::{Machine, MachineError};
+use crate::machine::{DEFAULT_MAX_RECURSION, Machine, MachineError};
 
 pub struct PdfFunc {
     script: Box<[u8]>,
-    n_out: usize,
+    domain: Box<[(f32, f32)]>,
+    range: Box<[(f32, f32)]>,
+    max_recursion: usize,
 }
 
 impl PdfFunc {
     /// Create a new PdfFunc.
     /// `script`: PostScript script.
-    /// `n_out`: number of return value.
-    pub fn new(script: impl Into<Box<[u8]>>, n_out: usize) -> Self {
+    /// `domain`: `(min, max)` of each input argument.
+    /// `range`: `(min, max)` of each return value.
+    pub fn new(
+        script: impl Into<Box<[u8]>>,
+        domain: impl Into<Box<[(f32, f32)]>>,
+        range: impl Into<Box<[(f32, f32)]>>,
+    ) -> Self {
         Self {
             script: script.into(),
-            n_out,
+            domain: domain.into(),
+            range: range.into(),
+            max_recursion: DEFAULT_MAX_RECURSION,
         }
     }
 
+    /// Set max nesting of procedure execution, execution fails if exceeded,
+    /// default to [`DEFAULT_MAX_RECURSION`].
+    pub fn with_max_recursion(mut self, max_recursion: usize) -> Self {
+        self.max_recursion = max_recursion;
+        self
+    }
+
+    /// Number of input arguments.
+    pub fn num_inputs(&self) -> usize {
+        self.domain.len()
+    }
+
+    /// Number of return values.
+    pub fn num_outputs(&self) -> usize {
+        self.range.len()
+    }
+
+    /// `(min, max)` of each input argument.
+    pub fn domain(&self) -> &[(f32, f32)] {
+        &self.domain
+    }
+
+    /// `(min, max)` of each return value.
+    pub fn range(&self) -> &[(f32, f32)] {
+        &self.range
+    }
+
     /// Execute the function.
     ///
     /// `args` pushed to stack before execution.
     /// return numbers of stack after execution.
     pub fn exec(&self, args: &[f32]) -> Result<Vec<f32>, MachineError> {
         let mut m = Machine::new(self.script.as_ref());
-        m.exec_as_function(args, self.n_out)
+        m.set_max_recursion(self.max_recursion);
+        m.exec_as_function(args, self.num_outputs())
     }
 }
 
@@ -4,13 +4,48 @@ use super::*;
 fn pdf_func() {
     // two-in, one-out
     let script = b"{ add }";
-    let func = PdfFunc::new(script.as_slice(), 1);
+    let func = PdfFunc::new(script.as_slice(), [(0.0, 1.0); 2], [(0.0, 2.0)]);
     let r = func.exec(&[1.0, 2.0]).unwrap();
     assert_eq!(r, vec![3.0]);
 
     // two-in, two-out
     let script = b"{ sub 2 }";
-    let func = PdfFunc::new(script.as_slice(), 2);
+    let func = PdfFunc::new(
+        script.as_slice(),
+        [(0.0, 1.0); 2],
+        [(-1.0, 1.0), (0.0, 2.0)],
+    );
     let r = func.exec(&[1.0, 2.0]).unwrap();
     assert_eq!(r, vec![-1.0, 2.0]);
 }
+
+#[test]
+fn pdf_func_signature() {
+    let func = PdfFunc::new(
+        b"{ pop }".as_slice(),
+        [(0.0, 1.0), (-1.0, 1.0)],
+        [(0.0, 10.0)],
+    );
+    assert_eq!(2, func.num_inputs());
+    assert_eq!(1, func.num_outputs());
+    assert_eq!(&[(0.0, 1.0), (-1.0, 1.0)], func.domain());
+    assert_eq!(&[(0.0, 10.0)], func.range());
+}
+
+#[test]
+fn pdf_func_recursion() {
+    let func = PdfFunc::new(b"{ /f { f } def f }".as_slice(), [(0.0, 1.0)], [(0.0, 1.0)]);
+    assert!(matches!(
+        func.exec(&[0.5]),
+        Err(MachineError::ExecStackOverflow { .. })
+    ));
+
+    // function body itself is a procedure
+    let func = PdfFunc::new(b"{ pop { 1 } exec }".as_slice(), [(0.0, 1.0)], [(0.0, 1.0)]);
+    assert_eq!(vec![1.0], func.exec(&[0.5]).unwrap());
+    let func = func.with_max_recursion(1);
+    assert!(matches!(
+        func.exec(&[0.5]),
+        Err(MachineError::ExecStackOverflow { limit: 1 })
+    ));
+}
@@ -20,8 +20,12 @@ pub struct Header {
 pub struct Font {
     header: Header,
     encoding: Option<Encoding>,
+    font_matrix: [f32; 6],
 }
 
+/// FontMatrix used if font not define it, or defined in unexpected form.
+const DEFAULT_FONT_MATRIX: [f32; 6] = [0.001, 0.0, 0.0, 0.001, 0.0, 0.0];
+
 fn parse_header(mut data: &[u8]) -> Result<Header, Whatever> {
     header
         .parse_next(&mut data)
@@ -36,11 +40,27 @@ fn parse_vec_encoding(arr: &Array) -> Encoding {
     Encoding::new(names)
 }
 
+fn parse_font_matrix(arr: &Array) -> Option<[f32; 6]> {
+    if arr.len() != 6 {
+        return None;
+    }
+
+    let mut r = [0.0; 6];
+    for (r, v) in r.iter_mut().zip(arr.iter()) {
+        *r = match v {
+            Value::Integer(i) => *i as f32,
+            Value::Real(f) => *f,
+            _ => return None,
+        };
+    }
+    Some(r)
+}
+
 impl Font {
     pub fn parse(data: &[u8]) -> Result<Self, Whatever> {
         let data = normalize_pfb(data);
         let header = parse_header(&data)?;
-        assert!(header.spec_ver.starts_with("1."), "Not Type1 font");
+        ensure_whatever!(header.spec_ver.starts_with("1."), "Not Type1 font");
 
         let mut machine = Machine::new(&data);
         let encoding = machine
@@ -51,10 +71,19 @@ impl Font {
             Value::PredefinedEncoding(encoding) => Encoding::predefined(encoding).unwrap(),
             _ => whatever!("Invalid encoding type"),
         };
+        // FontMatrix defined before Encoding, font dict is still the current dict
+        let font_matrix = machine
+            .current_dict()
+            .borrow()
+            .get(&sname("FontMatrix"))
+            .and_then(|v| v.opt_array())
+            .and_then(|arr| parse_font_matrix(&arr.borrow()))
+            .unwrap_or(DEFAULT_FONT_MATRIX);
 
         Ok(Font {
             header,
             encoding: Some(encoding),
+            font_matrix,
         })
     }
 
@@ -67,6 +96,13 @@ impl Font {
     pub fn encoding(&self) -> Option<&Encoding> {
         self.encoding.as_ref()
     }
+
+    /// Font matrix maps glyph space to text space, `[0.001 0 0 0.001 0 0]`
+    /// if not defined.
+    #[inline]
+    pub fn font_matrix(&self) -> [f32; 6] {
+        self.font_matrix
+    }
 }
 
 /// If file is pfb file, remove pfb section bytes
@@ -21,6 +21,17 @@ fn parse_pfa_file() {
     let data = include_bytes!("p052024l.pfa");
     let font = Font::parse(data).unwrap();
     assert_eq!("URWPalladioL-BoldItal", font.header.font_name);
+    assert_eq!([0.001, 0.0, 0.0, 0.001, 0.0, 0.0], font.font_matrix());
+}
+
+#[test]
+fn parse_font_matrix_not_default() {
+    let data = include_str!("p052024l.pfa").replace(
+        "/FontMatrix [0.001 0 0 0.001 0 0 ]",
+        "/FontMatrix [0.0005 0 0 0.0005 0 0 ]",
+    );
+    let font = Font::parse(data.as_bytes()).unwrap();
+    assert_eq!([0.0005, 0.0, 0.0, 0.0005, 0.0, 0.0], font.font_matrix());
 }
 
 #[test]
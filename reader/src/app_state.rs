@@ -3,10 +3,13 @@
 use anyhow::Result;
 use directories_next::ProjectDirs;
 use log::error;
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-/// Return the last opened file path. If directory not exists, create it.
-fn last_file_path() -> Result<PathBuf> {
+/// Return path of `name` in data directory. If directory not exists, create it.
+fn data_file_path(name: &str) -> Result<PathBuf> {
     let project_dirs = ProjectDirs::from("", "", crate::APP_NAME)
         .ok_or_else(|| anyhow::anyhow!("get project dirs failed"))?;
 
@@ -15,7 +18,12 @@ fn last_file_path() -> Result<PathBuf> {
         std::fs::create_dir_all(data_dir)?;
     }
 
-    Ok(data_dir.join("last_file_path"))
+    Ok(data_dir.join(name))
+}
+
+/// Return the last opened file path. If directory not exists, create it.
+fn last_file_path() -> Result<PathBuf> {
+    data_file_path("last_file_path")
 }
 
 fn log_and_forget<T>(rv: Result<T>, msg: &str) -> Option<T> {
@@ -50,3 +58,92 @@ pub fn load_last_file() -> Option<String> {
 
     log_and_forget(_do(), "load last file path failed")
 }
+
+/// Remembered viewing position of a document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DocumentState {
+    /// Zero based page index.
+    pub page: u32,
+    pub zoom: f32,
+}
+
+/// Per-document states keyed by file path.
+///
+/// Stored as text, one document per line: `page<TAB>zoom<TAB>file path`,
+/// file path placed last so that it can contain tab.
+#[derive(Debug, Default, PartialEq)]
+pub struct DocumentStates(HashMap<String, DocumentState>);
+
+impl DocumentStates {
+    /// Parse from text, malformed lines are ignored.
+    pub fn parse(s: &str) -> Self {
+        let parse_line = |line: &str| {
+            let mut parts = line.splitn(3, '\t');
+            let page = parts.next()?.parse().ok()?;
+            let zoom = parts.next()?.parse().ok()?;
+            let key = parts.next()?;
+            Some((key.to_owned(), DocumentState { page, zoom }))
+        };
+        Self(s.lines().filter_map(parse_line).collect())
+    }
+
+    /// Format to text, sorted by key to keep file stable.
+    pub fn to_text(&self) -> String {
+        let mut items: Vec<_> = self.0.iter().collect();
+        items.sort_by_key(|(k, _)| k.as_str());
+        items
+            .into_iter()
+            .map(|(k, v)| format!("{}\t{}\t{}\n", v.page, v.zoom, k))
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<DocumentState> {
+        self.0.get(key).copied()
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, state: DocumentState) {
+        self.0.insert(key.into(), state);
+    }
+
+    /// Load from file, returns empty states if file not exists.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+}
+
+fn document_states_path() -> Result<PathBuf> {
+    data_file_path("document_states")
+}
+
+/// Saves remembered state of document `file_path`. If error happened, error log and ignore it.
+pub fn save_document_state(file_path: &str, state: DocumentState) {
+    fn _do(file_path: &str, state: DocumentState) -> anyhow::Result<()> {
+        let path = document_states_path()?;
+        let mut states = DocumentStates::load(&path)?;
+        states.set(file_path, state);
+        states.save(&path)
+    }
+
+    log_and_forget(_do(file_path, state), "save document state failed");
+}
+
+/// Loads remembered state of document `file_path`. If error happened, error log and ignore it.
+pub fn load_document_state(file_path: &str) -> Option<DocumentState> {
+    fn _do(file_path: &str) -> anyhow::Result<Option<DocumentState>> {
+        let path = document_states_path()?;
+        Ok(DocumentStates::load(&path)?.get(file_path))
+    }
+
+    log_and_forget(_do(file_path), "load document state failed").flatten()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,42 @@
+use super::*;
+
+#[test]
+fn document_states_save_load() {
+    let path = std::env::temp_dir().join(format!("nipdf-document-states-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+        DocumentStates::default(),
+        DocumentStates::load(&path).unwrap()
+    );
+
+    let mut states = DocumentStates::default();
+    let a = DocumentState {
+        page: 3,
+        zoom: 1.25,
+    };
+    let b = DocumentState { page: 0, zoom: 2.0 };
+    states.set("/tmp/a.pdf", a);
+    states.set("/tmp/dir\twith tab/b.pdf", b);
+    states.set("/tmp/a.pdf", DocumentState { page: 5, ..a });
+    states.save(&path).unwrap();
+
+    let loaded = DocumentStates::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(states, loaded);
+    assert_eq!(
+        Some(DocumentState { page: 5, ..a }),
+        loaded.get("/tmp/a.pdf")
+    );
+    assert_eq!(Some(b), loaded.get("/tmp/dir\twith tab/b.pdf"));
+    assert_eq!(None, loaded.get("/tmp/c.pdf"));
+}
+
+#[test]
+fn document_states_ignore_malformed_lines() {
+    let states = DocumentStates::parse("1\t1.5\ta.pdf\nbad line\nx\t1\tb.pdf\n");
+    assert_eq!(
+        Some(DocumentState { page: 1, zoom: 1.5 }),
+        states.get("a.pdf")
+    );
+    assert_eq!(None, states.get("b.pdf"));
+}
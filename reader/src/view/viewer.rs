@@ -1,4 +1,7 @@
-use crate::{AppMessage, ShardedData};
+use crate::{
+    AppMessage, ShardedData,
+    app_state::{self, DocumentState},
+};
 use anyhow::Result;
 #[cfg(feature = "debug")]
 use iced::alignment::Horizontal;
@@ -25,10 +28,16 @@ use iced_aw::{
     native::menu::{ItemHeight, MenuTree},
 };
 use nipdf::file::File as PdfFile;
-use nipdf_render::{RenderOptionBuilder, render_page};
+use nipdf_render::{PageMipmap, RenderOptionBuilder, compose_rotate};
+use page_cache::PageCache;
 #[cfg(feature = "debug")]
 use std::time::{Duration, Instant};
 
+mod page_cache;
+
+/// Max bytes of rendered page images kept in cache.
+const PAGE_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+
 #[derive(Clone, Debug, Copy)]
 struct PageNavigator {
     current_page: u32,
@@ -58,12 +67,20 @@ impl PageNavigator {
 }
 
 /// Current displayed Pdf rendered page.
+#[derive(Clone)]
 struct Page {
     width: u32,
     height: u32,
     data: ShardedData,
 }
 
+impl Page {
+    /// Bytes of rendered image.
+    fn size(&self) -> usize {
+        self.data.0.len()
+    }
+}
+
 #[cfg(feature = "debug")]
 #[derive(Debug, Clone)]
 pub enum PageInputMessage {
@@ -134,6 +151,8 @@ pub enum ViewerMessage {
     PrevPage,
     ZoomIn,
     ZoomOut,
+    RotateCW,
+    RotateCCW,
 
     CurPageChange(String),
     CurPageChanged,
@@ -162,8 +181,15 @@ pub enum ViewerMessage {
 pub struct Viewer {
     file_path: String,
     page: Page,
+    page_cache: PageCache,
+    /// Current page rendered at the largest zoom since it is loaded, zoom
+    /// out is downscaled from it, see [`PageMipmap`] for quality tradeoff.
+    mipmap: Option<(u32, PageMipmap)>,
     navi: PageNavigator,
     zoom: f32,
+    /// Clockwise degrees rotated by user, in addition to page `/Rotate`, kept
+    /// while navigating.
+    rotate: i32,
     cur_page_editing: String,
     file: PdfFile,
     #[cfg(feature = "debug")]
@@ -187,11 +213,14 @@ impl Viewer {
                 height: 0,
                 data: ShardedData(vec![].into()),
             },
+            page_cache: PageCache::new(PAGE_CACHE_BUDGET),
+            mipmap: None,
             navi: PageNavigator {
                 current_page: 0,
                 total_pages: 0,
             },
             zoom: 1.75,
+            rotate: 0,
             cur_page_editing: "".to_owned(),
             #[cfg(feature = "debug")]
             render_time: Duration::default(),
@@ -201,7 +230,18 @@ impl Viewer {
             #[cfg(feature = "debug")]
             open_in_gvim: false,
         };
-        r.load_page(0)?;
+        // restore remembered page and zoom, fallback to first page if the
+        // document has fewer pages now
+        let page = app_state::load_document_state(&r.file_path).map_or(0, |state| {
+            r.zoom = state.zoom;
+            state.page
+        });
+        let total_pages = {
+            let resolver = r.file.resolver()?;
+            r.file.catalog(&resolver)?.page_count()?
+        };
+        let page = if (page as usize) < total_pages { page } else { 0 };
+        r.load_page(page)?;
         Ok(r)
     }
 
@@ -216,22 +256,47 @@ impl Viewer {
     fn load_page(&mut self, no: u32) -> Result<()> {
         #[cfg(feature = "debug")]
         let now = Instant::now();
-        let resolver = self.file.resolver()?;
-        let catalog = self.file.catalog(&resolver)?;
-        let pages = catalog.pages()?;
-        let page = &pages[no as usize];
-        let option = RenderOptionBuilder::new().zoom(self.zoom);
-        let image = render_page(page, option)?;
-        self.page = Page {
-            width: image.width(),
-            height: image.height(),
-            data: ShardedData(image.into_vec().into()),
-        };
-        self.navi = PageNavigator {
-            current_page: no,
-            total_pages: pages.len().try_into().unwrap(),
-        };
+        if let Some(page) = self.page_cache.get(no, self.zoom) {
+            self.page = page.clone();
+            self.navi.current_page = no;
+        } else {
+            let resolver = self.file.resolver()?;
+            let catalog = self.file.catalog(&resolver)?;
+            let page = &catalog.page_at(no as usize)?;
+            let image = match self
+                .mipmap
+                .as_ref()
+                .filter(|(page_no, _)| *page_no == no)
+                .and_then(|(_, mipmap)| mipmap.image(self.zoom))
+            {
+                Some(image) => image,
+                None => {
+                    // hide layers turned off in default configuration
+                    let option = RenderOptionBuilder::new()
+                        .optional_content(Some(catalog.optional_content()?))
+                        .rotate(self.rotate);
+                    let mipmap = PageMipmap::new(page, option, self.zoom)?;
+                    let image = mipmap.image(self.zoom).unwrap();
+                    self.mipmap = Some((no, mipmap));
+                    image
+                }
+            };
+            self.page = Page {
+                width: image.width(),
+                height: image.height(),
+                data: ShardedData(image.into_vec().into()),
+            };
+            self.page_cache.put(no, self.zoom, self.page.clone());
+            self.navi = PageNavigator {
+                current_page: no,
+                total_pages: catalog.page_count()?.try_into().unwrap(),
+            };
+        }
         self.update_cur_page_editing_from_navigation();
+        app_state::save_document_state(&self.file_path, DocumentState {
+            page: no,
+            zoom: self.zoom,
+        });
         #[cfg(feature = "debug")]
         {
             self.render_time = now.elapsed();
@@ -239,6 +304,15 @@ impl Viewer {
         Ok(())
     }
 
+    /// Rotate clockwise by `degrees`, rendered pages of previous rotation
+    /// dropped.
+    fn rotate_by(&mut self, degrees: i32) -> Result<()> {
+        self.rotate = compose_rotate(self.rotate, degrees);
+        self.page_cache.clear();
+        self.mipmap = None;
+        self.load_page(self.navi.current_page)
+    }
+
     #[cfg(feature = "debug")]
     fn page_object_number(&self) -> Result<u32> {
         let resolver = self.file.resolver()?;
@@ -318,9 +392,7 @@ impl Viewer {
         let page = &pages[page_no];
         let contents = page.content()?;
         let mut f = std::fs::File::create("/tmp/page-stream")?;
-        for buf in contents.as_ref() {
-            f.write_all(buf)?;
-        }
+        f.write_all(&contents.to_bytes())?;
         Ok(())
     }
 
@@ -429,6 +501,8 @@ impl Viewer {
                 self.zoom /= 1.25;
                 self.load_page(self.navi.current_page)
             }
+            ViewerMessage::RotateCW => self.rotate_by(90),
+            ViewerMessage::RotateCCW => self.rotate_by(-90),
             ViewerMessage::CurPageChange(s) => {
                 self.cur_page_editing = s;
                 Ok(())
@@ -535,6 +609,13 @@ impl Viewer {
                 button("Zoom Out")
                     .on_press(AppMessage::Viewer(ViewerMessage::ZoomOut))
                     .into(),
+                horizontal_space().width(16).into(),
+                button("Rotate Left")
+                    .on_press(AppMessage::Viewer(ViewerMessage::RotateCCW))
+                    .into(),
+                button("Rotate Right")
+                    .on_press(AppMessage::Viewer(ViewerMessage::RotateCW))
+                    .into(),
                 horizontal_space().width(Length::Fill).into(),
                 #[cfg(feature = "debug")]
                 text(format!("{} ms", self.render_time.as_millis())).into(),
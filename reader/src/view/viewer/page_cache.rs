@@ -0,0 +1,70 @@
+//! Cache rendered pages, so that revisiting a page not need to render it again.
+use super::Page;
+use std::collections::VecDeque;
+
+/// Key of cached page: page index and zoom.
+/// Zoom stored as bits of f32, because f32 not implements `Eq`.
+type Key = (u32, u32);
+
+/// Least-recently-used cache of rendered pages, total bytes of cached pages
+/// not exceed `budget`.
+pub struct PageCache {
+    budget: usize,
+    used: usize,
+    // most recently used at front
+    recents: VecDeque<(Key, Page)>,
+}
+
+impl PageCache {
+    /// Create cache, `budget` is the max bytes of all cached page images.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            recents: VecDeque::new(),
+        }
+    }
+
+    fn key(page_no: u32, zoom: f32) -> Key {
+        (page_no, zoom.to_bits())
+    }
+
+    /// Get cached page, and mark it as most recently used.
+    pub fn get(&mut self, page_no: u32, zoom: f32) -> Option<&Page> {
+        let key = Self::key(page_no, zoom);
+        let idx = self.recents.iter().position(|(k, _)| *k == key)?;
+        let entry = self.recents.remove(idx).unwrap();
+        self.recents.push_front(entry);
+        self.recents.front().map(|(_, p)| p)
+    }
+
+    /// Put page into cache, evict least recently used pages if exceeds budget.
+    /// Page larger than budget is not cached.
+    pub fn put(&mut self, page_no: u32, zoom: f32, page: Page) {
+        let key = Self::key(page_no, zoom);
+        if let Some(idx) = self.recents.iter().position(|(k, _)| *k == key) {
+            let (_, old) = self.recents.remove(idx).unwrap();
+            self.used -= old.size();
+        }
+
+        let size = page.size();
+        if size > self.budget {
+            return;
+        }
+        while self.used + size > self.budget {
+            let (_, evicted) = self.recents.pop_back().unwrap();
+            self.used -= evicted.size();
+        }
+        self.used += size;
+        self.recents.push_front((key, page));
+    }
+
+    /// Remove all cached pages, such as pages rendered in previous rotation.
+    pub fn clear(&mut self) {
+        self.recents.clear();
+        self.used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use super::*;
+use crate::ShardedData;
+
+fn page(size: usize) -> Page {
+    Page {
+        width: 1,
+        height: 1,
+        data: ShardedData(vec![0; size].into()),
+    }
+}
+
+#[test]
+fn evict_least_recently_used() {
+    let mut cache = PageCache::new(300);
+    cache.put(0, 1.0, page(100));
+    cache.put(1, 1.0, page(100));
+    cache.put(2, 1.0, page(100));
+    // page 0 becomes most recently used, page 1 is the least
+    assert!(cache.get(0, 1.0).is_some());
+
+    cache.put(3, 1.0, page(100));
+    assert!(cache.get(1, 1.0).is_none());
+    assert!(cache.get(0, 1.0).is_some());
+    assert!(cache.get(2, 1.0).is_some());
+    assert!(cache.get(3, 1.0).is_some());
+
+    // evict more than one page to fit a large one
+    cache.put(4, 1.0, page(200));
+    assert!(cache.get(0, 1.0).is_none());
+    assert!(cache.get(2, 1.0).is_none());
+    assert!(cache.get(3, 1.0).is_some());
+    assert!(cache.get(4, 1.0).is_some());
+}
+
+#[test]
+fn keyed_by_zoom() {
+    let mut cache = PageCache::new(300);
+    cache.put(0, 1.0, page(100));
+    assert!(cache.get(0, 1.25).is_none());
+    assert!(cache.get(0, 1.0).is_some());
+}
+
+#[test]
+fn page_larger_than_budget_not_cached() {
+    let mut cache = PageCache::new(300);
+    cache.put(0, 1.0, page(100));
+    cache.put(1, 1.0, page(301));
+    assert!(cache.get(1, 1.0).is_none());
+    assert!(cache.get(0, 1.0).is_some());
+}
+
+#[test]
+fn clear() {
+    let mut cache = PageCache::new(300);
+    cache.put(0, 1.0, page(100));
+    cache.put(1, 1.0, page(200));
+    cache.clear();
+    assert!(cache.get(0, 1.0).is_none());
+    // budget released
+    cache.put(2, 1.0, page(300));
+    assert!(cache.get(2, 1.0).is_some());
+}
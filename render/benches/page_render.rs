@@ -51,6 +51,17 @@ pub fn render_path(c: &mut Criterion) {
     });
 }
 
+/// Text heavy page, body text repeats the same glyphs many times.
+pub fn render_text(c: &mut Criterion) {
+    let buf = read_sample_file("../nipdf/sample_files/bizarre/imm5257b_1.pdf");
+    let f = File::parse(buf, "").unwrap();
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    c.bench_function("text render", |b| {
+        b.iter(|| render_page(&pages[0], RenderOptionBuilder::new().zoom(1.5)).unwrap())
+    });
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default();
@@ -63,4 +74,10 @@ criterion_group! {
     targets = render_inline_image, render_path
 }
 
-criterion_main!(benches, inline_image);
+criterion_group! {
+    name = text;
+    config = Criterion::default().sample_size(10);
+    targets = render_text
+}
+
+criterion_main!(benches, inline_image, text);
@@ -2,7 +2,7 @@ use euclid::Transform2D;
 use nipdf::{
     file::Rectangle,
     graphics::{
-        LineCapStyle, LineJoinStyle, Point,
+        BlendMode, LineCapStyle, LineJoinStyle, Point,
         color_space::{ColorComp, ColorCompConvertTo, ColorSpaceTrait, convert_color_to},
     },
 };
@@ -52,6 +52,31 @@ impl IntoSkia for LineJoinStyle {
     }
 }
 
+impl IntoSkia for BlendMode {
+    type Output = tiny_skia::BlendMode;
+
+    fn into_skia(self) -> Self::Output {
+        match self {
+            Self::Normal | Self::Compatible => Self::Output::SourceOver,
+            Self::Multiply => Self::Output::Multiply,
+            Self::Screen => Self::Output::Screen,
+            Self::Overlay => Self::Output::Overlay,
+            Self::Darken => Self::Output::Darken,
+            Self::Lighten => Self::Output::Lighten,
+            Self::ColorDodge => Self::Output::ColorDodge,
+            Self::ColorBurn => Self::Output::ColorBurn,
+            Self::HardLight => Self::Output::HardLight,
+            Self::SoftLight => Self::Output::SoftLight,
+            Self::Difference => Self::Output::Difference,
+            Self::Exclusion => Self::Output::Exclusion,
+            Self::Hue => Self::Output::Hue,
+            Self::Saturation => Self::Output::Saturation,
+            Self::Color => Self::Output::Color,
+            Self::Luminosity => Self::Output::Luminosity,
+        }
+    }
+}
+
 impl<S, D> IntoSkia for Transform2D<f32, S, D> {
     type Output = tiny_skia::Transform;
 
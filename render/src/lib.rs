@@ -1,19 +1,49 @@
 use educe::Educe;
-use euclid::Angle;
+use euclid::{Angle, Box2D};
 use image::RgbaImage;
+use log::warn;
 use nipdf::{
-    file::{Page, Rectangle},
-    graphics::trans::{LogicDeviceToDeviceSpace, UserToUserSpace, logic_device_to_device},
-    object::ObjectValueError,
+    file::{
+        File, OptionalContent, Page, Rectangle,
+        page::paint::fonts::{FontFaces, SharedFontCache},
+    },
+    graphics::trans::{
+        GlyphSpace, GlyphToDeviceSpace, LogicDeviceToDeviceSpace, UserToUserSpace,
+        logic_device_to_device,
+    },
+    object::{ObjectValueError, RuntimeObjectId},
 };
+use prescript::Name;
+use rayon::prelude::*;
 use tiny_skia::{Color, Pixmap};
 
+mod mipmap;
 mod render;
+mod search;
 mod shading;
+mod svg;
+pub use mipmap::PageMipmap;
 use render::{Render, State};
+pub use search::{SearchHit, SearchOption, search};
+use svg::SvgWriter;
 mod into_skia;
 pub(crate) use into_skia::*;
 use num_traits::ToPrimitive;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+
+/// Max pixels of canvas, zoom is reduced to fit larger page.
+const MAX_CANVAS_PIXELS: f32 = 1024.0 * 1024.0 * 100.0;
+
+/// Convert to u32, saturate on overflow, 0 if negative or NaN.
+fn saturating_u32(v: f32) -> u32 {
+    v.to_u32().unwrap_or(if v > 0.0 { u32::MAX } else { 0 })
+}
 
 #[derive(Debug, Educe, Clone, Copy)]
 #[educe(Default)]
@@ -37,19 +67,32 @@ impl PageDimension {
         }
         self.transform = transform;
 
-        self.width = dimension.width().to_u32().unwrap();
-        self.height = dimension.height().to_u32().unwrap();
+        self.width = saturating_u32(dimension.width());
+        self.height = saturating_u32(dimension.height());
         if self.swap_wh() {
             std::mem::swap(&mut self.width, &mut self.height);
         }
     }
 
+    /// Canvas width in pixels, at least 1 for empty or tiny page box, which
+    /// can not be created as `Pixmap`.
     pub fn canvas_width(&self) -> u32 {
-        (self.width as f32 * self.zoom).to_u32().unwrap()
+        saturating_u32(self.width as f32 * self.zoom()).max(1)
     }
 
+    /// Canvas height in pixels, at least 1, see `canvas_width()`.
     pub fn canvas_height(&self) -> u32 {
-        (self.height as f32 * self.zoom).to_u32().unwrap()
+        saturating_u32(self.height as f32 * self.zoom()).max(1)
+    }
+
+    /// Zoom reduced if canvas exceeds `MAX_CANVAS_PIXELS`.
+    fn zoom(&self) -> f32 {
+        let pixels = self.width as f32 * self.height as f32 * self.zoom * self.zoom;
+        if pixels > MAX_CANVAS_PIXELS {
+            self.zoom * (MAX_CANVAS_PIXELS / pixels).sqrt()
+        } else {
+            self.zoom
+        }
     }
 
     fn swap_wh(&self) -> bool {
@@ -64,15 +107,133 @@ impl PageDimension {
                 (self.width, self.height)
             };
 
-            let r = logic_device_to_device(h, self.zoom);
-            r.then_translate((w as f32 * self.zoom * -0.5, h as f32 * self.zoom * -0.5).into())
+            let zoom = self.zoom();
+            let r = logic_device_to_device(h, zoom);
+            // rotate around page center, then move center to canvas center,
+            // canvas size is `(self.width, self.height)`, swapped if rotated
+            // 90 or 270 degrees
+            r.then_translate((w as f32 * zoom * -0.5, h as f32 * zoom * -0.5).into())
                 .then_rotate(Angle::degrees(self.rotate as f32))
-                .then_translate((h as f32 * self.zoom * 0.5, w as f32 * self.zoom * 0.5).into())
+                .then_translate(
+                    (
+                        self.width as f32 * zoom * 0.5,
+                        self.height as f32 * zoom * 0.5,
+                    )
+                        .into(),
+                )
         } else {
-            logic_device_to_device(self.height, self.zoom)
+            logic_device_to_device(self.height, self.zoom())
         }
     }
 }
+
+/// Category of operations to render, see
+/// [`RenderOptionBuilder::content_filter()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFilter {
+    #[default]
+    All,
+    TextOnly,
+    /// Path painting and shading, without text and images.
+    GraphicsOnly,
+    ImagesOnly,
+}
+
+impl ContentFilter {
+    fn text(self) -> bool {
+        matches!(self, Self::All | Self::TextOnly)
+    }
+
+    fn graphics(self) -> bool {
+        matches!(self, Self::All | Self::GraphicsOnly)
+    }
+
+    fn images(self) -> bool {
+        matches!(self, Self::All | Self::ImagesOnly)
+    }
+}
+
+/// Time spent painting each category of operations, returned by
+/// [`render_page_with_stats()`]. Operations in forms are counted in their own
+/// categories, content of Type3 glyphs counted in the text operation using
+/// them. Patterns are rendered when set as color, not timed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    /// Path stroke and fill
+    pub path: Duration,
+    /// Text showing
+    pub text: Duration,
+    /// Image XObjects and inline images
+    pub image: Duration,
+    /// `sh` operator
+    pub shading: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StatsCategory {
+    Path,
+    Text,
+    Image,
+    Shading,
+}
+
+impl RenderStats {
+    pub(crate) fn add(&mut self, category: StatsCategory, d: Duration) {
+        let v = match category {
+            StatsCategory::Path => &mut self.path,
+            StatsCategory::Text => &mut self.text,
+            StatsCategory::Image => &mut self.image,
+            StatsCategory::Shading => &mut self.shading,
+        };
+        *v += d;
+    }
+
+    /// Sum of all categories.
+    pub fn total(&self) -> Duration {
+        self.path + self.text + self.image + self.shading
+    }
+}
+
+/// Glyph drawn by text showing operators, returned by [`glyph_runs()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphDraw {
+    /// Object id of font dictionary, None if the font is a direct object.
+    pub font_id: Option<RuntimeObjectId>,
+    /// Name of the font in `/Font` resources.
+    pub font_name: Name,
+    pub gid: u16,
+    /// Unicode of the char, read from `/ToUnicode` of the font if exists.
+    /// Several chars for ligatures, empty if the font can not map it.
+    pub unicode: String,
+    /// Glyph advance box in glyph space, from origin to advance width,
+    /// vertical extent estimated as -0.2 to 0.8 em, font ascent and descent
+    /// not read.
+    pub bbox: Box2D<f32, GlyphSpace>,
+    /// Glyph space to device space, the pixel space of rendered page image.
+    pub transform: GlyphToDeviceSpace,
+}
+
+/// Font to render all text, see [`RenderOptionBuilder::force_font()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForceFont {
+    /// TrueType/OpenType font file.
+    Path(PathBuf),
+    /// Family or PostScript name of a system font.
+    Name(Name),
+}
+
+impl From<PathBuf> for ForceFont {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<Name> for ForceFont {
+    fn from(name: Name) -> Self {
+        Self::Name(name)
+    }
+}
+
 /// Option for Render
 #[derive(Debug, Educe, Clone)]
 #[educe(Default)]
@@ -85,6 +246,32 @@ pub struct RenderOption {
     state: Option<State>,
     rotate: i32,
     dimension: PageDimension,
+    /// Font used to render all text except Type3 fonts, for debugging.
+    force_font: Option<ForceFont>,
+    content_filter: ContentFilter,
+    /// Minimum device space length of dash on segments, 0 to disable.
+    min_dash_length: f32,
+    /// Round glyph origins to device pixels.
+    snap_glyph_origin: bool,
+    /// Skip optional content hidden in the state, render all if None.
+    optional_content: Option<Rc<OptionalContent>>,
+    /// Substitute glyphs missing in document fonts from bundled font.
+    glyph_fallback: bool,
+    /// Stroke outline of clip paths, for debugging.
+    debug_clip: bool,
+    /// Collect unsupported features if set, see `RenderOptionBuilder::strict()`.
+    unsupported: Option<Rc<RefCell<Vec<String>>>>,
+    #[educe(Default = true)]
+    render_annotations: bool,
+    /// Paint popups and replies of annotations, see
+    /// `RenderOptionBuilder::render_popups()`.
+    render_popups: bool,
+    /// Emit vector content as SVG if set, see [`render_page_svg()`].
+    svg: Option<Rc<RefCell<SvgWriter>>>,
+    /// Font data shared with renders of other pages.
+    font_cache: Option<Arc<SharedFontCache>>,
+    /// FreeType faces shared with renders of other pages on current thread.
+    font_faces: Option<Rc<FontFaces>>,
 }
 
 impl RenderOption {
@@ -93,7 +280,7 @@ impl RenderOption {
             self.dimension.canvas_width() as u64,
             self.dimension.canvas_height() as u64,
         );
-        if w * h > 1024 * 1024 * 100 {
+        if (w * h) as f32 > MAX_CANVAS_PIXELS {
             panic!("page size too large: {}x{}", w, h);
         }
 
@@ -104,9 +291,25 @@ impl RenderOption {
         r
     }
 
+    /// Clear `canvas` to background color, so it can be reused to render
+    /// another page. Re-create it if its size not match.
+    pub fn reset_canvas(&self, canvas: &mut Pixmap) {
+        let (w, h) = (
+            self.dimension.canvas_width(),
+            self.dimension.canvas_height(),
+        );
+        if canvas.width() != w || canvas.height() != h {
+            *canvas = self.create_canvas();
+        } else if self.background_color.is_opaque() {
+            canvas.fill(self.background_color);
+        } else {
+            canvas.fill(Color::TRANSPARENT);
+        }
+    }
+
     /// Convert canvas to image, crop if crop option not None
     pub fn to_image(&self, canvas: Pixmap) -> RgbaImage {
-        RgbaImage::from_raw(canvas.width(), canvas.height(), canvas.take()).unwrap()
+        pixmap_to_image(canvas)
     }
 }
 #[derive(Educe)]
@@ -139,11 +342,119 @@ impl RenderOptionBuilder {
         self
     }
 
+    /// Rotate page clockwise by `rotate` degrees, a multiple of 90, in
+    /// addition to page `/Rotate`, such as rotated by user in the viewer.
     pub fn rotate(mut self, rotate: i32) -> Self {
         self.0.rotate = rotate;
         self
     }
 
+    /// Render all text using `font` instead of document fonts, glyphs looked
+    /// up by unicode of chars, glyph widths still read from document fonts.
+    /// Type3 fonts are not affected. Text rendered by document fonts if `font`
+    /// failed to load. Helps to find out whether a rendering bug is font related.
+    pub fn force_font(mut self, font: Option<ForceFont>) -> Self {
+        self.0.force_font = font;
+        self
+    }
+
+    /// Render only operations of the category, such as text only to export a
+    /// text layer over a raster background. Operations in forms are filtered
+    /// by their own category, content of patterns and Type3 glyphs is painted
+    /// as part of the operation using them, not filtered.
+    pub fn content_filter(mut self, filter: ContentFilter) -> Self {
+        self.0.content_filter = filter;
+        self
+    }
+
+    /// Lengthen dash on segments shorter than `len` pixels in device space,
+    /// keeps fine dotted lines visible when zoomed out. Default is 0, dash
+    /// pattern is rendered exactly as specified.
+    pub fn min_dash_length(mut self, len: f32) -> Self {
+        self.0.min_dash_length = len;
+        self
+    }
+
+    /// Move each glyph origin to the nearest device pixel, glyphs of the same
+    /// character are rasterized the same, small text looks sharper and
+    /// evenly spaced at low zoom, at the cost of exact glyph positions.
+    /// Default is false. Type3 glyphs are not affected.
+    pub fn snap_glyph_origin(mut self, snap: bool) -> Self {
+        self.0.snap_glyph_origin = snap;
+        self
+    }
+
+    /// Skip content of optional content groups hidden in `oc`, such as state
+    /// of default configuration returned by `Catalog::optional_content()`.
+    /// Default is None, all optional content rendered.
+    pub fn optional_content(mut self, oc: Option<OptionalContent>) -> Self {
+        self.0.optional_content = oc.map(Rc::new);
+        self
+    }
+
+    /// Render chars missing in document font, such as chars omitted by subset
+    /// fonts, using glyph of the same unicode in a bundled sans font, or the
+    /// first system font has the char if the bundled font lacks it, instead
+    /// of `.notdef`. Chars of unknown unicode still render `.notdef`,
+    /// widths read from document fonts. Default is false. Type3 fonts are not
+    /// affected.
+    pub fn glyph_fallback(mut self, fallback: bool) -> Self {
+        self.0.glyph_fallback = fallback;
+        self
+    }
+
+    /// Stroke outline of each clip path set by `W` and `W*` in semi-transparent
+    /// red, not clipped by current clip region, to visualize clipping regions
+    /// when debugging clip bugs. Default is false. Text clipping paths are not
+    /// drawn.
+    pub fn debug_clip(mut self, debug: bool) -> Self {
+        self.0.debug_clip = debug;
+        self
+    }
+
+    /// Report features not supported by the renderer, such as unknown
+    /// ExtGState keys and unsupported text rendering modes, as
+    /// [`ObjectValueError::Unsupported`] after page rendered, to quantify
+    /// unsupported features on a corpus. Default is false, unsupported
+    /// features are logged and skipped.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.0.unsupported = strict.then(Default::default);
+        self
+    }
+
+    /// Paint appearance streams of annotations over page content, such as
+    /// form fields and link borders. Annotations flagged hidden are not
+    /// painted, popups and replies painted only if [`Self::render_popups()`].
+    /// Default is true.
+    pub fn render_annotations(mut self, render: bool) -> Self {
+        self.0.render_annotations = render;
+        self
+    }
+
+    /// Paint `/Popup` annotations and replies (`/IRT`) along with other
+    /// annotations, like a viewer showing all popups opened. Default is false,
+    /// they are shown only on interaction.
+    pub fn render_popups(mut self, render: bool) -> Self {
+        self.0.render_popups = render;
+        self
+    }
+
+    /// Reuse font data loaded by renders of other pages, possibly on other
+    /// threads, see [`render_pages()`]. Default is None, fonts loaded for
+    /// each page.
+    pub fn font_cache(mut self, cache: Option<Arc<SharedFontCache>>) -> Self {
+        self.0.font_cache = cache;
+        self
+    }
+
+    /// Reuse FreeType faces parsed by renders of other pages on current
+    /// thread, faces can not be shared by threads unlike [`Self::font_cache()`].
+    /// Default is None, faces parsed for each page.
+    pub fn font_faces(mut self, faces: Option<Rc<FontFaces>>) -> Self {
+        self.0.font_faces = faces;
+        self
+    }
+
     fn state(mut self, state: State) -> Self {
         self.0.state = Some(state);
         self
@@ -154,46 +465,243 @@ impl RenderOptionBuilder {
     }
 }
 
+/// Render page to image, converted from [`render_page_pixmap()`].
 pub fn render_page(
     page: &Page,
     option: RenderOptionBuilder,
 ) -> Result<RgbaImage, ObjectValueError> {
-    render_steps(page, option, None, false)
+    render_steps(page, option, None, false, None)
+}
+
+/// Render pages at `indices` of pdf file `data` in parallel on rayon thread
+/// pool, results in the order of `indices`. Pages can not be sent to other
+/// threads, each rayon job opens `data` with `password` by itself. `option`
+/// creates render option of each page, fonts are shared by all pages through
+/// a [`SharedFontCache`] unless the option sets one, FreeType faces shared by
+/// pages of the same job unless the option sets [`FontFaces`].
+pub fn render_pages(
+    data: &[u8],
+    password: &str,
+    indices: &[usize],
+    option: impl Fn() -> RenderOptionBuilder + Sync,
+) -> Vec<Result<RgbaImage, ObjectValueError>> {
+    let font_cache = Arc::new(SharedFontCache::default());
+    indices
+        .par_iter()
+        .map_init(
+            || {
+                (
+                    File::parse(data.to_vec(), password),
+                    Rc::new(FontFaces::default()),
+                )
+            },
+            |(file, faces), &index| {
+                let to_err = |e: anyhow::Error| {
+                    e.downcast()
+                        .unwrap_or_else(|e| ObjectValueError::ParseError(e.to_string()))
+                };
+                let file = file
+                    .as_ref()
+                    .map_err(|e| ObjectValueError::ParseError(e.to_string()))?;
+                let resolver = file.resolver().map_err(to_err)?;
+                let page = file.catalog(&resolver)?.page_at(index).map_err(to_err)?;
+                let mut option = option();
+                if option.0.font_cache.is_none() {
+                    option = option.font_cache(Some(font_cache.clone()));
+                }
+                if option.0.font_faces.is_none() {
+                    option = option.font_faces(Some(faces.clone()));
+                }
+                render_page(&page, option)
+            },
+        )
+        .collect()
+}
+
+fn pixmap_to_image(canvas: Pixmap) -> RgbaImage {
+    RgbaImage::from_raw(canvas.width(), canvas.height(), canvas.take()).unwrap()
+}
+
+/// Render page to pixmap, the canonical render entry point, functions
+/// returning `RgbaImage` convert from its result. Returns the canvas as is,
+/// saves the conversion if caller encodes it by [`Pixmap::encode_png()`] or
+/// composes it further by `tiny_skia`.
+pub fn render_page_pixmap(
+    page: &Page,
+    option: RenderOptionBuilder,
+) -> Result<Pixmap, ObjectValueError> {
+    render_steps_pixmap(page, option, None, false, None)
+}
+
+/// Render page to SVG document, for vector output zoomed without loss.
+/// Paths and glyph outlines painted in solid colors are SVG `<path>`
+/// elements, clip paths are `<clipPath>`. Content can not be expressed in
+/// SVG, such as images, shadings, patterns, soft masks and blend modes, is
+/// rasterized at the zoom of `option` and embedded as PNG `<image>` layers.
+/// Text is not selectable, glyphs are outlines.
+pub fn render_page_svg(
+    page: &Page,
+    option: RenderOptionBuilder,
+) -> Result<String, ObjectValueError> {
+    let mut option = page_option(page, option, false);
+    let svg = Rc::new(RefCell::new(SvgWriter::new(
+        option.dimension.canvas_width(),
+        option.dimension.canvas_height(),
+        option.dimension.logic_device_to_device().into_skia(),
+        option.background_color,
+    )));
+    option.svg = Some(svg.clone());
+    // raster layers are transparent, background painted by svg
+    let mut canvas = option.create_canvas();
+    canvas.fill(Color::TRANSPARENT);
+    render_operations(page, option, None, None, None, None, &mut canvas)?;
+    let r = svg.borrow_mut().finish(&mut canvas);
+    Ok(r)
+}
+
+/// Render page, and time spent on each category of operations, to find out
+/// bottlenecks of slow pages. Use [`render_page()`] if not needed, timing
+/// each operation has overhead.
+pub fn render_page_with_stats(
+    page: &Page,
+    option: RenderOptionBuilder,
+) -> Result<(RgbaImage, RenderStats), ObjectValueError> {
+    let option = page_option(page, option, false);
+    let mut canvas = option.create_canvas();
+    let stats = Cell::default();
+    render_operations(
+        page,
+        option.clone(),
+        None,
+        None,
+        Some(&stats),
+        None,
+        &mut canvas,
+    )?;
+    Ok((option.to_image(canvas), stats.get()))
+}
+
+/// Glyphs drawn by the page in content order, with the glyph space to device
+/// space transform they would be painted with `option`, without rasterizing
+/// them, such as to compare two files glyph by glyph. Glyphs in forms are
+/// included, glyphs of invisible text are not. Other content is skipped.
+pub fn glyph_runs(
+    page: &Page,
+    option: RenderOptionBuilder,
+) -> Result<Vec<GlyphDraw>, ObjectValueError> {
+    let option = page_option(page, option.content_filter(ContentFilter::TextOnly), false);
+    let mut canvas = option.create_canvas();
+    let glyphs = RefCell::default();
+    render_operations(page, option, None, None, None, Some(&glyphs), &mut canvas)?;
+    Ok(glyphs.into_inner())
+}
+
+/// Render page into `canvas` instead of allocating a new one, to reduce
+/// allocation on repeated rendering, such as re-render on scroll.
+/// `canvas` is cleared first, and re-created if its size not match the page.
+pub fn render_page_into(
+    page: &Page,
+    option: RenderOptionBuilder,
+    canvas: &mut Pixmap,
+) -> Result<(), ObjectValueError> {
+    let option = page_option(page, option, false);
+    option.reset_canvas(canvas);
+    render_operations(page, option, None, None, None, None, canvas)
 }
 
+/// Render first `steps` operations of the page, all if None.
+///
+/// Render is stopped with [`ObjectValueError::Cancelled`] once `cancel` is
+/// set, such as another thread abandons the render when user switches to
+/// another page.
 pub fn render_steps(
     page: &Page,
     option: RenderOptionBuilder,
     steps: Option<usize>,
     no_crop: bool,
+    cancel: Option<&AtomicBool>,
 ) -> Result<RgbaImage, ObjectValueError> {
+    render_steps_pixmap(page, option, steps, no_crop, cancel).map(pixmap_to_image)
+}
+
+/// Pixmap version of [`render_steps()`], see [`render_page_pixmap()`].
+pub fn render_steps_pixmap(
+    page: &Page,
+    option: RenderOptionBuilder,
+    steps: Option<usize>,
+    no_crop: bool,
+    cancel: Option<&AtomicBool>,
+) -> Result<Pixmap, ObjectValueError> {
+    let option = page_option(page, option, no_crop);
+    let mut canvas = option.create_canvas();
+    render_operations(page, option, steps, cancel, None, None, &mut canvas)?;
+    Ok(canvas)
+}
+
+fn page_option(page: &Page, option: RenderOptionBuilder, no_crop: bool) -> RenderOption {
     let media_box = page.media_box();
     let crop_box = page.crop_box();
-    let mut canvas_box = crop_box.unwrap_or(media_box);
+    // with `no_crop`, canvas covers the whole media box, rotated the same way
+    let mut canvas_box = if no_crop {
+        media_box
+    } else {
+        crop_box.unwrap_or(media_box)
+    };
     // if canvas is empty, use default A4 size
     if canvas_box.width() == 0.0 || canvas_box.height() == 0.0 {
         canvas_box = Rectangle::from_xywh(0.0, 0.0, 597.6, 842.4);
     }
-    let option = option
-        .page_box(&canvas_box, page.rotate())
+    let rotate = compose_rotate(page.rotate(), option.0.rotate);
+    option
+        .page_box(&canvas_box, rotate)
         .crop((!no_crop && need_crop(crop_box, media_box)).then(|| crop_box.unwrap()))
-        .rotate(page.rotate())
-        .build();
+        .rotate(rotate)
+        .build()
+}
+
+/// Compose clockwise rotations in degrees, such as page `/Rotate` and user
+/// rotation, result normalized to 0, 90, 180 or 270.
+pub fn compose_rotate(a: i32, b: i32) -> i32 {
+    (a + b).rem_euclid(360)
+}
+
+fn render_operations(
+    page: &Page,
+    option: RenderOption,
+    steps: Option<usize>,
+    cancel: Option<&AtomicBool>,
+    stats: Option<&Cell<RenderStats>>,
+    glyphs: Option<&RefCell<Vec<GlyphDraw>>>,
+    canvas: &mut Pixmap,
+) -> Result<(), ObjectValueError> {
     let content = page.content()?;
     let ops = content.operations();
-    let mut canvas = option.create_canvas();
-    if !ops.is_empty() {
-        // skip render if no operations, fixes incorrect pdf files that no resources
+    let annots = if option.render_annotations {
+        page.painted_annotations(option.render_popups)
+            .unwrap_or_else(|e| {
+                warn!("read annotations error: {}", e);
+                vec![]
+            })
+    } else {
+        vec![]
+    };
+    if !ops.is_empty() || !annots.is_empty() {
+        // skip render if nothing to paint, fixes incorrect pdf files that no resources
         let resource = page.resources();
-        let mut renderer = Render::new(&mut canvas, option.clone(), &resource);
-        if let Some(steps) = steps {
-            ops.into_iter().take(steps).for_each(|op| renderer.exec(op));
-        } else {
-            ops.into_iter().for_each(|op| renderer.exec(op));
-        };
+        let unsupported = option.unsupported.clone();
+        let mut renderer = Render::new(canvas, option, &resource);
+        renderer.cancel = cancel;
+        renderer.stats = stats;
+        renderer.glyphs = glyphs;
+        renderer.exec_page(ops.into_iter().take(steps.unwrap_or(usize::MAX)), &annots);
+        if renderer.is_cancelled() {
+            return Err(ObjectValueError::Cancelled);
+        }
+        if let Some(features) = unsupported.filter(|f| !f.borrow().is_empty()) {
+            return Err(ObjectValueError::Unsupported(features.take()));
+        }
     }
-    let r = option.to_image(canvas);
-    Ok(r)
+    Ok(())
 }
 
 fn need_crop(crop: Option<Rectangle>, media: Rectangle) -> bool {
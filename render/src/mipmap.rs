@@ -0,0 +1,78 @@
+//! Render page once at base zoom, produce zoomed out views by downscaling
+//! the rendered image, such as a viewer zooming out a page.
+//!
+//! Downscaling a raster is much faster than re-rendering, but the quality is
+//! lower than rasterizing vector content at the target zoom: thin lines and
+//! small text become blurry and lighter, because they are anti-aliased twice.
+//! Views zoomed in past base zoom are not produced, upscaling is worse, page
+//! should be re-rendered instead.
+use crate::{RenderOptionBuilder, render_page, saturating_u32};
+use image::{
+    RgbaImage,
+    imageops::{FilterType, resize},
+};
+use nipdf::{file::Page, object::ObjectValueError};
+
+/// Stop halving if width or height of next level smaller than this.
+const MIN_LEVEL_SIZE: u32 = 16;
+
+/// Rendered page image and its successive halves.
+pub struct PageMipmap {
+    base_zoom: f32,
+    /// `levels[i]` is `levels[0]` scaled by `1 / 2^i`.
+    levels: Vec<RgbaImage>,
+}
+
+impl PageMipmap {
+    /// Render `page` at `base_zoom`, zoom set in `option` is ignored.
+    pub fn new(
+        page: &Page,
+        option: RenderOptionBuilder,
+        base_zoom: f32,
+    ) -> Result<Self, ObjectValueError> {
+        let base = render_page(page, option.zoom(base_zoom))?;
+        Ok(Self::from_image(base, base_zoom))
+    }
+
+    /// Build from page image rendered at `base_zoom`.
+    pub fn from_image(base: RgbaImage, base_zoom: f32) -> Self {
+        let mut levels = vec![base];
+        loop {
+            let last = levels.last().unwrap();
+            let (w, h) = (last.width() / 2, last.height() / 2);
+            if w < MIN_LEVEL_SIZE || h < MIN_LEVEL_SIZE {
+                break;
+            }
+            let next = resize(last, w, h, FilterType::Triangle);
+            levels.push(next);
+        }
+        Self { base_zoom, levels }
+    }
+
+    pub fn base_zoom(&self) -> f32 {
+        self.base_zoom
+    }
+
+    /// Page image at `zoom`, downscaled from the nearest level not smaller
+    /// than it. Returns None if `zoom` greater than base zoom or not positive,
+    /// re-render the page in that case.
+    pub fn image(&self, zoom: f32) -> Option<RgbaImage> {
+        if !(zoom > 0.0 && zoom <= self.base_zoom) {
+            return None;
+        }
+
+        let scale = zoom / self.base_zoom;
+        let level = saturating_u32((1.0 / scale).log2()) as usize;
+        let src = &self.levels[level.min(self.levels.len() - 1)];
+        let base = &self.levels[0];
+        let (w, h) = (
+            saturating_u32(base.width() as f32 * scale).max(1),
+            saturating_u32(base.height() as f32 * scale).max(1),
+        );
+        Some(if src.dimensions() == (w, h) {
+            src.clone()
+        } else {
+            resize(src, w, h, FilterType::Triangle)
+        })
+    }
+}
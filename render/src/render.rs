@@ -1,47 +1,59 @@
 use crate::{
-    IntoSkia, PageDimension, RenderOption, RenderOptionBuilder,
+    ContentFilter, ForceFont, GlyphDraw, IntoSkia, MAX_CANVAS_PIXELS, PageDimension, RenderOption,
+    RenderOptionBuilder, RenderStats, StatsCategory,
     into_skia::to_skia_color,
-    shading::{Axial, Radial, Shading, build_shading},
+    shading::{Axial, Mesh, Radial, Shading, build_shading, split_triangle},
+    svg::{PathPaint, SvgWriter},
 };
 use anyhow::Result as AnyResult;
 use educe::Educe;
 use either::Either::{self, Left, Right};
-use euclid::{Length, Scale, Transform2D, default::Size2D};
+use euclid::{Angle, Box2D, Length, Point2D, Scale, Transform2D, default::Size2D};
 use image::RgbaImage;
 use log::{debug, info, warn};
 use nipdf::{
     file::{
-        GraphicsStateParameterDict, PageContent, Rectangle, ResourceDict, XObjectDict, XObjectType,
-        paint::fonts::{FontCache, GlyphRender, PathSink},
+        AnnotationDict, AnnotationFlags, GraphicsStateParameterDict, OptionalContent, PageContent,
+        Rectangle, ResourceDict, SoftMaskType, XObjectDict, XObjectType,
+        paint::fonts::{FallbackFont, FontCache, FontOp, GlyphRender, PathSink, system_font_data},
     },
     function::Domain,
     graphics::{
-        ColorArgs, ColorArgsOrName, LineCapStyle, LineJoinStyle, NameOfDict, Operation, Point,
-        RenderingIntent, TextRenderingMode,
+        ColorArgs, ColorArgsOrName, ColorSpaceArgs, LineCapStyle, LineJoinStyle, NameOfDict,
+        NameOrDict, Operation, Point, RenderingIntent, TextRenderingMode,
         color_space::{ColorSpace, ColorSpaceTrait},
         parse_operations,
-        pattern::{PatternType, ShadingPatternDict, TilingPatternDict},
+        pattern::{
+            PatternType, ShadingPatternDict, TilingPaintType, TilingPatternDict, TilingType,
+        },
+        shading::ShadingType,
         trans::{
             GlyphLength, GlyphSpace, GlyphToTextSpace, GlyphToUserSpace, ImageToDeviceSpace,
             PatternSpace, PatternToUserSpace, TextPoint, TextSpace, TextToUserSpace,
             ThousandthsOfText, UserToDeviceSpace, UserToLogicDeviceSpace, UserToUserSpace, f_flip,
-            image_to_user_space, move_text_space_pos, move_text_space_right,
+            glyph_advance, image_to_user_space, move_text_space_pos, move_text_space_right,
         },
     },
-    object::{ImageMask, ImageMetadata, InlineImage, Object, PdfObject, TextStringOrNumber},
+    object::{
+        ImageMask, ImageMetadata, InlineImage, Object, ObjectValueError, PdfObject,
+        RuntimeObjectId, TextStringOrNumber,
+    },
 };
 use nom::{combinator::eof, sequence::terminated};
 use num_traits::ToPrimitive;
-use prescript::Name;
+use prescript::{Name, sname};
 use std::{
     borrow::Cow,
-    cell::{Ref, RefCell},
-    collections::VecDeque,
+    cell::{Cell, Ref, RefCell},
+    collections::{HashMap, VecDeque},
     rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
 };
 use tiny_skia::{
-    Color as SkiaColor, FillRule, FilterQuality, Mask, MaskType, Paint, Path as SkiaPath,
-    PathBuilder, Pixmap, PixmapPaint, PixmapRef, Rect, Stroke, StrokeDash, Transform,
+    BlendMode, Color as SkiaColor, FillRule, FilterQuality, Mask, MaskType, Paint,
+    Path as SkiaPath, PathBuilder, Pixmap, PixmapPaint, PixmapRef, Rect, Stroke, StrokeDash,
+    Transform,
 };
 
 trait CloneOrMove {
@@ -74,20 +86,20 @@ enum PaintCreator {
 }
 
 impl PaintCreator {
-    fn create(&self, alpha: f32) -> Cow<'_, Paint<'_>> {
-        match self {
+    fn create(&self, alpha: f32, blend_mode: BlendMode) -> Cow<'_, Paint<'_>> {
+        let mut r = match self {
             PaintCreator::Color(c) => {
                 let mut r = Paint::default();
                 let mut c = *c;
                 c.set_alpha(alpha);
                 r.set_color(c);
-                Cow::Owned(r)
+                r
             }
 
-            PaintCreator::Gradient((pattern, matrix)) => Cow::Owned(Paint {
+            PaintCreator::Gradient((pattern, matrix)) => Paint {
                 shader: pattern.to_skia(matrix, alpha).unwrap(),
                 ..Default::default()
-            }),
+            },
 
             PaintCreator::Tile((p, matrix, no_repeat)) => {
                 let mut r = Paint::default();
@@ -104,9 +116,11 @@ impl PaintCreator {
                     alpha,
                     transform.into_skia(),
                 );
-                Cow::Owned(r)
+                r
             }
-        }
+        };
+        r.blend_mode = blend_mode;
+        Cow::Owned(r)
     }
 }
 
@@ -185,6 +199,8 @@ struct ColorState {
     alpha: f32,
     #[educe(Default = true)]
     alpha_is_shape: bool,
+    #[educe(Default(expression = BlendMode::SourceOver))]
+    blend_mode: BlendMode,
 }
 
 impl ColorState {
@@ -230,11 +246,17 @@ impl ColorState {
         mask: Option<&Mask>,
     ) {
         if let Some(paint) = &self.background_paint {
-            canvas.stroke_path(path, &paint.create(self.alpha()), stroke, transform, mask);
+            canvas.stroke_path(
+                path,
+                &paint.create(self.alpha(), self.blend_mode),
+                stroke,
+                transform,
+                mask,
+            );
         }
         canvas.stroke_path(
             path,
-            &self.paint.create(self.alpha()),
+            &self.paint.create(self.alpha(), self.blend_mode),
             stroke,
             transform,
             mask,
@@ -242,7 +264,7 @@ impl ColorState {
     }
 
     pub fn create_paint(&self) -> Cow<'_, Paint<'_>> {
-        self.paint.create(self.alpha())
+        self.paint.create(self.alpha(), self.blend_mode)
     }
 
     /// If background_paint not null, fill using it before use self.paint
@@ -257,7 +279,7 @@ impl ColorState {
         if let Some(paint) = &self.background_paint {
             canvas.fill_path(
                 path,
-                &paint.create(self.alpha()),
+                &paint.create(self.alpha(), self.blend_mode),
                 fill_rule,
                 transform,
                 mask,
@@ -265,7 +287,7 @@ impl ColorState {
         }
         canvas.fill_path(
             path,
-            &self.paint.create(self.alpha()),
+            &self.paint.create(self.alpha(), self.blend_mode),
             fill_rule,
             transform,
             mask,
@@ -275,20 +297,59 @@ impl ColorState {
     fn set_alpha_is_shape(&mut self, v: bool) {
         self.alpha_is_shape = v;
     }
+
+    /// Color with alpha if paint is a solid color in normal blend mode, None
+    /// if painted by pattern or shading, or blended.
+    fn solid_color(&self) -> Option<SkiaColor> {
+        match (&self.paint, &self.background_paint, self.blend_mode) {
+            (PaintCreator::Color(c), None, BlendMode::SourceOver) => {
+                let mut c = *c;
+                c.set_alpha(self.alpha());
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+}
+
+/// Multiply soft mask with clip mask.
+fn combine_masks(soft_mask: &Mask, clip: Option<&MaskEntry>) -> Rc<RefCell<Mask>> {
+    let mut r = soft_mask.clone();
+    if let Some((_, clip)) = clip {
+        for (a, b) in r.data_mut().iter_mut().zip(clip.borrow().data()) {
+            *a = ((u16::from(*a) * u16::from(*b) + 127) / 255)
+                .to_u8()
+                .unwrap();
+        }
+    }
+    Rc::new(RefCell::new(r))
 }
+
 #[derive(Debug, Clone)]
 pub(super) struct State {
     dimension: PageDimension,
     ctm: UserToLogicDeviceSpace,
     user_to_device: UserToDeviceSpace,
     stroke: Stroke,
+    /// Dash array and phase in user space, `Stroke::dash` can not be read back.
+    dash: (Vec<f32>, f32),
     mask: Option<MaskEntry>,
+    /// Soft mask set by ExtGState `/SMask`, and it combined with clip mask.
+    soft_mask: Option<(Rc<Mask>, Rc<RefCell<Mask>>)>,
     mask_cache: Rc<RefCell<MaskCache<4>>>,
     text_object: TextObject,
     stroke_state: ColorState,
     fill_state: ColorState,
     /// If not None, update mask with path on end_path
     clipping: Option<FillRule>,
+    /// Collect unsupported features if set, see `RenderOptionBuilder::strict()`.
+    unsupported: Option<Rc<RefCell<Vec<String>>>>,
+    /// Id of SVG clip path of clip region, see [`SvgWriter::clip()`].
+    svg_clip: Option<usize>,
 }
 
 impl State {
@@ -299,12 +360,16 @@ impl State {
             user_to_device: UserToDeviceSpace::identity(),
             ctm: UserToLogicDeviceSpace::identity(),
             stroke: Stroke::default(),
+            dash: (vec![], 0.0),
             mask: None,
+            soft_mask: None,
             mask_cache: Rc::new(RefCell::new(MaskCache::new())),
             text_object: TextObject::new(),
             stroke_state: ColorState::default(),
             fill_state: ColorState::default(),
             clipping: None,
+            unsupported: None,
+            svg_clip: None,
         };
 
         r.set_ctm(UserToLogicDeviceSpace::identity());
@@ -313,10 +378,22 @@ impl State {
         r.set_miter_limit(10.0);
         r.set_dash_pattern(&[], 0.0);
         r.set_render_intent(RenderingIntent::default());
+        r.unsupported = option.unsupported.clone();
 
         r
     }
 
+    /// Report feature not supported, collected if in strict mode.
+    fn unsupported(&self, feature: impl Into<String>) {
+        if let Some(features) = &self.unsupported {
+            let feature = feature.into();
+            let mut features = features.borrow_mut();
+            if !features.contains(&feature) {
+                features.push(feature);
+            }
+        }
+    }
+
     fn update_user_to_device(&mut self) {
         self.user_to_device = self.ctm.then(&self.dimension.logic_device_to_device());
         debug!("ctm to {:?}", self.ctm);
@@ -347,6 +424,7 @@ impl State {
 
     fn set_dash_pattern(&mut self, pattern: &[f32], phase: f32) {
         self.stroke.dash = StrokeDash::new(pattern.to_owned(), phase);
+        self.dash = (pattern.to_owned(), phase);
     }
 
     fn set_miter_limit(&mut self, limit: f32) {
@@ -356,11 +434,15 @@ impl State {
     #[allow(clippy::needless_pass_by_ref_mut)]
     fn set_flatness(&mut self, flatness: f32) {
         info!("not implemented: flatness: {}", flatness);
+        self.unsupported("flatness");
     }
 
     #[allow(clippy::needless_pass_by_ref_mut)]
     fn set_render_intent(&mut self, intent: RenderingIntent) {
         info!("not implemented: render intent: {}", intent);
+        if intent != RenderingIntent::default() {
+            self.unsupported(format!("render intent {intent}"));
+        }
     }
 
     fn get_fill_paint(&self) -> Cow<'_, Paint<'_>> {
@@ -375,12 +457,56 @@ impl State {
         &self.stroke
     }
 
+    /// Stroke with dash on segments lengthened to at least `min_len` in
+    /// device space, returns current stroke if no segment is shorter.
+    fn get_stroke_with_min_dash(&self, min_len: f32) -> Cow<'_, Stroke> {
+        let (pattern, phase) = &self.dash;
+        if min_len <= 0.0 || pattern.is_empty() {
+            return Cow::Borrowed(&self.stroke);
+        }
+
+        let scale = self.user_to_device.determinant().abs().sqrt();
+        if scale == 0.0 {
+            return Cow::Borrowed(&self.stroke);
+        }
+        let min_len = min_len / scale;
+        if pattern.iter().step_by(2).all(|&on| on >= min_len) {
+            return Cow::Borrowed(&self.stroke);
+        }
+
+        let pattern = pattern
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| if i % 2 == 0 { v.max(min_len) } else { v })
+            .collect();
+        let mut stroke = self.stroke.clone();
+        stroke.dash = StrokeDash::new(pattern, *phase);
+        Cow::Owned(stroke)
+    }
+
     fn image_transform(&self, img_w: u32, img_h: u32) -> ImageToDeviceSpace {
         image_to_user_space(img_w, img_h).then(&self.user_to_device)
     }
 
     fn get_mask(&self) -> Option<Ref<Mask>> {
-        self.mask.as_ref().map(|m| m.1.borrow())
+        match &self.soft_mask {
+            Some((_, combined)) => Some(combined.borrow()),
+            None => self.mask.as_ref().map(|m| m.1.borrow()),
+        }
+    }
+
+    /// Painted content blends with backdrop, by blend mode or soft mask.
+    fn blends_backdrop(&self) -> bool {
+        self.soft_mask.is_some() || self.fill_state.blend_mode() != BlendMode::SourceOver
+    }
+
+    /// Set soft mask in device space, None to remove it.
+    fn set_soft_mask(&mut self, mask: Option<Mask>) {
+        self.soft_mask = mask.map(|m| {
+            let m = Rc::new(m);
+            let combined = combine_masks(&m, self.mask.as_ref());
+            (m, combined)
+        });
     }
 
     fn set_graphics_state(&mut self, res: &GraphicsStateParameterDict) {
@@ -396,15 +522,30 @@ impl State {
                 "CA" => self.set_stroke_alpha(res.stroke_alpha().unwrap().unwrap()),
                 "ca" => self.set_fill_alpha(res.fill_alpha().unwrap().unwrap()),
                 "AIS" => self.set_alpha_is_shape(res.alpha_is_shape().unwrap().unwrap()),
-                "Type" => (),
-                "SM" => debug!("ExtGState key: SM (smoothness tolerance) not implemented"),
+                "BM" => match res.blend_mode() {
+                    Ok(Some(mode)) => self.set_blend_mode(mode.into_skia()),
+                    r => warn!("invalid ExtGState BM: {:?}", r),
+                },
+                // soft mask needs resources and canvas, handled by Render
+                "Type" | "SMask" => (),
+                "SM" => {
+                    debug!("ExtGState key: SM (smoothness tolerance) not implemented");
+                    self.unsupported("ExtGState key SM");
+                }
                 k @ ("OPM" | "op" | "OP") => {
-                    debug!("ExtGState key {k} is for Overprint, which is not supported")
+                    debug!("ExtGState key {k} is for Overprint, which is not supported");
+                    self.unsupported(format!("ExtGState key {k}"));
                 }
                 "SA" => {
-                    debug!("Unknown or unsupported ExtGState key: SA (automatic stroke adjustment)")
+                    debug!(
+                        "Unknown or unsupported ExtGState key: SA (automatic stroke adjustment)"
+                    );
+                    self.unsupported("ExtGState key SA");
+                }
+                _ => {
+                    info!("Unknown or unsupported ExtGState key: {}", key.as_ref());
+                    self.unsupported(format!("ExtGState key {}", key.as_ref()));
                 }
-                _ => info!("Unknown or unsupported ExtGState key: {}", key.as_ref()),
             }
         }
     }
@@ -435,6 +576,10 @@ impl State {
             rule,
             new_mask,
         ));
+        if let Some((soft_mask, _)) = self.soft_mask.take() {
+            let combined = combine_masks(&soft_mask, self.mask.as_ref());
+            self.soft_mask = Some((soft_mask, combined));
+        }
         // use std::sync::atomic::{AtomicU32, Ordering};
         // static mut IDX: std::sync::atomic::AtomicU32 = AtomicU32::new(0);
         // if let Some(mask) = &self.mask {
@@ -448,9 +593,34 @@ impl State {
         // }
     }
 
+    /// Intersect SVG clip of the state with `path`, `transform` maps it to
+    /// logic device space, clip all if `path` is None. Do nothing if `svg` not
+    /// set.
+    fn update_svg_clip(
+        &mut self,
+        svg: Option<&RefCell<SvgWriter>>,
+        path: Option<&SkiaPath>,
+        transform: Transform,
+        rule: FillRule,
+    ) {
+        if let Some(svg) = svg {
+            self.svg_clip = Some(svg.borrow_mut().clip(path, transform, rule, self.svg_clip));
+        }
+    }
+
+    /// Intersect clip region with an empty path, makes everything clipped out.
+    fn clip_all(&mut self) {
+        // zero area path, mask intersects with it turns all zero
+        let mut pb = PathBuilder::new();
+        pb.move_to(0.0, 0.0);
+        pb.line_to(0.0, 0.0);
+        self.update_mask(pb.finish().unwrap(), FillRule::Winding, false);
+    }
+
     fn set_text_knockout_flag(&mut self, knockout: bool) {
         self.text_object.knockout = knockout;
-        todo!("text knockout");
+        info!("not implemented: text knockout");
+        self.unsupported("text knockout");
     }
 
     pub fn end_text_object(&mut self) {
@@ -476,6 +646,11 @@ impl State {
         self.stroke_state.set_alpha_is_shape(v);
         self.fill_state.set_alpha_is_shape(v);
     }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.stroke_state.blend_mode = mode;
+        self.fill_state.blend_mode = mode;
+    }
 }
 
 #[derive(Debug, Clone, Educe)]
@@ -602,6 +777,129 @@ pub struct Render<'a, 'b, 'c> {
     font_cache: FontCache<'c, SkiaPathSink>,
     resources: &'c ResourceDict<'a, 'b>,
     dimension: PageDimension,
+    #[educe(Debug(ignore))]
+    force_font: Option<Rc<[u8]>>,
+    /// Ignore color operators, set when rendering content of uncolored tiling
+    /// pattern, whose color is supplied by pattern user.
+    ignore_color: bool,
+    /// Stop executing operations if set.
+    pub(crate) cancel: Option<&'c AtomicBool>,
+    /// Accumulate time of operations if set. Passed to forms, content of
+    /// tiling patterns and Type3 glyphs counted in the operation using them.
+    pub(crate) stats: Option<&'c Cell<RenderStats>>,
+    /// Record glyphs instead of painting them if set. Passed to forms.
+    pub(crate) glyphs: Option<&'c RefCell<Vec<GlyphDraw>>>,
+    content_filter: ContentFilter,
+    min_dash_length: f32,
+    snap_glyph_origin: bool,
+    optional_content: Option<Rc<OptionalContent>>,
+    /// One entry per open marked content sequence, true if it is hidden
+    /// optional content.
+    marked_content: Vec<bool>,
+    /// Set if glyph fallback enabled, passed to forms.
+    #[educe(Debug(ignore))]
+    fallback_font: Option<Rc<FallbackFont>>,
+    /// Stroke outline of clip paths, passed to forms.
+    debug_clip: bool,
+    /// Emit paths of solid color as SVG if set, passed to forms and Type3
+    /// glyphs painting on the same canvas.
+    svg: Option<Rc<RefCell<SvgWriter>>>,
+    /// Glyph outlines by font resource name and glyph id, None if the glyph
+    /// has no outline. Not shared with forms, font names resolved in their
+    /// own resources.
+    #[educe(Debug(ignore))]
+    glyph_paths: HashMap<Name, HashMap<u16, Option<SkiaPath>>>,
+}
+
+/// Options of [`Render`] passed to nested renders of forms, tiling patterns
+/// and Type3 glyphs, see [`Render::inherit_from()`].
+#[derive(Clone)]
+struct InheritedOptions<'c> {
+    cancel: Option<&'c AtomicBool>,
+    stats: Option<&'c Cell<RenderStats>>,
+    content_filter: ContentFilter,
+    min_dash_length: f32,
+    snap_glyph_origin: bool,
+    optional_content: Option<Rc<OptionalContent>>,
+    fallback_font: Option<Rc<FallbackFont>>,
+    debug_clip: bool,
+    unsupported: Option<Rc<RefCell<Vec<String>>>>,
+}
+
+impl InheritedOptions<'_> {
+    /// Options of renders painting content of an operation, such as Type3
+    /// glyphs and pattern cells. Content is not filtered by category, and not
+    /// timed on its own, see [`RenderStats`].
+    fn for_operation_content(mut self) -> Self {
+        self.content_filter = ContentFilter::All;
+        self.stats = None;
+        self
+    }
+}
+
+/// Scale `matrix` slightly to make `tile` size whole device pixels, tiles
+/// repeat at the same pixel phase, no seams between tiles.
+fn snap_tile_to_device_pixels(
+    tile: &Pixmap,
+    matrix: PatternToUserSpace,
+    user_to_device: &UserToDeviceSpace,
+) -> PatternToUserSpace {
+    let to_device = matrix.then(user_to_device);
+    let snap = |len: f32| {
+        if len > 0.0 {
+            len.round().max(1.0) / len
+        } else {
+            1.0
+        }
+    };
+    let kx = snap(tile.width() as f32 * to_device.m11.hypot(to_device.m12));
+    let ky = snap(tile.height() as f32 * to_device.m21.hypot(to_device.m22));
+    Transform2D::<f32, PatternSpace, PatternSpace>::scale(kx, ky).then(&matrix)
+}
+
+/// Object id of font `name` in `/Font` resources, None if it is a direct object.
+fn font_object_id(resources: &ResourceDict, name: &Name) -> Option<RuntimeObjectId> {
+    let resolver = resources.resolver();
+    let fonts = resolver
+        .opt_resolve_container_value(resources.dict(), &sname("Font"))
+        .ok()??;
+    let font = fonts.as_dict().ok()?.get(name)?;
+    font.reference().ok().map(|r| r.id().id())
+}
+
+/// Translate glyph in user space to make its origin on device pixel grid.
+fn snap_glyph_origin(
+    matrix: GlyphToUserSpace,
+    user_to_device: &UserToDeviceSpace,
+) -> GlyphToUserSpace {
+    let Some(device_to_user) = user_to_device.inverse() else {
+        return matrix;
+    };
+    let origin = matrix
+        .then(user_to_device)
+        .transform_point(Point2D::origin());
+    let snapped = Point2D::new(origin.x.round(), origin.y.round());
+    let delta = device_to_user.transform_point(snapped) - device_to_user.transform_point(origin);
+    matrix.then_translate(delta)
+}
+
+/// Operations that set color or color space.
+fn is_color_operation(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::SetStrokeColorSpace(_)
+            | Operation::SetFillColorSpace(_)
+            | Operation::SetStrokeColor(_)
+            | Operation::SetStrokeGray(_)
+            | Operation::SetStrokeCMYK(_)
+            | Operation::SetStrokeRGB(_)
+            | Operation::SetStrokeColorOrWithPattern(_)
+            | Operation::SetFillColor(_)
+            | Operation::SetFillGray(_)
+            | Operation::SetFillCMYK(_)
+            | Operation::SetFillRGB(_)
+            | Operation::SetFillColorOrWithPattern(_)
+    )
 }
 
 impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
@@ -610,6 +908,7 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         canvas: &'c mut Pixmap,
         option: RenderOption,
         resources: &'c ResourceDict<'a, 'b>,
+        force_font: Option<Rc<[u8]>>,
     ) -> Self
     where
         'a: 'c,
@@ -634,21 +933,78 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             canvas,
             stack: vec![state],
             path: Path::default(),
-            font_cache: FontCache::new(resources).unwrap(),
+            font_cache: FontCache::new(
+                resources,
+                force_font.clone(),
+                option.font_cache.as_ref(),
+                option.font_faces.as_deref(),
+            )
+            .unwrap(),
             resources,
             dimension: option.dimension,
+            force_font,
+            ignore_color: false,
+            cancel: None,
+            stats: None,
+            glyphs: None,
+            content_filter: option.content_filter,
+            min_dash_length: option.min_dash_length,
+            snap_glyph_origin: option.snap_glyph_origin,
+            optional_content: option.optional_content,
+            marked_content: vec![],
+            fallback_font: None,
+            debug_clip: option.debug_clip,
+            svg: option.svg,
+            glyph_paths: HashMap::new(),
+        }
+    }
+
+    /// Options passed to nested renders, taken before the nested render
+    /// borrows canvas of `self`.
+    fn inherited_options(&self) -> InheritedOptions<'c> {
+        InheritedOptions {
+            cancel: self.cancel,
+            stats: self.stats,
+            content_filter: self.content_filter,
+            min_dash_length: self.min_dash_length,
+            snap_glyph_origin: self.snap_glyph_origin,
+            optional_content: self.optional_content.clone(),
+            fallback_font: self.fallback_font.clone(),
+            debug_clip: self.debug_clip,
+            unsupported: self.stack.last().unwrap().unsupported.clone(),
         }
     }
 
+    /// Apply options of parent render, see [`Render::inherited_options()`].
+    fn inherit_from(&mut self, parent: InheritedOptions<'c>) {
+        self.cancel = parent.cancel;
+        self.stats = parent.stats;
+        self.content_filter = parent.content_filter;
+        self.min_dash_length = parent.min_dash_length;
+        self.snap_glyph_origin = parent.snap_glyph_origin;
+        self.optional_content = parent.optional_content;
+        self.fallback_font = parent.fallback_font;
+        self.debug_clip = parent.debug_clip;
+        // state of tiling pattern created from options, not from parent state
+        self.current_mut().unsupported = parent.unsupported;
+    }
+
     /// Return None if nested level is greater than 10, to avoid infinite loop
     fn new_nested(
         cur_level: u16,
         canvas: &'c mut Pixmap,
         option: RenderOption,
         resources: &'c ResourceDict<'a, 'b>,
+        force_font: Option<Rc<[u8]>>,
     ) -> Option<Self> {
         if cur_level < 10 {
-            Some(Self::create(cur_level + 1, canvas, option, resources))
+            Some(Self::create(
+                cur_level + 1,
+                canvas,
+                option,
+                resources,
+                force_font,
+            ))
         } else {
             warn!("nested level is greater than 10");
             None
@@ -664,7 +1020,23 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         'a: 'c,
         'b: 'c,
     {
-        Self::create(0, canvas, option, resources)
+        let force_font = option.force_font.as_ref().and_then(|font| {
+            match font {
+                ForceFont::Path(path) => std::fs::read(path).map_err(anyhow::Error::from),
+                ForceFont::Name(name) => system_font_data(name.as_str()),
+            }
+            .inspect_err(|e| warn!("failed to load force font {:?}: {}", font, e))
+            .ok()
+            .map(Rc::from)
+        });
+        let glyph_fallback = option.glyph_fallback;
+        let mut r = Self::create(0, canvas, option, resources, force_font);
+        if glyph_fallback {
+            r.fallback_font = FallbackFont::shared()
+                .inspect_err(|e| warn!("failed to load fallback font: {}", e))
+                .ok();
+        }
+        r
     }
 
     fn device_width(&self) -> u32 {
@@ -698,8 +1070,289 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         &mut self.current_mut().text_object
     }
 
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// Execute operations in order, stop if cancelled.
+    pub(crate) fn exec_all(&mut self, ops: impl IntoIterator<Item = Operation>) {
+        for op in ops {
+            if self.is_cancelled() {
+                break;
+            }
+            self.exec(op);
+        }
+    }
+
+    /// Execute page content, then paint appearance of `annots` over it, each
+    /// annotation starts from the graphics state before page content.
+    pub(crate) fn exec_page(
+        &mut self,
+        ops: impl IntoIterator<Item = Operation>,
+        annots: &[AnnotationDict<'a, 'b>],
+    ) {
+        let state = self.stack[0].clone();
+        self.exec_all(ops);
+        for annot in annots {
+            if self.is_cancelled() {
+                break;
+            }
+            if let Err(e) = self.paint_annotation(annot, state.clone()) {
+                warn!("paint annotation error: {}", e);
+            }
+        }
+    }
+
+    /// Paint normal appearance of annotation as form XObject, `/BBox`
+    /// transformed by `/Matrix` is mapped to annotation `/Rect`, see PDF
+    /// 32000-1:2008 12.5.5. Appearance of `NoRotate` annotation is not rotated
+    /// with the page, `NoZoom` not scaled by zoom, both keep upper-left corner
+    /// of `/Rect` fixed, see 12.5.3. Annotation without `/AP` is painted by
+    /// [`AnnotationDict::fallback_appearance()`] if supported.
+    fn paint_annotation(
+        &mut self,
+        annot: &AnnotationDict<'a, 'b>,
+        mut state: State,
+    ) -> AnyResult<()> {
+        if let Some(oc) = annot.dict().get(&sname("OC")) {
+            if !self.is_oc_visible(oc) {
+                return Ok(());
+            }
+        }
+        let Some(x_object) = annot.normal_appearance()? else {
+            // synthesized appearance is in default user space of the page
+            if let Some(ops) = annot.fallback_appearance()? {
+                self.stack.push(state);
+                self.exec_all(ops);
+                self.pop();
+            }
+            return Ok(());
+        };
+        let form = x_object.as_form()?;
+        let matrix = form.matrix()?;
+        let b_box = form.b_box()?;
+        let b_box = Box2D::from_points(
+            [
+                (b_box.left_x, b_box.lower_y),
+                (b_box.right_x, b_box.lower_y),
+                (b_box.left_x, b_box.upper_y),
+                (b_box.right_x, b_box.upper_y),
+            ]
+            .map(|(x, y)| matrix.transform_point(Point2D::new(x, y))),
+        );
+        if b_box.is_empty() {
+            return Ok(());
+        }
+
+        let rect = annot.rect()?;
+        let flags = annot.flags()?;
+        let mut fixed = UserToUserSpace::translation(-rect.left_x, -rect.upper_y);
+        if flags.contains(AnnotationFlags::NO_ZOOM) {
+            let zoom = self.dimension.zoom();
+            fixed = fixed.then_scale(1.0 / zoom, 1.0 / zoom);
+        }
+        if flags.contains(AnnotationFlags::NO_ROTATE) {
+            // page rotates clockwise, counter-rotate in y-up user space
+            fixed = fixed.then_rotate(Angle::degrees(self.dimension.rotate as f32));
+        }
+        state.concat_ctm(
+            UserToUserSpace::translation(-b_box.min.x, -b_box.min.y)
+                .then_scale(rect.width() / b_box.width(), rect.height() / b_box.height())
+                .then_translate((rect.left_x, rect.lower_y).into())
+                .then(&fixed)
+                .then_translate((rect.left_x, rect.upper_y).into()),
+        );
+        self.stack.push(state);
+        let r = self.paint_form_x_object(&x_object);
+        self.pop();
+        r
+    }
+
+    /// Whether `oc`, value of `/OC`, is visible. Visible if optional content
+    /// state not set, or `oc` invalid.
+    fn is_oc_visible(&self, oc: &Object) -> bool {
+        let Some(state) = &self.optional_content else {
+            return true;
+        };
+        state
+            .is_visible(self.resources.resolver(), oc)
+            .unwrap_or_else(|e| {
+                warn!("evaluate optional content visibility error: {}", e);
+                true
+            })
+    }
+
+    /// OCG or OCMD of `/OC` marked content. Property list should be a name in
+    /// `/Properties` resources, inline dictionary can not reference them.
+    fn marked_content_oc(&self, props: &NameOrDict) -> Option<&'b Object> {
+        let NameOrDict::Name(name) = props else {
+            return None;
+        };
+        self.resources.properties().ok().flatten()?.get(name)
+    }
+
+    /// Track marked content sequences, returns true if `op` is inside hidden
+    /// optional content. Marked content operators themselves are not skipped,
+    /// to keep sequences paired.
+    fn in_hidden_content(&mut self, op: &Operation) -> bool {
+        if self.optional_content.is_none() {
+            return false;
+        }
+
+        let in_hidden = self.marked_content.last().copied().unwrap_or(false);
+        match op {
+            Operation::BeginMarkedContent(_) => self.marked_content.push(in_hidden),
+            Operation::BeginMarkedContentWithProperties(tag, props) => {
+                let hidden = in_hidden
+                    || (tag.0 == sname("OC")
+                        && self
+                            .marked_content_oc(props)
+                            .is_some_and(|oc| !self.is_oc_visible(oc)));
+                self.marked_content.push(hidden);
+            }
+            Operation::EndMarkedContent => {
+                self.marked_content.pop();
+            }
+            _ => {}
+        }
+        in_hidden
+    }
+
+    /// Skip painting and text showing operations inside hidden optional
+    /// content, path painting replaced by `n` to keep clipping and path reset.
+    /// Other operations still update graphics and text state.
+    fn hide_operation(op: Operation) -> Option<Operation> {
+        match op {
+            Operation::ShowText(_)
+            | Operation::ShowTexts(_)
+            | Operation::SetSpacingMoveToNextLineAndShowText(..)
+            | Operation::PaintShading(_)
+            | Operation::PaintInlineImage(_)
+            | Operation::PaintXObject(_) => None,
+            Operation::MoveToNextLineAndShowText(_) => Some(Operation::MoveToStartOfNextLine),
+            Operation::Stroke
+            | Operation::CloseAndStroke
+            | Operation::FillNonZero
+            | Operation::FillNonZeroDeprecated
+            | Operation::FillEvenOdd
+            | Operation::FillAndStrokeNonZero
+            | Operation::FillAndStrokeEvenOdd
+            | Operation::CloseFillAndStrokeNonZero
+            | Operation::CloseFillAndStrokeEvenOdd => Some(Operation::EndPath),
+            op => Some(op),
+        }
+    }
+
+    /// Skip text showing operations excluded by content filter, replace
+    /// excluded path painting operations by `n` to keep clipping and path
+    /// reset. Image XObjects are filtered in `paint_x_object()`.
+    fn filter_operation(&self, op: Operation) -> Option<Operation> {
+        let filter = self.content_filter;
+        match op {
+            Operation::ShowText(_)
+            | Operation::ShowTexts(_)
+            | Operation::SetSpacingMoveToNextLineAndShowText(..)
+                if !filter.text() =>
+            {
+                None
+            }
+            Operation::MoveToNextLineAndShowText(_) if !filter.text() => {
+                Some(Operation::MoveToStartOfNextLine)
+            }
+            Operation::Stroke
+            | Operation::CloseAndStroke
+            | Operation::FillNonZero
+            | Operation::FillNonZeroDeprecated
+            | Operation::FillEvenOdd
+            | Operation::FillAndStrokeNonZero
+            | Operation::FillAndStrokeEvenOdd
+            | Operation::CloseFillAndStrokeNonZero
+            | Operation::CloseFillAndStrokeEvenOdd
+                if !filter.graphics() =>
+            {
+                Some(Operation::EndPath)
+            }
+            Operation::PaintShading(_) if !filter.graphics() => None,
+            Operation::PaintInlineImage(_) if !filter.images() => None,
+            op => Some(op),
+        }
+    }
+
+    /// Category of operation timed in [`RenderStats`], None if not timed.
+    /// Form XObject not timed, its operations are timed individually.
+    fn stats_category(&self, op: &Operation) -> Option<StatsCategory> {
+        match op {
+            Operation::Stroke
+            | Operation::CloseAndStroke
+            | Operation::FillNonZero
+            | Operation::FillNonZeroDeprecated
+            | Operation::FillEvenOdd
+            | Operation::FillAndStrokeNonZero
+            | Operation::FillAndStrokeEvenOdd
+            | Operation::CloseFillAndStrokeNonZero
+            | Operation::CloseFillAndStrokeEvenOdd => Some(StatsCategory::Path),
+            Operation::ShowText(_)
+            | Operation::ShowTexts(_)
+            | Operation::MoveToNextLineAndShowText(_)
+            | Operation::SetSpacingMoveToNextLineAndShowText(..) => Some(StatsCategory::Text),
+            Operation::PaintShading(_) => Some(StatsCategory::Shading),
+            Operation::PaintInlineImage(_) => Some(StatsCategory::Image),
+            Operation::PaintXObject(nm) => {
+                let x_objects = self.resources.x_object().ok()?;
+                (x_objects.get(&nm.0)?.subtype().ok()? == XObjectType::Image)
+                    .then_some(StatsCategory::Image)
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn exec(&mut self, op: Operation) {
         debug!("handle operation: {:?}", op);
+        if self.ignore_color && is_color_operation(&op) {
+            debug!("ignore color operation in uncolored pattern");
+            return;
+        }
+        let op = if self.in_hidden_content(&op) {
+            let Some(op) = Self::hide_operation(op) else {
+                debug!("operation in hidden optional content");
+                return;
+            };
+            op
+        } else {
+            op
+        };
+        let Some(op) = self.filter_operation(op) else {
+            debug!("operation excluded by content filter");
+            return;
+        };
+        let timing = self
+            .stats
+            .and_then(|stats| Some((stats, self.stats_category(&op)?, Instant::now())));
+        self.exec_operation(op);
+        if let Some((stats, category, start)) = timing {
+            let mut r = stats.get();
+            r.add(category, start.elapsed());
+            stats.set(r);
+        }
+    }
+
+    /// Log error of operation `op` and continue with the next operation,
+    /// reported as unsupported feature in strict mode.
+    fn report_error(&self, op: &str, r: AnyResult<()>) {
+        let Err(e) = r else {
+            return;
+        };
+        warn!("{}: {}", op, e);
+        let state = self.stack.last().unwrap();
+        match e.downcast_ref::<ObjectValueError>() {
+            Some(ObjectValueError::Unsupported(features)) => {
+                features.iter().for_each(|f| state.unsupported(f.as_str()))
+            }
+            _ => state.unsupported(format!("{op}: {e}")),
+        }
+    }
+
+    fn exec_operation(&mut self, op: Operation) {
         match op {
             // General Graphics State Operations
             Operation::SetLineWidth(width) => self.current_mut().set_line_width(width),
@@ -715,6 +1368,12 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 let res = self.resources.ext_g_state().unwrap();
                 let res = res.get(&nm.0).expect("ExtGState not found");
                 self.current_mut().set_graphics_state(res);
+                if res.dict().contains_key(&sname("SMask")) {
+                    match self.soft_mask(res) {
+                        Ok(mask) => self.current_mut().set_soft_mask(mask),
+                        Err(e) => warn!("invalid ExtGState SMask: {e}"),
+                    }
+                }
             }
 
             // Special Graphics State Operations
@@ -773,7 +1432,13 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             Operation::SetTextRenderingMode(mode) => {
                 self.text_object_mut().set_text_rendering_mode(mode);
             }
-            Operation::SetTextRise(rise) => self.text_object_mut().set_text_rise(rise),
+            Operation::SetTextRise(rise) => {
+                if rise != 0.0 {
+                    info!("not implemented: text rise");
+                    self.current_mut().unsupported("text rise");
+                }
+                self.text_object_mut().set_text_rise(rise);
+            }
 
             // Text Positioning Operations
             Operation::MoveTextPosition(p) => self.text_object_mut().move_text_position(p),
@@ -821,9 +1486,10 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 ColorSpace::DeviceRGB,
                 Some(&color),
             ),
-            Operation::SetStrokeColorOrWithPattern(color_or_name) => self
-                .set_color_or_pattern(Self::stroke_color_state, &color_or_name)
-                .unwrap(),
+            Operation::SetStrokeColorOrWithPattern(color_or_name) => {
+                let r = self.set_color_or_pattern(Self::stroke_color_state, &color_or_name);
+                self.report_error("SCN", r);
+            }
             Operation::SetFillColor(args) => self.set_color_args(Self::fill_color_state, args),
             Operation::SetFillGray(color) => self.set_color_and_space(
                 Self::fill_color_state,
@@ -840,15 +1506,23 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 ColorSpace::DeviceRGB,
                 Some(&color),
             ),
-            Operation::SetFillColorOrWithPattern(color_or_name) => self
-                .set_color_or_pattern(Self::fill_color_state, &color_or_name)
-                .unwrap(),
+            Operation::SetFillColorOrWithPattern(color_or_name) => {
+                let r = self.set_color_or_pattern(Self::fill_color_state, &color_or_name);
+                self.report_error("scn", r);
+            }
 
             // Shading Operation
-            Operation::PaintShading(name) => self.paint_shading(name).unwrap(),
+            Operation::PaintShading(name) => {
+                self.mark_svg_raster();
+                let r = self.paint_shading(name);
+                self.report_error("sh", r);
+            }
 
             // XObject Operation
-            Operation::PaintXObject(name) => self.paint_x_object(&name).unwrap(),
+            Operation::PaintXObject(name) => {
+                let r = self.paint_x_object(&name);
+                self.report_error("Do", r);
+            }
 
             // Marked Content Operations
             Operation::DesignateMarkedContentPoint(_)
@@ -865,10 +1539,15 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             Operation::SetGlyphWidthAndBoundingBox(_, _, _) => {}
 
             Operation::PaintInlineImage(inline_image) => {
-                self.paint_inline_image(inline_image).unwrap()
+                self.mark_svg_raster();
+                let r = self.paint_inline_image(inline_image);
+                self.report_error("BI", r);
             }
 
-            _ => todo!("{:?}", op),
+            _ => {
+                warn!("not implemented: {:?}", op);
+                self.current_mut().unsupported(format!("operation {op:?}"));
+            }
         }
     }
 
@@ -900,14 +1579,22 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     fn stroke(&mut self) {
         if let Some(p) = self.path.finish() {
             let state = self.stack.last().unwrap();
-            let stroke = state.get_stroke();
-            state.stroke_state.stroke(
+            let stroke = state.get_stroke_with_min_dash(self.min_dash_length);
+            if !Self::svg_path(
+                self.svg.as_deref(),
                 self.canvas,
+                state,
                 p,
-                stroke,
-                state.user_to_device.into_skia(),
-                state.get_mask().as_deref(),
-            );
+                PathPaint::Stroke(&state.stroke, &state.dash),
+            ) {
+                state.stroke_state.stroke(
+                    self.canvas,
+                    p,
+                    &stroke,
+                    state.user_to_device.into_skia(),
+                    state.get_mask().as_deref(),
+                );
+            }
         } else {
             debug!("stroke: empty or invalid path");
         }
@@ -917,14 +1604,84 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     fn end_path(&mut self) {
         let state = self.stack.last_mut().unwrap();
         if let Some(rule) = state.clipping {
-            if let Some(p) = self.path.finish() {
-                state.update_mask(p, rule, true);
+            let path = self.path.finish();
+            let ctm = state.ctm.into_skia();
+            state.update_svg_clip(self.svg.as_deref(), path, ctm, rule);
+            match path {
+                Some(p) => {
+                    if self.debug_clip {
+                        Self::stroke_clip_outline(self.canvas, p, state);
+                    }
+                    state.update_mask(p, rule, true)
+                }
+                // clip by empty path results empty clip region, nothing visible
+                None => state.clip_all(),
             }
             state.clipping = None;
         }
         self.path.reset();
     }
 
+    /// Emit `path` in user space as SVG element if `svg` set and the paint is
+    /// a solid color. Returns false if not emitted, caller paints it on
+    /// canvas, marked as raster content if `svg` set.
+    fn svg_path(
+        svg: Option<&RefCell<SvgWriter>>,
+        canvas: &mut Pixmap,
+        state: &State,
+        path: &SkiaPath,
+        paint: PathPaint<'_>,
+    ) -> bool {
+        let Some(svg) = svg else {
+            return false;
+        };
+        let color_state = match paint {
+            PathPaint::Fill(_) => &state.fill_state,
+            PathPaint::Stroke(..) => &state.stroke_state,
+        };
+        let mut svg = svg.borrow_mut();
+        match color_state.solid_color() {
+            Some(color) if !state.blends_backdrop() => {
+                svg.path(
+                    canvas,
+                    path,
+                    state.ctm.into_skia(),
+                    color,
+                    paint,
+                    state.svg_clip,
+                    state.get_mask().as_deref(),
+                );
+                true
+            }
+            _ => {
+                svg.mark_raster(canvas, state.blends_backdrop());
+                false
+            }
+        }
+    }
+
+    /// Mark canvas painted by content can not be expressed in SVG, call
+    /// before painting.
+    fn mark_svg_raster(&mut self) {
+        if let Some(svg) = &self.svg {
+            let blend = self.stack.last().unwrap().blends_backdrop();
+            svg.borrow_mut().mark_raster(self.canvas, blend);
+        }
+    }
+
+    /// Stroke outline of clip path `p` in semi-transparent red hairline,
+    /// ignoring current clip region.
+    fn stroke_clip_outline(canvas: &mut Pixmap, p: &SkiaPath, state: &State) {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(255, 0, 0, 128);
+        paint.anti_alias = true;
+        let stroke = Stroke {
+            width: 0.0,
+            ..Default::default()
+        };
+        canvas.stroke_path(p, &paint, &stroke, state.user_to_device.into_skia(), None);
+    }
+
     fn close_path(&mut self) {
         self.path.close_path();
     }
@@ -937,13 +1694,21 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     fn _fill(&mut self, fill_rule: FillRule, reset_path: bool) {
         let state = self.stack.last().unwrap();
         if let Some(p) = self.path.finish() {
-            state.fill_state.fill(
+            if !Self::svg_path(
+                self.svg.as_deref(),
                 self.canvas,
+                state,
                 p,
-                fill_rule,
-                state.user_to_device.into_skia(),
-                state.get_mask().as_deref(),
-            );
+                PathPaint::Fill(fill_rule),
+            ) {
+                state.fill_state.fill(
+                    self.canvas,
+                    p,
+                    fill_rule,
+                    state.user_to_device.into_skia(),
+                    state.get_mask().as_deref(),
+                );
+            }
         }
         if reset_path {
             self.end_path();
@@ -1037,6 +1802,7 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
 
         let paint = PixmapPaint {
             opacity: state.fill_state.alpha(),
+            blend_mode: state.fill_state.blend_mode(),
             ..Default::default()
         };
         let img = PixmapRef::from_bytes(img.as_raw(), img.width(), img.height()).unwrap();
@@ -1114,9 +1880,22 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 Some(Self::load_image_as_mask(img.into_rgba8(), state, false).unwrap())
             });
 
+        // interpolating between palette entries makes colors not in the
+        // palette, scale indexed image by nearest neighbor
+        let is_indexed = x_object.color_space()?.is_some_and(|args| {
+            matches!(
+                ColorSpace::<f32>::from_args(
+                    &args,
+                    self.resources.resolver(),
+                    Some(self.resources)
+                ),
+                Ok(ColorSpace::Indexed(_))
+            )
+        });
         let paint = PixmapPaint {
             opacity: state.fill_state.alpha(),
-            quality: if x_object.interpolate()? {
+            blend_mode: state.fill_state.blend_mode(),
+            quality: if x_object.interpolate()? && !is_indexed {
                 FilterQuality::Bilinear
             } else {
                 FilterQuality::Nearest
@@ -1149,6 +1928,70 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     fn paint_form_x_object(&mut self, x_object: &XObjectDict<'a, 'b>) -> AnyResult<()> {
         debug!("Render form");
 
+        let form = x_object.as_form()?;
+        let state = self.stack.last().unwrap();
+        let mut inner_state = state.clone();
+
+        // If form is an isolated transparency group, or fill alpha is not
+        // opaque, or blend mode is not normal, render form to an offscreen
+        // canvas, then composite it at the alpha and blend mode as a unit, the
+        // form itself starts with opaque alpha and normal blend mode.
+        // Non-isolated group is painted in place, so blend modes of objects in
+        // it apply to the backdrop, such as Multiply highlight annotations. If
+        // composited at alpha, its offscreen canvas starts with the backdrop;
+        // composited at other blend mode, it is treated as isolated. Knockout
+        // groups not supported.
+        let alpha = state.fill_state.alpha();
+        let blend_mode = state.fill_state.blend_mode();
+        let isolated = form
+            .group()?
+            .map(|g| g.get(&sname("I")).map_or(Ok(false), |o| o.bool()))
+            .transpose()?;
+        let backdrop = isolated == Some(false) && blend_mode == BlendMode::SourceOver;
+        let mut group_canvas = (isolated == Some(true)
+            || alpha < 1.0
+            || blend_mode != BlendMode::SourceOver)
+            .then(|| {
+                inner_state.set_fill_alpha(1.0);
+                inner_state.set_stroke_alpha(1.0);
+                inner_state.set_blend_mode(BlendMode::SourceOver);
+                if backdrop {
+                    self.canvas.clone()
+                } else {
+                    Pixmap::new(self.canvas.width(), self.canvas.height()).unwrap()
+                }
+            });
+        self.exec_form(x_object, inner_state, group_canvas.as_mut())?;
+
+        if let Some(group_canvas) = group_canvas {
+            self.mark_svg_raster();
+            self.canvas.draw_pixmap(
+                0,
+                0,
+                group_canvas.as_ref(),
+                &PixmapPaint {
+                    opacity: alpha,
+                    blend_mode,
+                    ..Default::default()
+                },
+                Transform::identity(),
+                None,
+            );
+        }
+
+        debug!("End render form");
+        Ok(())
+    }
+
+    /// Execute content of form `x_object` in a sub render starts with `state`,
+    /// concatenates form's Matrix to its ctm. Paints on `canvas`, or current
+    /// canvas if None.
+    fn exec_form(
+        &mut self,
+        x_object: &XObjectDict<'a, 'b>,
+        mut state: State,
+        canvas: Option<&mut Pixmap>,
+    ) -> AnyResult<()> {
         let form = x_object.as_form()?;
         let matrix = form.matrix()?;
         let b_box = form.b_box()?;
@@ -1156,43 +1999,149 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         let stream = stream.decode(self.resources.resolver())?;
         let content = PageContent::new(vec![stream.into_owned()]);
         let resources = form.resources()?;
+        // form without `/Resources` inherits resources of the invoking
+        // context, which is the nearest enclosing form or page
         let resources = resources.as_ref().unwrap_or(self.resources);
 
-        let state = self.stack.last().unwrap();
-        let mut inner_state = state.clone();
         let ctm = matrix.then(&state.ctm).with_destination().with_source();
-        inner_state.set_ctm(ctm);
+        state.set_ctm(ctm);
+
+        // content painted on other canvas is not part of svg
+        let svg = canvas.is_none().then(|| self.svg.clone()).flatten();
+        let parent = self.inherited_options();
+        let canvas = match canvas {
+            Some(canvas) => canvas,
+            None => &mut *self.canvas,
+        };
         let Some(mut render) = Render::new_nested(
             self.nested_level,
-            self.canvas,
+            canvas,
             RenderOptionBuilder::default()
                 .dimension(self.dimension)
                 .crop(Some(b_box))
                 .background_color(SkiaColor::TRANSPARENT)
-                .state(inner_state)
+                .state(state)
                 .build(),
             resources,
+            self.force_font.clone(),
         ) else {
             return Ok(());
         };
-        content
-            .operations()
-            .into_iter()
-            .for_each(|op| render.exec(op));
-
-        debug!("End render form");
+        render.inherit_from(parent);
+        render.ignore_color = self.ignore_color;
+        render.glyphs = self.glyphs;
+        if let Some(svg) = svg {
+            let state = render.current_mut();
+            let ctm = state.ctm.into_skia();
+            let b_box = PathBuilder::from_rect(b_box.into_skia());
+            state.update_svg_clip(Some(&svg), Some(&b_box), ctm, FillRule::Winding);
+            render.svg = Some(svg);
+        }
+        render.exec_all(content.operations());
         Ok(())
     }
 
+    /// Compute soft mask of ExtGState in device space, None if `/SMask` is
+    /// `/None`.
+    ///
+    /// Group XObject painted using ctm at the time ExtGState set, on backdrop
+    /// color for luminosity mask, on transparent canvas for alpha mask.
+    /// Luminosity or alpha of the result mapped by transfer function `/TR`
+    /// is the mask value.
+    fn soft_mask(&mut self, res: &GraphicsStateParameterDict<'a, 'b>) -> AnyResult<Option<Mask>> {
+        let Some(soft_mask) = res.soft_mask()? else {
+            return Ok(None);
+        };
+        let subtype = soft_mask.subtype()?;
+        let group = soft_mask.group()?;
+        let backdrop = match soft_mask.backdrop_color()? {
+            _ if subtype == SoftMaskType::Alpha => SkiaColor::TRANSPARENT,
+            Some(color) => {
+                let cs = match group.as_form()?.group()?.and_then(|g| g.get(&sname("CS"))) {
+                    Some(cs) => ColorSpace::from_args(
+                        &ColorSpaceArgs::try_from(cs)?,
+                        self.resources.resolver(),
+                        Some(self.resources),
+                    )?,
+                    None => match color.len() {
+                        1 => ColorSpace::DeviceGray,
+                        4 => ColorSpace::DeviceCMYK,
+                        _ => ColorSpace::DeviceRGB,
+                    },
+                };
+                to_skia_color(&cs, &color)
+            }
+            None => SkiaColor::BLACK,
+        };
+
+        let mut canvas = Pixmap::new(self.canvas.width(), self.canvas.height()).unwrap();
+        canvas.fill(backdrop);
+        let mut state = self.stack.last().unwrap().clone();
+        state.mask = None;
+        state.soft_mask = None;
+        state.set_fill_alpha(1.0);
+        state.set_stroke_alpha(1.0);
+        state.set_blend_mode(BlendMode::SourceOver);
+        self.exec_form(&group, state, Some(&mut canvas))?;
+
+        // map luminosity or alpha through transfer function once per possible
+        // value
+        let transfer = soft_mask
+            .transfer_function()?
+            .map(|f| f.func())
+            .transpose()?;
+        let mut table = [0u8; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            let l = i as f32 / 255.0;
+            let l = match &transfer {
+                Some(f) => f.call(&[l])?[0],
+                None => l,
+            };
+            *v = (l.clamp(0.0, 1.0) * 255.0).round().to_u8().unwrap();
+        }
+
+        let mut mask = Mask::new(canvas.width(), canvas.height()).unwrap();
+        for (m, p) in mask.data_mut().iter_mut().zip(canvas.pixels()) {
+            let v = match subtype {
+                SoftMaskType::Alpha => usize::from(p.alpha()),
+                SoftMaskType::Luminosity => {
+                    let p = p.demultiply();
+                    // 0.3r + 0.59g + 0.11b in 8-bit fixed point
+                    (77 * usize::from(p.red())
+                        + 151 * usize::from(p.green())
+                        + 28 * usize::from(p.blue())
+                        + 128)
+                        >> 8
+                }
+            };
+            *m = table[v];
+        }
+        Ok(Some(mask))
+    }
+
     /// Paints the specified XObject. Only XObjectType::Image supported
     fn paint_x_object(&mut self, nm: &NameOfDict) -> AnyResult<()> {
         let x_objects = self.resources.x_object()?;
         let x_object = &x_objects[&nm.0];
+        if let Some(oc) = x_object.dict().get(&sname("OC")) {
+            if !self.is_oc_visible(oc) {
+                debug!("skip hidden optional content XObject: {}", nm.0);
+                return Ok(());
+            }
+        }
 
         match x_object.subtype()? {
-            XObjectType::Image => self.paint_image_x_object(x_object),
+            XObjectType::Image if !self.content_filter.images() => Ok(()),
+            XObjectType::Image => {
+                self.mark_svg_raster();
+                self.paint_image_x_object(x_object)
+            }
             XObjectType::Form => self.paint_form_x_object(x_object),
-            t => todo!("{:?}", t),
+            t => {
+                info!("not implemented: {:?} XObject", t);
+                self.current_mut().unsupported(format!("{t:?} XObject"));
+                Ok(())
+            }
         }
     }
 
@@ -1242,6 +2191,22 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     }
 
     fn paint_radial(&mut self, radial: &Radial) -> AnyResult<()> {
+        // BBox clips all painting of the shading, extend fills included
+        let Some(b_box) = radial.b_box else {
+            return self.paint_radial_in_clip(radial);
+        };
+        self.push();
+        self.current_mut().update_mask(
+            PathBuilder::from_rect(b_box.into_skia()),
+            FillRule::Winding,
+            true,
+        );
+        let r = self.paint_radial_in_clip(radial);
+        self.pop();
+        r
+    }
+
+    fn paint_radial_in_clip(&mut self, radial: &Radial) -> AnyResult<()> {
         let Domain { start: t0, end: t1 } = radial.domain;
         let (x0, y0) = (radial.start.point.x, radial.start.point.y);
         let (x1, y1) = (radial.end.point.x, radial.end.point.y);
@@ -1318,6 +2283,9 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         }
 
         for t in 0..=steps {
+            if self.is_cancelled() {
+                break;
+            }
             let t = t as f32 / steps as f32;
             let (x, y, r) = circle(t);
             let c = radial.function.call(&[t][..])?;
@@ -1340,13 +2308,83 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         Ok(())
     }
 
+    fn paint_mesh(&mut self, mesh: &Mesh) -> AnyResult<()> {
+        // BBox clips all painting of the shading
+        let Some(b_box) = mesh.b_box else {
+            return self.paint_mesh_in_clip(mesh);
+        };
+        self.push();
+        self.current_mut().update_mask(
+            PathBuilder::from_rect(b_box.into_skia()),
+            FillRule::Winding,
+            true,
+        );
+        let r = self.paint_mesh_in_clip(mesh);
+        self.pop();
+        r
+    }
+
+    fn paint_mesh_in_clip(&mut self, mesh: &Mesh) -> AnyResult<()> {
+        let state = self.stack.last().unwrap();
+        let ctm = state.user_to_device;
+        let mask = state.get_mask();
+        let alpha = state.fill_state.alpha();
+        let mut paint = Paint {
+            // anti-aliased edges of adjacent triangles leave visible seams
+            anti_alias: false,
+            blend_mode: state.fill_state.blend_mode(),
+            ..Default::default()
+        };
+
+        for triangle in &mesh.triangles {
+            if self.is_cancelled() {
+                break;
+            }
+
+            let vertices = triangle.clone().map(|v| {
+                let (x, y) = ctm.transform_point((v.point.x, v.point.y).into()).into();
+                (tiny_skia::Point::from_xy(x, y), v.color)
+            });
+            split_triangle(vertices, 0, &mut |[a, b, c], color| {
+                let mut pb = PathBuilder::new();
+                pb.move_to(a.x, a.y);
+                pb.line_to(b.x, b.y);
+                pb.line_to(c.x, c.y);
+                pb.close();
+                let Some(path) = pb.finish() else {
+                    return Ok(());
+                };
+
+                let mut color = mesh.color(color)?;
+                color.set_alpha(alpha);
+                paint.set_color(color);
+                self.canvas.fill_path(
+                    &path,
+                    &paint,
+                    FillRule::Winding,
+                    Transform::identity(),
+                    mask.as_deref(),
+                );
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
     fn paint_shading(&mut self, nm: NameOfDict) -> AnyResult<()> {
         let shading = self.resources.shading()?;
         let shading = &shading[&nm.0];
         match build_shading(shading, self.resources)? {
             Some(Shading::Radial(radial)) => self.paint_radial(&radial),
             Some(Shading::Axial(axial)) => self.paint_axial(axial),
-            None => Ok(()),
+            Some(Shading::Mesh(mesh)) => self.paint_mesh(&mesh),
+            None => {
+                // other shading types return None if degenerate
+                if shading.shading_type()? == ShadingType::Function {
+                    self.current_mut().unsupported("function-based shading");
+                }
+                Ok(())
+            }
         }
     }
 
@@ -1437,12 +2475,79 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         };
 
         Ok(match build_shading(&shading, resources)? {
+            Some(Shading::Mesh(mesh)) => {
+                drop(_restore);
+                let matrix = pattern.matrix()?.with_source().with_destination();
+                return Ok(self
+                    .mesh_pattern(&mesh, matrix)
+                    .map(|paint| (paint, background_color)));
+            }
             Some(shading) => Some((shading, pattern.matrix()?)),
             None => return Ok(None),
         }
         .map(|shader| (PaintCreator::Gradient(shader), background_color)))
     }
 
+    /// Paint mesh shading of shading pattern on a pixmap of pattern space at
+    /// device resolution, painted as a non-repeating tile. The pixmap has a
+    /// transparent border of one pixel, so that pad spreading leaves area
+    /// outside of the mesh unpainted. None if the mesh is empty.
+    fn mesh_pattern(&self, mesh: &Mesh, matrix: PatternToUserSpace) -> Option<PaintCreator> {
+        let mut bounds = euclid::default::Box2D::from_points(
+            mesh.triangles
+                .iter()
+                .flatten()
+                .map(|v| Point2D::new(v.point.x, v.point.y)),
+        );
+        if let Some(b_box) = mesh.b_box {
+            bounds = bounds.intersection_unchecked(&Box2D::new(
+                Point2D::new(b_box.left_x, b_box.lower_y),
+                Point2D::new(b_box.right_x, b_box.upper_y),
+            ));
+        }
+        if bounds.is_empty() {
+            return None;
+        }
+
+        let mut scale = self.dimension.zoom() * matrix.determinant().abs().sqrt();
+        let pixels = bounds.area() * scale * scale;
+        if pixels > MAX_CANVAS_PIXELS {
+            scale *= (MAX_CANVAS_PIXELS / pixels).sqrt();
+        }
+        let (w, h) = (
+            (bounds.width() * scale).ceil().max(1.0) + 2.0,
+            (bounds.height() * scale).ceil().max(1.0) + 2.0,
+        );
+        let option = RenderOptionBuilder::default()
+            .page_box(&Rectangle::from_xywh(0.0, 0.0, w, h), 0)
+            .background_color(SkiaColor::TRANSPARENT)
+            .build();
+        let mut canvas = option.create_canvas();
+        let parent = self.inherited_options().for_operation_content();
+        let mut render = Render::new_nested(
+            self.nested_level,
+            &mut canvas,
+            option,
+            self.resources,
+            self.force_font.clone(),
+        )?;
+        render.inherit_from(parent);
+        // user space of the pixmap is in pixels
+        let to_pixels = UserToUserSpace::translation(-bounds.min.x, -bounds.min.y)
+            .then_scale(scale, scale)
+            .then_translate(euclid::vec2(1.0, 1.0));
+        render.current_mut().concat_ctm(to_pixels);
+        if let Err(e) = render.paint_mesh(mesh) {
+            warn!("paint mesh of shading pattern: {}", e);
+        }
+        drop(render);
+
+        let to_pattern = Transform2D::translation(-1.0, -1.0)
+            .then_scale(1.0 / scale, 1.0 / scale)
+            .then_translate(bounds.min.to_vector().cast_unit());
+        Some(PaintCreator::Tile((canvas, to_pattern.then(&matrix), true)))
+    }
+
     fn tiling_pattern(
         &mut self,
         canvas_size: &Size2D<f32>,
@@ -1473,6 +2578,8 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             matrix = matrix.then_scale(2.0, 2.0);
         }
 
+        // pattern content, such as `sh` operator, resolves resources against
+        // pattern's own `/Resources`, not the resources of painting content
         let resources = tile.resources()?;
         let option = RenderOptionBuilder::default()
             .zoom(zoom)
@@ -1480,37 +2587,100 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             .background_color(SkiaColor::TRANSPARENT)
             .build();
         let mut canvas = option.create_canvas();
-        let Some(mut render) =
-            Render::new_nested(self.nested_level, &mut canvas, option, &resources)
-        else {
+        if tile.tiling_type()? == TilingType::NoDistortion {
+            let user_to_device = &self.stack.last().unwrap().user_to_device;
+            matrix = snap_tile_to_device_pixels(&canvas, matrix, user_to_device);
+        }
+        let Some(mut render) = Render::new_nested(
+            self.nested_level,
+            &mut canvas,
+            option,
+            &resources,
+            self.force_font.clone(),
+        ) else {
             return Ok(());
         };
-        let color_state = get_state(self);
-        if let Some(args) = color_args {
-            // set color used for paint matrix image
-            color_state.set_color_args(args);
+        if tile.paint_type()? == TilingPaintType::Uncolored {
+            // uncolored pattern paints in the color supplied by `scn`/`SCN`,
+            // color operators in pattern content are ignored
+            render.ignore_color = true;
+            if let Some(args) = color_args {
+                let color = to_skia_color(&get_state(self).color_space, args.as_ref());
+                let state = render.current_mut();
+                state.fill_state.set_paint(PaintCreator::Color(color), None);
+                state
+                    .stroke_state
+                    .set_paint(PaintCreator::Color(color), None);
+            }
         }
-        ops.into_iter().for_each(|op| render.exec(op));
+        render.inherit_from(self.inherited_options().for_operation_content());
+        render.exec_all(ops);
         drop(render);
+        let color_state = get_state(self);
         color_state.paint = PaintCreator::Tile((canvas, matrix, tile.x_step()? > b_box.width()));
         Ok(())
     }
 
-    fn gen_glyph_path(glyph_render: &dyn GlyphRender<SkiaPathSink>, gid: u16) -> PathBuilder {
+    /// Outline of glyph `gid`, None if the glyph is empty or missing in the
+    /// font.
+    fn gen_glyph_path(glyph_render: &dyn GlyphRender<SkiaPathSink>, gid: u16) -> Option<SkiaPath> {
+        let mut sink = SkiaPathSink(PathBuilder::new());
+        glyph_render
+            .render(gid, &mut sink)
+            .inspect_err(|e| warn!("render glyph {}: {}", gid, e))
+            .ok()?;
+        sink.into_inner().finish()
+    }
+
+    /// Path of the glyph of unicode of char `code` decoded as `ch` in
+    /// `fallback`, scaled to glyph space of `op`. None if unicode unknown,
+    /// maps to several chars, or not in `fallback`.
+    fn gen_fallback_glyph_path(
+        fallback: &FallbackFont,
+        op: &dyn FontOp,
+        code: u32,
+        ch: u32,
+    ) -> Option<SkiaPath> {
+        let ch = match op.char_to_unicode(code, ch)?.as_slice() {
+            &[ch] => ch,
+            _ => return None,
+        };
         let mut sink = SkiaPathSink(PathBuilder::new());
-        glyph_render.render(gid, &mut sink).unwrap();
+        let units_per_em = fallback
+            .render(ch, &mut sink)
+            .inspect_err(|e| warn!("render fallback glyph of {:?}: {}", ch, e))
+            .ok()??;
+        let scale = op.units_per_em() as f32 / f32::from(units_per_em);
         sink.into_inner()
+            .finish()?
+            .transform(Transform::from_scale(scale, scale))
     }
 
     fn render_glyph(
         canvas: &mut Pixmap,
+        svg: Option<&RefCell<SvgWriter>>,
         text_clip_path: &mut Path,
         state: &State,
         path: SkiaPath,
         render_mode: TextRenderingMode,
         trans: Transform,
     ) {
+        let fill = PathPaint::Fill(FillRule::Winding);
+        let stroke = PathPaint::Stroke(&state.stroke, &state.dash);
         match render_mode {
+            TextRenderingMode::Fill if Self::svg_path(svg, canvas, state, &path, fill) => {}
+            TextRenderingMode::Stroke if Self::svg_path(svg, canvas, state, &path, stroke) => {}
+            TextRenderingMode::FillAndStroke if Self::svg_path(svg, canvas, state, &path, fill) => {
+                if !Self::svg_path(svg, canvas, state, &path, stroke) {
+                    canvas.stroke_path(
+                        &path,
+                        &state.get_stroke_paint(),
+                        state.get_stroke(),
+                        trans,
+                        state.get_mask().as_deref(),
+                    );
+                }
+            }
             TextRenderingMode::Fill => {
                 canvas.fill_path(
                     &path,
@@ -1554,7 +2724,8 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 text_clip_path.path_builder().push_path(&path);
             }
             _ => {
-                todo!("Unsupported text rendering mode: {:?}", render_mode);
+                info!("Unsupported text rendering mode: {:?}", render_mode);
+                state.unsupported(format!("text rendering mode {render_mode:?}"));
             }
         }
     }
@@ -1565,10 +2736,12 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
             return;
         }
 
-        let font = self
-            .font_cache
-            .get_font(text_object.font_name.as_ref().unwrap())
-            .unwrap();
+        let font_name = text_object.font_name.as_ref();
+        let Some(font) = font_name.and_then(|n| self.font_cache.get_font(n)) else {
+            // such as `/Helv` of synthesized `/FreeText` appearance, not in resources
+            warn!("font not found: {:?}", text_object.font_name);
+            return;
+        };
         debug!(
             "font: {}, type: {:?}",
             text_object.font_name.as_ref().unwrap(),
@@ -1582,10 +2755,40 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
         let mut text_object = state.text_object.clone();
         text_object.set_units_per_em(op.units_per_em() as f32);
         let user_to_device = state.user_to_device.into_skia();
+        let font_name = text_object.font_name.clone().unwrap();
+        let font_id = self
+            .glyphs
+            .and_then(|_| font_object_id(self.resources, &font_name));
+        let em = op.units_per_em() as f32;
+        let record = |code, ch, matrix: GlyphToUserSpace| {
+            if let Some(glyphs) = self.glyphs {
+                glyphs.borrow_mut().push(GlyphDraw {
+                    font_id,
+                    font_name: font_name.clone(),
+                    gid: op.char_to_gid(ch),
+                    unicode: op.char_to_unicode(code, ch).into_iter().flatten().collect(),
+                    bbox: Box2D::new(
+                        Point2D::new(0.0, -0.2 * em),
+                        Point2D::new(op.char_width(ch).0, 0.8 * em),
+                    ),
+                    transform: matrix.then(&state.user_to_device),
+                });
+            }
+        };
 
         if let Some(type3_font) = font.as_type3() {
             let font_matrix = type3_font.matrix().unwrap();
+            if self.glyphs.is_some() {
+                for (code, ch) in op.decode_code_chars(text) {
+                    record(code, ch, text_object.type3_runtime_matrix(&font_matrix));
+                    text_object.move_to_next_pos(op.char_width(ch), ch == 32);
+                }
+                self.current_mut().text_object = text_object;
+                return;
+            }
+
             let resources = type3_font.resources().unwrap();
+            let parent = self.inherited_options().for_operation_content();
             let Some(mut render) = Render::new_nested(
                 self.nested_level,
                 self.canvas,
@@ -1595,9 +2798,12 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                     .state(state.clone())
                     .build(),
                 resources.as_ref().unwrap_or(self.resources),
+                self.force_font.clone(),
             ) else {
                 return;
             };
+            render.inherit_from(parent);
+            render.svg = self.svg.clone();
 
             for ch in op.decode_chars(text) {
                 render.current_mut().set_ctm(
@@ -1608,9 +2814,7 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                         .with_source(),
                 );
                 if let Some(glyph) = type3_font.get_glyph(op.char_to_gid(ch)) {
-                    for op in glyph.operations() {
-                        render.exec(op.clone());
-                    }
+                    render.exec_all(glyph.operations().iter().cloned());
                 }
 
                 text_object.move_to_next_pos(op.char_width(ch), ch == 32);
@@ -1620,22 +2824,47 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
                 .font_cache
                 .get_glyph_render(self.text_object().font_name.as_ref().unwrap())
                 .unwrap();
+            let glyph_paths = self.glyph_paths.entry(font_name.clone()).or_default();
             let mut text_clip_path = Path::default();
 
-            for ch in op.decode_chars(text) {
-                let path = Self::gen_glyph_path(glyph_render, op.char_to_gid(ch));
-                if !path.is_empty() {
-                    let path = path.finish().unwrap();
+            for (code, ch) in op.decode_code_chars(text) {
+                if self.glyphs.is_some() {
+                    let mut matrix = text_object.runtime_matrix();
+                    if self.snap_glyph_origin {
+                        matrix = snap_glyph_origin(matrix, &state.user_to_device);
+                    }
+                    record(code, ch, matrix);
+                    text_object.move_to_next_pos(op.char_width(ch), ch == 32);
+                    continue;
+                }
+
+                let gid = op.char_to_gid(ch);
+                // gid 0 is `.notdef`, the font has no glyph for the char
+                let path = self
+                    .fallback_font
+                    .as_deref()
+                    .filter(|_| gid == 0)
+                    .and_then(|fallback| Self::gen_fallback_glyph_path(fallback, op, code, ch))
+                    .or_else(|| {
+                        glyph_paths
+                            .entry(gid)
+                            .or_insert_with(|| Self::gen_glyph_path(glyph_render, gid))
+                            .clone()
+                    });
+                if let Some(path) = path {
                     // pre transform path to user space, render_glyph() will zoom line_width,
                     // pdf line_width state is in user space, but skia line_width is in device
                     // space so we need to transform path to user space,
                     // and zoom line_width in device space
-                    let path = path
-                        .transform(text_object.runtime_matrix().into_skia())
-                        .unwrap();
+                    let mut matrix = text_object.runtime_matrix();
+                    if self.snap_glyph_origin {
+                        matrix = snap_glyph_origin(matrix, &state.user_to_device);
+                    }
+                    let path = path.transform(matrix.into_skia()).unwrap();
 
                     Self::render_glyph(
                         self.canvas,
+                        self.svg.as_deref(),
                         &mut text_clip_path,
                         state,
                         path,
@@ -1669,7 +2898,24 @@ impl<'a, 'b: 'a, 'c> Render<'a, 'b, 'c> {
     }
 
     fn end_text(&mut self) {
-        self.current_mut().end_text_object();
+        let svg = self.svg.clone();
+        let state = self.current_mut();
+        if let Some(p) = state.text_object.text_clipping_path.finish() {
+            // text clipping path is in device space
+            let device_to_logic = state
+                .dimension
+                .logic_device_to_device()
+                .inverse()
+                .unwrap_or_default();
+            let p = p.clone();
+            state.update_svg_clip(
+                svg.as_deref(),
+                Some(&p),
+                device_to_logic.into_skia(),
+                FillRule::Winding,
+            );
+        }
+        state.end_text_object();
     }
 }
 
@@ -1716,10 +2962,13 @@ impl TextObject {
 
     pub fn type3_runtime_matrix(&self, font_matrix: &GlyphToTextSpace) -> GlyphToUserSpace {
         font_matrix
-            .then_scale(self.font_size * self.horiz_scaling.abs(), self.font_size)
+            .then_scale(self.font_size * self.horiz_scaling, self.font_size)
             .then(&self.matrix)
     }
 
+    /// Text rendering matrix `Trm`, see PDF 32000-1:2008 9.4.4. Negative
+    /// font size flips both axes, negative horizontal scaling mirrors x axis,
+    /// so text of negative size and `-100 Tz` is vertically flipped.
     pub fn runtime_matrix(&self) -> GlyphToUserSpace {
         Transform2D::scale(self.em_ratio.0, self.em_ratio.0)
             .then_scale(self.font_size * self.horiz_scaling, self.font_size)
@@ -1746,32 +2995,26 @@ impl TextObject {
         self.line_matrix = matrix;
     }
 
-    fn update_horizontal_scale(&mut self) {
-        let glyph_manipulate = if self.horiz_scaling == 1.0 {
-            Transform2D::<f32, TextSpace, TextSpace>::identity()
-        } else {
-            Transform2D::<f32, TextSpace, TextSpace>::scale(self.horiz_scaling, 1.0)
-        };
-        self.matrix = glyph_manipulate.then(&self.matrix);
-        self.line_matrix = glyph_manipulate.then(&self.line_matrix);
-    }
-
     fn set_text_matrix(&mut self, m: TextToUserSpace) {
         self.matrix = m;
         self.line_matrix = m;
-        self.update_horizontal_scale();
     }
 
     fn move_to_next_pos(&mut self, glyph_width: GlyphLength, word_boundary: bool) {
-        let mut w = glyph_width * self.em_ratio * self.font_size + self.char_spacing;
-        if word_boundary {
-            w += self.word_spacing;
-        }
+        let w = glyph_advance(
+            glyph_width,
+            self.em_ratio,
+            self.font_size,
+            self.char_spacing,
+            self.word_spacing,
+            word_boundary,
+            self.horiz_scaling,
+        );
         self.matrix = move_text_space_right(&self.matrix, w);
     }
 
     fn adjust_tj(&mut self, tj: Length<f32, ThousandthsOfText>) {
-        let n = tj * self.font_size * Scale::new(1.0 / 1000.0);
+        let n = tj * (self.font_size * self.horiz_scaling) * Scale::new(1.0 / 1000.0);
         self.matrix = move_text_space_right(&self.matrix, -n);
     }
 
@@ -1785,7 +3028,6 @@ impl TextObject {
 
     fn set_horizontal_scaling(&mut self, scale: f32) {
         self.horiz_scaling = scale / 100.0;
-        self.update_horizontal_scale();
     }
 
     fn set_leading(&mut self, leading: f32) {
@@ -1798,8 +3040,5 @@ impl TextObject {
 
     fn set_text_rise(&mut self, rise: f32) {
         self.rise = rise;
-        if rise != 0. {
-            todo!("text rise");
-        }
     }
 }
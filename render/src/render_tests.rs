@@ -1,10 +1,26 @@
 //! Test page render result using `insta` to ensure that the rendering result is not changed.
 //! This file checks file pdfreference1.0.pdf
-use crate::{RenderOptionBuilder, render_page};
+use crate::{
+    ContentFilter, ForceFont, GlyphDraw, PageDimension, PageMipmap, RenderOptionBuilder,
+    SearchOption, compose_rotate, glyph_runs, render_page, render_page_into, render_page_pixmap,
+    render_page_svg, render_page_with_stats, render_pages, render_steps, search,
+};
 use anyhow::Result as AnyResult;
+use base64::{Engine, engine::general_purpose::STANDARD};
 use insta::assert_ron_snapshot;
 use md5::{Digest, Md5};
-use nipdf::file::File;
+use nipdf::{
+    file::{
+        File, Page, Rectangle,
+        page::paint::fonts::{FallbackFont, PathSink, system_font_data},
+    },
+    graphics::Point,
+    object::{ObjectValueError, RuntimeObjectId},
+};
+use prescript::sname;
+use std::sync::atomic::{AtomicBool, Ordering};
+use test_case::test_case;
+use tiny_skia::Pixmap;
 
 /// Open file for testing. `file_path` relate to current crate directory.
 fn open_test_file(file_path: impl AsRef<std::path::Path>) -> File {
@@ -15,6 +31,14 @@ fn open_test_file(file_path: impl AsRef<std::path::Path>) -> File {
     File::parse(data, "").unwrap()
 }
 
+/// Call `f` with the first page of test file `file_path`.
+fn first_page<R>(file_path: &str, f: impl FnOnce(&Page) -> R) -> R {
+    let file = open_test_file(file_path);
+    let resolver = file.resolver().unwrap();
+    let pages = file.catalog(&resolver).unwrap().pages().unwrap();
+    f(&pages[0])
+}
+
 fn decode_file_page(path: &str, page_no: usize) -> AnyResult<String> {
     let f = open_test_file(path);
     let resolver = f.resolver()?;
@@ -206,3 +230,1116 @@ fn type1_font_units_per_em_not_1000() {
         &decode_file_page("../render/src/type1-units-per-em-not-1000.pdf", 0).unwrap()
     )
 }
+
+/// `/FontFile3` of `/Subtype /Type1C`, CFF encoding maps code 0 to "minus".
+#[test]
+fn type1c_font_custom_encoding() {
+    let img = first_page("sample_files/page/type1c-custom-encoding.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert_eq!([0, 0, 0, 255], img.get_pixel(50, 125).0);
+    assert_eq!([255, 255, 255, 255], img.get_pixel(50, 110).0);
+}
+
+#[test]
+fn clip_by_empty_path() {
+    let f = open_test_file("sample_files/path/empty-clip-path.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new()).unwrap();
+    // red fill after `W n` is clipped out
+    assert_eq!([0, 0, 255, 255], img.get_pixel(50, 50).0);
+    // clip region restored by `Q`
+    assert_eq!([0, 255, 0, 255], img.get_pixel(20, 80).0);
+}
+
+#[test]
+fn form_group_alpha() {
+    let f = open_test_file("sample_files/xobject/form-alpha.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new()).unwrap();
+    // form paints red twice at the same place, composited as a unit at 50% alpha,
+    // not 75% as if alpha applied to each fill
+    for p in img.pixels() {
+        assert_eq!(255, p[0]);
+        assert!((127..=128).contains(&p[1]), "{:?}", p);
+        assert!((127..=128).contains(&p[2]), "{:?}", p);
+    }
+}
+
+/// Black bar, then a transparency group form paints yellow box over it with
+/// `/BM /Multiply`, like a highlight annotation appearance, bar stays black.
+#[test]
+fn form_group_blend_mode() {
+    let img = first_page("sample_files/xobject/form-group-multiply.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert_eq!([0, 0, 0, 255], img.get_pixel(50, 50).0);
+    assert_eq!([255, 255, 0, 255], img.get_pixel(15, 50).0);
+    assert_eq!([255, 255, 255, 255], img.get_pixel(5, 5).0);
+}
+
+/// Highlight annotation appearance is a non-isolated group filling yellow
+/// with `/BM /Multiply` inside, text under it shows through.
+#[test]
+fn highlight_annotation_multiply() {
+    let img = first_page("sample_files/page/highlight-multiply.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let highlight: Vec<_> = (5..85)
+        .flat_map(|x| (35..65).map(move |y| (x, y)))
+        .map(|(x, y)| img.get_pixel(x, y).0)
+        .collect();
+    assert!(highlight.contains(&[255, 255, 0, 255]));
+    assert!(highlight.contains(&[0, 0, 0, 255]));
+    assert_eq!([255, 255, 255, 255], img.get_pixel(95, 5).0);
+}
+
+/// Orange background, 50% gray rectangles over it: left half `/BM /Multiply`
+/// darkens, right half `/BM [/Unknown /Screen]` lightens.
+#[test]
+fn ext_g_state_blend_mode() {
+    let img = first_page("sample_files/page/blend-mode.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let [r, g, b, a] = img.get_pixel(25, 50).0;
+    assert_eq!((255, 0), (a, b));
+    assert!((127..=128).contains(&r), "{r}");
+    assert!((63..=65).contains(&g), "{g}");
+    let [r, g, b, a] = img.get_pixel(75, 50).0;
+    assert_eq!((255, 255), (a, r));
+    assert!((190..=192).contains(&g), "{g}");
+    assert!((127..=128).contains(&b), "{b}");
+}
+
+/// Red rectangles masked by a luminosity soft mask, whose group is white at
+/// bottom and 25% gray at top. Left one with `/TR /Identity`, right one
+/// with `/TR` inverts luminosity.
+#[test]
+fn soft_mask_transfer_function() {
+    let img = first_page("sample_files/page/soft-mask-transfer.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let green = |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        assert_eq!((255, 255), (a, r));
+        assert_eq!(g, b);
+        g
+    };
+    assert_eq!(0, green(50, 75));
+    assert!((190..=192).contains(&green(50, 25)));
+    assert_eq!(255, green(150, 75));
+    assert!((63..=65).contains(&green(150, 25)));
+}
+
+/// Left red rectangle masked by an alpha soft mask, whose group is 50%
+/// opaque at bottom and empty at top. Right blue rectangle painted after
+/// `/SMask /None`.
+#[test]
+fn soft_mask_alpha() {
+    let img = first_page("sample_files/page/soft-mask-alpha.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let [r, g, b, a] = img.get_pixel(50, 75).0;
+    assert_eq!((255, 255, g), (a, r, b));
+    assert!((127..=128).contains(&g), "{g}");
+    assert_eq!([255, 255, 255, 255], img.get_pixel(50, 25).0);
+    assert_eq!([0, 0, 255, 255], img.get_pixel(150, 25).0);
+    assert_eq!([0, 0, 255, 255], img.get_pixel(150, 75).0);
+}
+
+/// Four red bands from top, marked by OCMD of `/AnyOn` on layer A and B,
+/// `/AllOn`, `A and not B`, and a form with `/OC` of the `/AllOn` OCMD.
+/// B is off by default. Returns which bands painted.
+#[test_case(None => [true, true, true, true]; "no optional content state")]
+#[test_case(Some(false) => [true, false, true, false]; "default state")]
+#[test_case(Some(true) => [true, true, false, true]; "B on")]
+fn optional_content_membership(b_on: Option<bool>) -> [bool; 4] {
+    let f = open_test_file("sample_files/page/optional-content-membership.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let oc = b_on.map(|b_on| {
+        let mut oc = catalog.optional_content().unwrap();
+        oc.set_visible(RuntimeObjectId(6), b_on);
+        oc
+    });
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new().optional_content(oc)).unwrap();
+    [12, 37, 62, 87].map(|y| img.get_pixel(50, y).0 == [255, 0, 0, 255])
+}
+
+/// Hidden content of layer `L` sets blue fill color and translates by `cm`,
+/// its painting skipped, but graphics state applies to the visible rectangle
+/// painted after it. Returns pixel of the visible and hidden rectangle.
+#[test_case(false => [[0, 0, 255, 255]; 2]; "no optional content state")]
+#[test_case(true => [[0, 0, 255, 255], [255; 4]]; "default state")]
+fn optional_content_graphics_state(oc: bool) -> [[u8; 4]; 2] {
+    let f = open_test_file("sample_files/page/optional-content-graphics-state.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let oc = oc.then(|| catalog.optional_content().unwrap());
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new().optional_content(oc)).unwrap();
+    [img.get_pixel(75, 25).0, img.get_pixel(75, 75).0]
+}
+
+/// Colored tiling pattern paints red, then blue in content of layer `L`
+/// which is off by default.
+#[test_case(false => [0, 0, 255, 255]; "no optional content state")]
+#[test_case(true => [255, 0, 0, 255]; "default state")]
+fn tiling_pattern_optional_content(oc: bool) -> [u8; 4] {
+    let f = open_test_file("sample_files/color-space/tiling-pattern-optional-content.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let oc = oc.then(|| catalog.optional_content().unwrap());
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new().optional_content(oc)).unwrap();
+    img.get_pixel(50, 50).0
+}
+
+#[test]
+fn inline_image_mask() {
+    let f = open_test_file("sample_files/xobject/inline-image-mask.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new()).unwrap();
+    let red = [255, 0, 0, 255];
+    let white = [255, 255, 255, 255];
+    let pixels = [5, 15, 25, 35, 45, 55].map(|x| img.get_pixel(x, 5).0);
+    // mask bits `10`, default Decode paints where sample is 0
+    assert_eq!([white, red], pixels[0..2]);
+    // Decode [1 0] inverts the mask, paints where sample is 1
+    assert_eq!([red, white], pixels[2..4]);
+    // indexed color space resolved by name from page resources
+    assert_eq!([[0, 255, 0, 255], [0, 0, 255, 255]], pixels[4..6]);
+}
+
+#[test]
+fn uncolored_tiling_pattern() {
+    let f = open_test_file("sample_files/color-space/uncolored-tiling-pattern.pdf");
+    let resolver = f.resolver().unwrap();
+    let catalog = f.catalog(&resolver).unwrap();
+    let pages = catalog.pages().unwrap();
+    let img = render_page(&pages[0], RenderOptionBuilder::new()).unwrap();
+    // pattern content and its form xobject set red and green, supplied blue wins
+    let blue = [0, 0, 255, 255];
+    for (x, y) in [(2, 50), (7, 50), (52, 2), (57, 97)] {
+        assert_eq!(blue, img.get_pixel(x, y).0, "pixel at ({x}, {y})");
+    }
+}
+
+#[test_case("sample_files/page/image-and-text.pdf")]
+#[test_case("sample_files/page/referenced-objects.pdf")]
+#[test_case("sample_files/xobject/form-alpha.pdf")]
+#[test_case("sample_files/xobject/inline-image-mask.pdf")]
+#[test_case("sample_files/color-space/uncolored-tiling-pattern.pdf")]
+#[test_case("sample_files/color-space/indexed-by-resource-name.pdf")]
+#[test_case("sample_files/file-structure/xref-stream.pdf")]
+#[test_case("sample_files/normal/ASCII85_RunLengthDecode.pdf")]
+#[test_case("sample_files/normal/lossless.pdf")]
+fn pruned_file_renders_same(file_path: &str) {
+    let f = open_test_file(file_path);
+    let pruned = File::parse(f.pruned_bytes().unwrap(), "").unwrap();
+    let (resolver, pruned_resolver) = (f.resolver().unwrap(), pruned.resolver().unwrap());
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let pruned_pages = pruned.catalog(&pruned_resolver).unwrap().pages().unwrap();
+    assert_eq!(pages.len(), pruned_pages.len());
+    for (page, pruned_page) in pages.iter().zip(&pruned_pages) {
+        let expected = render_page(page, RenderOptionBuilder::new()).unwrap();
+        let actual = render_page(pruned_page, RenderOptionBuilder::new()).unwrap();
+        assert!(
+            expected == actual,
+            "page {} renders differently",
+            page.id().0
+        );
+    }
+}
+
+#[test]
+fn render_page_pixmap_same_as_image() {
+    first_page("sample_files/page/contents-single-or-array.pdf", |page| {
+        let canvas = render_page_pixmap(page, RenderOptionBuilder::new()).unwrap();
+        let img = render_page(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(
+            (img.width(), img.height()),
+            (canvas.width(), canvas.height())
+        );
+        assert_eq!(img.as_raw(), canvas.data());
+    })
+}
+
+#[test]
+fn render_page_into_reused_canvas() {
+    let f1 = open_test_file("sample_files/color-space/uncolored-tiling-pattern.pdf");
+    let f2 = open_test_file("sample_files/page/contents-single-or-array.pdf");
+    let (r1, r2) = (f1.resolver().unwrap(), f2.resolver().unwrap());
+    let pages1 = f1.catalog(&r1).unwrap().pages().unwrap();
+    let pages2 = f2.catalog(&r2).unwrap().pages().unwrap();
+
+    // size not match, canvas re-created
+    let mut canvas = Pixmap::new(1, 1).unwrap();
+    render_page_into(&pages1[0], RenderOptionBuilder::new(), &mut canvas).unwrap();
+    assert_eq!((100, 100), (canvas.width(), canvas.height()));
+    let addr = canvas.data().as_ptr();
+
+    // page 1 fills the whole canvas, cleared before render page 2
+    render_page_into(&pages2[0], RenderOptionBuilder::new(), &mut canvas).unwrap();
+    assert_eq!(addr, canvas.data().as_ptr());
+    let expected = render_page(&pages2[0], RenderOptionBuilder::new()).unwrap();
+    assert!(expected.as_raw()[..] == *canvas.data());
+}
+
+#[test_case(1.0, 14; "zoom 1")]
+#[test_case(1.3, 18; "zoom 1.3")]
+fn tiling_type_no_distortion_snaps_to_device_pixels(zoom: f32, period: u32) {
+    let img = first_page("sample_files/color-space/tiling-type-2.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().zoom(zoom)).unwrap()
+    });
+    // tile is 13.7 device pixels at zoom 1, snapped to whole pixels, every
+    // stripe is rendered the same
+    for y in 0..img.height() - period {
+        assert_eq!(
+            img.get_pixel(5, y),
+            img.get_pixel(5, y + period),
+            "pixel at (5, {y})"
+        );
+    }
+}
+
+#[test]
+fn render_cancelled() {
+    first_page("sample_files/xobject/form-alpha.pdf", |page| {
+        let cancel = AtomicBool::new(false);
+        let img =
+            render_steps(page, RenderOptionBuilder::new(), None, false, Some(&cancel)).unwrap();
+        assert_eq!(img, render_page(page, RenderOptionBuilder::new()).unwrap());
+
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            render_steps(page, RenderOptionBuilder::new(), None, false, Some(&cancel)),
+            Err(ObjectValueError::Cancelled)
+        ));
+    })
+}
+
+/// ExtGState `/OP` (overprint) is not supported, reported in strict mode.
+#[test]
+fn strict_mode() {
+    first_page("sample_files/page/ext-g-state-overprint.pdf", |page| {
+        let img = render_page(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!([255, 0, 0, 255], img.get_pixel(25, 75).0);
+        assert_eq!(
+            Err(ObjectValueError::Unsupported(vec![
+                "ExtGState key OP".to_owned()
+            ])),
+            render_page(page, RenderOptionBuilder::new().strict(true))
+        );
+    })
+}
+
+/// Unsupported feature used in tiling pattern content also reported.
+#[test]
+fn strict_mode_tiling_pattern() {
+    first_page(
+        "sample_files/color-space/tiling-pattern-optional-content.pdf",
+        |page| {
+            assert_eq!(
+                Err(ObjectValueError::Unsupported(vec![
+                    "ExtGState key OP".to_owned()
+                ])),
+                render_page(page, RenderOptionBuilder::new().strict(true))
+            );
+        },
+    )
+}
+
+/// Widget appearance selected by `/AS`, appearance `/Matrix` mapped to
+/// `/Rect`, hidden annotation not painted.
+#[test]
+fn render_annotations() {
+    first_page("sample_files/page/annotation-appearance.pdf", |page| {
+        let img = render_page(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!([0, 255, 0, 255], img.get_pixel(25, 25).0);
+        assert_eq!([0, 255, 0, 255], img.get_pixel(11, 39).0);
+        assert_eq!([0, 0, 255, 255], img.get_pixel(75, 75).0);
+        assert_eq!([0, 0, 255, 255], img.get_pixel(61, 89).0);
+        assert_eq!([255, 255, 255, 255], img.get_pixel(75, 25).0);
+        assert_eq!([255, 255, 255, 255], img.get_pixel(50, 50).0);
+
+        let img = render_page(page, RenderOptionBuilder::new().render_annotations(false)).unwrap();
+        assert_eq!([255, 255, 255, 255], img.get_pixel(25, 25).0);
+        assert_eq!([255, 255, 255, 255], img.get_pixel(75, 75).0);
+    })
+}
+
+/// Square, Circle and FreeText annotations without `/AP` painted from their
+/// colors and borders, text of FreeText skipped, its `/Helv` font not in
+/// page resources.
+#[test]
+fn render_annotations_without_appearance() {
+    let img = first_page("sample_files/page/annotation-no-appearance.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    // green interior and red dashed border of square
+    assert_eq!([0, 255, 0, 255], img.get_pixel(50, 160).0);
+    assert_eq!([255, 0, 0, 255], img.get_pixel(23, 179).0);
+    // black border of circle, no interior
+    assert_eq!([0, 0, 0, 255], img.get_pixel(101, 160).0);
+    assert_eq!([255, 255, 255, 255], img.get_pixel(140, 160).0);
+    // yellow background of FreeText
+    assert_eq!([255, 255, 0, 255], img.get_pixel(150, 70).0);
+}
+
+/// Blue `/Popup` of a yellow Text annotation at `/Rect [40 40 90 90]`
+/// painted only if `render_popups()`.
+#[test_case(false => [255, 255, 255, 255]; "popup hidden")]
+#[test_case(true => [0, 0, 255, 255]; "popup painted")]
+fn render_popups(render: bool) -> [u8; 4] {
+    let img = first_page("sample_files/page/popup-appearance.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().render_popups(render)).unwrap()
+    });
+    assert_eq!([255, 255, 0, 255], img.get_pixel(20, 20).0);
+    img.get_pixel(65, 35).0
+}
+
+/// Page of `/Rotate 90` maps user space `(x, y)` to device `(y, x)` zoomed.
+/// Red annotation of `/Rect [10 70 30 80]` with `NoZoom` and `NoRotate`
+/// flags keeps upright and 20x10 pixels, hanging from its upper-left corner
+/// at device `(80, 10)` zoomed. Returns pixels inside and outside of it.
+#[test_case(1.0, [(90, 15), (75, 25)]; "no zoom")]
+#[test_case(2.0, [(170, 25), (190, 35)]; "zoom 2")]
+fn annotation_no_rotate_no_zoom(zoom: f32, [inside, outside]: [(u32, u32); 2]) {
+    let img = first_page("sample_files/page/annotation-no-rotate.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().zoom(zoom)).unwrap()
+    });
+    assert_eq!([255, 0, 0, 255], img.get_pixel(inside.0, inside.1).0);
+    assert_eq!([0, 0, 255, 255], img.get_pixel(outside.0, outside.1).0);
+}
+
+/// Clip path and dashed stroke as SVG elements.
+#[test]
+fn svg_clip_and_dash() {
+    let svg = first_page("sample_files/page/svg-clip-dash.pdf", |page| {
+        render_page_svg(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert!(svg.starts_with(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 100 100">"#
+    ));
+    // y axis flip of `logic_device_to_device()` in root group
+    assert!(svg.contains(r#"<g transform="matrix(1 0 0 -1 0 100)">"#));
+    assert!(svg.contains(
+        r#"<clipPath id="c0"><path transform="matrix(1 0 0 1 0 0)" d="M10 10 L50 10 L50 50 L10 50 Z" clip-rule="nonzero"/></clipPath>"#
+    ));
+    assert!(svg.contains(
+        r#"<g clip-path="url(#c0)"><path transform="matrix(1 0 0 1 0 0)" d="M0 0 L100 0 L100 100 L0 100 Z" fill="rgb(255,0,0)"/></g>"#
+    ));
+    assert!(
+        svg.contains(r#"d="M60 90 L90 90" fill="none" stroke="rgb(0,0,255)" stroke-width="2""#)
+    );
+    assert!(svg.contains(r#"stroke-dasharray="3,2" stroke-dashoffset="0""#));
+    assert!(!svg.contains("<image"));
+}
+
+/// Rectangles of blend mode blend with orange rectangle painted before them,
+/// rasterized on the page painted so far, same as rendered image.
+#[test]
+fn svg_blend_mode() {
+    first_page("sample_files/page/blend-mode.pdf", |page| {
+        let svg = render_page_svg(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(1, svg.matches("<path ").count());
+        assert_eq!(1, svg.matches("<image ").count());
+        let png = svg.split("base64,").nth(1).unwrap();
+        let png = &png[..png.find('"').unwrap()];
+        let layer = Pixmap::decode_png(&STANDARD.decode(png).unwrap()).unwrap();
+        let img = render_page_pixmap(page, RenderOptionBuilder::new()).unwrap();
+        for (x, y) in [(25, 50), (75, 50)] {
+            assert_eq!(img.pixel(x, y), layer.pixel(x, y));
+        }
+    })
+}
+
+/// Blended rectangle at `[25 25 75 75]` embedded as image of its bounds,
+/// rectangles painted before and after it stay vector.
+#[test]
+fn svg_blend_bounds() {
+    first_page("sample_files/page/svg-blend-bounds.pdf", |page| {
+        let svg = render_page_svg(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(3, svg.matches("<path ").count());
+        let image = svg.find(r#"<image x="25" y="25" width="50" height="50" "#);
+        let image = image.unwrap();
+        assert!(svg[..image].contains(r#"fill="rgb(0,0,255)""#));
+        assert!(svg[image..].contains(r#"fill="rgb(0,255,0)""#));
+        let png = svg.split("base64,").nth(1).unwrap();
+        let png = &png[..png.find('"').unwrap()];
+        let layer = Pixmap::decode_png(&STANDARD.decode(png).unwrap()).unwrap();
+        let img = render_page_pixmap(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(img.pixel(50, 50), layer.pixel(25, 25));
+    })
+}
+
+/// Glyphs are paths, image embedded as raster layer in content order.
+#[test]
+fn svg_text_and_image() {
+    let svg = first_page("sample_files/page/text-rect-image.pdf", |page| {
+        render_page_svg(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert_eq!(3, svg.matches("<path ").count());
+    assert_eq!(2, svg.matches(r#"fill="rgb(0,0,0)""#).count());
+    let image = svg.find(r#"<image width="200" height="100" "#).unwrap();
+    assert!(image > svg.rfind("<path ").unwrap());
+    assert!(svg[image..].contains(r#"href="data:image/png;base64,"#));
+}
+
+#[test_case(ContentFilter::All => (true, true, true))]
+#[test_case(ContentFilter::TextOnly => (true, false, false))]
+#[test_case(ContentFilter::GraphicsOnly => (false, true, false))]
+#[test_case(ContentFilter::ImagesOnly => (false, false, true))]
+fn content_filter(filter: ContentFilter) -> (bool, bool, bool) {
+    let img = first_page("sample_files/page/text-rect-image.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().content_filter(filter)).unwrap()
+    });
+    let white = [255, 255, 255, 255];
+    let has_text = (100..130)
+        .flat_map(|x| (40..65).map(move |y| (x, y)))
+        .any(|(x, y)| img.get_pixel(x, y).0 != white);
+    let has_rect = img.get_pixel(40, 60).0 == [255, 0, 0, 255];
+    let has_image = img.get_pixel(180, 80).0 == [0, 0, 0, 255];
+    (has_text, has_rect, has_image)
+}
+
+/// Dash on segments are 0.125 pixel long at zoom 0.25, returns whether
+/// dashes are clearly visible.
+#[test_case(0.0 => false; "exact dash pattern")]
+#[test_case(1.0 => true; "min dash length")]
+fn min_dash_length(len: f32) -> bool {
+    let img = first_page("sample_files/page/fine-dash.pdf", |page| {
+        render_page(
+            page,
+            RenderOptionBuilder::new().zoom(0.25).min_dash_length(len),
+        )
+        .unwrap()
+    });
+    (0..img.width()).any(|x| img.get_pixel(x, 5).0[0] < 128)
+}
+
+/// Two lines of the same text, glyph advances are whole pixels, the second
+/// line starts 0.1 pixel right. Returns whether two lines rasterized the same.
+#[test_case(false => false; "exact position")]
+#[test_case(true => true; "snap glyph origin")]
+fn snap_glyph_origin(snap: bool) -> bool {
+    let img = first_page("sample_files/page/snap-glyph-origin.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().snap_glyph_origin(snap)).unwrap()
+    });
+    (0..img.width())
+        .flat_map(|x| (35..55).map(move |y| (x, y)))
+        .all(|(x, y)| img.get_pixel(x, y) == img.get_pixel(x, y + 50))
+}
+
+/// "Hello" and "World" in two lines, 12pt Helvetica at (72, 720) of 792pt
+/// high page, line height 14.
+#[test]
+fn glyph_runs_of_page() {
+    first_page("sample_files/page/image-and-text.pdf", |page| {
+        let glyphs = glyph_runs(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(10, glyphs.len());
+        assert!(glyphs.iter().all(|g| g.font_id == Some(RuntimeObjectId(5))));
+        assert!(glyphs.iter().all(|g| g.font_name == sname("F1")));
+        // same char same glyph: "l" of "Hello", "o" of "Hello" and "World"
+        assert_eq!(glyphs[2].gid, glyphs[3].gid);
+        assert_eq!(glyphs[4].gid, glyphs[6].gid);
+        assert_ne!(glyphs[0].gid, glyphs[1].gid);
+
+        let origin = |g: &GlyphDraw| (g.transform.m31, g.transform.m32);
+        assert_eq!((72.0, 72.0), origin(&glyphs[0]));
+        assert_eq!((72.0, 86.0), origin(&glyphs[5]));
+        assert!(
+            glyphs[..5]
+                .windows(2)
+                .all(|w| origin(&w[0]).0 < origin(&w[1]).0)
+        );
+        // y axis flipped in device space
+        assert!(glyphs[0].transform.m22 < 0.0);
+
+        let zoomed = glyph_runs(page, RenderOptionBuilder::new().zoom(2.0)).unwrap();
+        assert_eq!((144.0, 144.0), origin(&zoomed[0]));
+        assert_eq!(
+            glyphs.iter().map(|g| g.gid).collect::<Vec<_>>(),
+            zoomed.iter().map(|g| g.gid).collect::<Vec<_>>()
+        );
+    })
+}
+
+/// Page 1 shows "Hello World", page 2 "Say hello again," and "worldwide" in
+/// two lines, Helvetica 20pt at (10, 50) of 200x100 page.
+#[test]
+fn search_text() {
+    let f = open_test_file("sample_files/page/search-text.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let hits = |query: &str, option: SearchOption| {
+        search(&pages, query, &option)
+            .unwrap()
+            .into_iter()
+            .map(|h| (h.page, h.text))
+            .collect::<Vec<_>>()
+    };
+
+    let hit = &search(&pages, "again", &SearchOption::default()).unwrap()[0];
+    assert_eq!(1, hit.page);
+    // "Say hello " is 4.391 em, "again" 2.446 em, x from 97.82 to 146.74,
+    // y from 0.2 em below baseline to 0.8 em above, device y axis flipped
+    let [bottom_start, _, top_end, _] = hit.quads[0];
+    let near = |a: f32, b: f32| (a - b).abs() < 0.1;
+    assert!(near(97.82, bottom_start.x), "{bottom_start:?}");
+    assert!(near(54.0, bottom_start.y), "{bottom_start:?}");
+    assert!(near(146.74, top_end.x), "{top_end:?}");
+    assert!(near(34.0, top_end.y), "{top_end:?}");
+
+    assert_eq!(
+        vec![(1, "hello".to_owned())],
+        hits("hello", SearchOption::default())
+    );
+    let ignore_case = || SearchOption {
+        ignore_case: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![(0, "Hello".to_owned()), (1, "hello".to_owned())],
+        hits("HELLO", ignore_case())
+    );
+    assert_eq!(
+        vec![(0, "World".to_owned()), (1, "world".to_owned())],
+        hits("world", ignore_case())
+    );
+    assert_eq!(
+        vec![(0, "World".to_owned())],
+        hits("world", SearchOption {
+            whole_word: true,
+            ..ignore_case()
+        })
+    );
+    assert_eq!(
+        vec![(0, "Hello".to_owned())],
+        hits("hello", SearchOption {
+            limit: Some(1),
+            ..ignore_case()
+        })
+    );
+
+    // line break matches space, one quad per line
+    let hit = &search(&pages, "again, world", &SearchOption::default()).unwrap()[0];
+    assert_eq!("again, world", hit.text);
+    assert_eq!(2, hit.quads.len());
+}
+
+/// `/ToUnicode` of the font maps char "A" to ligature "fi", glyph unicode and
+/// search read it instead of font encoding.
+#[test]
+fn search_text_to_unicode() {
+    let f = open_test_file("sample_files/page/extract-text.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let glyphs = glyph_runs(&pages[0], RenderOptionBuilder::new()).unwrap();
+    assert!(glyphs.iter().any(|g| g.unicode == "fi"));
+
+    let hits = search(&pages, "fiB", &SearchOption::default()).unwrap();
+    assert_eq!(1, hits.len());
+    assert_eq!("fiB", hits[0].text);
+    assert_eq!(1, hits[0].quads.len());
+}
+
+/// Page shows text and paints an image XObject, no path or shading.
+#[test]
+fn render_stats() {
+    first_page("sample_files/page/image-and-text.pdf", |page| {
+        let (img, stats) = render_page_with_stats(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(render_page(page, RenderOptionBuilder::new()).unwrap(), img);
+        assert!(!stats.image.is_zero());
+        assert!(!stats.text.is_zero());
+        assert!(stats.path.is_zero());
+        assert!(stats.shading.is_zero());
+        assert_eq!(stats.image + stats.text, stats.total());
+    })
+}
+
+/// Form without `/Resources` resolves names against the nearest enclosing
+/// resources: nested form uses font of its parent form, another form uses
+/// font of the page.
+#[test]
+fn form_inherit_resources() {
+    let img = first_page("sample_files/xobject/form-inherit-resources.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let has_text = |x: u32| {
+        (x..x + 50)
+            .flat_map(|x| (30..65).map(move |y| (x, y)))
+            .any(|(x, y)| img.get_pixel(x, y).0[0] < 128)
+    };
+    assert!(has_text(10));
+    assert!(has_text(110));
+}
+
+/// 2x1 indexed image of red and blue scaled to fill the page, `/Interpolate`
+/// ignored, no blended color between palette entries.
+#[test]
+fn indexed_image_not_interpolated() {
+    let img = first_page(
+        "sample_files/xobject/indexed-image-interpolate.pdf",
+        |page| render_page(page, RenderOptionBuilder::new()).unwrap(),
+    );
+    let (red, blue) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+    assert_eq!(red, img.get_pixel(49, 50).0);
+    assert_eq!(blue, img.get_pixel(50, 50).0);
+    assert!(img.pixels().all(|p| p.0 == red || p.0 == blue));
+}
+
+/// Page rotated 90 degrees, blue rect inside `/CropBox`, red rect outside.
+/// Returns canvas size, and whether blue and red rects are visible.
+#[test_case(false => ((100, 100), true, false); "crop")]
+#[test_case(true => ((100, 200), true, true); "no crop")]
+fn rotate_no_crop(no_crop: bool) -> ((u32, u32), bool, bool) {
+    let img = first_page("sample_files/page/rotate-crop-box.pdf", |page| {
+        render_steps(page, RenderOptionBuilder::new(), None, no_crop, None).unwrap()
+    });
+    // media box (x, y) maps to device (100 - y, x) if not cropped
+    let color_at =
+        |x: u32, y: u32| (x < img.width() && y < img.height()).then(|| img.get_pixel(x, y).0);
+    let (blue_y, red_y) = if no_crop { (50, 170) } else { (30, 150) };
+    (
+        img.dimensions(),
+        color_at(50, blue_y) == Some([0, 0, 255, 255]),
+        color_at(50, red_y) == Some([255, 0, 0, 255]),
+    )
+}
+
+/// Page of `/MediaBox [0 0 200 200]` filled red, `/CropBox [50 20 150 100]`
+/// filled blue, green 10x10 square at crop box top left corner. Pages
+/// rotated 0, 90, 180 and 270 degrees, returns canvas size and the green
+/// square position, no red visible.
+#[test_case(0 => ((100, 80), (5, 5)))]
+#[test_case(1 => ((80, 100), (75, 5)))]
+#[test_case(2 => ((100, 80), (95, 75)))]
+#[test_case(3 => ((80, 100), (5, 95)))]
+fn rotate_crop_box_offset(page_no: usize) -> ((u32, u32), (u32, u32)) {
+    let f = open_test_file("sample_files/page/rotate-crop-box-offset.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let img = render_page(&pages[page_no], RenderOptionBuilder::new().zoom(1.0)).unwrap();
+    assert!(img.pixels().all(|p| p.0 != [255, 0, 0, 255]));
+    let green: Vec<_> = img
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p.0 == [0, 255, 0, 255])
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    assert_eq!(100, green.len());
+    let (x, y) = green.iter().fold((0, 0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    // center of green square
+    (img.dimensions(), ((x + 50) / 100, (y + 50) / 100))
+}
+
+/// `/CropBox [-50 -20 150 80]` exceeds `/MediaBox [0 0 100 100]`, page
+/// filled red, canvas covers only their intersection.
+#[test]
+fn crop_box_exceeds_media_box() {
+    first_page("sample_files/page/crop-box-exceeds-media-box.pdf", |page| {
+        assert_eq!(
+            Some(Rectangle::from_lbrt(0.0, 0.0, 100.0, 80.0)),
+            page.crop_box()
+        );
+        let img = render_page(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!((100, 80), img.dimensions());
+        let red = [255, 0, 0, 255];
+        assert_eq!(red, img.get_pixel(0, 0).0);
+        assert_eq!(red, img.get_pixel(99, 79).0);
+    })
+}
+
+/// Content at the corners of `/MediaBox [-100 -100 500 700]` not clipped.
+#[test]
+fn negative_media_box_origin() {
+    let img = first_page("sample_files/page/negative-media-box.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert_eq!((600, 800), img.dimensions());
+    assert_eq!([255, 0, 0, 255], img.get_pixel(10, 10).0);
+    assert_eq!([0, 0, 255, 255], img.get_pixel(590, 790).0);
+}
+
+#[test_case(0, 0 => 0)]
+#[test_case(90, 90 => 180)]
+#[test_case(270, 90 => 0; "wrap around")]
+#[test_case(-90, 0 => 270; "negative page rotate")]
+#[test_case(90, -90 => 0; "counter clockwise")]
+#[test_case(180, 270 => 90)]
+fn compose_page_and_user_rotate(page: i32, user: i32) -> i32 {
+    compose_rotate(page, user)
+}
+
+/// User rotation composed with page `/Rotate` 90, canvas size swapped back
+/// by another 90 degrees, and green square moved accordingly.
+#[test_case(0 => ((80, 100), (75, 5)))]
+#[test_case(90 => ((100, 80), (95, 75)))]
+#[test_case(-90 => ((100, 80), (5, 5)); "counter clockwise")]
+fn user_rotate(rotate: i32) -> ((u32, u32), (u32, u32)) {
+    let f = open_test_file("sample_files/page/rotate-crop-box-offset.pdf");
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let option = RenderOptionBuilder::new().zoom(1.0).rotate(rotate);
+    let img = render_page(&pages[1], option).unwrap();
+    let green: Vec<_> = img
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p.0 == [0, 255, 0, 255])
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    let (x, y) = green
+        .iter()
+        .fold((0, 0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    (img.dimensions(), ((x + 50) / 100, (y + 50) / 100))
+}
+
+/// Pages rendered in parallel same as rendered one by one, in order of
+/// indices, error of index out of range.
+#[test]
+fn render_pages_in_parallel() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../nipdf/sample_files/page/search-text.pdf");
+    let data = std::fs::read(path).unwrap();
+    let f = File::parse(data.clone(), "").unwrap();
+    let resolver = f.resolver().unwrap();
+    let pages = f.catalog(&resolver).unwrap().pages().unwrap();
+    let option = || RenderOptionBuilder::new().zoom(1.5);
+
+    let r = render_pages(&data, "", &[1, 0, 1, 5], option);
+    assert_eq!(4, r.len());
+    for (img, page) in r.iter().zip([1, 0, 1]) {
+        assert_eq!(
+            &render_page(&pages[page], option()).unwrap(),
+            img.as_ref().unwrap()
+        );
+    }
+    assert!(matches!(
+        r[3],
+        Err(ObjectValueError::PageIndexOutOfRange(5))
+    ));
+}
+
+#[test_case(Rectangle::from_xywh(0., 0., 1e6, 1e6), 0 => (10240, 10240); "clamp huge box")]
+#[test_case(Rectangle::from_xywh(1e7, 1e7, 600., 800.), 90 => (800, 600); "large origin")]
+#[test_case(Rectangle::from_xywh(0., 0., 0.2, 100.), 0 => (1, 100); "tiny box")]
+#[test_case(Rectangle::from_xywh(0., 0., 0., 0.), 0 => (1, 1); "empty box")]
+fn page_dimension_canvas_size(rect: Rectangle, rotate: i32) -> (u32, u32) {
+    let mut dimension = PageDimension::default();
+    dimension.update(&rect, rotate);
+    (dimension.canvas_width(), dimension.canvas_height())
+}
+
+#[test]
+fn create_canvas_of_empty_page_box() {
+    let option = RenderOptionBuilder::new()
+        .page_box(&Rectangle::from_xywh(0., 0., 0., 0.), 0)
+        .build();
+    let canvas = option.create_canvas();
+    assert_eq!((1, 1), (canvas.width(), canvas.height()));
+}
+
+/// Extended radial shading painted by `sh` is clipped by its `/BBox`,
+/// `/Background` is ignored by `sh`.
+#[test]
+fn radial_shading_b_box() {
+    let img = first_page("sample_files/color-space/radial-shading-bbox.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let white = [255, 255, 255, 255];
+    assert_eq!(white, img.get_pixel(10, 10).0);
+    assert_eq!(white, img.get_pixel(190, 100).0);
+    // extended end color inside `/BBox`
+    assert_eq!([0, 255, 0, 255], img.get_pixel(55, 55).0);
+}
+
+/// Left half free-form triangles from black at bottom to white at top,
+/// right half Coons patch from red at left to blue at right.
+#[test]
+fn mesh_shading() {
+    let img = first_page("sample_files/color-space/mesh-shading.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let [r, g, b, a] = img.get_pixel(25, 50).0;
+    assert_eq!((255, r, r), (a, g, b));
+    assert!((120..=136).contains(&r), "{r}");
+    assert!(img.get_pixel(25, 95).0[0] < 30);
+    assert!(img.get_pixel(25, 5).0[0] > 225);
+
+    let [r, g, b, a] = img.get_pixel(75, 50).0;
+    assert_eq!((255, 0), (a, g));
+    assert!((115..=140).contains(&r), "{r}");
+    assert!((115..=140).contains(&b), "{b}");
+    let [r, _, b, _] = img.get_pixel(52, 50).0;
+    assert!(r > 230 && b < 25, "{r} {b}");
+}
+
+/// Shading pattern of Coons patch from red at left to blue at right, pattern
+/// matrix maps it to the upper right quarter of the page.
+#[test]
+fn mesh_shading_pattern() {
+    let img = first_page(
+        "sample_files/color-space/mesh-shading-pattern.pdf",
+        |page| render_page(page, RenderOptionBuilder::new()).unwrap(),
+    );
+    let [r, g, b, a] = img.get_pixel(75, 25).0;
+    assert_eq!((255, 0), (a, g));
+    assert!((115..=140).contains(&r), "{r}");
+    assert!((115..=140).contains(&b), "{b}");
+    let [r, _, b, _] = img.get_pixel(52, 25).0;
+    assert!(r > 230 && b < 25, "{r} {b}");
+    let [r, _, b, _] = img.get_pixel(98, 25).0;
+    assert!(r < 25 && b > 230, "{r} {b}");
+    assert_eq!([255, 255, 255, 255], img.get_pixel(25, 75).0);
+    assert_eq!([255, 255, 255, 255], img.get_pixel(75, 75).0);
+}
+
+/// Separation `/Orange` whose tint transform samples white, light orange and
+/// orange, filled at tint 1 and 0.5, then colorant `/All` at tint 1.
+#[test]
+fn separation_sampled_function() {
+    let img = first_page("sample_files/color-space/separation.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let [r, g, b, a] = img.get_pixel(25, 25).0;
+    assert_eq!((255, 255, 0), (a, r, b));
+    assert!((127..=129).contains(&g), "{g}");
+    assert_eq!([255, 191, 128, 255], img.get_pixel(75, 25).0);
+    assert_eq!([0, 0, 0, 255], img.get_pixel(125, 25).0);
+}
+
+/// Tiling pattern cell painted by `sh` from red to blue, `/Shading` only
+/// defined in pattern resources.
+#[test]
+fn tiling_pattern_shading() {
+    let img = first_page(
+        "sample_files/color-space/tiling-pattern-shading.pdf",
+        |page| render_page(page, RenderOptionBuilder::new()).unwrap(),
+    );
+    let reddish = |x: u32| {
+        let [r, _, b, _] = img.get_pixel(x, 100).0;
+        r > b
+    };
+    for cell in [0, 20, 180] {
+        assert!(reddish(cell + 2));
+        assert!(!reddish(cell + 18));
+    }
+}
+
+#[test_case(2.0 => Some((400, 400)); "base zoom")]
+#[test_case(1.0 => Some((200, 200)); "half")]
+#[test_case(0.3 => Some((60, 60)); "between levels")]
+#[test_case(0.01 => Some((2, 2)); "smaller than last level")]
+#[test_case(2.5 => None; "zoom in past base")]
+#[test_case(0.0 => None; "zero")]
+fn page_mipmap(zoom: f32) -> Option<(u32, u32)> {
+    first_page("sample_files/color-space/radial-shading-bbox.pdf", |page| {
+        let mipmap = PageMipmap::new(page, RenderOptionBuilder::new(), 2.0).unwrap();
+        assert_eq!(2.0, mipmap.base_zoom());
+        let img = mipmap.image(zoom)?;
+        // same as rendered at the zoom
+        let expected = render_page(page, RenderOptionBuilder::new().zoom(zoom)).unwrap();
+        assert_eq!(expected.dimensions(), img.dimensions());
+        if zoom >= 0.3 {
+            // inside solid area, not blurred by downscaling
+            let p = crate::saturating_u32(55.0 * zoom);
+            assert_eq!(expected.get_pixel(p, p), img.get_pixel(p, p));
+        }
+        Some(img.dimensions())
+    })
+}
+
+/// Type3 font glyphs: "re" named like an operator, "missing" not in
+/// `/CharProcs`, "bad" failed to parse. Only "re" is painted.
+#[test]
+fn type3_missing_glyph() {
+    let img = first_page("sample_files/page/type3-missing-glyph.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new()).unwrap()
+    });
+    assert_eq!([0, 0, 0, 255], img.get_pixel(40, 50).0);
+    let painted = (70..200)
+        .flat_map(|x| (0..100).map(move |y| (x, y)))
+        .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255]);
+    assert!(!painted);
+}
+
+/// Type3 glyph content is painted as part of text, not filtered as graphics,
+/// and its paths are timed in the text operation.
+#[test_case(ContentFilter::TextOnly => true)]
+#[test_case(ContentFilter::GraphicsOnly => false)]
+fn type3_glyph_content_filter(filter: ContentFilter) -> bool {
+    first_page("sample_files/page/type3-missing-glyph.pdf", |page| {
+        let option = RenderOptionBuilder::new().content_filter(filter);
+        let (img, stats) = render_page_with_stats(page, option).unwrap();
+        assert!(stats.path.is_zero());
+        img.get_pixel(40, 50).0 == [0, 0, 0, 255]
+    })
+}
+
+/// "Fg" of 40pt Helvetica, at baseline y 150 upright, at y 100 with `-40 Tf`,
+/// and at y 50 with `-40 Tf` and `-100 Tz`, on 200pt square page.
+#[test]
+fn negative_font_size() {
+    first_page("sample_files/page/negative-font-size.pdf", |page| {
+        let glyphs = glyph_runs(page, RenderOptionBuilder::new()).unwrap();
+        assert_eq!(6, glyphs.len());
+        let signs = |g: &GlyphDraw| (g.transform.m11 > 0.0, g.transform.m22 > 0.0);
+        // y axis flipped in device space, upright glyph has negative m22
+        assert_eq!((true, false), signs(&glyphs[0]));
+        // negative size flips both axes, text advances to left
+        assert_eq!((false, true), signs(&glyphs[2]));
+        assert!(glyphs[3].transform.m31 < glyphs[2].transform.m31);
+        // negative horizontal scaling mirrors it back, only vertically flipped
+        assert_eq!((true, true), signs(&glyphs[4]));
+        assert!(glyphs[5].transform.m31 > glyphs[4].transform.m31);
+
+        // vertically flipped "F" hangs below baseline
+        let img = render_page(page, RenderOptionBuilder::new()).unwrap();
+        let painted = |ys: std::ops::Range<u32>| {
+            (101..120)
+                .flat_map(|x| ys.clone().map(move |y| (x, y)))
+                .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255])
+        };
+        assert!(painted(155..175));
+        assert!(!painted(120..140));
+        // upright "F" above its baseline
+        assert!(painted(25..45));
+        assert!(!painted(55..75));
+    })
+}
+
+/// TrueType font of 2048 units per em, `/Widths` of "A" is 500. Three "A"
+/// at baseline y 100, then with `5 Tc` at y 50, "A A" with `7 Tw` at y 20,
+/// font size 20, all lines start at x 10.
+#[test]
+fn char_spacing_not_scaled_by_units_per_em() {
+    let glyphs = first_page("sample_files/page/char-spacing-2048-upem.pdf", |page| {
+        glyph_runs(page, RenderOptionBuilder::new()).unwrap()
+    });
+    let xs: Vec<_> = glyphs.iter().map(|g| g.transform.m31).collect();
+    // advance is `w0 * Tfs + Tc`, plus `Tw` for space: 500 / 1000 * 20 = 10
+    assert_eq!(
+        vec![10.0, 20.0, 30.0, 10.0, 25.0, 40.0, 10.0, 20.0, 27.0],
+        xs
+    );
+    // glyph scaled by 1 / 2048
+    assert_eq!(20.0 / 2048.0, glyphs[0].transform.m11);
+}
+
+/// "ABC" of 40pt at (20, 80), the subset TrueType font has glyph of "A" only,
+/// "B" starts at x 40, "C" maps to hebrew "א" by encoding differences, starts
+/// at x 67.
+#[test_case(false => false; "notdef")]
+#[test_case(true => true; "fallback")]
+fn glyph_fallback(fallback: bool) -> bool {
+    let img = first_page("sample_files/page/glyph-fallback.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().glyph_fallback(fallback)).unwrap()
+    });
+    let painted = |xs: std::ops::Range<u32>| {
+        xs.flat_map(|x| (95..118).map(move |y| (x, y)))
+            .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255])
+    };
+    // glyph of "A" always from document font
+    assert!(painted(25..35));
+    painted(44..60)
+}
+
+/// Glyph paths not needed, only whether a fallback font has the char.
+struct NoPath;
+
+impl PathSink for NoPath {
+    fn move_to(&mut self, _to: Point) {}
+    fn line_to(&mut self, _to: Point) {}
+    fn quad_to(&mut self, _ctrl: Point, _to: Point) {}
+    fn cubic_to(&mut self, _ctrl1: Point, _ctrl2: Point, _to: Point) {}
+    fn close(&mut self) {}
+}
+
+/// "א" not in bundled fallback fonts, rendered by a system font has it.
+/// Skipped if no system font has it.
+#[test]
+fn glyph_fallback_system_font() {
+    let fallback = FallbackFont::shared().unwrap();
+    if fallback.render('א', &mut NoPath).unwrap().is_none() {
+        return;
+    }
+
+    let img = first_page("sample_files/page/glyph-fallback.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().glyph_fallback(true)).unwrap()
+    });
+    let painted = (67..100)
+        .flat_map(|x| (80..118).map(move |y| (x, y)))
+        .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255]);
+    assert!(painted);
+}
+
+/// "C" maps to "α" by encoding differences, not in bundled sans font,
+/// rendered by bundled symbol font.
+#[test]
+fn glyph_fallback_bundled_symbol_font() {
+    let img = first_page("sample_files/page/glyph-fallback-symbol.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().glyph_fallback(true)).unwrap()
+    });
+    let painted = (70..92)
+        .flat_map(|x| (80..118).map(move |y| (x, y)))
+        .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255]);
+    assert!(painted);
+}
+
+/// Whether "α", not in document font, is painted with forced `font`.
+fn forced_symbol_painted(font: Option<ForceFont>) -> bool {
+    let img = first_page("sample_files/page/glyph-fallback-symbol.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().force_font(font)).unwrap()
+    });
+    (70..92)
+        .flat_map(|x| (80..118).map(move |y| (x, y)))
+        .any(|(x, y)| img.get_pixel(x, y).0 != [255, 255, 255, 255])
+}
+
+/// Document font used if forced font failed to load.
+#[test_case(None => false; "document font")]
+#[test_case(Some(ForceFont::Path("not-exist.ttf".into())) => false; "missing file ignored")]
+#[test_case(Some(ForceFont::Name(sname("Not Exist Font"))) => false; "missing font ignored")]
+fn force_font(font: Option<ForceFont>) -> bool {
+    forced_symbol_painted(font)
+}
+
+/// "α" rendered by forced font, looked up by unicode of encoding differences.
+/// Skipped if DejaVu Sans not installed.
+#[test]
+fn force_font_by_name_or_path() {
+    let Ok(data) = system_font_data("DejaVu Sans") else {
+        return;
+    };
+    assert!(forced_symbol_painted(Some(ForceFont::Name(sname(
+        "DejaVu Sans"
+    )))));
+
+    let path = std::env::temp_dir().join("nipdf-force-font-test.ttf");
+    std::fs::write(&path, data).unwrap();
+    assert!(forced_symbol_painted(Some(ForceFont::Path(path))));
+}
+
+/// Blue fill clipped by rect (20, 20, 60, 60), clip outline drawn in red
+/// blends with white background outside of clip region.
+#[test_case(false => false; "no outline")]
+#[test_case(true => true; "outline")]
+fn debug_clip(debug: bool) -> bool {
+    let img = first_page("sample_files/path/debug-clip.pdf", |page| {
+        render_page(page, RenderOptionBuilder::new().debug_clip(debug)).unwrap()
+    });
+    // clipped fill not changed
+    assert_eq!([0, 0, 255, 255], img.get_pixel(50, 50).0);
+    assert_eq!([255, 255, 255, 255], img.get_pixel(10, 50).0);
+    (18..22).any(|x| {
+        let [r, g, b, _] = img.get_pixel(x, 50).0;
+        r > g && r > b
+    })
+}
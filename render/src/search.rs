@@ -0,0 +1,203 @@
+//! Search text of pages, match against unicode of glyphs recorded by
+//! [`glyph_runs()`], chars of fonts can not map to unicode never match.
+use crate::{GlyphDraw, RenderOptionBuilder, glyph_runs};
+use educe::Educe;
+use euclid::{Point2D, Vector2D};
+use nipdf::{
+    file::{Page, text_separator},
+    graphics::trans::{DeviceSpace, GlyphSpace},
+    object::ObjectValueError,
+};
+
+#[derive(Debug, Clone, Educe)]
+#[educe(Default)]
+pub struct SearchOption {
+    pub ignore_case: bool,
+    /// Match only if the chars around the match are not alphanumeric.
+    pub whole_word: bool,
+    /// Stop searching following pages once hits reach the limit.
+    pub limit: Option<usize>,
+    /// Zoom of device space of hit quads, 1.0 is page size in points.
+    #[educe(Default = 1.0)]
+    pub zoom: f32,
+}
+
+/// A match returned by [`search()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// Index of the page in searched pages.
+    pub page: usize,
+    /// Matched text on the page, may differ from query in case and spaces.
+    pub text: String,
+    /// One quad per line of the match in device space, the pixel space of
+    /// page rendered at the zoom of search option. Corners are bottom start,
+    /// bottom end, top end and top start, in text direction.
+    pub quads: Vec<[Point2D<f32, DeviceSpace>; 4]>,
+}
+
+/// Search `query` in `pages` in order, pages after limit reached are not
+/// searched. Runs of whitespace in query match any whitespace, gaps between
+/// glyphs wider than a quarter of em and line breaks are treated as spaces.
+pub fn search(
+    pages: &[Page],
+    query: &str,
+    option: &SearchOption,
+) -> Result<Vec<SearchHit>, ObjectValueError> {
+    let normalize = |c: char| {
+        if c.is_whitespace() {
+            ' '
+        } else if option.ignore_case {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    };
+    let mut query: Vec<char> = query.trim().chars().map(normalize).collect();
+    query.dedup_by(|a, b| *a == ' ' && *b == ' ');
+    let mut r = vec![];
+    if query.is_empty() {
+        return Ok(r);
+    }
+
+    for (page_no, page) in pages.iter().enumerate() {
+        if option.limit.is_some_and(|limit| r.len() >= limit) {
+            break;
+        }
+
+        let glyphs = glyph_runs(page, RenderOptionBuilder::new().zoom(option.zoom))?;
+        let text = PageText::new(&glyphs);
+        let chars: Vec<char> = text.chars.iter().map(|c| normalize(c.ch)).collect();
+        let is_word_char = |i: Option<usize>| {
+            i.and_then(|i| chars.get(i))
+                .is_some_and(|c| c.is_alphanumeric())
+        };
+        let mut i = 0;
+        while i + query.len() <= chars.len() {
+            let end = i + query.len();
+            let matched = chars[i..end] == query[..]
+                && !(option.whole_word
+                    && (is_word_char(i.checked_sub(1)) || is_word_char(Some(end))));
+            if !matched {
+                i += 1;
+                continue;
+            }
+
+            r.push(SearchHit {
+                page: page_no,
+                text: text.chars[i..end].iter().map(|c| c.ch).collect(),
+                quads: text.quads(&glyphs, i..end),
+            });
+            if option.limit.is_some_and(|limit| r.len() >= limit) {
+                break;
+            }
+            i = end;
+        }
+    }
+    Ok(r)
+}
+
+struct PageChar {
+    ch: char,
+    /// Index of the glyph, None for space inserted for gap or line break.
+    glyph: Option<usize>,
+    /// First glyph of a line.
+    line_start: bool,
+}
+
+/// Chars of page glyphs in content order.
+struct PageText {
+    chars: Vec<PageChar>,
+}
+
+impl PageText {
+    fn new(glyphs: &[GlyphDraw]) -> Self {
+        let mut chars: Vec<PageChar> = Vec::with_capacity(glyphs.len());
+        let mut prev: Option<&GlyphDraw> = None;
+        for (i, g) in glyphs.iter().enumerate() {
+            let Some(first) = g.unicode.chars().next() else {
+                continue;
+            };
+
+            let mut line_start = true;
+            if let Some(prev) = prev {
+                let (gap, offset) = gap_to(prev, g);
+                let sep = text_separator(gap, offset);
+                line_start = sep == Some('\n');
+                let spaced =
+                    first.is_whitespace() || chars.last().is_some_and(|c| c.ch.is_whitespace());
+                if sep.is_some() && !spaced {
+                    chars.push(PageChar {
+                        ch: ' ',
+                        glyph: None,
+                        line_start: false,
+                    });
+                }
+            }
+            // chars of a ligature share the glyph
+            for (j, ch) in g.unicode.chars().enumerate() {
+                chars.push(PageChar {
+                    ch,
+                    glyph: Some(i),
+                    line_start: line_start && j == 0,
+                });
+            }
+            prev = Some(g);
+        }
+        Self { chars }
+    }
+
+    /// Quads of glyphs in `range` of chars, one quad per line.
+    fn quads(
+        &self,
+        glyphs: &[GlyphDraw],
+        range: std::ops::Range<usize>,
+    ) -> Vec<[Point2D<f32, DeviceSpace>; 4]> {
+        let mut lines: Vec<(&GlyphDraw, &GlyphDraw)> = vec![];
+        for c in &self.chars[range] {
+            let Some(g) = c.glyph.map(|i| &glyphs[i]) else {
+                continue;
+            };
+            match lines.last_mut() {
+                Some((_, last)) if !c.line_start => *last = g,
+                _ => lines.push((g, g)),
+            }
+        }
+        lines
+            .into_iter()
+            .map(|(first, last)| {
+                let corner =
+                    |g: &GlyphDraw, x: f32, y: f32| g.transform.transform_point(Point2D::new(x, y));
+                [
+                    corner(first, first.bbox.min.x, first.bbox.min.y),
+                    corner(last, last.bbox.max.x, last.bbox.min.y),
+                    corner(last, last.bbox.max.x, last.bbox.max.y),
+                    corner(first, first.bbox.min.x, first.bbox.max.y),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Distance from advance end of `prev` to origin of `g`, along and
+/// perpendicular to the baseline of `prev`, in em of `prev`.
+fn gap_to(prev: &GlyphDraw, g: &GlyphDraw) -> (f32, f32) {
+    let end = prev
+        .transform
+        .transform_point(Point2D::new(prev.bbox.max.x, 0.0));
+    let origin = g.transform.transform_point(Point2D::origin());
+    let em = prev.bbox.height();
+    let to_device = |v: Vector2D<f32, GlyphSpace>| prev.transform.transform_vector(v);
+    let (x, y) = (
+        to_device(Vector2D::new(em, 0.0)),
+        to_device(Vector2D::new(0.0, em)),
+    );
+    if x.square_length() == 0.0 || y.square_length() == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let d = origin - end;
+    (
+        d.dot(x) / x.square_length(),
+        (d.dot(y) / y.square_length()).abs(),
+    )
+}
@@ -7,11 +7,11 @@ use nipdf::{
     function::{Domain, Function, FunctionDict, Type as FunctionType},
     graphics::{
         Extend, Point, RadialCircle,
-        color_space::ColorSpace,
-        shading::{AxialCoords, RadialCoords, ShadingDict, ShadingType},
+        color_space::{ColorSpace, ColorSpaceTrait},
+        shading::{AxialCoords, MeshTriangle, RadialCoords, ShadingDict, ShadingType},
         trans::UserToLogicDeviceSpace,
     },
-    object::PdfObject,
+    object::{ObjectValueError, PdfObject},
 };
 use std::rc::Rc;
 use tiny_skia::{Color, GradientStop, LinearGradient, RadialGradient, Shader, Transform};
@@ -49,6 +49,7 @@ pub struct Radial {
     pub extend: Extend,
     pub color_space: ColorSpace,
     stops: Vec<(f32, Color)>,
+    pub b_box: Option<Rectangle>,
 }
 
 impl Radial {
@@ -75,10 +76,87 @@ fn stops_to_skia(stops: &[(f32, Color)], alpha: f32) -> Vec<GradientStop> {
         .collect()
 }
 
+/// Triangles of shading type 4 to 7, patches already divided into triangles.
+#[derive(Educe, Clone)]
+#[educe(Debug)]
+pub struct Mesh {
+    pub triangles: Vec<MeshTriangle>,
+    /// Map parametric value of vertices to color components, one function
+    /// for each component or a single function returns all of them.
+    #[educe(Debug(ignore))]
+    pub functions: Vec<Rc<dyn Function>>,
+    pub color_space: ColorSpace,
+    pub b_box: Option<Rectangle>,
+}
+
+impl Mesh {
+    pub fn color(&self, comps: &[f32]) -> AnyResult<Color> {
+        if self.functions.is_empty() {
+            return Ok(to_skia_color(&self.color_space, comps));
+        }
+
+        let mut color = Vec::with_capacity(self.color_space.components());
+        for f in &self.functions {
+            color.extend(f.call(comps)?);
+        }
+        Ok(to_skia_color(&self.color_space, &color))
+    }
+}
+
+/// Max recursion depth of `split_triangle()`.
+const MAX_SPLIT_DEPTH: u8 = 7;
+
+/// Split Gouraud-shaded triangle in device space into four by its edge
+/// midpoints, until it is small enough or its vertex colors are close, then
+/// call `f` to fill it with the average color of its vertices.
+pub fn split_triangle(
+    vertices: [(tiny_skia::Point, Vec<f32>); 3],
+    depth: u8,
+    f: &mut impl FnMut([tiny_skia::Point; 3], &[f32]) -> AnyResult<()>,
+) -> AnyResult<()> {
+    let [(pa, ca), (pb, cb), (pc, cc)] = &vertices;
+    let max_edge = [(pa, pb), (pb, pc), (pc, pa)]
+        .iter()
+        .map(|(p, q)| (p.x - q.x).hypot(p.y - q.y))
+        .fold(0f32, f32::max);
+    let max_color_diff = ca
+        .iter()
+        .zip(cb)
+        .zip(cc)
+        .map(|((a, b), c)| a.max(*b).max(*c) - a.min(*b).min(*c))
+        .fold(0f32, f32::max);
+    if depth >= MAX_SPLIT_DEPTH || max_edge <= 1.0 || max_color_diff <= 1.0 / 255.0 {
+        let color: Vec<f32> = ca
+            .iter()
+            .zip(cb)
+            .zip(cc)
+            .map(|((a, b), c)| (a + b + c) / 3.0)
+            .collect();
+        return f([*pa, *pb, *pc], &color);
+    }
+
+    let mid = |(p, c): &(tiny_skia::Point, Vec<f32>), (q, d): &(tiny_skia::Point, Vec<f32>)| {
+        (
+            tiny_skia::Point::from_xy((p.x + q.x) / 2.0, (p.y + q.y) / 2.0),
+            c.iter()
+                .zip(d)
+                .map(|(a, b)| (a + b) / 2.0)
+                .collect::<Vec<_>>(),
+        )
+    };
+    let [a, b, c] = vertices;
+    let (m_ab, m_bc, m_ca) = (mid(&a, &b), mid(&b, &c), mid(&c, &a));
+    split_triangle([a, m_ab.clone(), m_ca.clone()], depth + 1, f)?;
+    split_triangle([m_ab.clone(), b, m_bc.clone()], depth + 1, f)?;
+    split_triangle([m_ca.clone(), m_bc.clone(), c], depth + 1, f)?;
+    split_triangle([m_ab, m_bc, m_ca], depth + 1, f)
+}
+
 #[derive(Clone, Debug)]
 pub enum Shading {
     Axial(Axial),
     Radial(Radial),
+    Mesh(Mesh),
 }
 
 impl Shading {
@@ -90,6 +168,7 @@ impl Shading {
         match self {
             Self::Axial(axial) => axial.to_skia(transform.into_skia(), alpha),
             Self::Radial(radial) => radial.to_skia(transform.into_skia(), alpha),
+            Self::Mesh(_) => None,
         }
     }
 }
@@ -102,6 +181,10 @@ pub fn build_shading<'a, 'b>(
     Ok(match d.shading_type()? {
         ShadingType::Axial => build_axial(d, resources)?.map(Shading::Axial),
         ShadingType::Radial => build_radial(d, resources)?.map(Shading::Radial),
+        t @ (ShadingType::FreeForm
+        | ShadingType::LatticeForm
+        | ShadingType::CoonsPatchMesh
+        | ShadingType::TensorProductPatchMesh) => Some(Shading::Mesh(build_mesh(d, t, resources)?)),
         t => {
             error!("Shading not implemented: {:?}", t);
             None
@@ -136,6 +219,7 @@ fn build_radial<'a, 'b>(
 ) -> AnyResult<Option<Radial>> {
     let color_space = d.color_space()?;
     let color_space = ColorSpace::from_args(&color_space, resources.resolver(), Some(resources))?;
+    let b_box = d.b_box()?;
 
     let d = d.radial()?;
     let RadialCoords { start, end } = d.coords()?;
@@ -154,15 +238,41 @@ fn build_radial<'a, 'b>(
         function: function.into(),
         domain,
         extend,
+        b_box,
     }))
 }
 
+fn build_mesh<'a, 'b>(
+    d: &ShadingDict<'a, 'b>,
+    t: ShadingType,
+    resources: &ResourceDict<'a, 'b>,
+) -> AnyResult<Mesh> {
+    let color_space = d.color_space()?;
+    let color_space = ColorSpace::from_args(&color_space, resources.resolver(), Some(resources))?;
+    let mesh = d.mesh()?;
+    let functions = mesh
+        .function()?
+        .iter()
+        .map(|f| f.func().map(Rc::from))
+        .collect::<AnyResult<_>>()?;
+    Ok(Mesh {
+        triangles: mesh.triangles(t)?,
+        functions,
+        color_space,
+        b_box: d.b_box()?,
+    })
+}
+
 fn build_stops(
     cs: &ColorSpace,
     domain: Domain,
     mut f: Vec<FunctionDict>,
 ) -> AnyResult<Vec<(f32, Color)>> {
-    assert!(f.len() == 1, "todo: support functions");
+    if f.len() != 1 {
+        return Err(
+            ObjectValueError::Unsupported(vec!["shading of function array".to_owned()]).into(),
+        );
+    }
 
     let f = f.pop().unwrap();
     fn create_stop<F: Function>(cs: &ColorSpace, f: &F, x: f32) -> AnyResult<(f32, Color)> {
@@ -175,7 +285,12 @@ fn build_stops(
         FunctionType::ExponentialInterpolation => {
             let ef = f.exponential_interpolation()?;
             let eff = ef.func()?;
-            assert_eq!(ef.n()?, 1f32, "Only linear gradient function supported");
+            if ef.n()? != 1.0 {
+                return Err(ObjectValueError::Unsupported(vec![
+                    "shading of non-linear exponential function".to_owned(),
+                ])
+                .into());
+            }
             Ok(vec![
                 create_stop(cs, &eff, domain.start)?,
                 create_stop(cs, &eff, domain.end)?,
@@ -208,8 +323,8 @@ fn build_stops(
             }
             Ok(stops)
         }
-        _ => {
-            todo!("Unsupported function type: {:?}", f.function_type()?);
+        t => {
+            Err(ObjectValueError::Unsupported(vec![format!("shading of {:?} function", t)]).into())
         }
     }
 }
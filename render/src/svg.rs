@@ -0,0 +1,365 @@
+//! SVG output of [`render_page_svg()`], `Render` emits paths painted in solid
+//! colors as SVG elements, other content, such as images, shadings, patterns
+//! and soft masks, is painted on canvas as usual, and the canvas embedded as
+//! a PNG `<image>` layer before the next vector element.
+//!
+//! Content blends with backdrop, by blend mode or soft mask, can not be a
+//! layer of its own. It is painted on a copy of the page painted so far, and
+//! pixels it changed embedded as an image layer cropped to their bounds,
+//! elements before and after it stay vector.
+//!
+//! [`render_page_svg()`]: crate::render_page_svg
+use base64::{Engine, engine::general_purpose::STANDARD};
+use log::warn;
+use std::fmt::Write;
+use tiny_skia::{
+    Color, FillRule, LineCap, LineJoin, Mask, Paint, Path, PathSegment, Pixmap, PixmapPaint,
+    Stroke, StrokeDash, Transform,
+};
+
+/// How to paint a path.
+#[derive(Clone, Copy)]
+pub(crate) enum PathPaint<'a> {
+    Fill(FillRule),
+    /// Stroke, and dash array and phase in user space.
+    Stroke(&'a Stroke, &'a (Vec<f32>, f32)),
+}
+
+#[derive(Debug)]
+pub(crate) struct SvgWriter {
+    width: u32,
+    height: u32,
+    /// Logic device space to device space, transform of the root group, paths
+    /// are in logic device space.
+    root: Transform,
+    background: Color,
+    defs: String,
+    body: String,
+    next_clip_id: usize,
+    /// Canvas painted since last flushed.
+    raster: bool,
+    /// Page painted so far, flushed raster layers and vector elements, the
+    /// backdrop of blended content.
+    backdrop: Pixmap,
+    /// Canvas is a copy of backdrop painted with blended content, flushed as
+    /// pixels changed.
+    blend_layer: bool,
+}
+
+fn fmt_transform(t: Transform) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        t.sx, t.ky, t.kx, t.sy, t.tx, t.ty
+    )
+}
+
+fn fmt_color(c: Color) -> String {
+    let c = c.to_color_u8();
+    format!("rgb({},{},{})", c.red(), c.green(), c.blue())
+}
+
+fn path_data(path: &Path) -> String {
+    let mut r = String::new();
+    for seg in path.segments() {
+        if !r.is_empty() {
+            r.push(' ');
+        }
+        match seg {
+            PathSegment::MoveTo(p) => write!(r, "M{} {}", p.x, p.y),
+            PathSegment::LineTo(p) => write!(r, "L{} {}", p.x, p.y),
+            PathSegment::QuadTo(p1, p) => write!(r, "Q{} {} {} {}", p1.x, p1.y, p.x, p.y),
+            PathSegment::CubicTo(p1, p2, p) => {
+                write!(r, "C{} {} {} {} {} {}", p1.x, p1.y, p2.x, p2.y, p.x, p.y)
+            }
+            PathSegment::Close => write!(r, "Z"),
+        }
+        .unwrap();
+    }
+    r
+}
+
+/// Pixels of `canvas` differ from `backdrop`, cropped to their bounds, other
+/// pixels in bounds are transparent. Returns offset of the crop and the
+/// cropped pixmap, None if no pixel changed.
+fn changed_pixels(canvas: &Pixmap, backdrop: &Pixmap) -> Option<(u32, u32, Pixmap)> {
+    let w = canvas.width();
+    let (src, dst) = (canvas.pixels(), backdrop.pixels());
+    let changed = |x: u32, y: u32| src[(y * w + x) as usize] != dst[(y * w + x) as usize];
+    let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+    for y in 0..canvas.height() {
+        for x in (0..w).filter(|&x| changed(x, y)) {
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+    }
+    if x0 > x1 {
+        return None;
+    }
+
+    let mut r = Pixmap::new(x1 - x0 + 1, y1 - y0 + 1)?;
+    let crop_w = r.width();
+    let pixels = r.pixels_mut();
+    for y in y0..=y1 {
+        for x in (x0..=x1).filter(|&x| changed(x, y)) {
+            pixels[((y - y0) * crop_w + x - x0) as usize] = src[(y * w + x) as usize];
+        }
+    }
+    Some((x0, y0, r))
+}
+
+fn fill_rule(rule: FillRule) -> &'static str {
+    match rule {
+        FillRule::Winding => "nonzero",
+        FillRule::EvenOdd => "evenodd",
+    }
+}
+
+impl SvgWriter {
+    pub fn new(width: u32, height: u32, root: Transform, background: Color) -> Self {
+        let mut backdrop = Pixmap::new(width.max(1), height.max(1)).unwrap();
+        backdrop.fill(background);
+        Self {
+            width,
+            height,
+            root,
+            background,
+            defs: String::new(),
+            body: String::new(),
+            next_clip_id: 0,
+            raster: false,
+            backdrop,
+            blend_layer: false,
+        }
+    }
+
+    /// Mark canvas painted, flushed as an image layer before next element.
+    /// Call before painting, if the content blends with backdrop, canvas
+    /// becomes a copy of the page painted so far.
+    pub fn mark_raster(&mut self, canvas: &mut Pixmap, blend: bool) {
+        if blend && !self.blend_layer {
+            self.flush_raster(canvas);
+            canvas.clone_from(&self.backdrop);
+            self.blend_layer = true;
+        }
+        self.raster = true;
+    }
+
+    /// Embed `image` at device `(x, y)` as an image layer.
+    fn image(&mut self, x: u32, y: u32, image: &Pixmap) {
+        let png = match image.encode_png() {
+            Ok(png) => png,
+            Err(e) => {
+                warn!("encode raster layer of svg error: {}", e);
+                return;
+            }
+        };
+        self.body.push_str("<image ");
+        if (x, y) != (0, 0) {
+            write!(self.body, r#"x="{}" y="{}" "#, x, y).unwrap();
+        }
+        write!(
+            self.body,
+            r#"width="{}" height="{}" transform="{}" href="data:image/png;base64,{}"/>"#,
+            image.width(),
+            image.height(),
+            fmt_transform(self.root.invert().unwrap_or_default()),
+            STANDARD.encode(png),
+        )
+        .unwrap();
+    }
+
+    /// Embed canvas as an image layer if painted, and clear it.
+    fn flush_raster(&mut self, canvas: &mut Pixmap) {
+        if !std::mem::take(&mut self.raster) {
+            return;
+        }
+
+        if std::mem::take(&mut self.blend_layer) {
+            if let Some((x, y, layer)) = changed_pixels(canvas, &self.backdrop) {
+                self.image(x, y, &layer);
+            }
+            std::mem::swap(&mut self.backdrop, canvas);
+            canvas.fill(Color::TRANSPARENT);
+            return;
+        }
+
+        if canvas.pixels().iter().all(|p| p.alpha() == 0) {
+            return;
+        }
+        self.image(0, 0, canvas);
+        self.backdrop.draw_pixmap(
+            0,
+            0,
+            canvas.as_ref(),
+            &PixmapPaint::default(),
+            Transform::identity(),
+            None,
+        );
+        canvas.fill(Color::TRANSPARENT);
+    }
+
+    /// Define clip path of `path` intersects with clip `parent`, `transform`
+    /// maps path to logic device space. Clip all if `path` is None. Returns
+    /// id of the clip path.
+    pub fn clip(
+        &mut self,
+        path: Option<&Path>,
+        transform: Transform,
+        rule: FillRule,
+        parent: Option<usize>,
+    ) -> usize {
+        let id = self.next_clip_id;
+        self.next_clip_id += 1;
+        write!(self.defs, r#"<clipPath id="c{}""#, id).unwrap();
+        if let Some(parent) = parent {
+            write!(self.defs, r#" clip-path="url(#c{})""#, parent).unwrap();
+        }
+        self.defs.push('>');
+        if let Some(path) = path {
+            write!(
+                self.defs,
+                r#"<path transform="{}" d="{}" clip-rule="{}"/>"#,
+                fmt_transform(transform),
+                path_data(path),
+                fill_rule(rule),
+            )
+            .unwrap();
+        }
+        self.defs.push_str("</clipPath>");
+        id
+    }
+
+    /// Paint `path` in `color`, `transform` maps path to logic device space,
+    /// clipped by clip path `clip`, and also on backdrop clipped by `mask`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn path(
+        &mut self,
+        canvas: &mut Pixmap,
+        path: &Path,
+        transform: Transform,
+        color: Color,
+        paint: PathPaint<'_>,
+        clip: Option<usize>,
+        mask: Option<&Mask>,
+    ) {
+        self.flush_raster(canvas);
+        self.paint_backdrop(path, transform, color, paint, mask);
+        if let Some(clip) = clip {
+            write!(self.body, r#"<g clip-path="url(#c{})">"#, clip).unwrap();
+        }
+        write!(
+            self.body,
+            r#"<path transform="{}" d="{}""#,
+            fmt_transform(transform),
+            path_data(path)
+        )
+        .unwrap();
+        let opacity = color.alpha();
+        match paint {
+            PathPaint::Fill(rule) => {
+                write!(self.body, r#" fill="{}""#, fmt_color(color)).unwrap();
+                if rule == FillRule::EvenOdd {
+                    write!(self.body, r#" fill-rule="{}""#, fill_rule(rule)).unwrap();
+                }
+                if opacity < 1.0 {
+                    write!(self.body, r#" fill-opacity="{}""#, opacity).unwrap();
+                }
+            }
+            PathPaint::Stroke(stroke, (dash, phase)) => {
+                write!(self.body, r#" fill="none" stroke="{}""#, fmt_color(color)).unwrap();
+                if stroke.width == 0.0 {
+                    // hairline, thinnest line can be rendered on device
+                    self.body
+                        .push_str(r#" stroke-width="1" vector-effect="non-scaling-stroke""#);
+                } else {
+                    write!(self.body, r#" stroke-width="{}""#, stroke.width).unwrap();
+                }
+                let cap = match stroke.line_cap {
+                    LineCap::Butt => "butt",
+                    LineCap::Round => "round",
+                    LineCap::Square => "square",
+                };
+                let join = match stroke.line_join {
+                    LineJoin::Miter | LineJoin::MiterClip => "miter",
+                    LineJoin::Round => "round",
+                    LineJoin::Bevel => "bevel",
+                };
+                write!(
+                    self.body,
+                    r#" stroke-linecap="{}" stroke-linejoin="{}" stroke-miterlimit="{}""#,
+                    cap, join, stroke.miter_limit
+                )
+                .unwrap();
+                if !dash.is_empty() {
+                    let dash: Vec<_> = dash.iter().map(f32::to_string).collect();
+                    write!(
+                        self.body,
+                        r#" stroke-dasharray="{}" stroke-dashoffset="{}""#,
+                        dash.join(","),
+                        phase
+                    )
+                    .unwrap();
+                }
+                if opacity < 1.0 {
+                    write!(self.body, r#" stroke-opacity="{}""#, opacity).unwrap();
+                }
+            }
+        }
+        self.body.push_str("/>");
+        if clip.is_some() {
+            self.body.push_str("</g>");
+        }
+    }
+
+    fn paint_backdrop(
+        &mut self,
+        path: &Path,
+        transform: Transform,
+        color: Color,
+        paint: PathPaint<'_>,
+        mask: Option<&Mask>,
+    ) {
+        let mut skia_paint = Paint::default();
+        skia_paint.set_color(color);
+        let transform = self.root.pre_concat(transform);
+        match paint {
+            PathPaint::Fill(rule) => {
+                self.backdrop
+                    .fill_path(path, &skia_paint, rule, transform, mask)
+            }
+            PathPaint::Stroke(stroke, (dash, phase)) => {
+                let mut stroke = stroke.clone();
+                stroke.dash = StrokeDash::new(dash.clone(), *phase);
+                self.backdrop
+                    .stroke_path(path, &skia_paint, &stroke, transform, mask);
+            }
+        }
+    }
+
+    /// Flush remaining canvas content, returns the SVG document.
+    pub fn finish(&mut self, canvas: &mut Pixmap) -> String {
+        self.flush_raster(canvas);
+        let (w, h) = (self.width, self.height);
+        let mut r = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+        );
+        if !self.defs.is_empty() {
+            write!(r, "<defs>{}</defs>", self.defs).unwrap();
+        }
+        if self.background.is_opaque() {
+            write!(
+                r,
+                r#"<rect width="{w}" height="{h}" fill="{}"/>"#,
+                fmt_color(self.background)
+            )
+            .unwrap();
+        }
+        write!(
+            r,
+            r#"<g transform="{}">{}</g></svg>"#,
+            fmt_transform(self.root),
+            self.body
+        )
+        .unwrap();
+        r
+    }
+}
@@ -125,6 +125,7 @@ const IGNORED: [&str; 14] = [
 ];
 
 static PASSWORD: phf::Map<&'static str, &'static str> = phf::phf_map! {
+    "aes256-r6.pdf" => "user",
     "bug1782186.pdf" => "Hello",
     "issue15893_reduced.pdf" => "test",
     "issue3371.pdf" => "ELXRTQWS",